@@ -16,6 +16,8 @@ pub(crate) const COSMIAN_CLI_CONF_PATH: &str = ".cosmian/cosmian.toml";
 pub struct ClientConfig {
     pub kms_config: KmsClientConfig,
     pub findex_config: Option<RestClientConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkcs11_config: Option<Pkcs11Config>,
 }
 
 impl Default for ClientConfig {
@@ -23,10 +25,76 @@ impl Default for ClientConfig {
         Self {
             kms_config: KmsClientConfig::default(),
             findex_config: Some(RestClientConfig::default()),
+            pkcs11_config: None,
         }
     }
 }
 
+/// The configuration of the PKCS#11 provider library.
+///
+/// Hosts running several PKCS#11 applications (e.g. LUKS and Oracle TDE)
+/// point each of them to a dedicated configuration file, so that each token
+/// exposes its own set of keys.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Pkcs11Config {
+    /// The token label, padded or truncated to 32 characters
+    pub token_label: Option<String>,
+    /// The token manufacturer, padded or truncated to 32 characters
+    pub token_manufacturer: Option<String>,
+    /// The token model, padded or truncated to 16 characters
+    pub token_model: Option<String>,
+    /// The token serial number, padded or truncated to 16 characters
+    pub token_serial: Option<String>,
+    /// The tag selecting the KMS objects exposed by the token.
+    /// Defaults to the `COSMIAN_PKCS11_DISK_ENCRYPTION_TAG` environment variable,
+    /// then to `disk-encryption`.
+    pub tag: Option<String>,
+    /// The tag selecting the certificates, instead of `tag`
+    pub certificate_tag: Option<String>,
+    /// The tag selecting the private keys, instead of `tag`
+    pub private_key_tag: Option<String>,
+    /// The tag selecting the public keys, instead of `tag`
+    pub public_key_tag: Option<String>,
+    /// The tag selecting the data objects, instead of `tag`
+    pub data_object_tag: Option<String>,
+    /// Require the user to log in with a PIN before the objects of the token
+    /// can be used. The PIN decrypts `pin_protected_access_token` and
+    /// `pin_protected_pkcs12_password`; when neither is set, the PIN is the
+    /// password of the client PKCS#12 of the KMS configuration.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub login_required: bool,
+    /// The KMS access token, encrypted with the PIN by
+    /// `openssl enc -aes-256-cbc -pbkdf2 -iter 600000 -md sha256 -a -A`
+    pub pin_protected_access_token: Option<String>,
+    /// The password of the client PKCS#12, encrypted with the PIN
+    /// like `pin_protected_access_token`
+    pub pin_protected_pkcs12_password: Option<String>,
+    /// Let the keys created through the token without `CKA_EXTRACTABLE`
+    /// be extractable; they are not by default
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub extractable_keys: bool,
+    /// The logging level: `trace`, `debug`, `info`, `warn` or `error`
+    pub logging_level: Option<String>,
+    /// The folder of the `cosmian-pkcs11.log` file
+    pub logging_folder: Option<String>,
+    /// The slots following the first one, each exposing the token
+    /// of another KMS or of another set of keys
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub slots: Vec<Pkcs11SlotConfig>,
+}
+
+/// An additional slot of the PKCS#11 provider library
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Pkcs11SlotConfig {
+    /// The KMS of the slot; the KMS of the configuration file when not set
+    pub kms_config: Option<KmsClientConfig>,
+    /// The token identity and tags of the slot.
+    /// Its logging settings and slots are ignored.
+    pub pkcs11_config: Pkcs11Config,
+}
+
 #[expect(clippy::print_stdout)]
 impl ClientConfig {
     /// Load the default location of the configuration file.
@@ -84,7 +152,7 @@ mod tests {
     use cosmian_config_utils::{ConfigUtils, get_default_conf_path};
     use cosmian_logger::log_init;
 
-    use super::ClientConfig;
+    use super::{ClientConfig, Pkcs11Config, Pkcs11SlotConfig};
     use crate::config::{COSMIAN_CLI_CONF_ENV, COSMIAN_CLI_CONF_PATH};
 
     #[test]
@@ -139,4 +207,65 @@ mod tests {
 
         assert!(ClientConfig::from_toml(conf_path.to_str().unwrap()).is_ok());
     }
+
+    #[test]
+    pub(crate) fn test_pkcs11_config() {
+        log_init(None);
+        let dir = tempfile::tempdir().unwrap();
+        let conf_path = dir.path().join("cosmian.toml");
+        fs::write(
+            &conf_path,
+            r#"
+[kms_config.http_config]
+server_url = "http://localhost:9998"
+
+[pkcs11_config]
+token_label = "Oracle TDE"
+tag = "oracle-tde"
+private_key_tag = "oracle-tde-keys"
+logging_level = "debug"
+
+[[pkcs11_config.slots]]
+[pkcs11_config.slots.pkcs11_config]
+token_label = "Escrow"
+tag = "escrow"
+login_required = true
+pin_protected_access_token = "U2FsdGVkX1+MRbK3BZ5l0g=="
+"#,
+        )
+        .unwrap();
+        let conf = ClientConfig::from_toml(conf_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            conf.pkcs11_config,
+            Some(Pkcs11Config {
+                token_label: Some("Oracle TDE".to_owned()),
+                tag: Some("oracle-tde".to_owned()),
+                private_key_tag: Some("oracle-tde-keys".to_owned()),
+                logging_level: Some("debug".to_owned()),
+                slots: vec![Pkcs11SlotConfig {
+                    kms_config: None,
+                    pkcs11_config: Pkcs11Config {
+                        token_label: Some("Escrow".to_owned()),
+                        tag: Some("escrow".to_owned()),
+                        login_required: true,
+                        pin_protected_access_token: Some("U2FsdGVkX1+MRbK3BZ5l0g==".to_owned()),
+                        ..Default::default()
+                    },
+                }],
+                ..Default::default()
+            })
+        );
+
+        // the section is optional
+        fs::write(
+            &conf_path,
+            r#"
+[kms_config.http_config]
+server_url = "http://localhost:9998"
+"#,
+        )
+        .unwrap();
+        let conf = ClientConfig::from_toml(conf_path.to_str().unwrap()).unwrap();
+        assert!(conf.pkcs11_config.is_none());
+    }
 }
@@ -18,6 +18,7 @@ pub(crate) fn save_kms_cli_config(kms_ctx: &TestsContext) -> (String, String) {
         let conf = ClientConfig {
             kms_config: kms_ctx.owner_client_config.clone(),
             findex_config: None,
+            pkcs11_config: None,
         };
         conf.to_toml(&owner_file_path)
             .expect("Failed to save owner test config");
@@ -31,6 +32,7 @@ pub(crate) fn save_kms_cli_config(kms_ctx: &TestsContext) -> (String, String) {
         let conf = ClientConfig {
             kms_config: kms_ctx.user_client_config.clone(),
             findex_config: None,
+            pkcs11_config: None,
         };
         conf.to_toml(&user_file_path)
             .expect("Failed to save user test config");
@@ -47,6 +49,7 @@ pub(crate) fn force_save_kms_cli_config(kms_ctx: &TestsContext) -> (String, Stri
     let conf = ClientConfig {
         kms_config: kms_ctx.owner_client_config.clone(),
         findex_config: None,
+        pkcs11_config: None,
     };
     conf.to_toml(&owner_file_path)
         .expect("Failed to save owner test config");
@@ -58,6 +61,7 @@ pub(crate) fn force_save_kms_cli_config(kms_ctx: &TestsContext) -> (String, Stri
     let conf = ClientConfig {
         kms_config: kms_ctx.user_client_config.clone(),
         findex_config: None,
+        pkcs11_config: None,
     };
     conf.to_toml(&user_file_path)
         .expect("Failed to save user test config");
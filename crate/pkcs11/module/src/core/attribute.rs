@@ -23,22 +23,24 @@ use std::slice;
 use cosmian_logger::trace;
 use pkcs11_sys::{
     CK_ATTRIBUTE, CK_ATTRIBUTE_PTR, CK_ATTRIBUTE_TYPE, CK_BBOOL, CK_CERTIFICATE_CATEGORY,
-    CK_CERTIFICATE_TYPE, CK_FALSE, CK_KEY_TYPE, CK_OBJECT_CLASS, CK_PROFILE_ID, CK_TRUE, CK_ULONG,
-    CKA_ALWAYS_AUTHENTICATE, CKA_ALWAYS_SENSITIVE, CKA_APPLICATION, CKA_CERTIFICATE_CATEGORY,
-    CKA_CERTIFICATE_TYPE, CKA_CLASS, CKA_COEFFICIENT, CKA_DECRYPT, CKA_EC_PARAMS, CKA_EC_POINT,
-    CKA_ENCRYPT, CKA_EXPONENT_1, CKA_EXPONENT_2, CKA_EXTRACTABLE, CKA_ID, CKA_ISSUER, CKA_KEY_TYPE,
-    CKA_LABEL, CKA_MODIFIABLE, CKA_MODULUS, CKA_MODULUS_BITS, CKA_NEVER_EXTRACTABLE, CKA_PRIME_1,
-    CKA_PRIME_2, CKA_PRIVATE, CKA_PRIVATE_EXPONENT, CKA_PROFILE_ID, CKA_PUBLIC_EXPONENT,
-    CKA_SENSITIVE, CKA_SERIAL_NUMBER, CKA_SIGN, CKA_SIGN_RECOVER, CKA_SUBJECT, CKA_TOKEN,
-    CKA_TRUSTED, CKA_UNWRAP, CKA_VALUE, CKA_VALUE_LEN, CKA_VERIFY, CKA_VERIFY_RECOVER, CKA_WRAP,
-    CKC_X_509,
+    CK_CERTIFICATE_TYPE, CK_FALSE, CK_KEY_TYPE, CK_MECHANISM_TYPE, CK_OBJECT_CLASS, CK_PROFILE_ID,
+    CK_SLOT_ID, CK_TRUE, CK_ULONG, CKA_ALLOWED_MECHANISMS, CKA_ALWAYS_AUTHENTICATE,
+    CKA_ALWAYS_SENSITIVE, CKA_APPLICATION, CKA_CERTIFICATE_CATEGORY, CKA_CERTIFICATE_TYPE,
+    CKA_CLASS, CKA_COEFFICIENT, CKA_DECRYPT, CKA_DERIVE, CKA_EC_PARAMS, CKA_EC_POINT, CKA_ENCRYPT,
+    CKA_END_DATE, CKA_EXPONENT_1, CKA_EXPONENT_2, CKA_EXTRACTABLE, CKA_ID, CKA_ISSUER,
+    CKA_KEY_GEN_MECHANISM, CKA_KEY_TYPE, CKA_LABEL, CKA_LOCAL, CKA_MODIFIABLE, CKA_MODULUS,
+    CKA_MODULUS_BITS, CKA_NEVER_EXTRACTABLE, CKA_PRIME_1, CKA_PRIME_2, CKA_PRIVATE,
+    CKA_PRIVATE_EXPONENT, CKA_PROFILE_ID, CKA_PUBLIC_EXPONENT, CKA_SENSITIVE, CKA_SERIAL_NUMBER,
+    CKA_SIGN, CKA_SIGN_RECOVER, CKA_START_DATE, CKA_SUBJECT, CKA_TOKEN, CKA_TRUSTED, CKA_UNWRAP,
+    CKA_VALUE, CKA_VALUE_LEN, CKA_VERIFY, CKA_VERIFY_RECOVER, CKA_WRAP, CKC_X_509,
 };
 use strum_macros::Display;
 
-use crate::{ModuleError, ModuleResult, not_null};
+use crate::{ModuleError, ModuleResult, not_null, traits::backend_for};
 
 #[derive(Debug, Display, PartialEq, Eq, Clone, Copy)]
 pub enum AttributeType {
+    AllowedMechanisms,
     AlwaysAuthenticate,
     AlwaysSensitive,
     Application,
@@ -47,17 +49,21 @@ pub enum AttributeType {
     Class,
     Coefficient,
     Decrypt,
+    Derive,
     /// DER-encoding of an ANSI X9.62 Parameters value
     EcParams,
     EcPoint,
     Encrypt,
+    EndDate,
     Exponent1,
     Exponent2,
     Extractable,
     Id,
     Issuer,
+    KeyGenMechanism,
     KeyType,
     Label,
+    Local,
     Modulus,
     ModulusBits,
     Modifiable,
@@ -72,6 +78,7 @@ pub enum AttributeType {
     SerialNumber,
     Sign,
     SignRecover,
+    StartDate,
     Subject,
     Token,
     Trusted,
@@ -88,6 +95,7 @@ impl TryFrom<CK_ATTRIBUTE_TYPE> for AttributeType {
 
     fn try_from(type_: CK_ATTRIBUTE_TYPE) -> ModuleResult<Self> {
         match type_ {
+            CKA_ALLOWED_MECHANISMS => Ok(Self::AllowedMechanisms),
             CKA_ALWAYS_AUTHENTICATE => Ok(Self::AlwaysAuthenticate),
             CKA_ALWAYS_SENSITIVE => Ok(Self::AlwaysSensitive),
             CKA_APPLICATION => Ok(Self::Application),
@@ -96,16 +104,20 @@ impl TryFrom<CK_ATTRIBUTE_TYPE> for AttributeType {
             CKA_CLASS => Ok(Self::Class),
             CKA_COEFFICIENT => Ok(Self::Coefficient),
             CKA_DECRYPT => Ok(Self::Decrypt),
+            CKA_DERIVE => Ok(Self::Derive),
             CKA_EC_PARAMS => Ok(Self::EcParams),
             CKA_EC_POINT => Ok(Self::EcPoint),
             CKA_ENCRYPT => Ok(Self::Encrypt),
+            CKA_END_DATE => Ok(Self::EndDate),
             CKA_EXPONENT_1 => Ok(Self::Exponent1),
             CKA_EXPONENT_2 => Ok(Self::Exponent2),
             CKA_EXTRACTABLE => Ok(Self::Extractable),
             CKA_ID => Ok(Self::Id),
             CKA_ISSUER => Ok(Self::Issuer),
+            CKA_KEY_GEN_MECHANISM => Ok(Self::KeyGenMechanism),
             CKA_KEY_TYPE => Ok(Self::KeyType),
             CKA_LABEL => Ok(Self::Label),
+            CKA_LOCAL => Ok(Self::Local),
             CKA_MODULUS => Ok(Self::Modulus),
             CKA_MODULUS_BITS => Ok(Self::ModulusBits),
             CKA_NEVER_EXTRACTABLE => Ok(Self::NeverExtractable),
@@ -119,6 +131,7 @@ impl TryFrom<CK_ATTRIBUTE_TYPE> for AttributeType {
             CKA_SIGN => Ok(Self::Sign),
             CKA_SIGN_RECOVER => Ok(Self::SignRecover),
             CKA_SERIAL_NUMBER => Ok(Self::SerialNumber),
+            CKA_START_DATE => Ok(Self::StartDate),
             CKA_SUBJECT => Ok(Self::Subject),
             CKA_TOKEN => Ok(Self::Token),
             CKA_TRUSTED => Ok(Self::Trusted),
@@ -136,6 +149,7 @@ impl TryFrom<CK_ATTRIBUTE_TYPE> for AttributeType {
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Attribute {
+    AllowedMechanisms(Vec<CK_MECHANISM_TYPE>),
     AlwaysAuthenticate(bool),
     AlwaysSensitive(bool),
     Application(Vec<u8>),
@@ -144,17 +158,22 @@ pub enum Attribute {
     Class(CK_OBJECT_CLASS),
     Coefficient(Vec<u8>),
     Decrypt(bool),
+    Derive(bool),
     /// DER-encoding of an ANSI X9.62 Parameters value
     EcParams(Vec<u8>),
     EcPoint(Vec<u8>),
     Encrypt(bool),
+    /// A `CK_DATE` as its 8 `YYYYMMDD` characters, or empty
+    EndDate(Vec<u8>),
     Exponent1(Vec<u8>),
     Exponent2(Vec<u8>),
     Extractable(bool),
     Id(Vec<u8>),
     Issuer(Vec<u8>),
+    KeyGenMechanism(CK_MECHANISM_TYPE),
     KeyType(CK_KEY_TYPE),
     Label(String),
+    Local(bool),
     Modulus(Vec<u8>),
     Modifiable(Vec<u8>),
     ModulusBits(CK_ULONG),
@@ -169,6 +188,8 @@ pub enum Attribute {
     SerialNumber(Vec<u8>),
     Sign(bool),
     SignRecover(bool),
+    /// A `CK_DATE` as its 8 `YYYYMMDD` characters, or empty
+    StartDate(Vec<u8>),
     Subject(Vec<u8>),
     Token(bool),
     Trusted(bool),
@@ -184,6 +205,7 @@ impl Attribute {
     #[must_use]
     pub const fn attribute_type(&self) -> AttributeType {
         match self {
+            Self::AllowedMechanisms(_) => AttributeType::AllowedMechanisms,
             Self::AlwaysAuthenticate(_) => AttributeType::AlwaysAuthenticate,
             Self::AlwaysSensitive(_) => AttributeType::AlwaysSensitive,
             Self::Application(_) => AttributeType::Application,
@@ -192,16 +214,20 @@ impl Attribute {
             Self::Class(_) => AttributeType::Class,
             Self::Coefficient(_) => AttributeType::Coefficient,
             Self::Decrypt(_) => AttributeType::Decrypt,
+            Self::Derive(_) => AttributeType::Derive,
             Self::EcParams(_) => AttributeType::EcParams,
             Self::EcPoint(_) => AttributeType::EcPoint,
             Self::Encrypt(_) => AttributeType::Encrypt,
+            Self::EndDate(_) => AttributeType::EndDate,
             Self::Exponent1(_) => AttributeType::Exponent1,
             Self::Exponent2(_) => AttributeType::Exponent2,
             Self::Extractable(_) => AttributeType::Extractable,
             Self::Id(_) => AttributeType::Id,
             Self::Issuer(_) => AttributeType::Issuer,
+            Self::KeyGenMechanism(_) => AttributeType::KeyGenMechanism,
             Self::KeyType(_) => AttributeType::KeyType,
             Self::Label(_) => AttributeType::Label,
+            Self::Local(_) => AttributeType::Local,
             Self::Modifiable(_) => AttributeType::Modifiable,
             Self::Modulus(_) => AttributeType::Modulus,
             Self::ModulusBits(_) => AttributeType::ModulusBits,
@@ -216,6 +242,7 @@ impl Attribute {
             Self::SerialNumber(_) => AttributeType::SerialNumber,
             Self::Sign(_) => AttributeType::Sign,
             Self::SignRecover(_) => AttributeType::SignRecover,
+            Self::StartDate(_) => AttributeType::StartDate,
             Self::Subject(_) => AttributeType::Subject,
             Self::Token(_) => AttributeType::Token,
             Self::Trusted(_) => AttributeType::Trusted,
@@ -234,8 +261,10 @@ impl Attribute {
             Self::AlwaysAuthenticate(bool)
             | Self::AlwaysSensitive(bool)
             | Self::Decrypt(bool)
+            | Self::Derive(bool)
             | Self::Encrypt(bool)
             | Self::Extractable(bool)
+            | Self::Local(bool)
             | Self::NeverExtractable(bool)
             | Self::Private(bool)
             | Self::Sensitive(bool)
@@ -252,6 +281,7 @@ impl Attribute {
             Self::CertificateCategory(int)
             | Self::CertificateType(int)
             | Self::Class(int)
+            | Self::KeyGenMechanism(int)
             | Self::KeyType(int)
             | Self::ModulusBits(int)
             | Self::ProfileId(int)
@@ -259,6 +289,7 @@ impl Attribute {
             Self::Coefficient(bytes)
             | Self::EcParams(bytes)
             | Self::EcPoint(bytes)
+            | Self::EndDate(bytes)
             | Self::Exponent1(bytes)
             | Self::Exponent2(bytes)
             | Self::Issuer(bytes)
@@ -269,11 +300,16 @@ impl Attribute {
             | Self::PrivateExponent(bytes)
             | Self::PublicExponent(bytes)
             | Self::SerialNumber(bytes)
+            | Self::StartDate(bytes)
             | Self::Subject(bytes)
             | Self::Id(bytes)
             | Self::Value(bytes)
             | Self::Application(bytes) => bytes.clone(),
             Self::Label(string) => string.as_bytes().to_vec(),
+            Self::AllowedMechanisms(mechanisms) => mechanisms
+                .iter()
+                .flat_map(|mechanism| mechanism.to_ne_bytes())
+                .collect(),
         }
     }
 }
@@ -299,6 +335,9 @@ impl TryFrom<CK_ATTRIBUTE> for Attribute {
         trace!("Attribute::try_from: value: {val:?}");
 
         let attr = match attr_type {
+            AttributeType::AllowedMechanisms => Ok(Self::AllowedMechanisms(
+                try_u8_into_mechanisms(attr_type, val)?,
+            )),
             AttributeType::AlwaysAuthenticate => {
                 Ok(Self::AlwaysAuthenticate(try_u8_into_bool(val)?))
             }
@@ -315,18 +354,24 @@ impl TryFrom<CK_ATTRIBUTE> for Attribute {
             }
             AttributeType::Coefficient => Ok(Self::Coefficient(val.to_vec())),
             AttributeType::Decrypt => Ok(Self::Decrypt(try_u8_into_bool(val)?)),
+            AttributeType::Derive => Ok(Self::Derive(try_u8_into_bool(val)?)),
             AttributeType::EcParams => Ok(Self::EcParams(val.to_vec())),
             AttributeType::EcPoint => Ok(Self::EcPoint(val.to_vec())),
             AttributeType::Encrypt => Ok(Self::Encrypt(try_u8_into_bool(val)?)),
+            AttributeType::EndDate => Ok(Self::EndDate(try_u8_into_date(attr_type, val)?)),
             AttributeType::Exponent1 => Ok(Self::Exponent1(val.to_vec())),
             AttributeType::Exponent2 => Ok(Self::Exponent2(val.to_vec())),
             AttributeType::Extractable => Ok(Self::Extractable(try_u8_into_bool(val)?)),
             AttributeType::Id => Ok(Self::Id(val.to_vec())),
             AttributeType::Issuer => Ok(Self::Issuer(val.to_vec())),
+            AttributeType::KeyGenMechanism => Ok(Self::KeyGenMechanism(
+                CK_MECHANISM_TYPE::from_ne_bytes(val.try_into()?),
+            )),
             AttributeType::KeyType => {
                 Ok(Self::KeyType(CK_KEY_TYPE::from_ne_bytes(val.try_into()?)))
             }
             AttributeType::Label => Ok(Self::Label(String::from_utf8(val.to_vec())?)),
+            AttributeType::Local => Ok(Self::Local(try_u8_into_bool(val)?)),
             AttributeType::Modulus => Ok(Self::Modulus(val.to_vec())),
             AttributeType::Modifiable => Ok(Self::Modifiable(val.to_vec())),
             AttributeType::ModulusBits => {
@@ -346,6 +391,7 @@ impl TryFrom<CK_ATTRIBUTE> for Attribute {
             AttributeType::Subject => Ok(Self::Subject(val.to_vec())),
             AttributeType::Sign => Ok(Self::Sign(try_u8_into_bool(val)?)),
             AttributeType::SignRecover => Ok(Self::SignRecover(try_u8_into_bool(val)?)),
+            AttributeType::StartDate => Ok(Self::StartDate(try_u8_into_date(attr_type, val)?)),
             AttributeType::Token => Ok(Self::Token(try_u8_into_bool(val)?)),
             AttributeType::Trusted => Ok(Self::Trusted(try_u8_into_bool(val)?)),
             AttributeType::Unwrap => Ok(Self::Unwrap(try_u8_into_bool(val)?)),
@@ -372,6 +418,28 @@ fn try_u8_into_bool(slice: &[u8]) -> ModuleResult<bool> {
     Ok(!matches!(as_byte, 0_u8))
 }
 
+/// A `CK_DATE` is either empty or made of the 8 digits of `YYYYMMDD`
+fn try_u8_into_date(attribute_type: AttributeType, slice: &[u8]) -> ModuleResult<Vec<u8>> {
+    if !slice.is_empty() && (slice.len() != 8 || !slice.iter().all(u8::is_ascii_digit)) {
+        return Err(ModuleError::AttributeValueInvalid(attribute_type));
+    }
+    Ok(slice.to_vec())
+}
+
+/// A `CK_MECHANISM_TYPE_PTR` array, as the concatenation of its mechanism types
+fn try_u8_into_mechanisms(
+    attribute_type: AttributeType,
+    slice: &[u8],
+) -> ModuleResult<Vec<CK_MECHANISM_TYPE>> {
+    let chunks = slice.chunks_exact(size_of::<CK_MECHANISM_TYPE>());
+    if !chunks.remainder().is_empty() {
+        return Err(ModuleError::AttributeValueInvalid(attribute_type));
+    }
+    chunks
+        .map(|chunk| Ok(CK_MECHANISM_TYPE::from_ne_bytes(chunk.try_into()?)))
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct Attributes(Vec<Attribute>);
 
@@ -401,6 +469,66 @@ impl Attributes {
 
     get_attribute!(get_sensitive, AttributeType::Sensitive, Sensitive, bool);
 
+    get_attribute!(
+        get_extractable,
+        AttributeType::Extractable,
+        Extractable,
+        bool
+    );
+
+    get_attribute!(get_id, AttributeType::Id, Id, Vec<u8>);
+
+    get_attribute!(get_token, AttributeType::Token, Token, bool);
+
+    get_attribute!(
+        get_modulus_bits,
+        AttributeType::ModulusBits,
+        ModulusBits,
+        CK_ULONG
+    );
+
+    get_attribute!(get_ec_params, AttributeType::EcParams, EcParams, Vec<u8>);
+
+    get_attribute!(get_key_type, AttributeType::KeyType, KeyType, CK_KEY_TYPE);
+
+    get_attribute!(get_modulus, AttributeType::Modulus, Modulus, Vec<u8>);
+
+    get_attribute!(
+        get_public_exponent,
+        AttributeType::PublicExponent,
+        PublicExponent,
+        Vec<u8>
+    );
+
+    get_attribute!(
+        get_private_exponent,
+        AttributeType::PrivateExponent,
+        PrivateExponent,
+        Vec<u8>
+    );
+
+    get_attribute!(get_prime_1, AttributeType::Prime1, Prime1, Vec<u8>);
+
+    get_attribute!(get_prime_2, AttributeType::Prime2, Prime2, Vec<u8>);
+
+    /// The `CKA_EXTRACTABLE` of a key created through the token,
+    /// the policy of the backend of the slot `slot` when the template does not set it
+    pub(crate) fn extractable_or_default(&self, slot: CK_SLOT_ID) -> ModuleResult<bool> {
+        self.get_extractable()
+            .or_else(|_| backend_for(slot).map(|backend| backend.extractable_by_default()))
+    }
+
+    /// Ensure that the template contains all the `attribute_types`
+    /// required to create an object.
+    pub(crate) fn ensure_present(&self, attribute_types: &[AttributeType]) -> ModuleResult<()> {
+        attribute_types
+            .iter()
+            .find(|&&attribute_type| self.get(attribute_type).is_none())
+            .map_or(Ok(()), |&attribute_type| {
+                Err(ModuleError::TemplateIncomplete(attribute_type))
+            })
+    }
+
     #[must_use]
     pub fn get(&self, attribute_type: AttributeType) -> Option<&Attribute> {
         self.0
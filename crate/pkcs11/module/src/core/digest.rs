@@ -0,0 +1,95 @@
+use pkcs11_sys::CK_OBJECT_HANDLE;
+use sha2::Digest;
+
+use crate::{ModuleError, ModuleResult, traits::DigestType};
+
+enum Hasher {
+    Sha1(sha1::Sha1),
+    Sha224(sha2::Sha224),
+    Sha256(sha2::Sha256),
+    Sha384(sha2::Sha384),
+    Sha512(sha2::Sha512),
+}
+
+/// State of a digest operation started by `C_DigestInit`.
+///
+/// The data is hashed locally. The secret keys are hashed by the KMS,
+/// which cannot resume a local digest: a key must be the single part
+/// of the operation.
+pub(crate) struct DigestContext {
+    digest_type: DigestType,
+    hasher: Hasher,
+    /// Whether data has been hashed by `C_DigestUpdate`
+    has_data: bool,
+    /// Digest of the secret key computed by the KMS for `C_DigestKey`
+    key_digest: Option<Vec<u8>>,
+}
+
+impl DigestContext {
+    pub(crate) fn new(digest_type: DigestType) -> Self {
+        let hasher = match digest_type {
+            DigestType::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            DigestType::Sha224 => Hasher::Sha224(sha2::Sha224::new()),
+            DigestType::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            DigestType::Sha384 => Hasher::Sha384(sha2::Sha384::new()),
+            DigestType::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+        };
+        Self {
+            digest_type,
+            hasher,
+            has_data: false,
+            key_digest: None,
+        }
+    }
+
+    pub(crate) const fn digest_type(&self) -> &DigestType {
+        &self.digest_type
+    }
+
+    pub(crate) const fn digest_len(&self) -> usize {
+        self.digest_type.digest_len()
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) -> ModuleResult<()> {
+        if self.key_digest.is_some() {
+            return Err(ModuleError::BadArguments(
+                "digest: data cannot be digested after a secret key".to_owned(),
+            ));
+        }
+        self.has_data = true;
+        match &mut self.hasher {
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha224(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha384(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+        }
+        Ok(())
+    }
+
+    /// Record the digest of the secret key `key_handle` computed by the KMS.
+    pub(crate) fn update_key(
+        &mut self,
+        key_handle: CK_OBJECT_HANDLE,
+        key_digest: Vec<u8>,
+    ) -> ModuleResult<()> {
+        if self.has_data || self.key_digest.is_some() {
+            return Err(ModuleError::KeyIndigestible(key_handle));
+        }
+        self.key_digest = Some(key_digest);
+        Ok(())
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        if let Some(key_digest) = self.key_digest {
+            return key_digest;
+        }
+        match self.hasher {
+            Hasher::Sha1(h) => h.finalize().to_vec(),
+            Hasher::Sha224(h) => h.finalize().to_vec(),
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha384(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+        }
+    }
+}
@@ -21,41 +21,262 @@ use std::slice;
 
 use cosmian_logger::{debug, error};
 use pkcs11_sys::{
-    CK_MECHANISM, CK_MECHANISM_TYPE, CK_RSA_PKCS_PSS_PARAMS, CKG_MGF1_SHA1, CKG_MGF1_SHA224,
-    CKG_MGF1_SHA256, CKG_MGF1_SHA384, CKG_MGF1_SHA512, CKM_AES_CBC, CKM_AES_CBC_PAD,
-    CKM_AES_KEY_GEN, CKM_ECDSA, CKM_RSA_PKCS, CKM_RSA_PKCS_PSS, CKM_SHA_1, CKM_SHA1_RSA_PKCS,
-    CKM_SHA224, CKM_SHA256, CKM_SHA256_RSA_PKCS, CKM_SHA384, CKM_SHA384_RSA_PKCS, CKM_SHA512,
-    CKM_SHA512_RSA_PKCS,
+    CK_AES_CTR_PARAMS, CK_BYTE_PTR, CK_ECDH1_DERIVE_PARAMS, CK_EDDSA_PARAMS, CK_FALSE, CK_FLAGS,
+    CK_GCM_PARAMS, CK_HKDF_PARAMS, CK_KEY_TYPE, CK_MECHANISM, CK_MECHANISM_INFO, CK_MECHANISM_TYPE,
+    CK_PKCS5_PBKD2_PARAMS2, CK_RSA_PKCS_MGF_TYPE, CK_RSA_PKCS_OAEP_PARAMS, CK_RSA_PKCS_PSS_PARAMS,
+    CK_SLOT_ID, CK_ULONG, CKD_NULL, CKF_DECRYPT, CKF_DERIVE, CKF_DIGEST, CKF_ENCRYPT, CKF_GENERATE,
+    CKF_GENERATE_KEY_PAIR, CKF_HKDF_SALT_DATA, CKF_HKDF_SALT_NULL, CKF_SIGN, CKF_UNWRAP,
+    CKF_VERIFY, CKF_WRAP, CKG_MGF1_SHA1, CKG_MGF1_SHA224, CKG_MGF1_SHA256, CKG_MGF1_SHA384,
+    CKG_MGF1_SHA512, CKK_AES, CKK_EC, CKK_EC_EDWARDS, CKK_EC_MONTGOMERY, CKK_GENERIC_SECRET,
+    CKK_RSA, CKM_AES_CBC, CKM_AES_CBC_PAD, CKM_AES_CTR, CKM_AES_GCM, CKM_AES_KEY_GEN,
+    CKM_AES_KEY_WRAP, CKM_AES_KEY_WRAP_PAD, CKM_EC_EDWARDS_KEY_PAIR_GEN, CKM_EC_KEY_PAIR_GEN,
+    CKM_EC_MONTGOMERY_KEY_PAIR_GEN, CKM_ECDH1_DERIVE, CKM_ECDSA, CKM_EDDSA,
+    CKM_GENERIC_SECRET_KEY_GEN, CKM_HKDF_DERIVE, CKM_PKCS5_PBKD2, CKM_RSA_PKCS,
+    CKM_RSA_PKCS_KEY_PAIR_GEN, CKM_RSA_PKCS_OAEP, CKM_RSA_PKCS_PSS, CKM_SHA_1, CKM_SHA_1_HMAC,
+    CKM_SHA1_RSA_PKCS, CKM_SHA224, CKM_SHA224_HMAC, CKM_SHA256, CKM_SHA256_HMAC,
+    CKM_SHA256_RSA_PKCS, CKM_SHA384, CKM_SHA384_HMAC, CKM_SHA384_RSA_PKCS, CKM_SHA512,
+    CKM_SHA512_HMAC, CKM_SHA512_RSA_PKCS, CKP_PKCS5_PBKD2_HMAC_SHA1, CKP_PKCS5_PBKD2_HMAC_SHA224,
+    CKP_PKCS5_PBKD2_HMAC_SHA256, CKP_PKCS5_PBKD2_HMAC_SHA384, CKP_PKCS5_PBKD2_HMAC_SHA512,
+    CKZ_DATA_SPECIFIED, CKZ_SALT_SPECIFIED,
 };
 
 use crate::{
-    ModuleError, ModuleResult, not_null,
-    traits::{DigestType, EncryptionAlgorithm, KeyAlgorithm, SignatureAlgorithm},
+    ModuleError, ModuleResult,
+    core::attribute::AttributeType,
+    not_null,
+    traits::{
+        DigestType, EncryptionAlgorithm, KeyAlgorithm, KeyDerivationAlgorithm,
+        KeyWrappingAlgorithm, SignatureAlgorithm, backend_for,
+    },
 };
 
 pub const AES_IV_SIZE: usize = 16;
 
-pub const SUPPORTED_SIGNATURE_MECHANISMS: &[CK_MECHANISM_TYPE] = &[
-    CKM_RSA_PKCS,
-    CKM_SHA1_RSA_PKCS,
-    CKM_SHA256_RSA_PKCS,
-    CKM_SHA384_RSA_PKCS,
-    CKM_SHA512_RSA_PKCS,
-    CKM_ECDSA,
-    CKM_RSA_PKCS_PSS,
+const fn mechanism_info(
+    min_key_size: CK_ULONG,
+    max_key_size: CK_ULONG,
+    flags: CK_FLAGS,
+) -> CK_MECHANISM_INFO {
+    CK_MECHANISM_INFO {
+        ulMinKeySize: min_key_size,
+        ulMaxKeySize: max_key_size,
+        flags,
+    }
+}
+
+/// The registry of the mechanisms supported by the module.
+/// The AES key sizes are in bytes, the RSA and EC key sizes in bits.
+pub const SUPPORTED_MECHANISMS: &[(CK_MECHANISM_TYPE, CK_MECHANISM_INFO)] = &[
+    (CKM_AES_KEY_GEN, mechanism_info(16, 32, CKF_GENERATE)),
+    (
+        CKM_GENERIC_SECRET_KEY_GEN,
+        mechanism_info(16, 64, CKF_GENERATE),
+    ),
+    (
+        CKM_AES_CBC,
+        mechanism_info(16, 32, CKF_ENCRYPT | CKF_DECRYPT),
+    ),
+    (
+        CKM_AES_CBC_PAD,
+        mechanism_info(16, 32, CKF_ENCRYPT | CKF_DECRYPT),
+    ),
+    (
+        CKM_AES_CTR,
+        mechanism_info(16, 32, CKF_ENCRYPT | CKF_DECRYPT),
+    ),
+    (
+        CKM_AES_GCM,
+        mechanism_info(16, 32, CKF_ENCRYPT | CKF_DECRYPT),
+    ),
+    (
+        CKM_AES_KEY_WRAP,
+        mechanism_info(16, 32, CKF_WRAP | CKF_UNWRAP),
+    ),
+    (
+        CKM_AES_KEY_WRAP_PAD,
+        mechanism_info(16, 32, CKF_WRAP | CKF_UNWRAP),
+    ),
+    (
+        CKM_RSA_PKCS_KEY_PAIR_GEN,
+        mechanism_info(2048, 4096, CKF_GENERATE_KEY_PAIR),
+    ),
+    (
+        CKM_RSA_PKCS,
+        mechanism_info(
+            2048,
+            4096,
+            CKF_ENCRYPT | CKF_DECRYPT | CKF_SIGN | CKF_VERIFY,
+        ),
+    ),
+    (
+        CKM_SHA1_RSA_PKCS,
+        mechanism_info(2048, 4096, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA256_RSA_PKCS,
+        mechanism_info(2048, 4096, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA384_RSA_PKCS,
+        mechanism_info(2048, 4096, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA512_RSA_PKCS,
+        mechanism_info(2048, 4096, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_RSA_PKCS_PSS,
+        mechanism_info(2048, 4096, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_RSA_PKCS_OAEP,
+        mechanism_info(
+            2048,
+            4096,
+            CKF_ENCRYPT | CKF_DECRYPT | CKF_WRAP | CKF_UNWRAP,
+        ),
+    ),
+    (
+        CKM_EC_KEY_PAIR_GEN,
+        mechanism_info(256, 521, CKF_GENERATE_KEY_PAIR),
+    ),
+    (CKM_ECDSA, mechanism_info(256, 521, CKF_SIGN | CKF_VERIFY)),
+    (
+        CKM_EC_EDWARDS_KEY_PAIR_GEN,
+        mechanism_info(255, 448, CKF_GENERATE_KEY_PAIR),
+    ),
+    (CKM_EDDSA, mechanism_info(255, 448, CKF_SIGN | CKF_VERIFY)),
+    (
+        CKM_EC_MONTGOMERY_KEY_PAIR_GEN,
+        mechanism_info(255, 448, CKF_GENERATE_KEY_PAIR),
+    ),
+    (CKM_ECDH1_DERIVE, mechanism_info(256, 256, CKF_DERIVE)),
+    (CKM_HKDF_DERIVE, mechanism_info(0, 0, CKF_DERIVE)),
+    (CKM_PKCS5_PBKD2, mechanism_info(0, 0, CKF_DERIVE)),
+    (
+        CKM_SHA_1_HMAC,
+        mechanism_info(16, 64, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA224_HMAC,
+        mechanism_info(16, 64, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA256_HMAC,
+        mechanism_info(16, 64, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA384_HMAC,
+        mechanism_info(16, 64, CKF_SIGN | CKF_VERIFY),
+    ),
+    (
+        CKM_SHA512_HMAC,
+        mechanism_info(16, 64, CKF_SIGN | CKF_VERIFY),
+    ),
+    (CKM_SHA_1, mechanism_info(0, 0, CKF_DIGEST)),
+    (CKM_SHA224, mechanism_info(0, 0, CKF_DIGEST)),
+    (CKM_SHA256, mechanism_info(0, 0, CKF_DIGEST)),
+    (CKM_SHA384, mechanism_info(0, 0, CKF_DIGEST)),
+    (CKM_SHA512, mechanism_info(0, 0, CKF_DIGEST)),
 ];
 
+/// The mechanisms of the registry, as narrowed by the backend of the slot `slot`.
+pub(crate) fn supported_mechanisms(
+    slot: CK_SLOT_ID,
+) -> ModuleResult<Vec<(CK_MECHANISM_TYPE, CK_MECHANISM_INFO)>> {
+    let backend = backend_for(slot)?;
+    Ok(SUPPORTED_MECHANISMS
+        .iter()
+        .filter_map(|(mechanism, info)| {
+            backend
+                .mechanism_info(*mechanism, *info)
+                .map(|info| (*mechanism, info))
+        })
+        .collect())
+}
+
+/// The type of the keys a mechanism of the registry operates on,
+/// `None` for the digests and the password-based derivations.
+pub(crate) const fn mechanism_key_type(mechanism: CK_MECHANISM_TYPE) -> Option<CK_KEY_TYPE> {
+    match mechanism {
+        CKM_AES_KEY_GEN | CKM_AES_CBC | CKM_AES_CBC_PAD | CKM_AES_CTR | CKM_AES_GCM
+        | CKM_AES_KEY_WRAP | CKM_AES_KEY_WRAP_PAD => Some(CKK_AES),
+        CKM_RSA_PKCS_KEY_PAIR_GEN
+        | CKM_RSA_PKCS
+        | CKM_SHA1_RSA_PKCS
+        | CKM_SHA256_RSA_PKCS
+        | CKM_SHA384_RSA_PKCS
+        | CKM_SHA512_RSA_PKCS
+        | CKM_RSA_PKCS_PSS
+        | CKM_RSA_PKCS_OAEP => Some(CKK_RSA),
+        CKM_EC_KEY_PAIR_GEN | CKM_ECDSA | CKM_ECDH1_DERIVE => Some(CKK_EC),
+        CKM_EC_EDWARDS_KEY_PAIR_GEN | CKM_EDDSA => Some(CKK_EC_EDWARDS),
+        CKM_EC_MONTGOMERY_KEY_PAIR_GEN => Some(CKK_EC_MONTGOMERY),
+        CKM_GENERIC_SECRET_KEY_GEN
+        | CKM_HKDF_DERIVE
+        | CKM_SHA_1_HMAC
+        | CKM_SHA224_HMAC
+        | CKM_SHA256_HMAC
+        | CKM_SHA384_HMAC
+        | CKM_SHA512_HMAC => Some(CKK_GENERIC_SECRET),
+        _ => None,
+    }
+}
+
+/// The supported mechanisms which a key of type `key_type` may be used with,
+/// given the mechanism flags of its usages (`CKA_ALLOWED_MECHANISMS`).
+pub(crate) fn allowed_mechanisms(
+    slot: CK_SLOT_ID,
+    key_type: CK_KEY_TYPE,
+    usage: CK_FLAGS,
+) -> ModuleResult<Vec<CK_MECHANISM_TYPE>> {
+    Ok(supported_mechanisms(slot)?
+        .into_iter()
+        .filter(|(mechanism, info)| {
+            mechanism_key_type(*mechanism) == Some(key_type) && info.flags & usage != 0
+        })
+        .map(|(mechanism, _)| mechanism)
+        .collect())
+}
+
+/// The mechanism which generates the keys of type `key_type` (`CKA_KEY_GEN_MECHANISM`).
+pub(crate) const fn key_gen_mechanism(key_type: CK_KEY_TYPE) -> Option<CK_MECHANISM_TYPE> {
+    match key_type {
+        CKK_AES => Some(CKM_AES_KEY_GEN),
+        CKK_RSA => Some(CKM_RSA_PKCS_KEY_PAIR_GEN),
+        CKK_EC => Some(CKM_EC_KEY_PAIR_GEN),
+        CKK_EC_EDWARDS => Some(CKM_EC_EDWARDS_KEY_PAIR_GEN),
+        CKK_EC_MONTGOMERY => Some(CKM_EC_MONTGOMERY_KEY_PAIR_GEN),
+        CKK_GENERIC_SECRET => Some(CKM_GENERIC_SECRET_KEY_GEN),
+        _ => None,
+    }
+}
+
 #[derive(Debug)]
 pub enum Mechanism {
     AesKeyGen,
+    GenericSecretKeyGen,
     AesCbc {
         iv: [u8; AES_IV_SIZE],
     },
     AesCbcPad {
         iv: [u8; AES_IV_SIZE],
     },
+    AesCtr {
+        iv: [u8; AES_IV_SIZE],
+    },
+    AesGcm {
+        iv: Vec<u8>,
+        aad: Vec<u8>,
+        tag_length: usize,
+    },
     Ecdsa,
+    EcKeyPairGen,
+    EcEdwardsKeyPairGen,
+    EcMontgomeryKeyPairGen,
+    EdDsa,
     RsaPkcs,
+    RsaPkcsKeyPairGen,
     RsaPkcsSha1,
     RsaPkcsSha256,
     RsaPkcsSha384,
@@ -65,6 +286,57 @@ pub enum Mechanism {
         mask_generation_function: DigestType,
         salt_length: u64,
     },
+    AesKeyWrap,
+    AesKeyWrapPad,
+    RsaPkcsOaep {
+        digest_algorithm: DigestType,
+        mask_generation_function: DigestType,
+    },
+    Ecdh1Derive {
+        public_data: Vec<u8>,
+    },
+    HkdfDerive {
+        digest_algorithm: DigestType,
+        salt: Vec<u8>,
+        info: Vec<u8>,
+    },
+    Pkcs5Pbkd2 {
+        digest_algorithm: DigestType,
+        salt: Vec<u8>,
+        iterations: u64,
+    },
+    Digest {
+        digest_algorithm: DigestType,
+    },
+    Hmac {
+        digest_algorithm: DigestType,
+    },
+}
+
+impl Mechanism {
+    /// The IV and the additional authenticated data of the AES encryption mechanisms.
+    pub(crate) fn aes_iv_and_aad(&self) -> ModuleResult<(Vec<u8>, Option<Vec<u8>>)> {
+        match self {
+            Self::AesCbcPad { iv } | Self::AesCbc { iv } | Self::AesCtr { iv } => {
+                Ok((iv.to_vec(), None))
+            }
+            Self::AesGcm { iv, aad, .. } => Ok((iv.clone(), Some(aad.clone()))),
+            mech => Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(mech))),
+        }
+    }
+
+    /// The algorithm of the secret key of `key_length` bytes generated by the mechanism
+    pub(crate) fn key_gen_algorithm(&self, key_length: usize) -> ModuleResult<KeyAlgorithm> {
+        match self {
+            Self::AesKeyGen => KeyAlgorithm::aes(key_length)
+                .ok_or(ModuleError::AttributeValueInvalid(AttributeType::ValueLen)),
+            Self::GenericSecretKeyGen if key_length > 0 => Ok(KeyAlgorithm::GenericSecret),
+            Self::GenericSecretKeyGen => {
+                Err(ModuleError::AttributeValueInvalid(AttributeType::ValueLen))
+            }
+            mech => Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(mech))),
+        }
+    }
 }
 
 #[expect(clippy::missing_safety_doc)]
@@ -72,6 +344,22 @@ pub unsafe fn parse_mechanism(mechanism: CK_MECHANISM) -> Result<Mechanism, Modu
     debug!("parse_mechanism: {mechanism:?}");
     match mechanism.mechanism {
         CKM_AES_KEY_GEN => Ok(Mechanism::AesKeyGen),
+        CKM_GENERIC_SECRET_KEY_GEN => Ok(Mechanism::GenericSecretKeyGen),
+        CKM_SHA_1_HMAC => Ok(Mechanism::Hmac {
+            digest_algorithm: DigestType::Sha1,
+        }),
+        CKM_SHA224_HMAC => Ok(Mechanism::Hmac {
+            digest_algorithm: DigestType::Sha224,
+        }),
+        CKM_SHA256_HMAC => Ok(Mechanism::Hmac {
+            digest_algorithm: DigestType::Sha256,
+        }),
+        CKM_SHA384_HMAC => Ok(Mechanism::Hmac {
+            digest_algorithm: DigestType::Sha384,
+        }),
+        CKM_SHA512_HMAC => Ok(Mechanism::Hmac {
+            digest_algorithm: DigestType::Sha512,
+        }),
         CKM_AES_CBC_PAD | CKM_AES_CBC => {
             let iv_slice = unsafe {
                 slice::from_raw_parts(
@@ -93,8 +381,57 @@ pub unsafe fn parse_mechanism(mechanism: CK_MECHANISM) -> Result<Mechanism, Modu
             }
         }
 
+        CKM_AES_GCM => {
+            let mechanism_type = mechanism.mechanism;
+            let params: CK_GCM_PARAMS = unsafe { read_parameters(&mechanism) }?;
+            let tag_bits = params.ulTagBits;
+            if tag_bits == 0 || tag_bits > 128 || tag_bits % 8 != 0 {
+                error!("Unsupported AES GCM tag length: {tag_bits} bits");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+            let iv = unsafe { read_bytes(params.pIv, params.ulIvLen) }?;
+            if iv.is_empty() {
+                error!("parse_mechanism: missing AES GCM IV");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+            Ok(Mechanism::AesGcm {
+                iv,
+                aad: unsafe { read_bytes(params.pAAD, params.ulAADLen) }?,
+                tag_length: usize::try_from(tag_bits / 8)?,
+            })
+        }
+        CKM_AES_CTR => {
+            let params: CK_AES_CTR_PARAMS = unsafe { read_parameters(&mechanism) }?;
+            // The KMS increments the whole counter block
+            if params.ulCounterBits != 128 {
+                error!(
+                    "parse_mechanism: unsupported AES CTR counter size: {} bits",
+                    params.ulCounterBits
+                );
+                return Err(ModuleError::MechanismParamInvalid(CKM_AES_CTR));
+            }
+            Ok(Mechanism::AesCtr { iv: params.cb })
+        }
         CKM_ECDSA => Ok(Mechanism::Ecdsa),
+        CKM_EC_KEY_PAIR_GEN => Ok(Mechanism::EcKeyPairGen),
+        CKM_EC_EDWARDS_KEY_PAIR_GEN => Ok(Mechanism::EcEdwardsKeyPairGen),
+        CKM_EC_MONTGOMERY_KEY_PAIR_GEN => Ok(Mechanism::EcMontgomeryKeyPairGen),
+        CKM_EDDSA => {
+            let mechanism_type = mechanism.mechanism;
+            let parameter_ptr = mechanism.pParameter;
+            // The parameters are optional: only pure EdDSA, without pre-hashing
+            // nor context, is supported
+            if !parameter_ptr.is_null() {
+                let params: CK_EDDSA_PARAMS = unsafe { read_parameters(&mechanism) }?;
+                if params.phFlag != 0 || params.ulContextDataLen != 0 {
+                    error!("parse_mechanism: unsupported EdDSA pre-hashing or context");
+                    return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+                }
+            }
+            Ok(Mechanism::EdDsa)
+        }
         CKM_RSA_PKCS => Ok(Mechanism::RsaPkcs),
+        CKM_RSA_PKCS_KEY_PAIR_GEN => Ok(Mechanism::RsaPkcsKeyPairGen),
         CKM_SHA1_RSA_PKCS => Ok(Mechanism::RsaPkcsSha1),
         CKM_SHA256_RSA_PKCS => Ok(Mechanism::RsaPkcsSha256),
         CKM_SHA384_RSA_PKCS => Ok(Mechanism::RsaPkcsSha384),
@@ -120,29 +457,8 @@ pub unsafe fn parse_mechanism(mechanism: CK_MECHANISM) -> Result<Mechanism, Modu
             let hash_alg = params.hashAlg;
             let salt_len = params.sLen;
 
-            let mgf = match mgf {
-                CKG_MGF1_SHA1 => DigestType::Sha1,
-                CKG_MGF1_SHA224 => DigestType::Sha224,
-                CKG_MGF1_SHA256 => DigestType::Sha256,
-                CKG_MGF1_SHA384 => DigestType::Sha384,
-                CKG_MGF1_SHA512 => DigestType::Sha512,
-                _ => {
-                    error!("Unsupported mgf: {}", mgf);
-                    return Err(ModuleError::MechanismInvalid(mechanism_type));
-                }
-            };
-
-            let hash_alg = match hash_alg {
-                CKM_SHA_1 => DigestType::Sha1,
-                CKM_SHA224 => DigestType::Sha224,
-                CKM_SHA256 => DigestType::Sha256,
-                CKM_SHA384 => DigestType::Sha384,
-                CKM_SHA512 => DigestType::Sha512,
-                _ => {
-                    error!("Unsupported hashAlg: {}", hash_alg);
-                    return Err(ModuleError::MechanismInvalid(mechanism_type));
-                }
-            };
+            let mgf = parse_mgf(mgf, mechanism_type)?;
+            let hash_alg = parse_hash_alg(hash_alg, mechanism_type)?;
 
             #[expect(clippy::unnecessary_cast)]
             Ok(Mechanism::RsaPss {
@@ -152,23 +468,211 @@ pub unsafe fn parse_mechanism(mechanism: CK_MECHANISM) -> Result<Mechanism, Modu
                 salt_length: salt_len as u64,
             })
         }
+        CKM_SHA_1 | CKM_SHA224 | CKM_SHA256 | CKM_SHA384 | CKM_SHA512 => Ok(Mechanism::Digest {
+            digest_algorithm: parse_hash_alg(mechanism.mechanism, mechanism.mechanism)?,
+        }),
+        CKM_AES_KEY_WRAP => Ok(Mechanism::AesKeyWrap),
+        CKM_AES_KEY_WRAP_PAD => Ok(Mechanism::AesKeyWrapPad),
+        CKM_RSA_PKCS_OAEP => {
+            let mechanism_type = mechanism.mechanism;
+            let params: CK_RSA_PKCS_OAEP_PARAMS = unsafe { read_parameters(&mechanism) }?;
+            let label = match params.source {
+                0 => vec![],
+                CKZ_DATA_SPECIFIED => {
+                    unsafe { read_bytes(params.pSourceData.cast::<u8>(), params.ulSourceDataLen) }?
+                }
+                source => {
+                    error!("Unsupported OAEP source: {source}");
+                    return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+                }
+            };
+            // The KMS supports OAEP labels neither for encryption nor for key wrapping
+            if !label.is_empty() {
+                error!("Unsupported OAEP label");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+
+            Ok(Mechanism::RsaPkcsOaep {
+                digest_algorithm: parse_hash_alg(params.hashAlg, mechanism_type)?,
+                mask_generation_function: parse_mgf(params.mgf, mechanism_type)?,
+            })
+        }
+        CKM_ECDH1_DERIVE => {
+            let mechanism_type = mechanism.mechanism;
+            let params: CK_ECDH1_DERIVE_PARAMS = unsafe { read_parameters(&mechanism) }?;
+            let kdf = params.kdf;
+            let shared_data_len = params.ulSharedDataLen;
+            // The shared secret is used as is: no KDF is applied
+            if kdf != CKD_NULL || shared_data_len != 0 {
+                error!("Unsupported ECDH KDF: {kdf}, shared data length: {shared_data_len}");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+            let public_data = unsafe { read_bytes(params.pPublicData, params.ulPublicDataLen) }?;
+            if public_data.is_empty() {
+                error!("parse_mechanism: missing ECDH public data");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+            Ok(Mechanism::Ecdh1Derive { public_data })
+        }
+        CKM_HKDF_DERIVE => {
+            let mechanism_type = mechanism.mechanism;
+            let params: CK_HKDF_PARAMS = unsafe { read_parameters(&mechanism) }?;
+            // The KMS performs both the extract and the expand steps
+            if params.bExtract == CK_FALSE || params.bExpand == CK_FALSE {
+                error!("parse_mechanism: HKDF must both extract and expand");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+            let salt = match params.ulSaltType {
+                CKF_HKDF_SALT_NULL => vec![],
+                CKF_HKDF_SALT_DATA => unsafe { read_bytes(params.pSalt, params.ulSaltLen) }?,
+                salt_type => {
+                    error!("Unsupported HKDF salt type: {salt_type}");
+                    return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+                }
+            };
+            Ok(Mechanism::HkdfDerive {
+                digest_algorithm: parse_hash_alg(params.prfHashMechanism, mechanism_type)?,
+                salt,
+                info: unsafe { read_bytes(params.pInfo, params.ulInfoLen) }?,
+            })
+        }
+        CKM_PKCS5_PBKD2 => {
+            let mechanism_type = mechanism.mechanism;
+            let params: CK_PKCS5_PBKD2_PARAMS2 = unsafe { read_parameters(&mechanism) }?;
+            // The password is the base key
+            let password_len = params.ulPasswordLen;
+            if params.saltSource != CKZ_SALT_SPECIFIED || password_len != 0 {
+                error!("parse_mechanism: unsupported PBKDF2 salt source or password");
+                return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+            }
+            let digest_algorithm = match params.prf {
+                CKP_PKCS5_PBKD2_HMAC_SHA1 => DigestType::Sha1,
+                CKP_PKCS5_PBKD2_HMAC_SHA224 => DigestType::Sha224,
+                CKP_PKCS5_PBKD2_HMAC_SHA256 => DigestType::Sha256,
+                CKP_PKCS5_PBKD2_HMAC_SHA384 => DigestType::Sha384,
+                CKP_PKCS5_PBKD2_HMAC_SHA512 => DigestType::Sha512,
+                prf => {
+                    error!("Unsupported PBKDF2 prf: {prf}");
+                    return Err(ModuleError::MechanismParamInvalid(mechanism_type));
+                }
+            };
+            #[expect(clippy::unnecessary_cast)]
+            Ok(Mechanism::Pkcs5Pbkd2 {
+                digest_algorithm,
+                salt: unsafe {
+                    read_bytes(
+                        params.pSaltSourceData.cast::<u8>(),
+                        params.ulSaltSourceDataLen,
+                    )
+                }?,
+                iterations: params.iterations as u64,
+            })
+        }
         _ => Err(ModuleError::MechanismInvalid(mechanism.mechanism)),
     }
 }
 
+/// Read the mechanism parameters as a `T` structure, after checking their size.
+unsafe fn read_parameters<T>(mechanism: &CK_MECHANISM) -> ModuleResult<T> {
+    //  Bind to locals to prevent unaligned reads https://github.com/rust-lang/rust/issues/82523
+    let mechanism_type = mechanism.mechanism;
+    let parameter_ptr = mechanism.pParameter;
+    let parameter_len = mechanism.ulParameterLen;
+    not_null!(parameter_ptr, "parse_mechanism: parameter_ptr");
+    if (usize::try_from(parameter_len)?) != std::mem::size_of::<T>() {
+        error!(
+            "pParameter incorrect: {} != {}",
+            parameter_len,
+            std::mem::size_of::<T>()
+        );
+        return Err(ModuleError::MechanismInvalid(mechanism_type));
+    }
+    Ok(unsafe { parameter_ptr.cast::<T>().read() })
+}
+
+/// Copy the bytes of a mechanism parameter; a null pointer reads as empty.
+unsafe fn read_bytes(ptr: CK_BYTE_PTR, len: CK_ULONG) -> ModuleResult<Vec<u8>> {
+    if ptr.is_null() {
+        return Ok(vec![]);
+    }
+    Ok(unsafe { slice::from_raw_parts(ptr, usize::try_from(len)?) }.to_vec())
+}
+
+fn parse_mgf(
+    mgf: CK_RSA_PKCS_MGF_TYPE,
+    mechanism_type: CK_MECHANISM_TYPE,
+) -> ModuleResult<DigestType> {
+    match mgf {
+        CKG_MGF1_SHA1 => Ok(DigestType::Sha1),
+        CKG_MGF1_SHA224 => Ok(DigestType::Sha224),
+        CKG_MGF1_SHA256 => Ok(DigestType::Sha256),
+        CKG_MGF1_SHA384 => Ok(DigestType::Sha384),
+        CKG_MGF1_SHA512 => Ok(DigestType::Sha512),
+        _ => {
+            error!("Unsupported mgf: {}", mgf);
+            Err(ModuleError::MechanismInvalid(mechanism_type))
+        }
+    }
+}
+
+fn parse_hash_alg(
+    hash_alg: CK_MECHANISM_TYPE,
+    mechanism_type: CK_MECHANISM_TYPE,
+) -> ModuleResult<DigestType> {
+    match hash_alg {
+        CKM_SHA_1 => Ok(DigestType::Sha1),
+        CKM_SHA224 => Ok(DigestType::Sha224),
+        CKM_SHA256 => Ok(DigestType::Sha256),
+        CKM_SHA384 => Ok(DigestType::Sha384),
+        CKM_SHA512 => Ok(DigestType::Sha512),
+        _ => {
+            error!("Unsupported hashAlg: {}", hash_alg);
+            Err(ModuleError::MechanismInvalid(mechanism_type))
+        }
+    }
+}
+
 impl From<&Mechanism> for CK_MECHANISM_TYPE {
     fn from(mechanism: &Mechanism) -> Self {
         match mechanism {
             Mechanism::AesKeyGen => CKM_AES_KEY_GEN,
+            Mechanism::GenericSecretKeyGen => CKM_GENERIC_SECRET_KEY_GEN,
             Mechanism::AesCbcPad { .. } => CKM_AES_CBC_PAD,
             Mechanism::AesCbc { .. } => CKM_AES_CBC,
+            Mechanism::AesCtr { .. } => CKM_AES_CTR,
+            Mechanism::AesGcm { .. } => CKM_AES_GCM,
             Mechanism::Ecdsa => CKM_ECDSA,
+            Mechanism::EcKeyPairGen => CKM_EC_KEY_PAIR_GEN,
+            Mechanism::EcEdwardsKeyPairGen => CKM_EC_EDWARDS_KEY_PAIR_GEN,
+            Mechanism::EcMontgomeryKeyPairGen => CKM_EC_MONTGOMERY_KEY_PAIR_GEN,
+            Mechanism::EdDsa => CKM_EDDSA,
             Mechanism::RsaPkcs => CKM_RSA_PKCS,
+            Mechanism::RsaPkcsKeyPairGen => CKM_RSA_PKCS_KEY_PAIR_GEN,
             Mechanism::RsaPkcsSha1 => CKM_SHA1_RSA_PKCS,
             Mechanism::RsaPkcsSha256 => CKM_SHA256_RSA_PKCS,
             Mechanism::RsaPkcsSha384 => CKM_SHA384_RSA_PKCS,
             Mechanism::RsaPkcsSha512 => CKM_SHA512_RSA_PKCS,
             Mechanism::RsaPss { .. } => CKM_RSA_PKCS_PSS,
+            Mechanism::AesKeyWrap => CKM_AES_KEY_WRAP,
+            Mechanism::AesKeyWrapPad => CKM_AES_KEY_WRAP_PAD,
+            Mechanism::RsaPkcsOaep { .. } => CKM_RSA_PKCS_OAEP,
+            Mechanism::Ecdh1Derive { .. } => CKM_ECDH1_DERIVE,
+            Mechanism::HkdfDerive { .. } => CKM_HKDF_DERIVE,
+            Mechanism::Pkcs5Pbkd2 { .. } => CKM_PKCS5_PBKD2,
+            Mechanism::Digest { digest_algorithm } => match digest_algorithm {
+                DigestType::Sha1 => CKM_SHA_1,
+                DigestType::Sha224 => CKM_SHA224,
+                DigestType::Sha256 => CKM_SHA256,
+                DigestType::Sha384 => CKM_SHA384,
+                DigestType::Sha512 => CKM_SHA512,
+            },
+            Mechanism::Hmac { digest_algorithm } => match digest_algorithm {
+                DigestType::Sha1 => CKM_SHA_1_HMAC,
+                DigestType::Sha224 => CKM_SHA224_HMAC,
+                DigestType::Sha256 => CKM_SHA256_HMAC,
+                DigestType::Sha384 => CKM_SHA384_HMAC,
+                DigestType::Sha512 => CKM_SHA512_HMAC,
+            },
         }
     }
 }
@@ -179,6 +683,10 @@ impl TryFrom<Mechanism> for SignatureAlgorithm {
     fn try_from(mechanism: Mechanism) -> ModuleResult<Self> {
         match mechanism {
             Mechanism::Ecdsa => Ok(Self::Ecdsa),
+            Mechanism::EdDsa => Ok(Self::EdDsa),
+            Mechanism::Hmac { digest_algorithm } => Ok(Self::Hmac {
+                digest: digest_algorithm,
+            }),
             Mechanism::RsaPkcs => Ok(Self::RsaPkcs1v15Raw),
             Mechanism::RsaPkcsSha1 => Ok(Self::RsaPkcs1v15Sha1),
             Mechanism::RsaPkcsSha256 => Ok(Self::RsaPkcs1v15Sha256),
@@ -206,18 +714,63 @@ impl TryFrom<Mechanism> for EncryptionAlgorithm {
             Mechanism::RsaPkcs => Ok(Self::RsaPkcs1v15),
             Mechanism::AesCbcPad { .. } => Ok(Self::AesCbcPad),
             Mechanism::AesCbc { .. } => Ok(Self::AesCbc),
+            Mechanism::AesCtr { .. } => Ok(Self::AesCtr),
+            Mechanism::AesGcm { tag_length, .. } => Ok(Self::AesGcm { tag_length }),
+            Mechanism::RsaPkcsOaep {
+                digest_algorithm,
+                mask_generation_function,
+            } => Ok(Self::RsaOaep {
+                digest: digest_algorithm,
+                mask_generation_function,
+            }),
             x => Err(ModuleError::AlgorithmNotSupported(format!("{x:?}"))),
         }
     }
 }
 
-impl TryFrom<Mechanism> for KeyAlgorithm {
+impl TryFrom<Mechanism> for KeyWrappingAlgorithm {
     type Error = ModuleError;
 
     fn try_from(mechanism: Mechanism) -> ModuleResult<Self> {
         match mechanism {
-            // When generating AES key where `CKM_AES_KEY_GEN` is given as `Mechanism`, we assume an AES-256 key is required
-            Mechanism::AesKeyGen => Ok(Self::Aes256),
+            Mechanism::AesKeyWrap => Ok(Self::AesKeyWrap),
+            Mechanism::AesKeyWrapPad => Ok(Self::AesKeyWrapPad),
+            Mechanism::RsaPkcsOaep {
+                digest_algorithm,
+                mask_generation_function,
+            } => Ok(Self::RsaOaep {
+                digest: digest_algorithm,
+                mask_generation_function,
+            }),
+            x => Err(ModuleError::AlgorithmNotSupported(format!("{x:?}"))),
+        }
+    }
+}
+
+impl TryFrom<Mechanism> for KeyDerivationAlgorithm {
+    type Error = ModuleError;
+
+    fn try_from(mechanism: Mechanism) -> ModuleResult<Self> {
+        match mechanism {
+            Mechanism::Ecdh1Derive { public_data } => Ok(Self::Ecdh { public_data }),
+            Mechanism::HkdfDerive {
+                digest_algorithm,
+                salt,
+                info,
+            } => Ok(Self::Hkdf {
+                digest: digest_algorithm,
+                salt,
+                info,
+            }),
+            Mechanism::Pkcs5Pbkd2 {
+                digest_algorithm,
+                salt,
+                iterations,
+            } => Ok(Self::Pbkdf2 {
+                digest: digest_algorithm,
+                salt,
+                iterations,
+            }),
             x => Err(ModuleError::AlgorithmNotSupported(format!("{x:?}"))),
         }
     }
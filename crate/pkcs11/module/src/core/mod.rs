@@ -18,5 +18,6 @@
 // limitations under the License.
 
 pub mod attribute;
+pub(crate) mod digest;
 pub mod mechanism;
 pub mod object;
@@ -21,18 +21,69 @@ use std::sync::Arc;
 
 use cosmian_logger::debug;
 use log::error;
-use p256::{elliptic_curve::sec1::ToEncodedPoint, pkcs8::der::Encode};
+use p256::pkcs8::der::Encode;
 use pkcs1::EncodeRsaPrivateKey;
 use pkcs11_sys::{
-    CK_CERTIFICATE_CATEGORY_UNSPECIFIED, CK_PROFILE_ID, CKC_X_509, CKO_CERTIFICATE, CKO_DATA,
-    CKO_PRIVATE_KEY, CKO_PROFILE, CKO_PUBLIC_KEY,
+    CK_CERTIFICATE_CATEGORY_UNSPECIFIED, CK_KEY_TYPE, CK_PROFILE_ID, CK_SLOT_ID,
+    CK_UNAVAILABLE_INFORMATION, CKC_X_509, CKO_CERTIFICATE, CKO_DATA, CKO_PRIVATE_KEY, CKO_PROFILE,
+    CKO_PUBLIC_KEY,
 };
-use rsa::{RsaPrivateKey, pkcs8::DecodePrivateKey, traits::PublicKeyParts};
+use rsa::{RsaPrivateKey, pkcs8::DecodePrivateKey};
 
 use crate::{
     ModuleError, ModuleResult,
-    core::attribute::{Attribute, AttributeType},
-    traits::{Certificate, DataObject, KeyAlgorithm, PrivateKey, PublicKey, SymmetricKey},
+    core::{
+        attribute::{Attribute, AttributeType},
+        mechanism::{allowed_mechanisms, key_gen_mechanism},
+    },
+    traits::{
+        Certificate, DataObject, KeyAlgorithm, KeyMetadata, KeyUsage, PrivateKey, PublicKey,
+        SymmetricKey,
+    },
+};
+
+/// The usages reported for a private key when the backend does not know them
+const PRIVATE_KEY_USAGE: KeyUsage = KeyUsage {
+    encrypt: false,
+    decrypt: true,
+    sign: true,
+    verify: false,
+    wrap: false,
+    unwrap: true,
+    derive: false,
+};
+
+/// The usages reported for a public key when the backend does not know them
+const PUBLIC_KEY_USAGE: KeyUsage = KeyUsage {
+    encrypt: true,
+    decrypt: false,
+    sign: false,
+    verify: true,
+    wrap: true,
+    unwrap: false,
+    derive: false,
+};
+
+/// The usages reported for a symmetric key when the backend does not know them
+const SYMMETRIC_KEY_USAGE: KeyUsage = KeyUsage {
+    encrypt: true,
+    decrypt: true,
+    sign: false,
+    verify: false,
+    wrap: true,
+    unwrap: true,
+    derive: false,
+};
+
+/// The usages reported for a generic secret when the backend does not know them
+const GENERIC_SECRET_USAGE: KeyUsage = KeyUsage {
+    encrypt: false,
+    decrypt: false,
+    sign: true,
+    verify: true,
+    wrap: false,
+    unwrap: false,
+    derive: false,
 };
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -91,8 +142,41 @@ impl Object {
         .to_owned()
     }
 
+    /// Whether `C_SetAttributeValue` can change the attribute `type_` of this object.
+    /// The `CKA_ID` of the certificates and keys can be changed: the backend keeps it
+    /// aside from their remote id, while the `CKA_ID` of a data object is its remote id.
+    #[must_use]
+    pub const fn is_modifiable(&self, type_: AttributeType) -> bool {
+        match self {
+            Self::Certificate(_) => matches!(type_, AttributeType::Label | AttributeType::Id),
+            Self::DataObject(_) => {
+                matches!(type_, AttributeType::Label | AttributeType::Application)
+            }
+            Self::PrivateKey(_) | Self::PublicKey(_) | Self::SymmetricKey(_) => matches!(
+                type_,
+                AttributeType::Label
+                    | AttributeType::Id
+                    | AttributeType::StartDate
+                    | AttributeType::EndDate
+                    | AttributeType::Encrypt
+                    | AttributeType::Decrypt
+                    | AttributeType::Sign
+                    | AttributeType::Verify
+                    | AttributeType::Wrap
+                    | AttributeType::Unwrap
+                    | AttributeType::Derive
+            ),
+            Self::Profile(_) => false,
+        }
+    }
+
+    /// The attribute `type_` of the object, which belongs to the slot `slot`
     #[expect(clippy::too_many_lines)]
-    pub fn attribute(&self, type_: AttributeType) -> ModuleResult<Option<Attribute>> {
+    pub fn attribute(
+        &self,
+        slot: CK_SLOT_ID,
+        type_: AttributeType,
+    ) -> ModuleResult<Option<Attribute>> {
         let attribute = match self {
             Self::Certificate(cert) => match type_ {
                 AttributeType::CertificateCategory => Some(Attribute::CertificateCategory(
@@ -100,9 +184,17 @@ impl Object {
                 )),
                 AttributeType::CertificateType => Some(Attribute::CertificateType(CKC_X_509)),
                 AttributeType::Class => Some(Attribute::Class(CKO_CERTIFICATE)),
-                AttributeType::Id => Some(Attribute::Id(cert.private_key_id().into_bytes())),
+                AttributeType::Id => Some(Attribute::Id(
+                    cert.metadata()
+                        .id
+                        .unwrap_or_else(|| cert.private_key_id().into_bytes()),
+                )),
                 AttributeType::Issuer => cert.issuer().map(Attribute::Issuer).ok(),
-                AttributeType::Label => Some(Attribute::Label("Certificate".to_owned())),
+                AttributeType::Label => Some(Attribute::Label(
+                    cert.metadata()
+                        .label
+                        .unwrap_or_else(|| "Certificate".to_owned()),
+                )),
                 AttributeType::Token => Some(Attribute::Token(true)),
                 AttributeType::Trusted => Some(Attribute::Trusted(true)),
                 AttributeType::SerialNumber => {
@@ -124,23 +216,55 @@ impl Object {
             },
             Self::SymmetricKey(sym_key) => match type_ {
                 AttributeType::Class => Some(Attribute::Class(CKO_DATA)),
-                AttributeType::Id => Some(Attribute::Id(sym_key.remote_id().into_bytes())),
+                AttributeType::Id => Some(Attribute::Id(
+                    sym_key
+                        .metadata()
+                        .id
+                        .unwrap_or_else(|| sym_key.remote_id().into_bytes()),
+                )),
                 AttributeType::KeyType => {
                     Some(Attribute::KeyType(sym_key.algorithm().to_ck_key_type()))
                 }
-                AttributeType::Label => Some(Attribute::Label("Symmetric Key".to_owned())),
+                AttributeType::Label => Some(Attribute::Label(
+                    sym_key
+                        .metadata()
+                        .label
+                        .unwrap_or_else(|| "Symmetric Key".to_owned()),
+                )),
                 AttributeType::Token => Some(Attribute::Token(true)),
+                AttributeType::Value if sym_key.metadata().protects_value() => {
+                    return Err(ModuleError::AttributeSensitive(type_));
+                }
                 AttributeType::Value => Some(Attribute::Value(sym_key.raw_bytes()?.to_vec())),
                 _ => {
-                    error!("symmetric_key: type_ unimplemented: {type_:?}");
-                    None
+                    let metadata = sym_key.metadata();
+                    let default_usage = if sym_key.algorithm() == KeyAlgorithm::GenericSecret {
+                        GENERIC_SECRET_USAGE
+                    } else {
+                        SYMMETRIC_KEY_USAGE
+                    };
+                    secret_key_attribute(type_, &metadata)
+                        .map_or_else(
+                            || {
+                                key_attribute(
+                                    slot,
+                                    type_,
+                                    sym_key.algorithm().to_ck_key_type(),
+                                    &metadata,
+                                    default_usage,
+                                )
+                            },
+                            |attribute| Ok(Some(attribute)),
+                        )?
+                        .or_else(|| {
+                            error!("symmetric_key: type_ unimplemented: {type_:?}");
+                            None
+                        })
                 }
             },
             Self::PrivateKey(private_key) => match type_ {
-                AttributeType::AlwaysSensitive => Some(Attribute::AlwaysSensitive(true)),
                 AttributeType::AlwaysAuthenticate => Some(Attribute::AlwaysAuthenticate(false)),
                 AttributeType::Class => Some(Attribute::Class(CKO_PRIVATE_KEY)),
-                AttributeType::Decrypt => Some(Attribute::Decrypt(true)),
                 AttributeType::EcParams => {
                     let algorithm = private_key.algorithm();
                     match algorithm {
@@ -156,35 +280,35 @@ impl Object {
                         _ => None,
                     }
                 }
-                AttributeType::Extractable => Some(Attribute::Extractable(false)),
-                AttributeType::Id => Some(Attribute::Id(private_key.remote_id().into_bytes())),
+                AttributeType::Id => Some(Attribute::Id(
+                    private_key
+                        .metadata()
+                        .id
+                        .unwrap_or_else(|| private_key.remote_id().into_bytes()),
+                )),
                 AttributeType::KeyType => {
                     Some(Attribute::KeyType(private_key.algorithm().to_ck_key_type()))
                 }
-                AttributeType::Label => Some(Attribute::Label("Private Key".to_owned())),
+                AttributeType::Label => Some(Attribute::Label(
+                    private_key
+                        .metadata()
+                        .label
+                        .unwrap_or_else(|| "Private Key".to_owned()),
+                )),
+                // the public components are read from the public key:
+                // the private key is not exported
                 AttributeType::Modulus => {
-                    let der_bytes = private_key.pkcs8_der_bytes()?;
-                    let sk = RsaPrivateKey::from_pkcs8_der(der_bytes.as_ref()).map_err(|e| {
-                        error!("Failed to fetch the PKCS1 DER bytes: {e:?}");
-                        ModuleError::Cryptography("Failed to fetch the PKCS1 DER bytes".to_owned())
-                    })?;
-                    Some(Attribute::Modulus(sk.n().to_bytes_be()))
+                    Some(Attribute::Modulus(private_key.public_key()?.rsa_modulus()?))
                 }
-                AttributeType::NeverExtractable => Some(Attribute::NeverExtractable(true)),
                 AttributeType::Private => Some(Attribute::Private(true)),
-                AttributeType::PublicExponent => {
-                    let der_bytes = private_key.pkcs8_der_bytes()?;
-                    let sk = RsaPrivateKey::from_pkcs8_der(der_bytes.as_ref()).map_err(|e| {
-                        error!("Failed to fetch the PKCS1 DER bytes: {e:?}");
-                        ModuleError::Cryptography("Failed to fetch the PKCS1 DER bytes".to_owned())
-                    })?;
-                    Some(Attribute::PublicExponent(sk.e().to_bytes_be()))
-                }
-                AttributeType::Sensitive => Some(Attribute::Sensitive(true)),
-                AttributeType::Sign => Some(Attribute::Sign(true)),
+                AttributeType::PublicExponent => Some(Attribute::PublicExponent(
+                    private_key.public_key()?.rsa_public_exponent()?,
+                )),
                 AttributeType::SignRecover => Some(Attribute::SignRecover(false)),
                 AttributeType::Token => Some(Attribute::Token(true)),
-                AttributeType::Unwrap => Some(Attribute::Unwrap(true)),
+                AttributeType::Value if private_key.metadata().protects_value() => {
+                    return Err(ModuleError::AttributeSensitive(type_));
+                }
                 AttributeType::Value => match private_key.algorithm() {
                     KeyAlgorithm::Rsa => {
                         let der_bytes = private_key.pkcs8_der_bytes()?;
@@ -202,7 +326,10 @@ impl Object {
                     KeyAlgorithm::EccP256
                     | KeyAlgorithm::Secp224k1
                     | KeyAlgorithm::Secp256k1
+                    | KeyAlgorithm::Aes128
+                    | KeyAlgorithm::Aes192
                     | KeyAlgorithm::Aes256
+                    | KeyAlgorithm::GenericSecret
                     | KeyAlgorithm::EccP384
                     | KeyAlgorithm::EccP521
                     | KeyAlgorithm::Ed25519
@@ -213,8 +340,24 @@ impl Object {
                     }
                 },
                 _ => {
-                    error!("private_key: type_ unimplemented: {type_:?}");
-                    None
+                    let metadata = private_key.metadata();
+                    secret_key_attribute(type_, &metadata)
+                        .map_or_else(
+                            || {
+                                key_attribute(
+                                    slot,
+                                    type_,
+                                    private_key.algorithm().to_ck_key_type(),
+                                    &metadata,
+                                    PRIVATE_KEY_USAGE,
+                                )
+                            },
+                            |attribute| Ok(Some(attribute)),
+                        )?
+                        .or_else(|| {
+                            error!("private_key: type_ unimplemented: {type_:?}");
+                            None
+                        })
                 }
             },
             Self::Profile(id) => match type_ {
@@ -229,23 +372,26 @@ impl Object {
             },
             Self::PublicKey(pk) => match type_ {
                 AttributeType::Class => Some(Attribute::Class(CKO_PUBLIC_KEY)),
-                AttributeType::Label => Some(Attribute::Label("Public Key".to_owned())),
+                AttributeType::Label => Some(Attribute::Label(
+                    pk.metadata()
+                        .label
+                        .unwrap_or_else(|| "Public Key".to_owned()),
+                )),
                 AttributeType::Modulus => Some(Attribute::Modulus(pk.rsa_modulus()?)),
                 AttributeType::PublicExponent => {
                     Some(Attribute::PublicExponent(pk.rsa_public_exponent()?))
                 }
                 AttributeType::KeyType => Some(Attribute::KeyType(pk.algorithm().to_ck_key_type())),
-                AttributeType::Id => Some(Attribute::Id(pk.remote_id().into_bytes())),
+                AttributeType::Id => Some(Attribute::Id(
+                    pk.metadata()
+                        .id
+                        .unwrap_or_else(|| pk.remote_id().into_bytes()),
+                )),
                 AttributeType::EcPoint => {
                     if !pk.algorithm().is_ecc() {
                         return Ok(None);
                     }
-                    Some(Attribute::EcPoint(
-                        pk.ec_p256_public_key()?
-                            .to_encoded_point(false)
-                            .to_bytes()
-                            .to_vec(),
-                    ))
+                    Some(Attribute::EcPoint(pk.ec_point()?))
                 }
                 AttributeType::EcParams => {
                     if !pk.algorithm().is_ecc() {
@@ -253,19 +399,30 @@ impl Object {
                     }
                     Some(Attribute::EcParams(pk.algorithm().to_oid()?.to_der()?))
                 }
-                _ => {
+                _ => key_attribute(
+                    slot,
+                    type_,
+                    pk.algorithm().to_ck_key_type(),
+                    &pk.metadata(),
+                    PUBLIC_KEY_USAGE,
+                )?
+                .or_else(|| {
                     error!("public_key: type_ unimplemented: {type_:?}");
                     None
-                }
+                }),
             },
             Self::DataObject(data) => match type_ {
                 AttributeType::Class => Some(Attribute::Class(CKO_DATA)),
                 AttributeType::Id => Some(Attribute::Id(data.remote_id().into_bytes())),
                 // TODO(BGR) should we hold zeroizable values here ?
-                AttributeType::Value => Some(Attribute::Value(data.value().to_vec())),
+                AttributeType::Value => Some(Attribute::Value(data.value()?.to_vec())),
                 AttributeType::Application => Some(Attribute::Application(data.application())),
                 AttributeType::Private => Some(Attribute::Private(true)),
-                AttributeType::Label => Some(Attribute::Label("Data Object".to_owned())),
+                AttributeType::Label => Some(Attribute::Label(
+                    data.metadata()
+                        .label
+                        .unwrap_or_else(|| "Data Object".to_owned()),
+                )),
                 _ => {
                     error!("Data object: type_ unimplemented: {type_:?}");
                     None
@@ -281,3 +438,57 @@ impl Object {
         Ok(attribute)
     }
 }
+
+/// The sensitivity and extractability of the private and secret keys.
+/// The history of these attributes is not kept: a key is reported as always sensitive
+/// when it is sensitive, and as never extractable when it is not extractable.
+fn secret_key_attribute(type_: AttributeType, metadata: &KeyMetadata) -> Option<Attribute> {
+    match type_ {
+        AttributeType::Sensitive => Some(Attribute::Sensitive(metadata.is_sensitive())),
+        AttributeType::AlwaysSensitive => Some(Attribute::AlwaysSensitive(metadata.is_sensitive())),
+        AttributeType::Extractable => Some(Attribute::Extractable(metadata.is_extractable())),
+        AttributeType::NeverExtractable => {
+            Some(Attribute::NeverExtractable(!metadata.is_extractable()))
+        }
+        _ => None,
+    }
+}
+
+/// The attributes common to all the keys, derived from their metadata.
+/// The usages fall back to `default_usage` when the backend does not know them.
+fn key_attribute(
+    slot: CK_SLOT_ID,
+    type_: AttributeType,
+    key_type: CK_KEY_TYPE,
+    metadata: &KeyMetadata,
+    default_usage: KeyUsage,
+) -> ModuleResult<Option<Attribute>> {
+    let usage = metadata.usage.unwrap_or(default_usage);
+    let local = metadata.local.unwrap_or(false);
+    let date = |date: Option<&str>| date.unwrap_or_default().as_bytes().to_vec();
+    Ok(match type_ {
+        AttributeType::Encrypt => Some(Attribute::Encrypt(usage.encrypt)),
+        AttributeType::Decrypt => Some(Attribute::Decrypt(usage.decrypt)),
+        AttributeType::Sign => Some(Attribute::Sign(usage.sign)),
+        AttributeType::Verify => Some(Attribute::Verify(usage.verify)),
+        AttributeType::Wrap => Some(Attribute::Wrap(usage.wrap)),
+        AttributeType::Unwrap => Some(Attribute::Unwrap(usage.unwrap)),
+        AttributeType::Derive => Some(Attribute::Derive(usage.derive)),
+        AttributeType::StartDate => {
+            Some(Attribute::StartDate(date(metadata.start_date.as_deref())))
+        }
+        AttributeType::EndDate => Some(Attribute::EndDate(date(metadata.end_date.as_deref()))),
+        AttributeType::Local => Some(Attribute::Local(local)),
+        AttributeType::KeyGenMechanism => Some(Attribute::KeyGenMechanism(
+            key_gen_mechanism(key_type)
+                .filter(|_| local)
+                .unwrap_or(CK_UNAVAILABLE_INFORMATION),
+        )),
+        AttributeType::AllowedMechanisms => Some(Attribute::AllowedMechanisms(allowed_mechanisms(
+            slot,
+            key_type,
+            usage.flags(),
+        )?)),
+        _ => None,
+    })
+}
@@ -20,13 +20,17 @@ use std::sync::{PoisonError, RwLockReadGuard, RwLockWriteGuard};
 // limitations under the License.
 use pkcs11_sys::{
     CK_ATTRIBUTE_TYPE, CK_MECHANISM_TYPE, CK_OBJECT_HANDLE, CK_RV, CK_SESSION_HANDLE, CK_SLOT_ID,
-    CKR_ARGUMENTS_BAD, CKR_ATTRIBUTE_TYPE_INVALID, CKR_ATTRIBUTE_VALUE_INVALID,
-    CKR_BUFFER_TOO_SMALL, CKR_CRYPTOKI_ALREADY_INITIALIZED, CKR_CRYPTOKI_NOT_INITIALIZED,
+    CK_USER_TYPE, CKR_ARGUMENTS_BAD, CKR_ATTRIBUTE_READ_ONLY, CKR_ATTRIBUTE_SENSITIVE,
+    CKR_ATTRIBUTE_TYPE_INVALID, CKR_ATTRIBUTE_VALUE_INVALID, CKR_BUFFER_TOO_SMALL,
+    CKR_CRYPTOKI_ALREADY_INITIALIZED, CKR_CRYPTOKI_NOT_INITIALIZED, CKR_DEVICE_ERROR,
     CKR_FUNCTION_NOT_PARALLEL, CKR_FUNCTION_NOT_SUPPORTED, CKR_GENERAL_ERROR,
-    CKR_KEY_HANDLE_INVALID, CKR_MECHANISM_INVALID, CKR_NEED_TO_CREATE_THREADS,
-    CKR_OBJECT_HANDLE_INVALID, CKR_OPERATION_NOT_INITIALIZED, CKR_RANDOM_NO_RNG,
-    CKR_SESSION_HANDLE_INVALID, CKR_SESSION_PARALLEL_NOT_SUPPORTED, CKR_SLOT_ID_INVALID,
-    CKR_TOKEN_WRITE_PROTECTED,
+    CKR_KEY_FUNCTION_NOT_PERMITTED, CKR_KEY_HANDLE_INVALID, CKR_KEY_INDIGESTIBLE,
+    CKR_KEY_UNEXTRACTABLE, CKR_MECHANISM_INVALID, CKR_MECHANISM_PARAM_INVALID,
+    CKR_NEED_TO_CREATE_THREADS, CKR_OBJECT_HANDLE_INVALID, CKR_OPERATION_NOT_INITIALIZED,
+    CKR_PIN_INCORRECT, CKR_RANDOM_NO_RNG, CKR_SESSION_HANDLE_INVALID,
+    CKR_SESSION_PARALLEL_NOT_SUPPORTED, CKR_SIGNATURE_INVALID, CKR_SLOT_ID_INVALID,
+    CKR_TEMPLATE_INCOMPLETE, CKR_TOKEN_WRITE_PROTECTED, CKR_USER_ALREADY_LOGGED_IN,
+    CKR_USER_NOT_LOGGED_IN, CKR_USER_TYPE_INVALID,
 };
 use thiserror::Error;
 
@@ -42,6 +46,10 @@ pub enum ModuleError {
     // Cryptoki errors.
     #[error("bad arguments: {0}")]
     BadArguments(String),
+    #[error("attribute {0} cannot be modified")]
+    AttributeReadOnly(AttributeType),
+    #[error("attribute {0} is sensitive and cannot be revealed")]
+    AttributeSensitive(AttributeType),
     #[error("{0} is not a valid attribute type")]
     AttributeTypeInvalid(CK_ATTRIBUTE_TYPE),
     #[error("the value for attribute {0} is invalid")]
@@ -52,30 +60,54 @@ pub enum ModuleError {
     CryptokiAlreadyInitialized,
     #[error("cryptoki module has not been initialized")]
     CryptokiNotInitialized,
+    #[error("device error: {0}")]
+    DeviceError(String),
     #[error("function not parallel")]
     FunctionNotParallel,
     #[error("function not supported")]
     FunctionNotSupported,
+    #[error("key {0} does not allow this operation")]
+    KeyFunctionNotPermitted(CK_OBJECT_HANDLE),
     #[error("key handle {0} is invalid")]
     KeyHandleInvalid(CK_OBJECT_HANDLE),
+    #[error("key {0} cannot be digested")]
+    KeyIndigestible(CK_OBJECT_HANDLE),
+    #[error("key {0} is not extractable")]
+    KeyUnextractable(CK_OBJECT_HANDLE),
     #[error("module cannot function without being able to spawn threads")]
     NeedToCreateThreads,
     #[error("{0} is not a valid mechanism")]
     MechanismInvalid(CK_MECHANISM_TYPE),
+    #[error("the parameters of mechanism {0} are invalid")]
+    MechanismParamInvalid(CK_MECHANISM_TYPE),
     #[error("object {0} is invalid")]
     ObjectHandleInvalid(CK_OBJECT_HANDLE),
+    #[error("object {0} was not found in the backend")]
+    ObjectNotFound(String),
     #[error("operation has not been initialized, session: {0}")]
     OperationNotInitialized(CK_SESSION_HANDLE),
+    #[error("the PIN is incorrect")]
+    PinIncorrect,
     #[error("no random number generator")]
     RandomNoRng,
     #[error("session handle {0} is invalid")]
     SessionHandleInvalid(CK_SESSION_HANDLE),
     #[error("token does not support parallel sessions")]
     SessionParallelNotSupported,
+    #[error("signature is invalid")]
+    SignatureInvalid,
     #[error("slot id {0} is invalid")]
     SlotIdInvalid(CK_SLOT_ID),
+    #[error("the template misses the {0} attribute")]
+    TemplateIncomplete(AttributeType),
     #[error("token is write protected")]
     TokenWriteProtected,
+    #[error("the user is already logged in")]
+    UserAlreadyLoggedIn,
+    #[error("the user is not logged in")]
+    UserNotLoggedIn,
+    #[error("{0} is not a valid user type")]
+    UserTypeInvalid(CK_USER_TYPE),
     // Other errors.
     #[error(transparent)]
     FromUtf8(#[from] std::string::FromUtf8Error),
@@ -118,23 +150,38 @@ impl From<ModuleError> for CK_RV {
     fn from(e: ModuleError) -> Self {
         match e {
             ModuleError::BadArguments(_) => CKR_ARGUMENTS_BAD,
+            ModuleError::AttributeReadOnly(_) => CKR_ATTRIBUTE_READ_ONLY,
+            ModuleError::AttributeSensitive(_) => CKR_ATTRIBUTE_SENSITIVE,
             ModuleError::AttributeTypeInvalid(_) => CKR_ATTRIBUTE_TYPE_INVALID,
             ModuleError::AttributeValueInvalid(_) => CKR_ATTRIBUTE_VALUE_INVALID,
             ModuleError::BufferTooSmall => CKR_BUFFER_TOO_SMALL,
             ModuleError::CryptokiAlreadyInitialized => CKR_CRYPTOKI_ALREADY_INITIALIZED,
             ModuleError::CryptokiNotInitialized => CKR_CRYPTOKI_NOT_INITIALIZED,
+            ModuleError::DeviceError(_) => CKR_DEVICE_ERROR,
             ModuleError::FunctionNotParallel => CKR_FUNCTION_NOT_PARALLEL,
             ModuleError::FunctionNotSupported => CKR_FUNCTION_NOT_SUPPORTED,
+            ModuleError::KeyFunctionNotPermitted(_) => CKR_KEY_FUNCTION_NOT_PERMITTED,
             ModuleError::KeyHandleInvalid(_) => CKR_KEY_HANDLE_INVALID,
+            ModuleError::KeyIndigestible(_) => CKR_KEY_INDIGESTIBLE,
+            ModuleError::KeyUnextractable(_) => CKR_KEY_UNEXTRACTABLE,
             ModuleError::MechanismInvalid(_) => CKR_MECHANISM_INVALID,
+            ModuleError::MechanismParamInvalid(_) => CKR_MECHANISM_PARAM_INVALID,
             ModuleError::NeedToCreateThreads => CKR_NEED_TO_CREATE_THREADS,
-            ModuleError::ObjectHandleInvalid(_) => CKR_OBJECT_HANDLE_INVALID,
+            ModuleError::ObjectHandleInvalid(_) | ModuleError::ObjectNotFound(_) => {
+                CKR_OBJECT_HANDLE_INVALID
+            }
             ModuleError::OperationNotInitialized(_) => CKR_OPERATION_NOT_INITIALIZED,
+            ModuleError::PinIncorrect => CKR_PIN_INCORRECT,
             ModuleError::RandomNoRng => CKR_RANDOM_NO_RNG,
             ModuleError::SessionHandleInvalid(_) => CKR_SESSION_HANDLE_INVALID,
             ModuleError::SessionParallelNotSupported => CKR_SESSION_PARALLEL_NOT_SUPPORTED,
+            ModuleError::SignatureInvalid => CKR_SIGNATURE_INVALID,
             ModuleError::SlotIdInvalid(_) => CKR_SLOT_ID_INVALID,
+            ModuleError::TemplateIncomplete(_) => CKR_TEMPLATE_INCOMPLETE,
             ModuleError::TokenWriteProtected => CKR_TOKEN_WRITE_PROTECTED,
+            ModuleError::UserAlreadyLoggedIn => CKR_USER_ALREADY_LOGGED_IN,
+            ModuleError::UserNotLoggedIn => CKR_USER_NOT_LOGGED_IN,
+            ModuleError::UserTypeInvalid(_) => CKR_USER_TYPE_INVALID,
 
             ModuleError::Backend(_)
             | ModuleError::AlgorithmNotSupported(_)
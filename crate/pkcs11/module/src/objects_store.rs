@@ -5,7 +5,7 @@ use std::{
 };
 
 use cosmian_logger::debug;
-use pkcs11_sys::CK_OBJECT_HANDLE;
+use pkcs11_sys::{CK_OBJECT_HANDLE, CK_SESSION_HANDLE, CK_SLOT_ID};
 
 use crate::{
     ModuleError, ModuleResult,
@@ -13,73 +13,140 @@ use crate::{
 };
 
 /// The objects store is a global store for all the objects that are fetched by the PKCS#11 module.
-/// These objects are visible across all the sessions and are not session-specific.
+/// These objects are visible across all the sessions of their slot; session objects are however
+/// evicted when the session which created them is closed.
+///
+/// The handles are unique across the slots, but only valid in their own slot:
+/// the lookups take the slot of the session they are made for.
 pub(crate) static OBJECTS_STORE: std::sync::LazyLock<sync::RwLock<ObjectsStore>> =
     std::sync::LazyLock::new(Default::default);
 
 #[derive(Default, Debug)]
 pub struct ObjectsStore {
-    /// The PKCS#11 objects manipulated by this store; the key is the remote id.
-    pub objects: HashMap<String, (Arc<Object>, CK_OBJECT_HANDLE)>,
-    pub ids: HashMap<CK_OBJECT_HANDLE, Weak<Object>>,
+    /// The PKCS#11 objects manipulated by this store; the key is the slot and the remote id.
+    pub objects: HashMap<(CK_SLOT_ID, String), (Arc<Object>, CK_OBJECT_HANDLE)>,
+    pub ids: HashMap<CK_OBJECT_HANDLE, (CK_SLOT_ID, Weak<Object>)>,
+    /// The session objects (`CKA_TOKEN` false) and the session which owns them.
+    /// The other objects are token objects, which live until they are destroyed.
+    session_objects: HashMap<CK_OBJECT_HANDLE, CK_SESSION_HANDLE>,
+    /// The last allocated handle. Handles are never reused,
+    /// so that a stale handle cannot designate another object.
+    last_handle: CK_OBJECT_HANDLE,
 }
 
 impl ObjectsStore {
-    /// Insert the object
-    pub(crate) fn upsert(&mut self, object: Arc<Object>) -> CK_OBJECT_HANDLE {
+    /// Insert the object of the slot `slot` as a token object
+    pub(crate) fn upsert(&mut self, slot: CK_SLOT_ID, object: Arc<Object>) -> CK_OBJECT_HANDLE {
         // check if the object already exists in the store by searching it by ID
         let id = object.remote_id();
-        if let Some((object, handle)) = self.objects.get_mut(&id) {
+        if let Some((stored, handle)) = self.objects.get_mut(&(slot, id.clone())) {
             debug!("STORE: updating object with remote id: {id} and handle: {handle}");
-            *object = object.clone();
-            self.ids.insert(*handle, Arc::downgrade(object));
+            *stored = object;
+            self.ids.insert(*handle, (slot, Arc::downgrade(stored)));
             return *handle;
         }
-        let handle = if self.ids.is_empty() {
-            1 // start from 1, 0 is reserved for invalid handle
-        } else {
-            1 + self.ids.len() as CK_OBJECT_HANDLE
-        };
-        debug!("STORE: inserting new object with remote id: {id} and handle: {handle}");
-        self.ids.insert(handle, Arc::downgrade(&object));
-        self.objects.insert(id, (object, handle));
+        // start from 1, 0 is reserved for invalid handle
+        self.last_handle += 1;
+        let handle = self.last_handle;
+        debug!(
+            "STORE: inserting new object with remote id: {id} and handle: {handle} in slot: {slot}"
+        );
+        self.ids.insert(handle, (slot, Arc::downgrade(&object)));
+        self.objects.insert((slot, id), (object, handle));
         handle
     }
 
-    pub(crate) fn get_using_handle(&self, handle: CK_OBJECT_HANDLE) -> Option<Arc<Object>> {
-        let weak = self.ids.get(&handle)?;
+    /// Insert the object of the slot `slot` as a session object owned by `session`
+    pub(crate) fn upsert_session_object(
+        &mut self,
+        slot: CK_SLOT_ID,
+        object: Arc<Object>,
+        session: CK_SESSION_HANDLE,
+    ) -> CK_OBJECT_HANDLE {
+        let handle = self.upsert(slot, object);
+        self.session_objects.insert(handle, session);
+        handle
+    }
+
+    /// Remove the session objects owned by `session` and return them
+    pub(crate) fn evict_session_objects(&mut self, session: CK_SESSION_HANDLE) -> Vec<Arc<Object>> {
+        let handles = self
+            .session_objects
+            .iter()
+            .filter(|(_, owner)| **owner == session)
+            .map(|(handle, _)| *handle)
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .filter_map(|handle| {
+                let object = self.ids.get(&handle).and_then(|(_, weak)| weak.upgrade());
+                self.evict_handle(handle);
+                object
+            })
+            .collect()
+    }
+
+    /// Remove the object with this handle, if any
+    pub(crate) fn evict_handle(&mut self, handle: CK_OBJECT_HANDLE) {
+        self.ids.remove(&handle);
+        self.session_objects.remove(&handle);
+        self.objects.retain(|_, (_, h)| *h != handle);
+    }
+
+    pub(crate) fn get_using_handle(
+        &self,
+        slot: CK_SLOT_ID,
+        handle: CK_OBJECT_HANDLE,
+    ) -> Option<Arc<Object>> {
+        let (object_slot, weak) = self.ids.get(&handle)?;
+        if *object_slot != slot {
+            return None;
+        }
         weak.upgrade()
     }
 
-    pub(crate) fn get_using_id(&self, id: &str) -> Option<(Arc<Object>, CK_OBJECT_HANDLE)> {
-        self.objects.get(id).cloned()
+    pub(crate) fn get_using_id(
+        &self,
+        slot: CK_SLOT_ID,
+        id: &str,
+    ) -> Option<(Arc<Object>, CK_OBJECT_HANDLE)> {
+        self.objects.get(&(slot, id.to_owned())).cloned()
     }
 
     /// Get Using he Object Type
     pub(crate) fn get_using_type(
         &self,
+        slot: CK_SLOT_ID,
         object_type: &ObjectType,
     ) -> Vec<(Arc<Object>, CK_OBJECT_HANDLE)> {
         self.objects
             .iter()
-            .filter(|(_, (object, _))| &object.object_type() == object_type)
+            .filter(|((s, _), (object, _))| *s == slot && &object.object_type() == object_type)
             .map(|(_, (object, handle))| (object.clone(), *handle))
             .collect()
     }
 
-    pub(crate) fn remove_by_handle(&mut self, handle: CK_OBJECT_HANDLE) -> ModuleResult<()> {
-        self.ids.remove(&handle).ok_or_else(|| {
-            ModuleError::Default(
+    pub(crate) fn remove_by_handle(
+        &mut self,
+        slot: CK_SLOT_ID,
+        handle: CK_OBJECT_HANDLE,
+    ) -> ModuleResult<()> {
+        if !self
+            .ids
+            .get(&handle)
+            .is_some_and(|(object_slot, _)| *object_slot == slot)
+        {
+            return Err(ModuleError::Default(
                 "Unexpected failure while removing handle from object store".to_owned(),
-            )
-        })?;
+            ));
+        }
         // Remove the object from the store
-        self.objects.retain(|_, (_, h)| *h != handle);
+        self.evict_handle(handle);
         Ok(())
     }
 
     /// The number of objects in the store
-    #[expect(dead_code)]
+    #[cfg_attr(not(test), expect(dead_code))]
     pub(crate) fn len(&self) -> usize {
         self.objects.len()
     }
@@ -90,3 +157,54 @@ impl Display for ObjectsStore {
         write!(f, "ObjectsStore {{ objects: {:#?} }}", self.objects)
     }
 }
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT: CK_SLOT_ID = 1;
+
+    #[test]
+    fn test_handles_are_not_reused() {
+        let mut store = ObjectsStore::default();
+        let first = store.upsert(SLOT, Arc::new(Object::Profile(1)));
+        let second = store.upsert(SLOT, Arc::new(Object::Profile(2)));
+        assert_eq!((first, second), (1, 2));
+        // upserting the same remote object keeps its handle
+        assert_eq!(store.upsert(SLOT, Arc::new(Object::Profile(1))), first);
+
+        store.remove_by_handle(SLOT, first).unwrap();
+        assert!(store.get_using_handle(SLOT, first).is_none());
+        let third = store.upsert(SLOT, Arc::new(Object::Profile(3)));
+        assert_eq!(third, 3);
+        assert_eq!(
+            store.get_using_handle(SLOT, second).unwrap().remote_id(),
+            "2"
+        );
+        // the handles are only valid in their own slot
+        assert!(store.get_using_handle(SLOT + 1, second).is_none());
+        assert_eq!(store.len(), 2);
+        assert!(store.remove_by_handle(SLOT, first).is_err());
+    }
+
+    #[test]
+    fn test_object_lifetimes() {
+        let mut store = ObjectsStore::default();
+        let token_object = store.upsert(SLOT, Arc::new(Object::Profile(1)));
+        let session_object = store.upsert_session_object(SLOT, Arc::new(Object::Profile(2)), 7);
+        let other_session_object =
+            store.upsert_session_object(SLOT, Arc::new(Object::Profile(3)), 8);
+
+        let evicted = store.evict_session_objects(7);
+        assert_eq!(evicted.len(), 1);
+        assert!(store.get_using_handle(SLOT, session_object).is_none());
+        assert!(store.get_using_handle(SLOT, token_object).is_some());
+        assert!(store.get_using_handle(SLOT, other_session_object).is_some());
+
+        store.evict_handle(token_object);
+        assert!(store.get_using_handle(SLOT, token_object).is_none());
+        assert!(store.get_using_id(SLOT, "1").is_none());
+        assert_eq!(store.len(), 1);
+    }
+}
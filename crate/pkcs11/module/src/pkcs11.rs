@@ -24,15 +24,16 @@ use std::{
 use cosmian_logger::{debug, error, info, trace};
 use pkcs11_sys::{
     CK_ATTRIBUTE_PTR, CK_BBOOL, CK_BYTE_PTR, CK_C_INITIALIZE_ARGS_PTR, CK_FLAGS, CK_FUNCTION_LIST,
-    CK_INFO, CK_INFO_PTR, CK_MECHANISM_INFO, CK_MECHANISM_INFO_PTR, CK_MECHANISM_PTR,
-    CK_MECHANISM_TYPE, CK_MECHANISM_TYPE_PTR, CK_NOTIFY, CK_OBJECT_HANDLE, CK_OBJECT_HANDLE_PTR,
-    CK_RV, CK_SESSION_HANDLE, CK_SESSION_HANDLE_PTR, CK_SESSION_INFO, CK_SESSION_INFO_PTR,
-    CK_SLOT_ID, CK_SLOT_ID_PTR, CK_SLOT_INFO, CK_SLOT_INFO_PTR, CK_TOKEN_INFO, CK_TOKEN_INFO_PTR,
-    CK_ULONG, CK_ULONG_PTR, CK_UNAVAILABLE_INFORMATION, CK_USER_TYPE, CK_UTF8CHAR_PTR, CK_VERSION,
-    CK_VOID_PTR, CKF_HW_SLOT, CKF_PROTECTED_AUTHENTICATION_PATH, CKF_RNG, CKF_RW_SESSION,
-    CKF_SERIAL_SESSION, CKF_SIGN, CKF_TOKEN_INITIALIZED, CKF_TOKEN_PRESENT,
-    CKF_USER_PIN_INITIALIZED, CKF_WRITE_PROTECTED, CKR_OK, CKS_RO_USER_FUNCTIONS,
-    CKS_RW_USER_FUNCTIONS, CRYPTOKI_VERSION_MAJOR, CRYPTOKI_VERSION_MINOR,
+    CK_INFO, CK_INFO_PTR, CK_MECHANISM_INFO_PTR, CK_MECHANISM_PTR, CK_MECHANISM_TYPE,
+    CK_MECHANISM_TYPE_PTR, CK_NOTIFY, CK_OBJECT_HANDLE, CK_OBJECT_HANDLE_PTR, CK_RV,
+    CK_SESSION_HANDLE, CK_SESSION_HANDLE_PTR, CK_SESSION_INFO, CK_SESSION_INFO_PTR, CK_SLOT_ID,
+    CK_SLOT_ID_PTR, CK_SLOT_INFO, CK_SLOT_INFO_PTR, CK_TOKEN_INFO, CK_TOKEN_INFO_PTR, CK_ULONG,
+    CK_ULONG_PTR, CK_UNAVAILABLE_INFORMATION, CK_USER_TYPE, CK_UTF8CHAR_PTR, CK_VERSION,
+    CK_VOID_PTR, CKF_HW_SLOT, CKF_LOGIN_REQUIRED, CKF_PROTECTED_AUTHENTICATION_PATH, CKF_RNG,
+    CKF_RW_SESSION, CKF_SERIAL_SESSION, CKF_TOKEN_INITIALIZED, CKF_TOKEN_PRESENT,
+    CKF_USER_PIN_INITIALIZED, CKF_WRITE_PROTECTED, CKR_OK, CKS_RO_PUBLIC_SESSION,
+    CKS_RO_USER_FUNCTIONS, CKS_RW_PUBLIC_SESSION, CKS_RW_USER_FUNCTIONS, CKU_USER,
+    CRYPTOKI_VERSION_MAJOR, CRYPTOKI_VERSION_MINOR,
 };
 use rand::RngCore;
 
@@ -40,16 +41,21 @@ use crate::{
     MResultHelper, ModuleError, ModuleResult,
     core::{
         attribute::{AttributeType, Attributes},
-        mechanism::{Mechanism, SUPPORTED_SIGNATURE_MECHANISMS, parse_mechanism},
+        digest::DigestContext,
+        mechanism::{Mechanism, parse_mechanism, supported_mechanisms},
         object::Object,
     },
     objects_store::OBJECTS_STORE,
-    sessions::{self, Session},
-    traits::{DecryptContext, EncryptContext, EncryptionAlgorithm, SignContext, backend},
+    sessions,
+    traits::{
+        DecryptContext, EncryptContext, EncryptionAlgorithm, SignContext, SignatureAlgorithm,
+        VerifyContext, backend_for, initialize_backends, release_backends, slot_ids,
+    },
 };
 
 pub(crate) const SLOT_DESCRIPTION: &[u8; 64] =
     b"Platform Cryptography Support                                   ";
+/// The id of the first slot; the slots of the other backends follow it
 pub const SLOT_ID: CK_SLOT_ID = 1;
 
 pub(crate) static INITIALIZED: AtomicBool = AtomicBool::new(false);
@@ -112,17 +118,26 @@ macro_rules! initialized {
     };
 }
 
+/// Check that the session exists, and evaluate to the slot on which it was opened
 macro_rules! valid_session {
     ($handle:expr) => {
-        if !sessions::exists($handle)? {
-            return Err(ModuleError::SessionHandleInvalid($handle));
+        sessions::slot_id($handle)?
+    };
+}
+
+/// Refuse the use of the token objects of the slot until the user is logged in,
+/// when the token requires a login.
+macro_rules! logged_in {
+    ($slot:expr) => {
+        if !sessions::authorized($slot)? {
+            return Err(ModuleError::UserNotLoggedIn);
         }
     };
 }
 
 macro_rules! valid_slot {
     ($id:expr) => {
-        if $id != SLOT_ID {
+        if !slot_ids().contains(&$id) {
             return Err(ModuleError::SlotIdInvalid($id));
         }
     };
@@ -212,6 +227,13 @@ cryptoki_fn!(
                 ));
             }
         }
+        if INITIALIZED.load(Ordering::SeqCst) {
+            return Err(ModuleError::CryptokiAlreadyInitialized);
+        }
+        // The backend is built here rather than when the library is loaded:
+        // a failure is reported to the caller, which may call C_Initialize again
+        // once the configuration or the server is available.
+        initialize_backends()?;
         if INITIALIZED.swap(true, Ordering::SeqCst) {
             return Err(ModuleError::CryptokiAlreadyInitialized);
         }
@@ -228,6 +250,8 @@ cryptoki_fn!(
             ));
         }
         INITIALIZED.store(false, Ordering::SeqCst);
+        sessions::finalize()?;
+        release_backends();
         Ok(())
     }
 );
@@ -236,7 +260,9 @@ cryptoki_fn!(
     unsafe fn C_GetInfo(pInfo: CK_INFO_PTR) {
         initialized!();
         not_null!(pInfo, "C_GetInfo: pInfo");
-        let backend = backend();
+        // The library information is the same for all the slots:
+        // it is the one of the backend of the first slot
+        let backend = backend_for(SLOT_ID)?;
         let info = CK_INFO {
             cryptokiVersion: CK_VERSION {
                 major: CRYPTOKI_VERSION_MAJOR,
@@ -265,15 +291,16 @@ cryptoki_fn!(
     ) {
         initialized!();
         not_null!(pulCount, "C_GetSlotList: pulCount");
+        let slots = slot_ids();
         unsafe {
             if !pSlotList.is_null() {
-                if *pulCount < 1 {
+                if usize::try_from(*pulCount)? < slots.len() {
+                    *pulCount = slots.len().try_into()?;
                     return Err(ModuleError::BufferTooSmall);
                 }
-                // TODO: this should be an array.
-                *pSlotList = SLOT_ID;
+                slice::from_raw_parts_mut(pSlotList, slots.len()).copy_from_slice(&slots);
             }
-            *pulCount = 1;
+            *pulCount = slots.len().try_into()?;
         }
         Ok(())
     }
@@ -284,7 +311,7 @@ cryptoki_fn!(
         initialized!();
         valid_slot!(slotID);
         not_null!(pInfo, "C_GetSlotInfo: pInfo");
-        let backend = backend();
+        let backend = backend_for(slotID)?;
         let info = CK_SLOT_INFO {
             slotDescription: *SLOT_DESCRIPTION,
             manufacturerID: backend.token_manufacturer_id(),
@@ -311,7 +338,7 @@ cryptoki_fn!(
         valid_slot!(slotID);
         not_null!(pInfo, "C_GetTokenInfo: pInfo");
 
-        let backend = backend();
+        let backend = backend_for(slotID)?;
 
         let info = CK_TOKEN_INFO {
             label: backend.token_label(),
@@ -319,7 +346,11 @@ cryptoki_fn!(
             model: backend.token_model(),
             serialNumber: backend.token_serial_number(),
             flags: CKF_TOKEN_INITIALIZED
-                | CKF_PROTECTED_AUTHENTICATION_PATH
+                | if backend.login_required() {
+                    CKF_LOGIN_REQUIRED
+                } else {
+                    CKF_PROTECTED_AUTHENTICATION_PATH
+                }
                 | CKF_WRITE_PROTECTED
                 | CKF_USER_PIN_INITIALIZED
                 | CKF_RNG
@@ -352,16 +383,20 @@ cryptoki_fn!(
         initialized!();
         not_null!(pulCount, "C_GetMechanismList: pulCount");
         valid_slot!(slotID);
+        let mechanisms = supported_mechanisms(slotID)?
+            .into_iter()
+            .map(|(mechanism, _)| mechanism)
+            .collect::<Vec<_>>();
         unsafe {
             if !pMechanismList.is_null() {
-                if (usize::try_from(*pulCount)?) < SUPPORTED_SIGNATURE_MECHANISMS.len() {
-                    *pulCount = SUPPORTED_SIGNATURE_MECHANISMS.len() as CK_ULONG;
+                if (usize::try_from(*pulCount)?) < mechanisms.len() {
+                    *pulCount = mechanisms.len() as CK_ULONG;
                     return Err(ModuleError::BufferTooSmall);
                 }
-                slice::from_raw_parts_mut(pMechanismList, SUPPORTED_SIGNATURE_MECHANISMS.len())
-                    .copy_from_slice(SUPPORTED_SIGNATURE_MECHANISMS);
+                slice::from_raw_parts_mut(pMechanismList, mechanisms.len())
+                    .copy_from_slice(&mechanisms);
             }
-            *pulCount = SUPPORTED_SIGNATURE_MECHANISMS.len() as CK_ULONG;
+            *pulCount = mechanisms.len() as CK_ULONG;
         }
         Ok(())
     }
@@ -376,12 +411,11 @@ cryptoki_fn!(
         initialized!();
         valid_slot!(slotID);
         not_null!(pInfo, "C_GetMechanismInfo: pInfo");
-        if !SUPPORTED_SIGNATURE_MECHANISMS.contains(&mechType) {
+        let Some((_, info)) = supported_mechanisms(slotID)?
+            .into_iter()
+            .find(|(mechanism, _)| *mechanism == mechType)
+        else {
             return Err(ModuleError::MechanismInvalid(mechType));
-        }
-        let info = CK_MECHANISM_INFO {
-            flags: CKF_SIGN,
-            ..Default::default()
         };
         unsafe {
             *pInfo = info;
@@ -440,7 +474,7 @@ cryptoki_fn!(
             return Err(ModuleError::SessionParallelNotSupported);
         }
         unsafe {
-            *phSession = sessions::create(flags);
+            *phSession = sessions::create(slotID, flags);
         }
         info!(
             "C_OpenSession: slot: {:?}, flags: {:?}, session: {}",
@@ -468,7 +502,7 @@ cryptoki_fn!(
         initialized!();
         valid_slot!(slotID);
         info!("C_CloseAllSessions: slot: {:?}", slotID);
-        sessions::close_all()?;
+        sessions::close_all(slotID)?;
         Ok(())
     }
 );
@@ -476,16 +510,17 @@ cryptoki_fn!(
 cryptoki_fn!(
     unsafe fn C_GetSessionInfo(hSession: CK_SESSION_HANDLE, pInfo: CK_SESSION_INFO_PTR) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
         not_null!(pInfo, "C_GetSessionInfo: pInfo");
         let flags = sessions::flags(hSession)?;
-        let state = if flags & CKF_RW_SESSION == 0 {
-            CKS_RO_USER_FUNCTIONS
-        } else {
-            CKS_RW_USER_FUNCTIONS
+        let state = match (flags & CKF_RW_SESSION == 0, sessions::logged_in(slot_id)?) {
+            (true, true) => CKS_RO_USER_FUNCTIONS,
+            (true, false) => CKS_RO_PUBLIC_SESSION,
+            (false, true) => CKS_RW_USER_FUNCTIONS,
+            (false, false) => CKS_RW_PUBLIC_SESSION,
         };
         let info = CK_SESSION_INFO {
-            slotID: SLOT_ID,
+            slotID: slot_id,
             state,
             flags,
             ulDeviceError: 0,
@@ -495,7 +530,7 @@ cryptoki_fn!(
         }
         trace!(
             "C_GetSessionInfo: session: {:?}, slot: {:?}, state: {:?}, flags: {:?}",
-            hSession, SLOT_ID, state, flags
+            hSession, slot_id, state, flags
         );
         Ok(())
     }
@@ -518,23 +553,33 @@ cryptoki_fn_not_supported!(
 );
 
 cryptoki_fn!(
-    fn C_Login(
+    unsafe fn C_Login(
         hSession: CK_SESSION_HANDLE,
         userType: CK_USER_TYPE,
         pPin: CK_UTF8CHAR_PTR,
         ulPinLen: CK_ULONG,
     ) {
         initialized!();
-        valid_session!(hSession);
-        Ok(())
+        let slot_id = valid_session!(hSession);
+        if userType != CKU_USER {
+            return Err(ModuleError::UserTypeInvalid(userType));
+        }
+        let pin = if pPin.is_null() {
+            &[][..]
+        } else {
+            unsafe { slice::from_raw_parts(pPin, usize::try_from(ulPinLen)?) }
+        };
+        info!("C_Login: session: {hSession:?}");
+        sessions::login(slot_id, pin)
     }
 );
 
 cryptoki_fn!(
     fn C_Logout(hSession: CK_SESSION_HANDLE) {
         initialized!();
-        valid_session!(hSession);
-        Ok(())
+        let slot_id = valid_session!(hSession);
+        info!("C_Logout: session: {hSession:?}");
+        sessions::logout(slot_id)
     }
 );
 
@@ -546,7 +591,8 @@ cryptoki_fn!(
         phObject: CK_OBJECT_HANDLE_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pTemplate, "C_CreateObject: pTemplate");
         not_null!(phObject, "C_CreateObject: phObject");
 
@@ -557,9 +603,9 @@ cryptoki_fn!(
         let attributes = Attributes::try_from((pTemplate, ulCount))
             .context("C_CreateObject: attributes conversion failed")?;
 
-        sessions::session(hSession, |_session| -> ModuleResult<()> {
+        sessions::session(hSession, |session| -> ModuleResult<()> {
             unsafe {
-                *phObject = Session::create_object(&attributes)?;
+                *phObject = session.create_object(hSession, &attributes)?;
             };
 
             Ok(())
@@ -579,12 +625,13 @@ cryptoki_fn_not_supported!(
 cryptoki_fn!(
     unsafe fn C_DestroyObject(hSession: CK_SESSION_HANDLE, hObject: CK_OBJECT_HANDLE) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
 
         debug!("C_DestroyObject: session: {hSession:?}, hObject: {hObject}");
 
-        sessions::session(hSession, |_session| -> ModuleResult<()> {
-            Session::destroy_object(hObject)?;
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            session.destroy_object(hObject)?;
             Ok(())
         })
     }
@@ -609,12 +656,12 @@ cryptoki_fn!(
             hSession, hObject
         );
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pTemplate, "C_GetAttributeValue: pTemplate");
 
         sessions::session(hSession, |_session| -> ModuleResult<()> {
-            let find_ctx = OBJECTS_STORE.read()?;
-            let Some(object) = find_ctx.get_using_handle(hObject) else {
+            let Some(object) = OBJECTS_STORE.read()?.get_using_handle(slot_id, hObject) else {
                 return Err(ModuleError::ObjectHandleInvalid(hObject));
             };
             let template = if ulCount > 0 {
@@ -627,6 +674,8 @@ cryptoki_fn!(
             } else {
                 &mut []
             };
+            // The values of the other attributes are returned with `CKR_ATTRIBUTE_SENSITIVE`
+            let mut sensitive = None;
             for attribute in template.iter_mut() {
                 let type_: AttributeType = attribute.type_.try_into().map_err(|e| {
                     let attribute_type = attribute.type_;
@@ -645,7 +694,15 @@ cryptoki_fn!(
                     hObject,
                     type_.to_string(),
                 );
-                if let Some(value) = object.attribute(type_)? {
+                let value = match object.attribute(slot_id, type_) {
+                    Err(ModuleError::AttributeSensitive(type_)) => {
+                        attribute.ulValueLen = CK_UNAVAILABLE_INFORMATION;
+                        sensitive = Some(type_);
+                        continue;
+                    }
+                    value => sessions::evict_if_not_found(hObject, value)?,
+                };
+                if let Some(value) = value {
                     let value = value.as_raw_value();
                     attribute.ulValueLen = value.len() as CK_ULONG;
                     if attribute.pValue.is_null() {
@@ -662,17 +719,32 @@ cryptoki_fn!(
                     attribute.ulValueLen = CK_UNAVAILABLE_INFORMATION;
                 }
             }
-            Ok(())
+            sensitive.map_or(Ok(()), |type_| Err(ModuleError::AttributeSensitive(type_)))
         })
     }
 );
 
-cryptoki_fn_not_supported!(
-    C_SetAttributeValue,
-    hSession: CK_SESSION_HANDLE,
-    hObject: CK_OBJECT_HANDLE,
-    pTemplate: CK_ATTRIBUTE_PTR,
-    ulCount: CK_ULONG
+cryptoki_fn!(
+    unsafe fn C_SetAttributeValue(
+        hSession: CK_SESSION_HANDLE,
+        hObject: CK_OBJECT_HANDLE,
+        pTemplate: CK_ATTRIBUTE_PTR,
+        ulCount: CK_ULONG,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pTemplate, "C_SetAttributeValue: pTemplate");
+
+        debug!("C_SetAttributeValue: session: {hSession:?}, hObject: {hObject}");
+        // The errors of the conversion are kept, so that an unknown or
+        // malformed attribute is reported as such
+        let template = Attributes::try_from((pTemplate, ulCount))?;
+
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            session.set_attribute_value(hObject, &template)
+        })
+    }
 );
 
 cryptoki_fn!(
@@ -682,11 +754,17 @@ cryptoki_fn!(
         ulCount: CK_ULONG,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
 
         let attributes = Attributes::try_from((pTemplate, ulCount))
             .context("C_FindObjectsInit: attributes conversion failed")?;
         sessions::session(hSession, |session| -> ModuleResult<()> {
+            // All the objects of the token are private: none is visible before login
+            if !sessions::authorized(slot_id)? {
+                debug!("C_FindObjectsInit: session: {hSession:?}, user not logged in");
+                session.find_objects_ctx.clear();
+                return Ok(());
+            }
             info!(
                 "C_FindObjectsInit: session: {hSession:?}, load Objects Store context for \
                  attributes: {attributes:?}"
@@ -761,13 +839,14 @@ cryptoki_fn!(
         hKey: CK_OBJECT_HANDLE,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pMechanism, "C_EncryptInit: pMechanism");
         sessions::session(hSession, |session| -> ModuleResult<()> {
             let parsed_mechanism = unsafe { pMechanism.read() };
             let mechanism = unsafe { parse_mechanism(parsed_mechanism) }?;
             let find_ctx = OBJECTS_STORE.read()?;
-            let object = find_ctx.get_using_handle(hKey);
+            let object = find_ctx.get_using_handle(slot_id, hKey);
             debug!(
                 "C_EncryptInit: session: {hSession:?}, hKey: {hKey:?}, mechanism: {mechanism:?}, \
                  object: {object:?}",
@@ -778,38 +857,33 @@ cryptoki_fn!(
                         remote_object_id: pk.remote_id(),
                         algorithm: mechanism.try_into()?,
                         iv: None,
+                        aad: None,
+                        correlation_value: None,
+                        pending_output: None,
                     });
                     Ok(())
                 }
                 Some(Object::SymmetricKey(sk)) => {
-                    let iv = match &mechanism {
-                        Mechanism::AesCbcPad { iv } | Mechanism::AesCbc { iv } => Some(iv.to_vec()),
-                        mech => {
-                            return Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(
-                                mech,
-                            )));
-                        }
-                    };
+                    let (iv, aad) = mechanism.aes_iv_and_aad()?;
                     session.encrypt_ctx = Some(EncryptContext {
                         remote_object_id: sk.remote_id(),
                         algorithm: EncryptionAlgorithm::try_from(mechanism)?,
-                        iv,
+                        iv: Some(iv),
+                        aad,
+                        correlation_value: None,
+                        pending_output: None,
                     });
                     Ok(())
                 }
                 Some(Object::DataObject(data)) => {
-                    let iv = match &mechanism {
-                        Mechanism::AesCbcPad { iv } | Mechanism::AesCbc { iv } => Some(iv.to_vec()),
-                        mech => {
-                            return Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(
-                                mech,
-                            )));
-                        }
-                    };
+                    let (iv, aad) = mechanism.aes_iv_and_aad()?;
                     session.encrypt_ctx = Some(EncryptContext {
                         remote_object_id: data.remote_id(),
                         algorithm: EncryptionAlgorithm::try_from(mechanism)?,
-                        iv,
+                        iv: Some(iv),
+                        aad,
+                        correlation_value: None,
+                        pending_output: None,
                     });
                     Ok(())
                 }
@@ -828,7 +902,8 @@ cryptoki_fn!(
         pulEncryptedDataLen: CK_ULONG_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         debug!(
             "C_Encrypt: pData: {pData:?}, ulDataLen: {ulDataLen:?}, pEncryptedData: \
              {pEncryptedData:?}, pulEncryptedDataLen: {pulEncryptedDataLen:?}"
@@ -859,20 +934,49 @@ cryptoki_fn!(
     }
 );
 
-cryptoki_fn_not_supported!(
-    C_EncryptUpdate,
-    hSession: CK_SESSION_HANDLE,
-    pPart: CK_BYTE_PTR,
-    ulPartLen: CK_ULONG,
-    pEncryptedPart: CK_BYTE_PTR,
-    pulEncryptedPartLen: CK_ULONG_PTR
+cryptoki_fn!(
+    unsafe fn C_EncryptUpdate(
+        hSession: CK_SESSION_HANDLE,
+        pPart: CK_BYTE_PTR,
+        ulPartLen: CK_ULONG,
+        pEncryptedPart: CK_BYTE_PTR,
+        pulEncryptedPartLen: CK_ULONG_PTR,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pPart, "C_EncryptUpdate: pPart");
+        not_null!(pulEncryptedPartLen, "C_EncryptUpdate: pulEncryptedPartLen");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let part = unsafe { slice::from_raw_parts(pPart, usize::try_from(ulPartLen)?) };
+            unsafe {
+                session.encrypt_multipart(Some(part.to_vec()), pEncryptedPart, pulEncryptedPartLen)
+            }?;
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_EncryptFinal,
-    hSession: CK_SESSION_HANDLE,
-    pLastEncryptedPart: CK_BYTE_PTR,
-    pulLastEncryptedPartLen: CK_ULONG_PTR
+cryptoki_fn!(
+    unsafe fn C_EncryptFinal(
+        hSession: CK_SESSION_HANDLE,
+        pLastEncryptedPart: CK_BYTE_PTR,
+        pulLastEncryptedPartLen: CK_ULONG_PTR,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(
+            pulLastEncryptedPartLen,
+            "C_EncryptFinal: pulLastEncryptedPartLen"
+        );
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            unsafe {
+                session.encrypt_multipart(None, pLastEncryptedPart, pulLastEncryptedPartLen)
+            }?;
+            Ok(())
+        })
+    }
 );
 
 cryptoki_fn!(
@@ -882,13 +986,14 @@ cryptoki_fn!(
         hKey: CK_OBJECT_HANDLE,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pMechanism, "C_DecryptInit: pMechanism");
         sessions::session(hSession, |session| -> ModuleResult<()> {
             let parsed_mechanism = unsafe { pMechanism.read() };
             let mechanism = unsafe { parse_mechanism(parsed_mechanism) }?;
             let find_ctx = OBJECTS_STORE.read()?;
-            let object = find_ctx.get_using_handle(hKey);
+            let object = find_ctx.get_using_handle(slot_id, hKey);
             debug!(
                 "C_DecryptInit: session: {hSession:?}, hKey: {hKey:?}, mechanism: {mechanism:?}, \
                  object: {object:?}",
@@ -899,39 +1004,34 @@ cryptoki_fn!(
                         remote_object_id: sk.remote_id(),
                         algorithm: mechanism.try_into()?,
                         iv: None,
+                        aad: None,
+                        correlation_value: None,
+                        pending_output: None,
                     });
                     Ok(())
                 }
                 Some(Object::SymmetricKey(sk)) => {
-                    let iv = match &mechanism {
-                        Mechanism::AesCbcPad { iv } | Mechanism::AesCbc { iv } => Some(iv.to_vec()),
-                        mech => {
-                            return Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(
-                                mech,
-                            )));
-                        }
-                    };
+                    let (iv, aad) = mechanism.aes_iv_and_aad()?;
 
                     session.decrypt_ctx = Some(DecryptContext {
                         remote_object_id: sk.remote_id(),
                         algorithm: mechanism.try_into()?,
-                        iv,
+                        iv: Some(iv),
+                        aad,
+                        correlation_value: None,
+                        pending_output: None,
                     });
                     Ok(())
                 }
                 Some(Object::DataObject(data)) => {
-                    let iv = match &mechanism {
-                        Mechanism::AesCbcPad { iv } | Mechanism::AesCbc { iv } => Some(iv.to_vec()),
-                        mech => {
-                            return Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(
-                                mech,
-                            )));
-                        }
-                    };
+                    let (iv, aad) = mechanism.aes_iv_and_aad()?;
                     session.decrypt_ctx = Some(DecryptContext {
                         remote_object_id: data.remote_id(),
                         algorithm: mechanism.try_into()?,
-                        iv,
+                        iv: Some(iv),
+                        aad,
+                        correlation_value: None,
+                        pending_output: None,
                     });
                     Ok(())
                 }
@@ -950,7 +1050,8 @@ cryptoki_fn!(
         pulDataLen: CK_ULONG_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         debug!(
             "C_Decrypt: pEncryptedData: {pEncryptedData:?}, ulEncryptedDataLen: \
              {ulEncryptedDataLen:?}, pData: {pData:?}, pulDataLen: {pulDataLen:?}"
@@ -992,16 +1093,22 @@ cryptoki_fn!(
         pulPartLen: CK_ULONG_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         if ulEncryptedPartLen == 0 {
             return Err(ModuleError::BadArguments(
                 "C_DecryptUpdate: ulEncryptedPartLen is 0".to_owned(),
             ));
         }
         not_null!(pEncryptedPart, "C_DecryptUpdate: pEncryptedPart");
-        not_null!(pPart, "C_DecryptUpdate: pPart");
         not_null!(pulPartLen, "C_DecryptUpdate: pulPartLen");
-        Err(ModuleError::FunctionNotSupported)
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let encrypted_part = unsafe {
+                slice::from_raw_parts(pEncryptedPart, usize::try_from(ulEncryptedPartLen)?)
+            };
+            unsafe { session.decrypt_multipart(Some(encrypted_part.to_vec()), pPart, pulPartLen) }?;
+            Ok(())
+        })
     }
 );
 
@@ -1012,46 +1119,116 @@ cryptoki_fn!(
         pulLastPartLen: CK_ULONG_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
-        not_null!(pLastPart, "C_DecryptFinal: pLastPart");
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pulLastPartLen, "C_DecryptFinal: pulLastPartLen");
-        Err(ModuleError::FunctionNotSupported)
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            unsafe { session.decrypt_multipart(None, pLastPart, pulLastPartLen) }?;
+            Ok(())
+        })
     }
 );
 
-cryptoki_fn_not_supported!(
-    C_DigestInit,
-    hSession: CK_SESSION_HANDLE,
-    pMechanism: CK_MECHANISM_PTR
+cryptoki_fn!(
+    unsafe fn C_DigestInit(hSession: CK_SESSION_HANDLE, pMechanism: CK_MECHANISM_PTR) {
+        initialized!();
+        valid_session!(hSession);
+        not_null!(pMechanism, "C_DigestInit: pMechanism");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+            match mechanism {
+                Mechanism::Digest { digest_algorithm } => {
+                    session.digest_ctx = Some(DigestContext::new(digest_algorithm));
+                    Ok(())
+                }
+                m => Err(ModuleError::MechanismInvalid((&m).into())),
+            }
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_Digest,
-    hSession: CK_SESSION_HANDLE,
-    pData: CK_BYTE_PTR,
-    ulDataLen: CK_ULONG,
-    pDigest: CK_BYTE_PTR,
-    pulDigestLen: CK_ULONG_PTR
+cryptoki_fn!(
+    unsafe fn C_Digest(
+        hSession: CK_SESSION_HANDLE,
+        pData: CK_BYTE_PTR,
+        ulDataLen: CK_ULONG,
+        pDigest: CK_BYTE_PTR,
+        pulDigestLen: CK_ULONG_PTR,
+    ) {
+        initialized!();
+        valid_session!(hSession);
+        not_null!(pData, "C_Digest: pData");
+        not_null!(pulDigestLen, "C_Digest: pulDigestLen");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let data = unsafe { slice::from_raw_parts(pData, usize::try_from(ulDataLen)?) };
+            unsafe { session.digest(hSession, Some(data), pDigest, pulDigestLen) }?;
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_DigestUpdate,
-    hSession: CK_SESSION_HANDLE,
-    pPart: CK_BYTE_PTR,
-    ulPartLen: CK_ULONG
+cryptoki_fn!(
+    unsafe fn C_DigestUpdate(hSession: CK_SESSION_HANDLE, pPart: CK_BYTE_PTR, ulPartLen: CK_ULONG) {
+        initialized!();
+        valid_session!(hSession);
+        not_null!(pPart, "C_DigestUpdate: pPart");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let Some(digest_ctx) = session.digest_ctx.as_mut() else {
+                return Err(ModuleError::OperationNotInitialized(hSession));
+            };
+            digest_ctx.update(unsafe { slice::from_raw_parts(pPart, usize::try_from(ulPartLen)?) })
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_DigestKey,
-    hSession: CK_SESSION_HANDLE,
-    hKey: CK_OBJECT_HANDLE
+cryptoki_fn!(
+    unsafe fn C_DigestKey(hSession: CK_SESSION_HANDLE, hKey: CK_OBJECT_HANDLE) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let Some(digest_ctx) = session.digest_ctx.as_mut() else {
+                return Err(ModuleError::OperationNotInitialized(hSession));
+            };
+            // The objects store is not locked while the backend digests the key
+            let key = OBJECTS_STORE.read()?.get_using_handle(slot_id, hKey);
+            let key_digest = match key.as_deref() {
+                Some(Object::SymmetricKey(key)) if !key.metadata().protects_value() => {
+                    match backend_for(slot_id)?
+                        .digest_key(&key.remote_id(), digest_ctx.digest_type())
+                    {
+                        Ok(key_digest) => key_digest,
+                        // the backend does not know the handle of the key
+                        Err(ModuleError::KeyIndigestible(_)) => {
+                            return Err(ModuleError::KeyIndigestible(hKey));
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                Some(Object::SymmetricKey(_) | Object::PrivateKey(_) | Object::PublicKey(_)) => {
+                    return Err(ModuleError::KeyIndigestible(hKey));
+                }
+                Some(_) | None => return Err(ModuleError::KeyHandleInvalid(hKey)),
+            };
+            digest_ctx.update_key(hKey, key_digest)
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_DigestFinal,
-    hSession: CK_SESSION_HANDLE,
-    pDigest: CK_BYTE_PTR,
-    pulDigestLen: CK_ULONG_PTR
+cryptoki_fn!(
+    unsafe fn C_DigestFinal(
+        hSession: CK_SESSION_HANDLE,
+        pDigest: CK_BYTE_PTR,
+        pulDigestLen: CK_ULONG_PTR,
+    ) {
+        initialized!();
+        valid_session!(hSession);
+        not_null!(pulDigestLen, "C_DigestFinal: pulDigestLen");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            unsafe { session.digest(hSession, None, pDigest, pulDigestLen) }?;
+            Ok(())
+        })
+    }
 );
 
 cryptoki_fn!(
@@ -1061,20 +1238,30 @@ cryptoki_fn!(
         hKey: CK_OBJECT_HANDLE,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pMechanism, "C_SignInit: pMechanism");
         sessions::session(hSession, |session| -> ModuleResult<()> {
             let find_ctx = OBJECTS_STORE.read()?;
             // .map_err(|_| ModuleError::OperationNotInitialized(hSession))?;
-            let object = find_ctx.get_using_handle(hKey);
-            let Some(Object::PrivateKey(private_key)) = object.as_deref() else {
-                return Err(ModuleError::KeyHandleInvalid(hKey));
-            };
+            let object = find_ctx.get_using_handle(slot_id, hKey);
             let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+            let algorithm = SignatureAlgorithm::try_from(mechanism)?;
+            // An HMAC is computed with a generic secret, a signature with a private key
+            let remote_object_id = match object.as_deref() {
+                Some(Object::PrivateKey(private_key)) if !algorithm.is_hmac() => {
+                    private_key.remote_id()
+                }
+                Some(Object::SymmetricKey(secret_key)) if algorithm.is_hmac() => {
+                    secret_key.remote_id()
+                }
+                _ => return Err(ModuleError::KeyHandleInvalid(hKey)),
+            };
             session.sign_ctx = Some(SignContext {
-                algorithm: mechanism.try_into()?,
-                private_key: private_key.clone(),
+                remote_object_id,
+                algorithm,
                 payload: None,
+                signature: None,
             });
             Ok(())
         })
@@ -1090,7 +1277,8 @@ cryptoki_fn!(
         pulSignatureLen: CK_ULONG_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pData, "C_Sign: pData");
         not_null!(pulSignatureLen, "C_Sign: pulSignatureLen");
         sessions::session(hSession, |session| -> ModuleResult<()> {
@@ -1104,7 +1292,8 @@ cryptoki_fn!(
 cryptoki_fn!(
     unsafe fn C_SignUpdate(hSession: CK_SESSION_HANDLE, pPart: CK_BYTE_PTR, ulPartLen: CK_ULONG) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pPart, "C_SignUpdate: pPart");
         sessions::session(hSession, |session| -> ModuleResult<()> {
             let Some(sign_ctx) = session.sign_ctx.as_mut() else {
@@ -1128,7 +1317,8 @@ cryptoki_fn!(
         pulSignatureLen: CK_ULONG_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pSignature, "C_SignFinal: pSignature");
         not_null!(pulSignatureLen, "C_SignFinal: pulSignatureLen");
         sessions::session(hSession, |session| -> ModuleResult<()> {
@@ -1154,34 +1344,99 @@ cryptoki_fn_not_supported!(
     pulSignatureLen: CK_ULONG_PTR
 );
 
-cryptoki_fn_not_supported!(
-    C_VerifyInit,
-    hSession: CK_SESSION_HANDLE,
-    pMechanism: CK_MECHANISM_PTR,
-    hKey: CK_OBJECT_HANDLE
+cryptoki_fn!(
+    unsafe fn C_VerifyInit(
+        hSession: CK_SESSION_HANDLE,
+        pMechanism: CK_MECHANISM_PTR,
+        hKey: CK_OBJECT_HANDLE,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pMechanism, "C_VerifyInit: pMechanism");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let find_ctx = OBJECTS_STORE.read()?;
+            let object = find_ctx.get_using_handle(slot_id, hKey);
+            let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+            let algorithm = SignatureAlgorithm::try_from(mechanism)?;
+            let remote_object_id = match object.as_deref() {
+                Some(Object::PublicKey(public_key)) if !algorithm.is_hmac() => {
+                    public_key.remote_id()
+                }
+                Some(Object::SymmetricKey(secret_key)) if algorithm.is_hmac() => {
+                    secret_key.remote_id()
+                }
+                _ => return Err(ModuleError::KeyHandleInvalid(hKey)),
+            };
+            session.verify_ctx = Some(VerifyContext {
+                remote_object_id,
+                algorithm,
+                payload: None,
+            });
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_Verify,
-    hSession: CK_SESSION_HANDLE,
-    pData: CK_BYTE_PTR,
-    ulDataLen: CK_ULONG,
-    pSignature: CK_BYTE_PTR,
-    ulSignatureLen: CK_ULONG
+cryptoki_fn!(
+    unsafe fn C_Verify(
+        hSession: CK_SESSION_HANDLE,
+        pData: CK_BYTE_PTR,
+        ulDataLen: CK_ULONG,
+        pSignature: CK_BYTE_PTR,
+        ulSignatureLen: CK_ULONG,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pData, "C_Verify: pData");
+        not_null!(pSignature, "C_Verify: pSignature");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let data = unsafe { slice::from_raw_parts(pData, usize::try_from(ulDataLen)?) };
+            let signature =
+                unsafe { slice::from_raw_parts(pSignature, usize::try_from(ulSignatureLen)?) };
+            session.verify(Some(data), signature)
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_VerifyUpdate,
-    hSession: CK_SESSION_HANDLE,
-    pPart: CK_BYTE_PTR,
-    ulPartLen: CK_ULONG
+cryptoki_fn!(
+    unsafe fn C_VerifyUpdate(hSession: CK_SESSION_HANDLE, pPart: CK_BYTE_PTR, ulPartLen: CK_ULONG) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pPart, "C_VerifyUpdate: pPart");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let Some(verify_ctx) = session.verify_ctx.as_mut() else {
+                return Err(ModuleError::OperationNotInitialized(hSession));
+            };
+            verify_ctx
+                .payload
+                .get_or_insert(vec![])
+                .extend_from_slice(unsafe {
+                    slice::from_raw_parts(pPart, usize::try_from(ulPartLen)?)
+                });
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_VerifyFinal,
-    hSession: CK_SESSION_HANDLE,
-    pSignature: CK_BYTE_PTR,
-    ulSignatureLen: CK_ULONG
+cryptoki_fn!(
+    unsafe fn C_VerifyFinal(
+        hSession: CK_SESSION_HANDLE,
+        pSignature: CK_BYTE_PTR,
+        ulSignatureLen: CK_ULONG,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pSignature, "C_VerifyFinal: pSignature");
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let signature =
+                unsafe { slice::from_raw_parts(pSignature, usize::try_from(ulSignatureLen)?) };
+            session.verify(None, signature)
+        })
+    }
 );
 
 cryptoki_fn_not_supported!(
@@ -1245,7 +1500,8 @@ cryptoki_fn!(
         phKey: CK_OBJECT_HANDLE_PTR,
     ) {
         initialized!();
-        valid_session!(hSession);
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
         not_null!(pMechanism, "C_GenerateKey: pMechanism");
         not_null!(pTemplate, "C_GenerateKey: pTemplate");
 
@@ -1256,11 +1512,11 @@ cryptoki_fn!(
         let attributes = Attributes::try_from((pTemplate, ulCount))
             .context("C_GenerateKey: attributes conversion failed")?;
 
-        sessions::session(hSession, |_session| -> ModuleResult<()> {
+        sessions::session(hSession, |session| -> ModuleResult<()> {
             let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
 
             unsafe {
-                *phKey = Session::generate_key(mechanism, &attributes)?;
+                *phKey = session.generate_key(hSession, mechanism, &attributes)?;
             };
 
             Ok(())
@@ -1268,48 +1524,179 @@ cryptoki_fn!(
     }
 );
 
-cryptoki_fn_not_supported!(
-    C_GenerateKeyPair,
-    hSession: CK_SESSION_HANDLE,
-    pMechanism: CK_MECHANISM_PTR,
-    pPublicKeyTemplate: CK_ATTRIBUTE_PTR,
-    ulPublicKeyAttributeCount: CK_ULONG,
-    pPrivateKeyTemplate: CK_ATTRIBUTE_PTR,
-    ulPrivateKeyAttributeCount: CK_ULONG,
-    phPublicKey: CK_OBJECT_HANDLE_PTR,
-    phPrivateKey: CK_OBJECT_HANDLE_PTR
+cryptoki_fn!(
+    unsafe fn C_GenerateKeyPair(
+        hSession: CK_SESSION_HANDLE,
+        pMechanism: CK_MECHANISM_PTR,
+        pPublicKeyTemplate: CK_ATTRIBUTE_PTR,
+        ulPublicKeyAttributeCount: CK_ULONG,
+        pPrivateKeyTemplate: CK_ATTRIBUTE_PTR,
+        ulPrivateKeyAttributeCount: CK_ULONG,
+        phPublicKey: CK_OBJECT_HANDLE_PTR,
+        phPrivateKey: CK_OBJECT_HANDLE_PTR,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pMechanism, "C_GenerateKeyPair: pMechanism");
+        not_null!(pPublicKeyTemplate, "C_GenerateKeyPair: pPublicKeyTemplate");
+        not_null!(
+            pPrivateKeyTemplate,
+            "C_GenerateKeyPair: pPrivateKeyTemplate"
+        );
+        not_null!(phPublicKey, "C_GenerateKeyPair: phPublicKey");
+        not_null!(phPrivateKey, "C_GenerateKeyPair: phPrivateKey");
+
+        debug!(
+            "C_GenerateKeyPair: session: {hSession:?}, pMechanism: {pMechanism:?}, \
+             pPublicKeyTemplate: {pPublicKeyTemplate:?}, ulPublicKeyAttributeCount: \
+             {ulPublicKeyAttributeCount:?}, pPrivateKeyTemplate: {pPrivateKeyTemplate:?}, \
+             ulPrivateKeyAttributeCount: {ulPrivateKeyAttributeCount:?}"
+        );
+        let public_key_attributes =
+            Attributes::try_from((pPublicKeyTemplate, ulPublicKeyAttributeCount))
+                .context("C_GenerateKeyPair: public key attributes conversion failed")?;
+        let private_key_attributes =
+            Attributes::try_from((pPrivateKeyTemplate, ulPrivateKeyAttributeCount))
+                .context("C_GenerateKeyPair: private key attributes conversion failed")?;
+
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+
+            let (public_key_handle, private_key_handle) = session.generate_key_pair(
+                hSession,
+                mechanism,
+                &public_key_attributes,
+                &private_key_attributes,
+            )?;
+            unsafe {
+                *phPublicKey = public_key_handle;
+                *phPrivateKey = private_key_handle;
+            };
+
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_WrapKey,
-    hSession: CK_SESSION_HANDLE,
-    pMechanism: CK_MECHANISM_PTR,
-    hWrappingKey: CK_OBJECT_HANDLE,
-    hKey: CK_OBJECT_HANDLE,
-    pWrappedKey: CK_BYTE_PTR,
-    pulWrappedKeyLen: CK_ULONG_PTR
+cryptoki_fn!(
+    unsafe fn C_WrapKey(
+        hSession: CK_SESSION_HANDLE,
+        pMechanism: CK_MECHANISM_PTR,
+        hWrappingKey: CK_OBJECT_HANDLE,
+        hKey: CK_OBJECT_HANDLE,
+        pWrappedKey: CK_BYTE_PTR,
+        pulWrappedKeyLen: CK_ULONG_PTR,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pMechanism, "C_WrapKey: pMechanism");
+        not_null!(pulWrappedKeyLen, "C_WrapKey: pulWrappedKeyLen");
+
+        debug!(
+            "C_WrapKey: session: {hSession:?}, pMechanism: {pMechanism:?}, hWrappingKey: \
+             {hWrappingKey:?}, hKey: {hKey:?}, pWrappedKey: {pWrappedKey:?}"
+        );
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+            // Wrapping is stateless in the KMS: a size query simply performs it
+            let wrapped_key = session.wrap_key(mechanism, hWrappingKey, hKey)?;
+            if !pWrappedKey.is_null() {
+                if (unsafe { usize::try_from(*pulWrappedKeyLen)? }) < wrapped_key.len() {
+                    return Err(ModuleError::BufferTooSmall);
+                }
+                unsafe { slice::from_raw_parts_mut(pWrappedKey, wrapped_key.len()) }
+                    .copy_from_slice(&wrapped_key);
+            }
+            unsafe {
+                *pulWrappedKeyLen = wrapped_key.len().try_into()?;
+            }
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_UnwrapKey,
-    hSession: CK_SESSION_HANDLE,
-    pMechanism: CK_MECHANISM_PTR,
-    hUnwrappingKey: CK_OBJECT_HANDLE,
-    pWrappedKey: CK_BYTE_PTR,
-    ulWrappedKeyLen: CK_ULONG,
-    pTemplate: CK_ATTRIBUTE_PTR,
-    ulAttributeCount: CK_ULONG,
-    phKey: CK_OBJECT_HANDLE_PTR
+cryptoki_fn!(
+    unsafe fn C_UnwrapKey(
+        hSession: CK_SESSION_HANDLE,
+        pMechanism: CK_MECHANISM_PTR,
+        hUnwrappingKey: CK_OBJECT_HANDLE,
+        pWrappedKey: CK_BYTE_PTR,
+        ulWrappedKeyLen: CK_ULONG,
+        pTemplate: CK_ATTRIBUTE_PTR,
+        ulAttributeCount: CK_ULONG,
+        phKey: CK_OBJECT_HANDLE_PTR,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pMechanism, "C_UnwrapKey: pMechanism");
+        not_null!(pWrappedKey, "C_UnwrapKey: pWrappedKey");
+        not_null!(pTemplate, "C_UnwrapKey: pTemplate");
+        not_null!(phKey, "C_UnwrapKey: phKey");
+
+        debug!(
+            "C_UnwrapKey: session: {hSession:?}, pMechanism: {pMechanism:?}, hUnwrappingKey: \
+             {hUnwrappingKey:?}, ulWrappedKeyLen: {ulWrappedKeyLen:?}, pTemplate: {pTemplate:?}, \
+             ulAttributeCount: {ulAttributeCount:?}"
+        );
+        let attributes = Attributes::try_from((pTemplate, ulAttributeCount))
+            .context("C_UnwrapKey: attributes conversion failed")?;
+
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+            let wrapped_key =
+                unsafe { slice::from_raw_parts(pWrappedKey, usize::try_from(ulWrappedKeyLen)?) };
+
+            unsafe {
+                *phKey = session.unwrap_key(
+                    hSession,
+                    mechanism,
+                    hUnwrappingKey,
+                    wrapped_key,
+                    &attributes,
+                )?;
+            };
+
+            Ok(())
+        })
+    }
 );
 
-cryptoki_fn_not_supported!(
-    C_DeriveKey,
-    hSession: CK_SESSION_HANDLE,
-    pMechanism: CK_MECHANISM_PTR,
-    hBaseKey: CK_OBJECT_HANDLE,
-    pTemplate: CK_ATTRIBUTE_PTR,
-    ulAttributeCount: CK_ULONG,
-    phKey: CK_OBJECT_HANDLE_PTR
+cryptoki_fn!(
+    unsafe fn C_DeriveKey(
+        hSession: CK_SESSION_HANDLE,
+        pMechanism: CK_MECHANISM_PTR,
+        hBaseKey: CK_OBJECT_HANDLE,
+        pTemplate: CK_ATTRIBUTE_PTR,
+        ulAttributeCount: CK_ULONG,
+        phKey: CK_OBJECT_HANDLE_PTR,
+    ) {
+        initialized!();
+        let slot_id = valid_session!(hSession);
+        logged_in!(slot_id);
+        not_null!(pMechanism, "C_DeriveKey: pMechanism");
+        not_null!(pTemplate, "C_DeriveKey: pTemplate");
+        not_null!(phKey, "C_DeriveKey: phKey");
+
+        debug!(
+            "C_DeriveKey: session: {hSession:?}, pMechanism: {pMechanism:?}, hBaseKey: \
+             {hBaseKey:?}, pTemplate: {pTemplate:?}, ulAttributeCount: {ulAttributeCount:?}"
+        );
+        let attributes = Attributes::try_from((pTemplate, ulAttributeCount))
+            .context("C_DeriveKey: attributes conversion failed")?;
+
+        sessions::session(hSession, |session| -> ModuleResult<()> {
+            let mechanism = unsafe { parse_mechanism(pMechanism.read()) }?;
+
+            unsafe {
+                *phKey = session.derive_key(hSession, mechanism, hBaseKey, &attributes)?;
+            };
+
+            Ok(())
+        })
+    }
 );
 
 cryptoki_fn!(
@@ -18,25 +18,33 @@
 // limitations under the License.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{self, Arc, atomic::Ordering},
 };
 
-use cosmian_logger::{debug, trace, warn};
+use cosmian_logger::{debug, error, trace, warn};
 use pkcs11_sys::{
-    CK_BYTE_PTR, CK_FLAGS, CK_OBJECT_CLASS, CK_OBJECT_HANDLE, CK_SESSION_HANDLE, CK_ULONG,
-    CK_ULONG_PTR,
+    CK_BYTE_PTR, CK_FLAGS, CK_MECHANISM_TYPE, CK_OBJECT_HANDLE, CK_SESSION_HANDLE, CK_SLOT_ID,
+    CK_ULONG, CK_ULONG_PTR, CKK_AES, CKK_EC, CKK_GENERIC_SECRET, CKK_RSA, CKO_CERTIFICATE,
+    CKO_DATA, CKO_PRIVATE_KEY, CKO_SECRET_KEY,
 };
+use rsa::{BigUint, RsaPrivateKey, pkcs8::EncodePrivateKey};
+use zeroize::Zeroizing;
 
 use crate::{
     MResultHelper, ModuleError, ModuleResult,
     core::{
-        attribute::Attributes,
-        mechanism::Mechanism,
+        attribute::{Attribute, AttributeType, Attributes},
+        digest::DigestContext,
+        mechanism::{Mechanism, mechanism_key_type},
         object::{Object, ObjectType},
     },
-    objects_store::OBJECTS_STORE,
-    traits::{DecryptContext, EncryptContext, KeyAlgorithm, SearchOptions, SignContext, backend},
+    objects_store::{OBJECTS_STORE, ObjectsStore},
+    traits::{
+        Certificate, DecryptContext, EncryptContext, EncryptionAlgorithm, KeyAlgorithm,
+        KeyDerivationAlgorithm, KeyWrappingAlgorithm, PrivateKey, SearchOptions, SearchTemplate,
+        SignContext, SymmetricKey, VerifyContext, backend_for,
+    },
 };
 
 /// Prefix used to identify Oracle Key Management (KM) encryption keys.
@@ -60,20 +68,52 @@ static NEXT_SESSION_HANDLE: sync::atomic::AtomicU64 = sync::atomic::AtomicU64::n
 #[cfg(target_os = "windows")]
 static NEXT_SESSION_HANDLE: sync::atomic::AtomicU32 = sync::atomic::AtomicU32::new(1);
 
-type SessionMap = HashMap<CK_SESSION_HANDLE, Session>;
+type SessionMap = HashMap<CK_SESSION_HANDLE, OpenSession>;
 
 static SESSIONS: std::sync::LazyLock<sync::Mutex<SessionMap>> =
     std::sync::LazyLock::new(Default::default);
 
+/// The slots on which the user is logged in.
+/// The login state is shared by all the sessions of a slot.
+static LOGGED_IN: std::sync::LazyLock<sync::Mutex<HashSet<CK_SLOT_ID>>> =
+    std::sync::LazyLock::new(Default::default);
+
+/// An entry of the sessions map.
+/// The operations state of the session has its own lock, so that the sessions
+/// map is not locked while an operation waits for the backend.
+struct OpenSession {
+    flags: CK_FLAGS,
+    /// The slot on which the session was opened
+    slot_id: CK_SLOT_ID,
+    session: Arc<sync::Mutex<Session>>,
+}
+
+impl OpenSession {
+    fn new(flags: CK_FLAGS, slot_id: CK_SLOT_ID) -> Self {
+        Self {
+            flags,
+            slot_id,
+            session: Arc::new(sync::Mutex::new(Session {
+                slot_id,
+                ..Session::default()
+            })),
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Session {
-    flags: CK_FLAGS,
+    /// The slot on which the session was opened: its objects and operations
+    /// are the ones of the backend of this slot
+    pub slot_id: CK_SLOT_ID,
     /// The objects found by `C_FindObjectsInit`
     /// and that have not yet been read by `C_FindObjects`
     pub find_objects_ctx: Vec<CK_OBJECT_HANDLE>,
     pub sign_ctx: Option<SignContext>,
+    pub verify_ctx: Option<VerifyContext>,
     pub decrypt_ctx: Option<DecryptContext>,
     pub encrypt_ctx: Option<EncryptContext>,
+    pub digest_ctx: Option<DigestContext>,
 }
 
 impl Session {
@@ -82,11 +122,30 @@ impl Session {
         object: Arc<Object>,
     ) -> ModuleResult<CK_OBJECT_HANDLE> {
         let mut objects_store = OBJECTS_STORE.write()?;
-        let handle = objects_store.upsert(object);
+        let handle = objects_store.upsert(self.slot_id, object);
         self.find_objects_ctx.push(handle);
         Ok(handle)
     }
 
+    /// Register a newly created object in the objects store.
+    ///
+    /// Objects are token objects unless `CKA_TOKEN` is explicitly false in
+    /// their template, in which case they are owned by `session`
+    /// and destroyed when it is closed.
+    fn register_object(
+        &self,
+        objects_store: &mut ObjectsStore,
+        session: CK_SESSION_HANDLE,
+        object: Arc<Object>,
+        attributes: &Attributes,
+    ) -> CK_OBJECT_HANDLE {
+        if attributes.get_token().is_ok_and(|token| !token) {
+            objects_store.upsert_session_object(self.slot_id, object, session)
+        } else {
+            objects_store.upsert(self.slot_id, object)
+        }
+    }
+
     /// Conversion example:
     /// Map
     /// `ORACLE.SECURITY.KM.ENCRYPTION.30363946333744303931413733443446313342463243453932314542324346303830`
@@ -116,156 +175,112 @@ impl Session {
                 "load_find_context: empty attributes".to_owned(),
             ));
         }
-        // Find all objects
-        for object in backend().find_all_objects()? {
-            self.update_find_objects_context(object)?;
+        let mut template = SearchTemplate::from(attributes);
+        if let Some(label) = &template.label {
+            template.label = Some(Self::map_oracle_tde_security_to_mk(label)?);
         }
+        debug!("load_find_context: loading for template: {template:?}");
+        self.clear_find_objects_ctx();
 
-        let search_class = attributes.get_class();
-        if let Ok(search_class) = search_class {
-            self.load_find_context_by_class(attributes, search_class)
-        } else {
-            let label = attributes.get_label()?;
-            let label = Self::map_oracle_tde_security_to_mk(&label)?;
-            let find_ctx = OBJECTS_STORE.read()?;
-            debug!(
-                "load_find_context: loading for label: {label:?} and attributes: {attributes:?}"
-            );
-            debug!("load_find_context: display current store: {find_ctx}");
-            if let Some((object, handle)) = find_ctx.get_using_id(&label) {
+        match (template.class, template.id.clone()) {
+            (Some(pkcs11_sys::CKO_CERTIFICATE), Some(id)) => {
+                self.load_certificate_by_id(&id)?;
+            }
+            (Some(pkcs11_sys::CKO_PUBLIC_KEY), Some(id)) => {
+                // The CKA_ID may be the one of the matching private key:
+                // the backend resolves the public key
+                match backend_for(self.slot_id)?.find_public_key(SearchOptions::Id(id.clone())) {
+                    Ok(public_key) => {
+                        let handle = self
+                            .update_find_objects_context(Arc::new(Object::PublicKey(public_key)))?;
+                        debug!(
+                            "load_find_context: search by id: {id:?} -> handle: {handle} -> \
+                             public key"
+                        );
+                    }
+                    Err(e) => {
+                        warn!("load_find_context: no public key for id {id:?}: {e}");
+                    }
+                }
+            }
+            (class, _) => {
+                if class == Some(pkcs11_sys::CKO_CERTIFICATE) {
+                    attributes.ensure_X509_or_none()?;
+                }
+                let objects = backend_for(self.slot_id)?.find_objects(&template)?;
+                let handles = objects
+                    .into_iter()
+                    .map(|object| self.update_find_objects_context(object))
+                    .collect::<ModuleResult<Vec<_>>>()?;
                 debug!(
-                    "load_find_context: search by id: {label} -> handle: {} -> object: {}: {}",
-                    handle,
-                    object.name(),
-                    object.remote_id()
+                    "load_find_context: added {} objects with handles: {:?}",
+                    handles.len(),
+                    handles
                 );
-                self.clear_find_objects_ctx();
-                self.add_to_find_objects_ctx(handle);
-            } else {
-                warn!("load_find_context: id {label} not found in store");
-                self.clear_find_objects_ctx();
-                return Ok(());
             }
-            Ok(())
-        }?;
+        }
 
         trace!("load_find_context succeeded");
         Ok(())
     }
 
-    #[expect(clippy::too_many_lines)]
-    pub(crate) fn load_find_context_by_class(
-        &mut self,
-        attributes: &Attributes,
-        search_class: CK_OBJECT_CLASS,
-    ) -> ModuleResult<()> {
-        if attributes.is_empty() {
-            return Err(ModuleError::BadArguments(
-                "load_find_context_by_class: empty attributes".to_owned(),
-            ));
-        }
-        let search_options = SearchOptions::try_from(attributes)?;
-        debug!(
-            "load_find_context_by_class: loading for class: {search_class:?} and options: \
-             {search_options:?}, attributes: {attributes:?}",
-        );
-        match search_options {
-            SearchOptions::All => {
-                self.clear_find_objects_ctx();
-                let res = match search_class {
-                    pkcs11_sys::CKO_CERTIFICATE => {
-                        attributes.ensure_X509_or_none()?;
-                        backend()
-                            .find_all_certificates()?
-                            .into_iter()
-                            .map(|c| {
-                                self.update_find_objects_context(Arc::new(Object::Certificate(c)))
-                            })
-                            .collect::<ModuleResult<Vec<_>>>()?
-                    }
-                    pkcs11_sys::CKO_PUBLIC_KEY => backend()
-                        .find_all_public_keys()?
-                        .into_iter()
-                        .map(|c| self.update_find_objects_context(Arc::new(Object::PublicKey(c))))
-                        .collect::<ModuleResult<Vec<_>>>()?,
-                    pkcs11_sys::CKO_PRIVATE_KEY => backend()
-                        .find_all_private_keys()?
-                        .into_iter()
-                        .map(|c| self.update_find_objects_context(Arc::new(Object::PrivateKey(c))))
-                        .collect::<ModuleResult<Vec<_>>>()?,
-                    pkcs11_sys::CKO_SECRET_KEY => backend()
-                        .find_all_symmetric_keys()?
-                        .into_iter()
-                        .map(|c| {
-                            self.update_find_objects_context(Arc::new(Object::SymmetricKey(c)))
-                        })
-                        .collect::<ModuleResult<Vec<_>>>()?,
-                    pkcs11_sys::CKO_DATA => backend()
-                        .find_all_data_objects()?
-                        .into_iter()
-                        .map(|c| self.update_find_objects_context(Arc::new(Object::DataObject(c))))
-                        .collect::<ModuleResult<Vec<_>>>()?,
-                    o => return Err(ModuleError::Todo(format!("Object not supported: {o}"))),
-                };
-                debug!(
-                    "load_find_context_by_class: added {} objects with handles: {:?}",
-                    res.len(),
-                    res
-                );
-            }
-
-            SearchOptions::Id(cka_id) => {
-                if search_class == pkcs11_sys::CKO_CERTIFICATE {
-                    let id = String::from_utf8(cka_id)?;
-                    // Find certificates which have this CKA_ID as private key ID
-                    let find_ctx = OBJECTS_STORE.read()?;
-                    let certificates = find_ctx.get_using_type(&ObjectType::Certificate);
-                    for (object, handle) in certificates {
-                        match &*object {
-                            Object::Certificate(c) => {
-                                if c.private_key_id() == id {
-                                    debug!(
-                                        "load_find_context_by_class: search by id: {} -> handle: \
-                                         {} -> certificate: {}:{}",
-                                        id,
-                                        handle,
-                                        object.name(),
-                                        object.remote_id()
-                                    );
-                                    self.clear_find_objects_ctx();
-                                    self.add_to_find_objects_ctx(handle);
-                                }
-                            }
-                            //TODO may be we should treat Public Keys the same as Certificates
-                            o => {
-                                return Err(ModuleError::Todo(format!(
-                                    "This should not happen, returning: {:?}",
-                                    o.object_type()
-                                )));
-                            }
+    /// Find the certificate which has this CKA_ID as private key ID,
+    /// first in the objects store then in the backend.
+    fn load_certificate_by_id(&mut self, id: &[u8]) -> ModuleResult<()> {
+        self.clear_find_objects_ctx();
+        let handle = {
+            let find_ctx = OBJECTS_STORE.read()?;
+            let mut found = None;
+            for (object, handle) in find_ctx.get_using_type(self.slot_id, &ObjectType::Certificate)
+            {
+                match &*object {
+                    Object::Certificate(c) => {
+                        if c.metadata().id.as_deref() == Some(id)
+                            || c.private_key_id().as_bytes() == id
+                            || c.remote_id().as_bytes() == id
+                        {
+                            debug!(
+                                "load_certificate_by_id: search by id: {:?} -> handle: {} -> \
+                                 certificate: {}:{}",
+                                id,
+                                handle,
+                                object.name(),
+                                object.remote_id()
+                            );
+                            found = Some(handle);
                         }
                     }
-                } else {
-                    let id = String::from_utf8(cka_id)?;
-
-                    let find_ctx = OBJECTS_STORE.read()?;
-                    let (object, handle) = find_ctx.get_using_id(&id).ok_or_else(|| {
-                        ModuleError::BadArguments(format!(
-                            "load_find_context_by_class: id {id} not found in store"
-                        ))
-                    })?;
-                    debug!(
-                        "load_find_context_by_class: search by id: {} -> handle: {} -> object: \
-                         {}:{}",
-                        id,
-                        handle,
-                        object.name(),
-                        object.remote_id()
-                    );
-                    self.clear_find_objects_ctx();
-                    self.add_to_find_objects_ctx(handle);
+                    o => {
+                        return Err(ModuleError::Todo(format!(
+                            "This should not happen, returning: {:?}",
+                            o.object_type()
+                        )));
+                    }
+                }
+            }
+            found
+        };
+        let certificate = if handle.is_some() {
+            None
+        } else {
+            match backend_for(self.slot_id)?.find_certificate(SearchOptions::Id(id.to_vec())) {
+                Ok(certificate) => certificate,
+                // An unknown CKA_ID matches no certificate
+                Err(ModuleError::ObjectNotFound(e)) => {
+                    debug!("load_certificate_by_id: {e}");
+                    None
                 }
+                Err(e) => return Err(e),
             }
+        };
+        if let Some(handle) = handle {
+            self.add_to_find_objects_ctx(handle);
+        } else if let Some(certificate) = certificate {
+            let handle =
+                self.update_find_objects_context(Arc::new(Object::Certificate(certificate)))?;
+            debug!("load_certificate_by_id: search by id: {id:?} -> handle: {handle} -> backend");
+        } else {
+            warn!("load_certificate_by_id: no certificate found for id {id:?}");
         }
         Ok(())
     }
@@ -290,23 +305,32 @@ impl Session {
         let Some(sign_ctx) = self.sign_ctx.as_mut() else {
             return Err(ModuleError::OperationNotInitialized(0));
         };
-        let data = data
-            .or(sign_ctx.payload.as_deref())
-            .ok_or(ModuleError::OperationNotInitialized(0))?;
-        let signature = match sign_ctx.private_key.sign(&sign_ctx.algorithm, data) {
-            Ok(sig) => sig,
-            Err(e) => {
-                return Err(ModuleError::BadArguments(format!(
-                    "signature failed: {e:?}"
-                )));
+        let signature = if let Some(signature) = sign_ctx.signature.take() {
+            signature
+        } else {
+            let data = data
+                .or(sign_ctx.payload.as_deref())
+                .ok_or(ModuleError::OperationNotInitialized(0))?;
+            match backend_for(self.slot_id)?.sign(
+                &sign_ctx.remote_object_id,
+                &sign_ctx.algorithm,
+                data,
+            ) {
+                Ok(sig) => sig,
+                Err(e) => {
+                    return Err(ModuleError::BadArguments(format!(
+                        "signature failed: {e:?}"
+                    )));
+                }
             }
         };
-        if !pSignature.is_null() {
-            // TODO(bweeks): This will cause a second sign call when this function is
-            // called again with an appropriately-sized buffer. Do we really need to
-            // sign twice for ECDSA? Consider storing the signature in the ctx for the next
-            // call.
+        if pSignature.is_null() {
+            // The signature is computed remotely: keep it for the next call,
+            // made with an appropriately-sized buffer, instead of signing twice.
+            sign_ctx.signature = Some(signature.clone());
+        } else {
             if (unsafe { usize::try_from(*pulSignatureLen)? }) < signature.len() {
+                sign_ctx.signature = Some(signature);
                 return Err(ModuleError::BufferTooSmall);
             }
             unsafe { std::slice::from_raw_parts_mut(pSignature, signature.len()) }
@@ -319,6 +343,25 @@ impl Session {
         Ok(())
     }
 
+    /// Verify the signature against `data`, or against the payload accumulated
+    /// by `C_VerifyUpdate` when `data` is `None`.
+    /// The verify operation is terminated, whatever the result.
+    pub(crate) fn verify(&mut self, data: Option<&[u8]>, signature: &[u8]) -> ModuleResult<()> {
+        let verify_ctx = self
+            .verify_ctx
+            .take()
+            .ok_or(ModuleError::OperationNotInitialized(0))?;
+        let data = data
+            .or(verify_ctx.payload.as_deref())
+            .ok_or(ModuleError::OperationNotInitialized(0))?;
+        backend_for(self.slot_id)?.verify(
+            &verify_ctx.remote_object_id,
+            &verify_ctx.algorithm,
+            data,
+            signature,
+        )
+    }
+
     pub(crate) fn decrypt(
         &mut self,
         ciphertext: Vec<u8>,
@@ -329,7 +372,7 @@ impl Session {
             .decrypt_ctx
             .as_ref()
             .ok_or_else(|| ModuleError::OperationNotInitialized(0))?;
-        let cleartext = backend().decrypt(decrypt_ctx, ciphertext)?;
+        let cleartext = backend_for(self.slot_id)?.decrypt(decrypt_ctx, ciphertext)?;
         unsafe {
             if pData.is_null() {
                 *pulDataLen = cleartext.len() as CK_ULONG;
@@ -351,26 +394,164 @@ impl Session {
         pEncryptedData: CK_BYTE_PTR,
         pulEncryptedDataLen: CK_ULONG_PTR,
     ) -> ModuleResult<()> {
-        let encrypt_ctx = self
-            .encrypt_ctx
-            .as_ref()
-            .ok_or_else(|| ModuleError::OperationNotInitialized(0))?;
-        let ciphertext = backend().encrypt(encrypt_ctx, cleartext)?;
+        let Some(encrypt_ctx) = self.encrypt_ctx.as_mut() else {
+            return Err(ModuleError::OperationNotInitialized(0));
+        };
+        let ciphertext = if let Some(ciphertext) = encrypt_ctx.pending_output.take() {
+            ciphertext
+        } else {
+            Zeroizing::new(backend_for(self.slot_id)?.encrypt(encrypt_ctx, cleartext)?)
+        };
+        if pEncryptedData.is_null() {
+            // The ciphertext is computed remotely: keep it for the next call,
+            // made with an appropriately-sized buffer, instead of encrypting twice.
+            encrypt_ctx.pending_output = Some(ciphertext.clone());
+        } else {
+            if (unsafe { usize::try_from(*pulEncryptedDataLen)? }) < ciphertext.len() {
+                encrypt_ctx.pending_output = Some(ciphertext);
+                return Err(ModuleError::BufferTooSmall);
+            }
+            unsafe { std::slice::from_raw_parts_mut(pEncryptedData, ciphertext.len()) }
+                .copy_from_slice(&ciphertext);
+            self.encrypt_ctx = None;
+        }
         unsafe {
-            *pulEncryptedDataLen = ciphertext.len() as CK_ULONG;
-            if !pEncryptedData.is_null() {
-                if (usize::try_from(*pulEncryptedDataLen)?) < ciphertext.len() {
-                    return Err(ModuleError::BufferTooSmall);
-                }
-                std::slice::from_raw_parts_mut(pEncryptedData, ciphertext.len())
-                    .copy_from_slice(&ciphertext);
+            *pulEncryptedDataLen = ciphertext.len().try_into()?;
+        }
+        Ok(())
+    }
+
+    /// Digest the provided data, or terminate a multipart digest if data is not provided.
+    /// `session_handle` is the handle of this session.
+    pub(crate) unsafe fn digest(
+        &mut self,
+        session_handle: CK_SESSION_HANDLE,
+        data: Option<&[u8]>,
+        pDigest: CK_BYTE_PTR,
+        pulDigestLen: CK_ULONG_PTR,
+    ) -> ModuleResult<()> {
+        let Some(digest_ctx) = self.digest_ctx.as_mut() else {
+            return Err(ModuleError::OperationNotInitialized(session_handle));
+        };
+        // The digest length is known beforehand: the context is only consumed
+        // once the caller provides an appropriately-sized buffer.
+        let digest_len = digest_ctx.digest_len();
+        unsafe {
+            if !pDigest.is_null() && usize::try_from(*pulDigestLen)? < digest_len {
+                *pulDigestLen = digest_len.try_into()?;
+                return Err(ModuleError::BufferTooSmall);
+            }
+            *pulDigestLen = digest_len.try_into()?;
+        }
+        if pDigest.is_null() {
+            return Ok(());
+        }
+        if let Some(data) = data {
+            digest_ctx.update(data)?;
+        }
+        let digest = self
+            .digest_ctx
+            .take()
+            .ok_or(ModuleError::OperationNotInitialized(session_handle))?
+            .finalize();
+        unsafe { std::slice::from_raw_parts_mut(pDigest, digest.len()) }.copy_from_slice(&digest);
+        Ok(())
+    }
+
+    /// Encrypt a part of a multipart encryption, or terminate it when `part` is `None`.
+    pub(crate) unsafe fn encrypt_multipart(
+        &mut self,
+        part: Option<Vec<u8>>,
+        pEncryptedPart: CK_BYTE_PTR,
+        pulEncryptedPartLen: CK_ULONG_PTR,
+    ) -> ModuleResult<()> {
+        let Some(encrypt_ctx) = self.encrypt_ctx.as_mut() else {
+            return Err(ModuleError::OperationNotInitialized(0));
+        };
+        if !encrypt_ctx.algorithm.is_multipart() {
+            error!(
+                "encrypt_multipart: unsupported algorithm: {:?}",
+                encrypt_ctx.algorithm
+            );
+            return Err(ModuleError::FunctionNotSupported);
+        }
+        let is_final = part.is_none();
+        let output = if let Some(output) = encrypt_ctx.pending_output.take() {
+            output
+        } else if is_final
+            && encrypt_ctx.correlation_value.is_none()
+            && !matches!(encrypt_ctx.algorithm, EncryptionAlgorithm::AesCbcPad)
+        {
+            // No part has been sent to the KMS and there is no padding block to add
+            Zeroizing::new(vec![])
+        } else {
+            Zeroizing::new(backend_for(self.slot_id)?.encrypt_multipart(encrypt_ctx, part)?)
+        };
+        if pEncryptedPart.is_null() {
+            // The KMS state has moved forward: keep the output for the next call,
+            // made with an appropriately-sized buffer, instead of encrypting twice.
+            encrypt_ctx.pending_output = Some(output.clone());
+        } else {
+            if (unsafe { usize::try_from(*pulEncryptedPartLen)? }) < output.len() {
+                encrypt_ctx.pending_output = Some(output);
+                return Err(ModuleError::BufferTooSmall);
+            }
+            unsafe { std::slice::from_raw_parts_mut(pEncryptedPart, output.len()) }
+                .copy_from_slice(&output);
+            if is_final {
                 self.encrypt_ctx = None;
             }
         }
+        unsafe {
+            *pulEncryptedPartLen = output.len().try_into()?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt a part of a multipart decryption, or terminate it when `part` is `None`.
+    pub(crate) unsafe fn decrypt_multipart(
+        &mut self,
+        part: Option<Vec<u8>>,
+        pPart: CK_BYTE_PTR,
+        pulPartLen: CK_ULONG_PTR,
+    ) -> ModuleResult<()> {
+        let Some(decrypt_ctx) = self.decrypt_ctx.as_mut() else {
+            return Err(ModuleError::OperationNotInitialized(0));
+        };
+        if !decrypt_ctx.algorithm.is_multipart() {
+            error!(
+                "decrypt_multipart: unsupported algorithm: {:?}",
+                decrypt_ctx.algorithm
+            );
+            return Err(ModuleError::FunctionNotSupported);
+        }
+        let is_final = part.is_none();
+        let output = if let Some(output) = decrypt_ctx.pending_output.take() {
+            output
+        } else {
+            backend_for(self.slot_id)?.decrypt_multipart(decrypt_ctx, part)?
+        };
+        if pPart.is_null() {
+            decrypt_ctx.pending_output = Some(output.clone());
+        } else {
+            if (unsafe { usize::try_from(*pulPartLen)? }) < output.len() {
+                decrypt_ctx.pending_output = Some(output);
+                return Err(ModuleError::BufferTooSmall);
+            }
+            unsafe { std::slice::from_raw_parts_mut(pPart, output.len()) }.copy_from_slice(&output);
+            if is_final {
+                self.decrypt_ctx = None;
+            }
+        }
+        unsafe {
+            *pulPartLen = output.len().try_into()?;
+        }
         Ok(())
     }
 
     pub(crate) fn generate_key(
+        &self,
+        session: CK_SESSION_HANDLE,
         mechanism: Mechanism,
         attributes: &Attributes,
     ) -> ModuleResult<CK_OBJECT_HANDLE> {
@@ -385,25 +566,297 @@ impl Session {
             mechanism, attributes
         );
 
-        let mut objects_store = OBJECTS_STORE.write()?;
-
-        let key_length = attributes.get_value_len()?;
+        let key_length = usize::try_from(attributes.get_value_len()?)?;
         let sensitive = attributes.get_sensitive()?;
         let label = attributes.get_label()?;
 
-        let object = backend().generate_key(
-            KeyAlgorithm::try_from(mechanism)?,
-            key_length.try_into()?,
+        let object = backend_for(self.slot_id)?.generate_key(
+            mechanism.key_gen_algorithm(key_length)?,
+            key_length,
             sensitive,
+            attributes.extractable_or_default(self.slot_id)?,
             Some(&label),
         )?;
-        let handle = objects_store.upsert(Arc::new(Object::SymmetricKey(object)));
+        let handle = self.register_object(
+            &mut *OBJECTS_STORE.write()?,
+            session,
+            Arc::new(Object::SymmetricKey(object)),
+            attributes,
+        );
 
         debug!("generate_key: generated key with handle: {handle}");
         Ok(handle)
     }
 
-    pub(crate) fn create_object(attributes: &Attributes) -> ModuleResult<CK_OBJECT_HANDLE> {
+    /// Generate a key pair in the backend and register both keys in the objects store.
+    ///
+    /// Returns the `(public key handle, private key handle)` tuple.
+    pub(crate) fn generate_key_pair(
+        &self,
+        session: CK_SESSION_HANDLE,
+        mechanism: Mechanism,
+        public_key_attributes: &Attributes,
+        private_key_attributes: &Attributes,
+    ) -> ModuleResult<(CK_OBJECT_HANDLE, CK_OBJECT_HANDLE)> {
+        debug!(
+            "generate_key_pair: mechanism: {mechanism:?}, public key attributes: \
+             {public_key_attributes:?}, private key attributes: {private_key_attributes:?}"
+        );
+
+        let (algorithm, key_length) = match mechanism {
+            Mechanism::RsaPkcsKeyPairGen => (
+                KeyAlgorithm::Rsa,
+                Some(usize::try_from(public_key_attributes.get_modulus_bits()?)?),
+            ),
+            Mechanism::EcKeyPairGen
+            | Mechanism::EcEdwardsKeyPairGen
+            | Mechanism::EcMontgomeryKeyPairGen => {
+                // The curve must match the key type generated by the mechanism
+                let key_type = mechanism_key_type(CK_MECHANISM_TYPE::from(&mechanism));
+                let algorithm =
+                    KeyAlgorithm::from_ec_params(&public_key_attributes.get_ec_params()?)
+                        .filter(|algorithm| Some(algorithm.to_ck_key_type()) == key_type)
+                        .ok_or(ModuleError::AttributeValueInvalid(AttributeType::EcParams))?;
+                (algorithm, None)
+            }
+            m => return Err(ModuleError::MechanismInvalid(CK_MECHANISM_TYPE::from(&m))),
+        };
+
+        // The label and id are usually set on both templates: the private key
+        // template takes precedence.
+        let label = private_key_attributes
+            .get_label()
+            .or_else(|_| public_key_attributes.get_label())
+            .ok();
+        let id = private_key_attributes
+            .get_id()
+            .or_else(|_| public_key_attributes.get_id())
+            .ok();
+        let sensitive = private_key_attributes.get_sensitive().unwrap_or(false);
+
+        let (public_key, private_key) = backend_for(self.slot_id)?.generate_key_pair(
+            algorithm,
+            key_length,
+            sensitive,
+            private_key_attributes.extractable_or_default(self.slot_id)?,
+            id.as_deref(),
+            label.as_deref(),
+        )?;
+
+        let mut objects_store = OBJECTS_STORE.write()?;
+        let public_key_handle = self.register_object(
+            &mut objects_store,
+            session,
+            Arc::new(Object::PublicKey(public_key)),
+            public_key_attributes,
+        );
+        let private_key_handle = self.register_object(
+            &mut objects_store,
+            session,
+            Arc::new(Object::PrivateKey(private_key)),
+            private_key_attributes,
+        );
+
+        debug!(
+            "generate_key_pair: generated public key handle: {public_key_handle}, private key \
+             handle: {private_key_handle}"
+        );
+        Ok((public_key_handle, private_key_handle))
+    }
+
+    /// Wrap the key `key_handle` with the key `wrapping_key_handle`.
+    ///
+    /// AES key wrapping requires a symmetric wrapping key, RSA OAEP a public key.
+    /// The wrapping key must allow wrapping (`CKA_WRAP`) and the key must be extractable.
+    pub(crate) fn wrap_key(
+        &self,
+        mechanism: Mechanism,
+        wrapping_key_handle: CK_OBJECT_HANDLE,
+        key_handle: CK_OBJECT_HANDLE,
+    ) -> ModuleResult<Vec<u8>> {
+        debug!(
+            "wrap_key: mechanism: {mechanism:?}, wrapping key: {wrapping_key_handle}, key: \
+             {key_handle}"
+        );
+        let objects_store = OBJECTS_STORE.read()?;
+        let wrapping_key = objects_store
+            .get_using_handle(self.slot_id, wrapping_key_handle)
+            .ok_or(ModuleError::ObjectHandleInvalid(wrapping_key_handle))?;
+        let key = objects_store
+            .get_using_handle(self.slot_id, key_handle)
+            .ok_or(ModuleError::ObjectHandleInvalid(key_handle))?;
+        drop(objects_store);
+
+        let algorithm = KeyWrappingAlgorithm::try_from(mechanism)?;
+        let wrapping_key_id = match (&algorithm, wrapping_key.as_ref()) {
+            (
+                KeyWrappingAlgorithm::AesKeyWrap | KeyWrappingAlgorithm::AesKeyWrapPad,
+                Object::SymmetricKey(k),
+            ) => k.remote_id(),
+            (KeyWrappingAlgorithm::RsaOaep { .. }, Object::PublicKey(k)) => k.remote_id(),
+            (_, o) => {
+                error!("wrap_key: invalid wrapping key: {o:?} for algorithm {algorithm:?}");
+                return Err(ModuleError::KeyHandleInvalid(wrapping_key_handle));
+            }
+        };
+        let (key_id, metadata) = match key.as_ref() {
+            Object::SymmetricKey(k) => (k.remote_id(), k.metadata()),
+            Object::PrivateKey(k) => (k.remote_id(), k.metadata()),
+            o => {
+                error!("wrap_key: the object cannot be wrapped: {o:?}");
+                return Err(ModuleError::KeyHandleInvalid(key_handle));
+            }
+        };
+        if wrapping_key.attribute(AttributeType::Wrap)? != Some(Attribute::Wrap(true)) {
+            return Err(ModuleError::KeyFunctionNotPermitted(wrapping_key_handle));
+        }
+        // A key which is not extractable cannot leave the token, not even wrapped
+        if !metadata.is_extractable() {
+            return Err(ModuleError::KeyUnextractable(key_handle));
+        }
+
+        backend_for(self.slot_id)?.wrap_key(&wrapping_key_id, &key_id, &algorithm)
+    }
+
+    /// Unwrap `wrapped_key` with the key `unwrapping_key_handle` into a new
+    /// secret key registered in the objects store.
+    ///
+    /// AES key unwrapping requires a symmetric unwrapping key, RSA OAEP a private key.
+    pub(crate) fn unwrap_key(
+        &self,
+        session: CK_SESSION_HANDLE,
+        mechanism: Mechanism,
+        unwrapping_key_handle: CK_OBJECT_HANDLE,
+        wrapped_key: &[u8],
+        attributes: &Attributes,
+    ) -> ModuleResult<CK_OBJECT_HANDLE> {
+        debug!(
+            "unwrap_key: mechanism: {mechanism:?}, unwrapping key: {unwrapping_key_handle}, \
+             attributes: {attributes:?}"
+        );
+        let class = attributes.get_class().unwrap_or(CKO_SECRET_KEY);
+        if class != CKO_SECRET_KEY {
+            error!("unwrap_key: only secret keys can be unwrapped, got class: {class}");
+            return Err(ModuleError::AttributeValueInvalid(AttributeType::Class));
+        }
+
+        let unwrapping_key = OBJECTS_STORE
+            .read()?
+            .get_using_handle(self.slot_id, unwrapping_key_handle)
+            .ok_or(ModuleError::ObjectHandleInvalid(unwrapping_key_handle))?;
+        let algorithm = KeyWrappingAlgorithm::try_from(mechanism)?;
+        let unwrapping_key_id = match (&algorithm, unwrapping_key.as_ref()) {
+            (
+                KeyWrappingAlgorithm::AesKeyWrap | KeyWrappingAlgorithm::AesKeyWrapPad,
+                Object::SymmetricKey(k),
+            ) => k.remote_id(),
+            (KeyWrappingAlgorithm::RsaOaep { .. }, Object::PrivateKey(k)) => k.remote_id(),
+            (_, o) => {
+                error!("unwrap_key: invalid unwrapping key: {o:?} for algorithm {algorithm:?}");
+                return Err(ModuleError::KeyHandleInvalid(unwrapping_key_handle));
+            }
+        };
+        let label = attributes.get_label().ok();
+
+        let object = backend_for(self.slot_id)?.unwrap_key(
+            &unwrapping_key_id,
+            &algorithm,
+            wrapped_key,
+            label.as_deref(),
+        )?;
+        let handle = self.register_object(
+            &mut *OBJECTS_STORE.write()?,
+            session,
+            Arc::new(Object::SymmetricKey(object)),
+            attributes,
+        );
+
+        debug!("unwrap_key: unwrapped key with handle: {handle}");
+        Ok(handle)
+    }
+
+    /// Derive a new secret key from the key `base_key_handle` and register
+    /// it in the objects store.
+    ///
+    /// ECDH requires an EC private key, HKDF and PBKDF2 a symmetric key or a secret data.
+    pub(crate) fn derive_key(
+        &self,
+        session: CK_SESSION_HANDLE,
+        mechanism: Mechanism,
+        base_key_handle: CK_OBJECT_HANDLE,
+        attributes: &Attributes,
+    ) -> ModuleResult<CK_OBJECT_HANDLE> {
+        debug!(
+            "derive_key: mechanism: {mechanism:?}, base key: {base_key_handle}, attributes: \
+             {attributes:?}"
+        );
+        let class = attributes.get_class().unwrap_or(CKO_SECRET_KEY);
+        if class != CKO_SECRET_KEY {
+            error!("derive_key: only secret keys can be derived, got class: {class}");
+            return Err(ModuleError::AttributeValueInvalid(AttributeType::Class));
+        }
+
+        let base_key = OBJECTS_STORE
+            .read()?
+            .get_using_handle(self.slot_id, base_key_handle)
+            .ok_or(ModuleError::ObjectHandleInvalid(base_key_handle))?;
+        let base_key_id = match (&mechanism, base_key.as_ref()) {
+            (Mechanism::Ecdh1Derive { .. }, Object::PrivateKey(k)) if k.algorithm().is_ecc() => {
+                k.remote_id()
+            }
+            (
+                Mechanism::HkdfDerive { .. } | Mechanism::Pkcs5Pbkd2 { .. },
+                Object::SymmetricKey(k),
+            ) => k.remote_id(),
+            (
+                Mechanism::HkdfDerive { .. } | Mechanism::Pkcs5Pbkd2 { .. },
+                Object::DataObject(d),
+            ) => d.remote_id(),
+            (_, o) => {
+                error!("derive_key: invalid base key: {o:?} for mechanism {mechanism:?}");
+                return Err(ModuleError::KeyHandleInvalid(base_key_handle));
+            }
+        };
+        let key_length = attributes
+            .get_value_len()
+            .ok()
+            .map(usize::try_from)
+            .transpose()?;
+        // The derived key is an AES key unless the template asks for a generic secret
+        let key_algorithm = match attributes.get_key_type().unwrap_or(CKK_AES) {
+            CKK_AES => key_length
+                .map_or(Some(KeyAlgorithm::Aes256), KeyAlgorithm::aes)
+                .ok_or(ModuleError::AttributeValueInvalid(AttributeType::ValueLen))?,
+            CKK_GENERIC_SECRET => KeyAlgorithm::GenericSecret,
+            _ => return Err(ModuleError::AttributeValueInvalid(AttributeType::KeyType)),
+        };
+        let label = attributes.get_label().ok();
+
+        let object = backend_for(self.slot_id)?.derive_key(
+            &base_key_id,
+            &KeyDerivationAlgorithm::try_from(mechanism)?,
+            key_algorithm,
+            key_length,
+            attributes.get_sensitive().unwrap_or(false),
+            attributes.extractable_or_default(self.slot_id)?,
+            label.as_deref(),
+        )?;
+        let handle = self.register_object(
+            &mut *OBJECTS_STORE.write()?,
+            session,
+            Arc::new(Object::SymmetricKey(object)),
+            attributes,
+        );
+
+        debug!("derive_key: derived key with handle: {handle}");
+        Ok(handle)
+    }
+
+    pub(crate) fn create_object(
+        &self,
+        session: CK_SESSION_HANDLE,
+        attributes: &Attributes,
+    ) -> ModuleResult<CK_OBJECT_HANDLE> {
         if attributes.is_empty() {
             return Err(ModuleError::BadArguments(
                 "create_object: empty attributes".to_owned(),
@@ -412,38 +865,144 @@ impl Session {
 
         debug!("create_object: attributes: {attributes:?}");
 
-        let mut objects_store = OBJECTS_STORE.write()?;
         let class = attributes.get_class()?;
         trace!("create_object: class: {class:?}");
-        let label = attributes.get_label()?;
-        let value = attributes.get_value()?;
         let object = match class {
-            pkcs11_sys::CKO_DATA => backend().create_object(&label, &value)?,
+            CKO_DATA => {
+                let label = attributes.get_label()?;
+                let value = attributes.get_value()?;
+                Object::DataObject(backend_for(self.slot_id)?.create_object(&label, &value)?)
+            }
+            CKO_SECRET_KEY => Object::SymmetricKey(self.import_symmetric_key(attributes)?),
+            CKO_PRIVATE_KEY => Object::PrivateKey(self.import_private_key(attributes)?),
+            CKO_CERTIFICATE => Object::Certificate(self.import_certificate(attributes)?),
             o => {
                 trace!("create_object: Object not supported: {o}");
                 return Err(ModuleError::Todo(format!("Object not supported: {o}")));
             }
         };
 
-        let handle = objects_store.upsert(Arc::new(Object::DataObject(object)));
+        let mut objects_store = OBJECTS_STORE.write()?;
+        let handle =
+            self.register_object(&mut objects_store, session, Arc::new(object), attributes);
 
         debug!("create_object: created object with handle: {handle}");
         Ok(handle)
     }
 
-    pub(crate) fn destroy_object(handle: CK_OBJECT_HANDLE) -> ModuleResult<()> {
-        debug!("destroy_object: handle: {handle}");
+    /// Import the secret key of a `C_CreateObject` template from its `CKA_VALUE`
+    fn import_symmetric_key(&self, attributes: &Attributes) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        attributes.ensure_present(&[AttributeType::KeyType, AttributeType::Value])?;
+        let value = Zeroizing::new(attributes.get_value()?);
+        let algorithm = match attributes.get_key_type()? {
+            CKK_AES => KeyAlgorithm::aes(value.len()),
+            CKK_GENERIC_SECRET => (!value.is_empty()).then_some(KeyAlgorithm::GenericSecret),
+            _ => return Err(ModuleError::AttributeValueInvalid(AttributeType::KeyType)),
+        }
+        .ok_or(ModuleError::AttributeValueInvalid(AttributeType::Value))?;
+        backend_for(self.slot_id)?.import_symmetric_key(
+            algorithm,
+            value,
+            attributes.get_sensitive().unwrap_or(false),
+            attributes.extractable_or_default(self.slot_id)?,
+            attributes.get_label().ok().as_deref(),
+        )
+    }
 
-        let mut objects_store = OBJECTS_STORE.write()?;
-        match objects_store.get_using_handle(handle) {
-            Some(object) => {
-                backend().revoke_object(&object.remote_id())?;
-                backend().destroy_object(&object.remote_id())?;
+    /// Import the private key of a `C_CreateObject` template, converted to PKCS#8
+    fn import_private_key(&self, attributes: &Attributes) -> ModuleResult<Arc<dyn PrivateKey>> {
+        attributes.ensure_present(&[AttributeType::KeyType])?;
+        let (algorithm, pkcs8) = match attributes.get_key_type()? {
+            CKK_RSA => (KeyAlgorithm::Rsa, rsa_private_key_pkcs8(attributes)?),
+            CKK_EC => ec_private_key_pkcs8(attributes)?,
+            _ => return Err(ModuleError::AttributeValueInvalid(AttributeType::KeyType)),
+        };
+        let id = attributes.get_id().ok();
+        backend_for(self.slot_id)?.import_private_key(
+            algorithm,
+            pkcs8,
+            attributes.get_sensitive().unwrap_or(false),
+            attributes.extractable_or_default(self.slot_id)?,
+            id.as_deref(),
+            attributes.get_label().ok().as_deref(),
+        )
+    }
+
+    /// Import the X.509 certificate of a `C_CreateObject` template.
+    /// Its optional `CKA_ID` is the `CKA_ID` of its private key.
+    fn import_certificate(&self, attributes: &Attributes) -> ModuleResult<Arc<dyn Certificate>> {
+        attributes.ensure_X509_or_none()?;
+        attributes.ensure_present(&[AttributeType::Value])?;
+        let id = attributes.get_id().ok();
+        backend_for(self.slot_id)?.import_certificate(
+            &attributes.get_value()?,
+            id.as_deref(),
+            attributes.get_label().ok().as_deref(),
+        )
+    }
+
+    /// Apply a `C_SetAttributeValue` template to the object `handle`, then replace
+    /// the object of the store by the one refreshed from the backend.
+    /// The attributes already set to the requested value are left untouched;
+    /// the other ones must be modifiable.
+    pub(crate) fn set_attribute_value(
+        &self,
+        handle: CK_OBJECT_HANDLE,
+        template: &Attributes,
+    ) -> ModuleResult<()> {
+        debug!("set_attribute_value: handle: {handle}, template: {template:?}");
+
+        let object = OBJECTS_STORE
+            .read()?
+            .get_using_handle(self.slot_id, handle)
+            .ok_or(ModuleError::ObjectHandleInvalid(handle))?;
+        let mut changes = Vec::with_capacity(template.len());
+        for attribute in template.iter() {
+            let type_ = attribute.attribute_type();
+            if object
+                .attribute(self.slot_id, type_)
+                .ok()
+                .flatten()
+                .as_ref()
+                == Some(attribute)
+            {
+                continue;
             }
-            None => return Err(ModuleError::ObjectHandleInvalid(handle)),
+            if !object.is_modifiable(type_) {
+                return Err(ModuleError::AttributeReadOnly(type_));
+            }
+            changes.push(attribute.clone());
+        }
+        if changes.is_empty() {
+            return Ok(());
         }
 
-        objects_store.remove_by_handle(handle)?;
+        let object = evict_if_not_found(
+            handle,
+            backend_for(self.slot_id)?.set_attributes(&object, &changes.into()),
+        )?;
+        let handle = OBJECTS_STORE.write()?.upsert(self.slot_id, object);
+        debug!("set_attribute_value: refreshed object with handle: {handle}");
+        Ok(())
+    }
+
+    pub(crate) fn destroy_object(&self, handle: CK_OBJECT_HANDLE) -> ModuleResult<()> {
+        debug!("destroy_object: handle: {handle}");
+
+        let Some(object) = OBJECTS_STORE.read()?.get_using_handle(self.slot_id, handle) else {
+            return Err(ModuleError::ObjectHandleInvalid(handle));
+        };
+        let result = backend_for(self.slot_id).and_then(|backend| {
+            backend.revoke_object(&object.remote_id())?;
+            backend.destroy_object(&object.remote_id())
+        });
+        if matches!(result, Ok(()) | Err(ModuleError::ObjectNotFound(_))) {
+            // The handle of an object destroyed elsewhere is stale: evict it too
+            OBJECTS_STORE
+                .write()?
+                .remove_by_handle(self.slot_id, handle)?;
+        }
+        result?;
         debug!("destroy_object: handle: {handle}");
 
         Ok(())
@@ -458,18 +1017,12 @@ fn ignore_sessions() -> bool {
 }
 
 #[expect(clippy::expect_used)]
-pub(crate) fn create(flags: CK_FLAGS) -> CK_SESSION_HANDLE {
+pub(crate) fn create(slot_id: CK_SLOT_ID, flags: CK_FLAGS) -> CK_SESSION_HANDLE {
     if ignore_sessions() {
         {
             let mut session_map = SESSIONS.lock().expect("failed locking the sessions map");
             if session_map.is_empty() {
-                session_map.insert(
-                    0,
-                    Session {
-                        flags,
-                        ..Default::default()
-                    },
-                );
+                session_map.insert(0, OpenSession::new(flags, slot_id));
             }
         }
         0
@@ -478,64 +1031,243 @@ pub(crate) fn create(flags: CK_FLAGS) -> CK_SESSION_HANDLE {
         SESSIONS
             .lock()
             .expect("failed locking the sessions map")
-            .insert(
-                handle,
-                Session {
-                    flags,
-                    ..Default::default()
-                },
-            );
+            .insert(handle, OpenSession::new(flags, slot_id));
         handle
     }
 }
 
-pub(crate) fn exists(handle: CK_SESSION_HANDLE) -> ModuleResult<bool> {
+pub(crate) fn flags(handle: CK_SESSION_HANDLE) -> ModuleResult<CK_FLAGS> {
     Ok(SESSIONS
         .lock()
         .context("failed locking the sessions map")?
-        .contains_key(&handle))
+        .get(&handle)
+        .ok_or_else(|| ModuleError::SessionHandleInvalid(handle))?
+        .flags)
 }
 
-pub(crate) fn flags(handle: CK_SESSION_HANDLE) -> ModuleResult<CK_FLAGS> {
+pub(crate) fn slot_id(handle: CK_SESSION_HANDLE) -> ModuleResult<CK_SLOT_ID> {
     Ok(SESSIONS
         .lock()
         .context("failed locking the sessions map")?
         .get(&handle)
         .ok_or_else(|| ModuleError::SessionHandleInvalid(handle))?
-        .flags)
+        .slot_id)
 }
 
 pub(crate) fn session<F>(h: CK_SESSION_HANDLE, callback: F) -> ModuleResult<()>
 where
     F: FnOnce(&mut Session) -> ModuleResult<()>,
 {
-    let mut session_map = SESSIONS.lock().context("failed locking the sessions map")?;
-    let session = session_map
-        .get_mut(&h)
-        .ok_or(ModuleError::SessionHandleInvalid(h))?;
+    let session = SESSIONS
+        .lock()
+        .context("failed locking the sessions map")?
+        .get(&h)
+        .ok_or(ModuleError::SessionHandleInvalid(h))?
+        .session
+        .clone();
     debug!("session: {h} found");
-    callback(session)
+    // Only this session is locked while the callback calls the backend
+    let mut session = session.lock().context("failed locking the session")?;
+    callback(&mut session)
 }
 
 pub(crate) fn close(handle: CK_SESSION_HANDLE) -> ModuleResult<bool> {
     if !ignore_sessions() {
-        return Ok(SESSIONS
-            .lock()
-            .context("failed locking the sessions map")?
-            .remove(&handle)
-            .is_some());
+        let (session, last) = {
+            let mut session_map = SESSIONS.lock().context("failed locking the sessions map")?;
+            let Some(session) = session_map.remove(&handle) else {
+                return Ok(false);
+            };
+            let last = !session_map
+                .values()
+                .any(|other| other.slot_id == session.slot_id);
+            (session, last)
+        };
+        destroy_session_objects(session.slot_id, handle)?;
+        // Closing the last session of a slot logs the user out
+        if last {
+            logout_slot(session.slot_id)?;
+        }
+        return Ok(true);
     }
     Ok(true)
 }
 
-pub(crate) fn close_all() -> ModuleResult<()> {
-    SESSIONS
+/// Close all the sessions opened on `slot_id`
+pub(crate) fn close_all(slot_id: CK_SLOT_ID) -> ModuleResult<()> {
+    let handles = {
+        let mut session_map = SESSIONS.lock().context("failed locking the sessions map")?;
+        let handles = session_map
+            .iter()
+            .filter(|(_, session)| session.slot_id == slot_id)
+            .map(|(handle, _)| *handle)
+            .collect::<Vec<_>>();
+        for handle in &handles {
+            session_map.remove(handle);
+        }
+        handles
+    };
+    for handle in handles {
+        destroy_session_objects(slot_id, handle)?;
+    }
+    logout_slot(slot_id)?;
+    Ok(())
+}
+
+/// Log the user in on `slot_id`.
+/// When the token requires a login, the backend unlocks its credentials with `pin`.
+pub(crate) fn login(slot_id: CK_SLOT_ID, pin: &[u8]) -> ModuleResult<()> {
+    let mut logged_in = LOGGED_IN.lock().context("failed locking the login state")?;
+    if logged_in.contains(&slot_id) {
+        return Err(ModuleError::UserAlreadyLoggedIn);
+    }
+    let backend = backend_for(slot_id)?;
+    if backend.login_required() {
+        backend.login(pin)?;
+    }
+    logged_in.insert(slot_id);
+    debug!("login: user logged in on slot {slot_id}");
+    Ok(())
+}
+
+/// Log the user out of `slot_id`.
+pub(crate) fn logout(slot_id: CK_SLOT_ID) -> ModuleResult<()> {
+    if !logout_slot(slot_id)? {
+        return Err(ModuleError::UserNotLoggedIn);
+    }
+    Ok(())
+}
+
+/// Log the user out of `slot_id`, and return whether the user was logged in.
+fn logout_slot(slot_id: CK_SLOT_ID) -> ModuleResult<bool> {
+    let logged_out = LOGGED_IN
+        .lock()
+        .context("failed locking the login state")?
+        .remove(&slot_id);
+    if logged_out {
+        backend_for(slot_id)?.logout();
+        debug!("logout: user logged out of slot {slot_id}");
+    }
+    Ok(logged_out)
+}
+
+/// Close the sessions of all the slots, destroying their session objects,
+/// and forget the login state of all the slots; called by `C_Finalize`.
+pub(crate) fn finalize() -> ModuleResult<()> {
+    let sessions = SESSIONS
         .lock()
         .context("failed locking the sessions map")?
+        .drain()
+        .map(|(handle, session)| (handle, session.slot_id))
+        .collect::<Vec<_>>();
+    for (handle, slot_id) in sessions {
+        destroy_session_objects(slot_id, handle)?;
+    }
+    LOGGED_IN
+        .lock()
+        .context("failed locking the login state")?
         .clear();
     Ok(())
 }
 
+/// Evict the object of `handle` from the objects store when the backend reports
+/// that its remote object no longer exists, so that the stale handle is not used again.
+/// The objects store must not be locked by the caller.
+pub(crate) fn evict_if_not_found<T>(
+    handle: CK_OBJECT_HANDLE,
+    result: ModuleResult<T>,
+) -> ModuleResult<T> {
+    if let Err(ModuleError::ObjectNotFound(e)) = &result {
+        debug!("evict_if_not_found: evicting handle {handle}: {e}");
+        OBJECTS_STORE.write()?.evict_handle(handle);
+    }
+    result
+}
+
+/// Whether the user is logged in on `slot_id`
+pub(crate) fn logged_in(slot_id: CK_SLOT_ID) -> ModuleResult<bool> {
+    Ok(LOGGED_IN
+        .lock()
+        .context("failed locking the login state")?
+        .contains(&slot_id))
+}
+
+/// Whether the objects of the token of `slot_id` can be used:
+/// the token does not require a login, or the user is logged in.
+pub(crate) fn authorized(slot_id: CK_SLOT_ID) -> ModuleResult<bool> {
+    Ok(!backend_for(slot_id)?.login_required() || logged_in(slot_id)?)
+}
+
+/// Encode the RSA private key of a template as PKCS#8.
+/// The CRT components are computed again from the primes, which are
+/// recovered from the exponents when the template does not have them.
+fn rsa_private_key_pkcs8(attributes: &Attributes) -> ModuleResult<Zeroizing<Vec<u8>>> {
+    attributes.ensure_present(&[
+        AttributeType::Modulus,
+        AttributeType::PublicExponent,
+        AttributeType::PrivateExponent,
+    ])?;
+    let primes = match (attributes.get_prime_1(), attributes.get_prime_2()) {
+        (Ok(p), Ok(q)) => vec![BigUint::from_bytes_be(&p), BigUint::from_bytes_be(&q)],
+        _ => vec![],
+    };
+    let private_key = RsaPrivateKey::from_components(
+        BigUint::from_bytes_be(&attributes.get_modulus()?),
+        BigUint::from_bytes_be(&attributes.get_public_exponent()?),
+        BigUint::from_bytes_be(&Zeroizing::new(attributes.get_private_exponent()?)),
+        primes,
+    )
+    .and_then(|private_key| private_key.validate().map(|()| private_key))
+    .map_err(|e| {
+        error!("rsa_private_key_pkcs8: invalid RSA private key: {e}");
+        ModuleError::AttributeValueInvalid(AttributeType::PrivateExponent)
+    })?;
+    let pkcs8 = private_key
+        .to_pkcs8_der()
+        .map_err(|e| ModuleError::Cryptography(format!("PKCS#8 encoding failed: {e}")))?;
+    Ok(Zeroizing::new(pkcs8.as_bytes().to_vec()))
+}
+
+/// Encode the EC private key of a template as PKCS#8: only P-256 keys are supported.
+fn ec_private_key_pkcs8(
+    attributes: &Attributes,
+) -> ModuleResult<(KeyAlgorithm, Zeroizing<Vec<u8>>)> {
+    attributes.ensure_present(&[AttributeType::EcParams, AttributeType::Value])?;
+    if KeyAlgorithm::from_ec_params(&attributes.get_ec_params()?) != Some(KeyAlgorithm::EccP256) {
+        return Err(ModuleError::AttributeValueInvalid(AttributeType::EcParams));
+    }
+    let private_key = p256::SecretKey::from_slice(&Zeroizing::new(attributes.get_value()?))
+        .map_err(|e| {
+            error!("ec_private_key_pkcs8: invalid P-256 private key: {e}");
+            ModuleError::AttributeValueInvalid(AttributeType::Value)
+        })?;
+    let pkcs8 = private_key
+        .to_pkcs8_der()
+        .map_err(|e| ModuleError::Cryptography(format!("PKCS#8 encoding failed: {e}")))?;
+    Ok((
+        KeyAlgorithm::EccP256,
+        Zeroizing::new(pkcs8.as_bytes().to_vec()),
+    ))
+}
+
+/// Evict the session objects of a closed session from the objects store
+/// and destroy them in the backend of its slot `slot_id`.
+fn destroy_session_objects(slot_id: CK_SLOT_ID, handle: CK_SESSION_HANDLE) -> ModuleResult<()> {
+    let objects = OBJECTS_STORE.write()?.evict_session_objects(handle);
+    for object in objects {
+        let remote_id = object.remote_id();
+        debug!("close: destroying session object: {remote_id}");
+        // The session is closed anyway: failures are only logged
+        if let Err(e) = backend_for(slot_id).and_then(|backend| {
+            backend.revoke_object(&remote_id)?;
+            backend.destroy_object(&remote_id)
+        }) {
+            error!("close: failed destroying session object {remote_id}: {e}");
+        }
+    }
+    Ok(())
+}
+
 #[allow(clippy::unwrap_used)]
 #[cfg(test)]
 mod tests {
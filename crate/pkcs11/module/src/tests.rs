@@ -5,13 +5,33 @@ use std::{
 
 use cosmian_logger::log_init;
 use pkcs11_sys::{
-    CK_ATTRIBUTE, CK_C_INITIALIZE_ARGS, CK_C_INITIALIZE_ARGS_PTR, CK_FALSE, CK_FUNCTION_LIST,
-    CK_FUNCTION_LIST_PTR_PTR, CK_INFO, CK_INVALID_HANDLE, CK_MECHANISM_INFO, CK_MECHANISM_TYPE,
-    CK_OBJECT_HANDLE, CK_SESSION_INFO, CK_SLOT_INFO, CK_TOKEN_INFO, CK_ULONG, CK_VOID_PTR,
-    CKA_CLASS, CKF_SERIAL_SESSION, CKM_DSA, CKO_PRIVATE_KEY, CKR_ARGUMENTS_BAD,
-    CKR_BUFFER_TOO_SMALL, CKR_CRYPTOKI_ALREADY_INITIALIZED, CKR_CRYPTOKI_NOT_INITIALIZED,
-    CKR_FUNCTION_NOT_PARALLEL, CKR_MECHANISM_INVALID, CKR_OBJECT_HANDLE_INVALID, CKR_OK,
-    CKR_SESSION_HANDLE_INVALID, CKR_SESSION_PARALLEL_NOT_SUPPORTED, CKR_SLOT_ID_INVALID,
+    CK_AES_CTR_PARAMS, CK_ATTRIBUTE, CK_ATTRIBUTE_TYPE, CK_C_INITIALIZE_ARGS,
+    CK_C_INITIALIZE_ARGS_PTR, CK_ECDH1_DERIVE_PARAMS, CK_EDDSA_PARAMS, CK_FALSE, CK_FUNCTION_LIST,
+    CK_FUNCTION_LIST_PTR_PTR, CK_GCM_PARAMS, CK_HKDF_PARAMS, CK_INFO, CK_INVALID_HANDLE,
+    CK_KEY_TYPE, CK_MECHANISM, CK_MECHANISM_INFO, CK_MECHANISM_TYPE, CK_OBJECT_HANDLE,
+    CK_RSA_PKCS_OAEP_PARAMS, CK_RV, CK_SESSION_HANDLE, CK_SESSION_INFO, CK_SLOT_INFO,
+    CK_TOKEN_INFO, CK_TRUE, CK_ULONG, CK_UNAVAILABLE_INFORMATION, CK_VOID_PTR, CKA_APPLICATION,
+    CKA_CERTIFICATE_TYPE, CKA_CLASS, CKA_EC_PARAMS, CKA_EC_POINT, CKA_EXTRACTABLE, CKA_ID,
+    CKA_KEY_TYPE, CKA_LABEL, CKA_MODULUS, CKA_MODULUS_BITS, CKA_PRIME_1, CKA_PRIME_2,
+    CKA_PRIVATE_EXPONENT, CKA_PUBLIC_EXPONENT, CKA_SENSITIVE, CKA_SIGN, CKA_START_DATE, CKA_TOKEN,
+    CKA_VALUE, CKA_VALUE_LEN, CKC_X_509, CKD_NULL, CKD_SHA1_KDF, CKF_DECRYPT, CKF_ENCRYPT,
+    CKF_HKDF_SALT_DATA, CKF_LOGIN_REQUIRED, CKF_PROTECTED_AUTHENTICATION_PATH, CKF_SERIAL_SESSION,
+    CKF_SIGN, CKF_VERIFY, CKG_MGF1_SHA256, CKK_AES, CKK_EC, CKK_EC_EDWARDS, CKK_GENERIC_SECRET,
+    CKK_RSA, CKM_AES_CBC_PAD, CKM_AES_CTR, CKM_AES_GCM, CKM_AES_KEY_GEN, CKM_AES_KEY_WRAP,
+    CKM_AES_KEY_WRAP_PAD, CKM_DSA, CKM_EC_EDWARDS_KEY_PAIR_GEN, CKM_EC_KEY_PAIR_GEN,
+    CKM_ECDH1_DERIVE, CKM_EDDSA, CKM_GENERIC_SECRET_KEY_GEN, CKM_HKDF_DERIVE,
+    CKM_RSA_PKCS_KEY_PAIR_GEN, CKM_RSA_PKCS_OAEP, CKM_SHA_1, CKM_SHA256, CKM_SHA256_HMAC,
+    CKM_SHA256_RSA_PKCS, CKO_CERTIFICATE, CKO_DATA, CKO_PRIVATE_KEY, CKO_SECRET_KEY,
+    CKR_ARGUMENTS_BAD, CKR_ATTRIBUTE_READ_ONLY, CKR_ATTRIBUTE_SENSITIVE,
+    CKR_ATTRIBUTE_TYPE_INVALID, CKR_ATTRIBUTE_VALUE_INVALID, CKR_BUFFER_TOO_SMALL,
+    CKR_CRYPTOKI_ALREADY_INITIALIZED, CKR_CRYPTOKI_NOT_INITIALIZED, CKR_DEVICE_ERROR,
+    CKR_FUNCTION_NOT_PARALLEL, CKR_GENERAL_ERROR, CKR_KEY_FUNCTION_NOT_PERMITTED,
+    CKR_KEY_HANDLE_INVALID, CKR_KEY_INDIGESTIBLE, CKR_KEY_UNEXTRACTABLE, CKR_MECHANISM_INVALID,
+    CKR_MECHANISM_PARAM_INVALID, CKR_OBJECT_HANDLE_INVALID, CKR_OK, CKR_OPERATION_NOT_INITIALIZED,
+    CKR_PIN_INCORRECT, CKR_SESSION_HANDLE_INVALID, CKR_SESSION_PARALLEL_NOT_SUPPORTED,
+    CKR_SIGNATURE_INVALID, CKR_SLOT_ID_INVALID, CKR_TEMPLATE_INCOMPLETE,
+    CKR_USER_ALREADY_LOGGED_IN, CKR_USER_NOT_LOGGED_IN, CKR_USER_TYPE_INVALID,
+    CKS_RO_PUBLIC_SESSION, CKS_RO_USER_FUNCTIONS, CKU_SO, CKU_USER, CKZ_DATA_SPECIFIED,
 };
 use serial_test::serial;
 use zeroize::{Zeroize, Zeroizing};
@@ -19,18 +39,27 @@ use zeroize::{Zeroize, Zeroizing};
 use super::*;
 use crate::{
     core::{
-        mechanism::{AES_IV_SIZE, SUPPORTED_SIGNATURE_MECHANISMS},
+        attribute::{Attribute, AttributeType, Attributes},
+        mechanism::{AES_IV_SIZE, SUPPORTED_MECHANISMS},
         object::Object,
     },
+    objects_store::OBJECTS_STORE,
     pkcs11::{
-        C_CloseSession, C_Finalize, C_FindObjects, C_FindObjectsFinal, C_FindObjectsInit,
-        C_GetAttributeValue, C_GetFunctionStatus, C_GetInfo, C_GetMechanismInfo,
-        C_GetMechanismList, C_GetSessionInfo, C_GetSlotInfo, C_GetSlotList, C_GetTokenInfo,
-        C_Initialize, C_OpenSession, FUNC_LIST, INITIALIZED, SLOT_ID,
+        C_CloseAllSessions, C_CloseSession, C_CreateObject, C_Decrypt, C_DecryptFinal,
+        C_DecryptInit, C_DecryptUpdate, C_DeriveKey, C_DestroyObject, C_Digest, C_DigestFinal,
+        C_DigestInit, C_DigestKey, C_DigestUpdate, C_Encrypt, C_EncryptFinal, C_EncryptInit,
+        C_EncryptUpdate, C_Finalize, C_FindObjects, C_FindObjectsFinal, C_FindObjectsInit,
+        C_GenerateKey, C_GenerateKeyPair, C_GetAttributeValue, C_GetFunctionStatus, C_GetInfo,
+        C_GetMechanismInfo, C_GetMechanismList, C_GetSessionInfo, C_GetSlotInfo, C_GetSlotList,
+        C_GetTokenInfo, C_Initialize, C_Login, C_Logout, C_OpenSession, C_SetAttributeValue,
+        C_Sign, C_SignInit, C_UnwrapKey, C_Verify, C_VerifyFinal, C_VerifyInit, C_VerifyUpdate,
+        C_WrapKey, FUNC_LIST, INITIALIZED, SLOT_ID,
     },
     traits::{
-        Backend, Certificate, DataObject, DecryptContext, EncryptContext, KeyAlgorithm, PrivateKey,
-        PublicKey, SearchOptions, SymmetricKey, Version, register_backend,
+        Backend, Certificate, DataObject, DecryptContext, DigestType, EncryptContext, KeyAlgorithm,
+        KeyDerivationAlgorithm, KeyMetadata, KeyUsage, KeyWrappingAlgorithm, PrivateKey, PublicKey,
+        SearchOptions, SearchTemplate, SignatureAlgorithm, SymmetricKey, Version, backend_for,
+        register_backend,
     },
 };
 
@@ -59,22 +88,22 @@ impl DataObject for DummyDataObject {
         self.remote_id.clone()
     }
 
-    fn value(&self) -> Zeroizing<Vec<u8>> {
-        self.value.clone()
+    fn value(&self) -> ModuleResult<Zeroizing<Vec<u8>>> {
+        Ok(self.value.clone())
     }
 
     fn application(&self) -> Vec<u8> {
         b"Test PKCS#11 Application".to_vec()
     }
 
-    fn data_hash(&self) -> Vec<u8> {
+    fn data_hash(&self) -> ModuleResult<Vec<u8>> {
         // Simple test hash - just the first 32 bytes repeated or padded
         let mut hash = vec![0_u8; 32];
         let data = self.value.as_slice();
         for (i, &byte) in data.iter().take(32).enumerate() {
             hash[i] = byte;
         }
-        hash
+        Ok(hash)
     }
 }
 
@@ -98,11 +127,151 @@ impl SymmetricKey for DummySymKey {
     }
 }
 
-struct TestBackend;
+/// A symmetric key whose metadata is known to the backend
+struct DummyMetadataKey(KeyAlgorithm, KeyMetadata);
+
+impl SymmetricKey for DummyMetadataKey {
+    fn remote_id(&self) -> String {
+        "dummy_metadata_key".to_owned()
+    }
+
+    fn algorithm(&self) -> KeyAlgorithm {
+        self.0
+    }
+
+    fn key_size(&self) -> usize {
+        match self.0 {
+            KeyAlgorithm::Aes128 => 16,
+            KeyAlgorithm::Aes192 => 24,
+            _ => 32,
+        }
+    }
+
+    fn raw_bytes(&self) -> ModuleResult<Zeroizing<Vec<u8>>> {
+        Ok(Zeroizing::new(vec![0; self.key_size()]))
+    }
+
+    fn metadata(&self) -> KeyMetadata {
+        self.1.clone()
+    }
+}
+
+struct DummyPublicKey(KeyAlgorithm);
+
+impl PublicKey for DummyPublicKey {
+    fn remote_id(&self) -> String {
+        "dummy_public_key".to_owned()
+    }
+
+    fn fingerprint(&self) -> ModuleResult<&[u8]> {
+        Ok(&[])
+    }
+
+    fn delete(self: Arc<Self>) {}
+
+    fn algorithm(&self) -> KeyAlgorithm {
+        self.0
+    }
+
+    fn rsa_public_key(&self) -> ModuleResult<pkcs1::RsaPublicKey<'_>> {
+        Err(ModuleError::FunctionNotSupported)
+    }
+
+    fn ec_public_point(&self) -> ModuleResult<Vec<u8>> {
+        match self.0 {
+            KeyAlgorithm::Ed25519 | KeyAlgorithm::X25519 => Ok(vec![0x2a; 32]),
+            _ => Err(ModuleError::FunctionNotSupported),
+        }
+    }
+}
+
+struct DummyPrivateKey(KeyAlgorithm, usize);
+
+impl PrivateKey for DummyPrivateKey {
+    fn remote_id(&self) -> String {
+        "dummy_private_key".to_owned()
+    }
+
+    fn algorithm(&self) -> KeyAlgorithm {
+        self.0
+    }
+
+    fn key_size(&self) -> usize {
+        self.1
+    }
+
+    fn pkcs8_der_bytes(&self) -> ModuleResult<Zeroizing<Vec<u8>>> {
+        Err(ModuleError::FunctionNotSupported)
+    }
+
+    fn rsa_public_exponent(&self) -> ModuleResult<Vec<u8>> {
+        Err(ModuleError::FunctionNotSupported)
+    }
+
+    fn public_key(&self) -> ModuleResult<Arc<dyn PublicKey>> {
+        Err(ModuleError::FunctionNotSupported)
+    }
+}
+
+#[derive(Debug)]
+struct DummyCertificate {
+    der: Vec<u8>,
+    private_key_id: String,
+}
+
+impl Certificate for DummyCertificate {
+    fn remote_id(&self) -> String {
+        "dummy_certificate".to_owned()
+    }
+
+    fn to_der(&self) -> ModuleResult<Vec<u8>> {
+        Ok(self.der.clone())
+    }
+
+    fn public_key(&self) -> ModuleResult<Box<dyn PublicKey>> {
+        Ok(Box::new(DummyPublicKey(KeyAlgorithm::Rsa)))
+    }
+
+    fn issuer(&self) -> ModuleResult<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn serial_number(&self) -> ModuleResult<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn subject(&self) -> ModuleResult<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn private_key_id(&self) -> String {
+        self.private_key_id.clone()
+    }
+}
+
+struct TestBackend {
+    token_label: [u8; 32],
+    /// The user PIN, when the token requires a login
+    pin: Option<&'static [u8]>,
+}
+
+impl Default for TestBackend {
+    fn default() -> Self {
+        Self {
+            token_label: *b"Foo software token              ",
+            pin: None,
+        }
+    }
+}
+
+/// The backends of the slots of the tests: a single slot
+fn test_backends() -> ModuleResult<Vec<Box<dyn Backend>>> {
+    Ok(vec![Box::new(TestBackend::default())])
+}
 
 impl Backend for TestBackend {
     fn token_label(&self) -> [u8; 32] {
-        *b"Foo software token              "
+        self.token_label
     }
 
     fn token_manufacturer_id(&self) -> [u8; 32] {
@@ -125,11 +294,25 @@ impl Backend for TestBackend {
         Version { major: 1, minor: 0 }
     }
 
-    fn find_certificate(
-        &self,
-        _query: SearchOptions,
-    ) -> ModuleResult<Option<Arc<dyn Certificate>>> {
-        Ok(None)
+    fn login_required(&self) -> bool {
+        self.pin.is_some()
+    }
+
+    fn login(&self, pin: &[u8]) -> ModuleResult<()> {
+        if self.pin.is_some_and(|expected| expected != pin) {
+            return Err(ModuleError::PinIncorrect);
+        }
+        Ok(())
+    }
+
+    /// No certificate is known by the backend
+    fn find_certificate(&self, query: SearchOptions) -> ModuleResult<Option<Arc<dyn Certificate>>> {
+        match query {
+            SearchOptions::Id(id) => Err(ModuleError::ObjectNotFound(
+                String::from_utf8_lossy(&id).into_owned(),
+            )),
+            SearchOptions::All => Ok(None),
+        }
     }
 
     fn find_all_certificates(&self) -> ModuleResult<Vec<Arc<dyn Certificate>>> {
@@ -168,25 +351,128 @@ impl Backend for TestBackend {
         Ok(vec![])
     }
 
-    fn find_all_objects(&self) -> ModuleResult<Vec<Arc<Object>>> {
-        Ok(vec![])
+    fn find_objects(&self, template: &SearchTemplate) -> ModuleResult<Vec<Arc<Object>>> {
+        // The only object of the test token is a 32-byte AES key
+        let matches = template.class.is_none_or(|class| class == CKO_SECRET_KEY)
+            && template.key_type.is_none_or(|key_type| key_type == CKK_AES)
+            && template.value_len.is_none_or(|value_len| value_len == 32);
+        Ok(if matches {
+            vec![Arc::new(Object::SymmetricKey(Arc::new(DummySymKey)))]
+        } else {
+            vec![]
+        })
     }
 
     fn generate_key(
         &self,
-        _algorithm: KeyAlgorithm,
+        algorithm: KeyAlgorithm,
         _key_length: usize,
-        _sensitive: bool,
+        sensitive: bool,
+        extractable: bool,
         _label: Option<&str>,
     ) -> ModuleResult<Arc<dyn SymmetricKey>> {
-        Ok(Arc::new(DummySymKey {}))
+        Ok(Arc::new(DummyMetadataKey(
+            algorithm,
+            KeyMetadata {
+                sensitive: Some(sensitive),
+                extractable: Some(extractable),
+                ..KeyMetadata::default()
+            },
+        )))
+    }
+
+    fn generate_key_pair(
+        &self,
+        algorithm: KeyAlgorithm,
+        key_length: Option<usize>,
+        _sensitive: bool,
+        _extractable: bool,
+        _id: Option<&[u8]>,
+        _label: Option<&str>,
+    ) -> ModuleResult<(Arc<dyn PublicKey>, Arc<dyn PrivateKey>)> {
+        Ok((
+            Arc::new(DummyPublicKey(algorithm)),
+            Arc::new(DummyPrivateKey(algorithm, key_length.unwrap_or(256))),
+        ))
     }
 
     fn create_object(&self, label: &str, data: &[u8]) -> ModuleResult<Arc<dyn DataObject>> {
         Ok(Arc::new(DummyDataObject::new(label, data)))
     }
 
-    fn revoke_object(&self, _remote_id: &str) -> ModuleResult<()> {
+    fn import_symmetric_key(
+        &self,
+        algorithm: KeyAlgorithm,
+        _value: Zeroizing<Vec<u8>>,
+        sensitive: bool,
+        extractable: bool,
+        _label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        Ok(Arc::new(DummyMetadataKey(
+            algorithm,
+            KeyMetadata {
+                sensitive: Some(sensitive),
+                extractable: Some(extractable),
+                ..KeyMetadata::default()
+            },
+        )))
+    }
+
+    fn import_private_key(
+        &self,
+        algorithm: KeyAlgorithm,
+        pkcs8: Zeroizing<Vec<u8>>,
+        _sensitive: bool,
+        _extractable: bool,
+        _id: Option<&[u8]>,
+        _label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn PrivateKey>> {
+        Ok(Arc::new(DummyPrivateKey(algorithm, pkcs8.len())))
+    }
+
+    fn import_certificate(
+        &self,
+        der: &[u8],
+        id: Option<&[u8]>,
+        _label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn Certificate>> {
+        Ok(Arc::new(DummyCertificate {
+            der: der.to_vec(),
+            private_key_id: String::from_utf8_lossy(id.unwrap_or_default()).into_owned(),
+        }))
+    }
+
+    fn set_attributes(
+        &self,
+        object: &Object,
+        attributes: &Attributes,
+    ) -> ModuleResult<Arc<Object>> {
+        // the CKA_ID of a certificate is the id of its private key
+        match (object, attributes.get(AttributeType::Id)) {
+            (Object::Certificate(certificate), Some(Attribute::Id(id))) => {
+                Ok(Arc::new(Object::Certificate(Arc::new(DummyCertificate {
+                    der: certificate.to_der()?,
+                    private_key_id: String::from_utf8(id.clone())?,
+                }))))
+            }
+            (Object::SymmetricKey(key), Some(Attribute::Id(id))) => {
+                Ok(Arc::new(Object::SymmetricKey(Arc::new(DummyMetadataKey(
+                    key.algorithm(),
+                    KeyMetadata {
+                        id: Some(id.clone()),
+                        ..key.metadata()
+                    },
+                )))))
+            }
+            _ => Ok(Arc::new(object.clone())),
+        }
+    }
+
+    fn revoke_object(&self, remote_id: &str) -> ModuleResult<()> {
+        // The "stale" data object is destroyed outside of the module
+        if remote_id == "test-data-stale" {
+            return Err(ModuleError::ObjectNotFound(remote_id.to_owned()));
+        }
         Ok(())
     }
 
@@ -194,6 +480,82 @@ impl Backend for TestBackend {
         Ok(())
     }
 
+    fn sign(
+        &self,
+        _remote_id: &str,
+        _algorithm: &SignatureAlgorithm,
+        _data: &[u8],
+    ) -> ModuleResult<Vec<u8>> {
+        Ok(vec![0; 64])
+    }
+
+    /// Only the dummy signature returned by `sign` is valid
+    fn verify(
+        &self,
+        _remote_id: &str,
+        _algorithm: &SignatureAlgorithm,
+        _data: &[u8],
+        signature: &[u8],
+    ) -> ModuleResult<()> {
+        if signature == [0; 64] {
+            Ok(())
+        } else {
+            Err(ModuleError::SignatureInvalid)
+        }
+    }
+
+    fn wrap_key(
+        &self,
+        _wrapping_key_id: &str,
+        _key_id: &str,
+        _algorithm: &KeyWrappingAlgorithm,
+    ) -> ModuleResult<Vec<u8>> {
+        // AES key wrap of a 256-bit key
+        Ok(vec![0; 40])
+    }
+
+    fn unwrap_key(
+        &self,
+        _unwrapping_key_id: &str,
+        _algorithm: &KeyWrappingAlgorithm,
+        _wrapped_key: &[u8],
+        _label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        Ok(Arc::new(DummySymKey {}))
+    }
+
+    fn derive_key(
+        &self,
+        _base_key_id: &str,
+        _algorithm: &KeyDerivationAlgorithm,
+        key_algorithm: KeyAlgorithm,
+        _key_length: Option<usize>,
+        sensitive: bool,
+        extractable: bool,
+        _label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        Ok(Arc::new(DummyMetadataKey(
+            key_algorithm,
+            KeyMetadata {
+                sensitive: Some(sensitive),
+                extractable: Some(extractable),
+                ..KeyMetadata::default()
+            },
+        )))
+    }
+
+    fn mechanism_info(
+        &self,
+        mechanism: CK_MECHANISM_TYPE,
+        info: CK_MECHANISM_INFO,
+    ) -> Option<CK_MECHANISM_INFO> {
+        (mechanism != CKM_AES_KEY_WRAP_PAD).then_some(info)
+    }
+
+    fn digest_key(&self, _key_id: &str, digest_type: &DigestType) -> ModuleResult<Vec<u8>> {
+        Ok(vec![0; digest_type.digest_len()])
+    }
+
     fn encrypt(&self, _encrypt_ctx: &EncryptContext, cleartext: Vec<u8>) -> ModuleResult<Vec<u8>> {
         Ok(vec![0; cleartext.len() + AES_IV_SIZE])
     }
@@ -205,6 +567,23 @@ impl Backend for TestBackend {
     ) -> ModuleResult<Zeroizing<Vec<u8>>> {
         Ok(Zeroizing::new(vec![0; 32]))
     }
+
+    fn encrypt_multipart(
+        &self,
+        encrypt_ctx: &mut EncryptContext,
+        part: Option<Vec<u8>>,
+    ) -> ModuleResult<Vec<u8>> {
+        encrypt_ctx.correlation_value = part.is_some().then(|| b"correlation".to_vec());
+        Ok(part.unwrap_or_else(|| vec![0; AES_IV_SIZE]))
+    }
+
+    fn decrypt_multipart(
+        &self,
+        _decrypt_ctx: &mut DecryptContext,
+        part: Option<Vec<u8>>,
+    ) -> ModuleResult<Zeroizing<Vec<u8>>> {
+        Ok(Zeroizing::new(part.unwrap_or_default()))
+    }
 }
 
 cryptoki_fn!(
@@ -213,7 +592,7 @@ cryptoki_fn!(
         unsafe {
             *ppFunctionList = addr_of_mut!(FUNC_LIST);
         }
-        register_backend(Box::new(TestBackend {}));
+        register_backend(test_backends);
         Ok(())
     }
 );
@@ -264,6 +643,46 @@ fn finalize() {
         C_Finalize(std::ptr::dangling_mut::<u32>().cast::<std::ffi::c_void>()),
         CKR_ARGUMENTS_BAD
     );
+
+    // C_Finalize closes all the sessions and destroys their session objects
+    let handle = test_open_session();
+    let session_object = test_create_data_object(handle, "finalized", false);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    assert!(
+        OBJECTS_STORE
+            .read()
+            .unwrap()
+            .get_using_handle(SLOT_ID, session_object)
+            .is_none()
+    );
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    assert_eq!(C_CloseSession(handle), CKR_SESSION_HANDLE_INVALID);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    // the backends are released
+    assert!(matches!(
+        backend_for(SLOT_ID),
+        Err(ModuleError::CryptokiNotInitialized)
+    ));
+}
+
+#[test]
+#[serial]
+fn initialize_backend_failure() {
+    test_init();
+    // a broken configuration or an unreachable KMS is reported by C_Initialize
+    register_backend(|| Err(ModuleError::Default("no configuration".to_owned())));
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_GENERAL_ERROR);
+    register_backend(|| Err(ModuleError::DeviceError("KMS unreachable".to_owned())));
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_DEVICE_ERROR);
+    let mut info = CK_INFO::default();
+    assert_eq!(
+        unsafe { C_GetInfo(&raw mut info) },
+        CKR_CRYPTOKI_NOT_INITIALIZED
+    );
+    // the backend is built again by the next call
+    register_backend(test_backends);
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    assert_eq!(unsafe { C_GetInfo(&raw mut info) }, CKR_OK);
     assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
 }
 
@@ -324,6 +743,94 @@ fn get_slot_list() {
     assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
 }
 
+#[test]
+#[serial]
+fn multiple_slots() {
+    test_init();
+    register_backend(|| {
+        Ok(vec![
+            Box::new(TestBackend::default()),
+            Box::new(TestBackend {
+                token_label: *b"Bar escrow token                ",
+                ..Default::default()
+            }),
+        ])
+    });
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+
+    let mut count = 0;
+    assert_eq!(
+        unsafe { C_GetSlotList(CK_FALSE, ptr::null_mut(), &raw mut count) },
+        CKR_OK
+    );
+    assert_eq!(count, 2);
+    let mut slots = vec![0; 2];
+    assert_eq!(
+        unsafe { C_GetSlotList(CK_FALSE, slots.as_mut_ptr(), &raw mut count) },
+        CKR_OK
+    );
+    assert_eq!(slots, vec![SLOT_ID, SLOT_ID + 1]);
+
+    // each slot exposes the token of its own backend
+    let mut info = CK_TOKEN_INFO::default();
+    assert_eq!(
+        unsafe { C_GetTokenInfo(SLOT_ID + 1, &raw mut info) },
+        CKR_OK
+    );
+    assert_eq!(&info.label, b"Bar escrow token                ");
+    assert_eq!(unsafe { C_GetTokenInfo(SLOT_ID, &raw mut info) }, CKR_OK);
+    assert_eq!(&info.label, b"Foo software token              ");
+    assert_eq!(
+        unsafe { C_GetTokenInfo(SLOT_ID + 2, &raw mut info) },
+        CKR_SLOT_ID_INVALID
+    );
+
+    let first_session = test_open_session();
+    let mut second_session = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID + 1,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut second_session,
+            )
+        },
+        CKR_OK
+    );
+    let mut session_info = CK_SESSION_INFO::default();
+    assert_eq!(
+        unsafe { C_GetSessionInfo(second_session, &raw mut session_info) },
+        CKR_OK
+    );
+    assert_eq!(session_info.slotID, SLOT_ID + 1);
+
+    // the objects of a slot are not visible from the other slots
+    let object = test_create_data_object(first_session, "slot-object", true);
+    assert_eq!(
+        unsafe { C_DestroyObject(second_session, object) },
+        CKR_OBJECT_HANDLE_INVALID
+    );
+    assert_eq!(unsafe { C_DestroyObject(first_session, object) }, CKR_OK);
+
+    // closing the sessions of a slot leaves the other slots untouched
+    assert_eq!(C_CloseAllSessions(SLOT_ID + 1), CKR_OK);
+    assert_eq!(
+        unsafe { C_GetSessionInfo(second_session, &raw mut session_info) },
+        CKR_SESSION_HANDLE_INVALID
+    );
+    assert_eq!(
+        unsafe { C_GetSessionInfo(first_session, &raw mut session_info) },
+        CKR_OK
+    );
+    assert_eq!(session_info.slotID, SLOT_ID);
+
+    assert_eq!(C_CloseSession(first_session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    register_backend(test_backends);
+}
+
 #[test]
 #[serial]
 fn get_slot_info() {
@@ -346,50 +853,148 @@ fn get_slot_info() {
 
 #[test]
 #[serial]
-fn get_token_info() {
+fn login_logout() {
     test_init();
+    register_backend(|| {
+        Ok(vec![Box::new(TestBackend {
+            pin: Some(b"1234"),
+            ..Default::default()
+        })])
+    });
     assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
-    unsafe {
-        assert_eq!(
-            C_GetTokenInfo(SLOT_ID, &mut CK_TOKEN_INFO::default()),
-            CKR_OK
-        );
-        // Expect CKR_SLOT_ID_INVALID if slotID references a nonexistent slot.
-        assert_eq!(
-            C_GetTokenInfo(SLOT_ID + 1, ptr::null_mut()),
-            CKR_SLOT_ID_INVALID
-        );
-        // Expect CKR_ARGUMENTS_BAD if pInfo is null.
-        assert_eq!(C_GetSlotInfo(SLOT_ID, ptr::null_mut()), CKR_ARGUMENTS_BAD);
-        // Expect CKR_CRYPTOKI_NOT_INITIALIZED if token is not initialized.
-        assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
-        assert_eq!(
-            C_GetTokenInfo(SLOT_ID, &mut CK_TOKEN_INFO::default()),
-            CKR_CRYPTOKI_NOT_INITIALIZED
-        );
-    }
-}
 
-#[test]
-#[serial]
-fn get_mechanism_list() {
-    test_init();
-    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
-    let mut count = 0;
-    unsafe {
-        assert_eq!(
-            C_GetMechanismList(SLOT_ID, ptr::null_mut(), &raw mut count),
-            CKR_OK
-        );
-        assert_ne!(count, 0);
-        let mut mechanisms =
-            Vec::<CK_MECHANISM_TYPE>::with_capacity(usize::try_from(count).unwrap());
-        assert_eq!(
-            C_GetMechanismList(SLOT_ID, mechanisms.as_mut_ptr(), &raw mut count),
+    let mut token_info = CK_TOKEN_INFO::default();
+    assert_eq!(
+        unsafe { C_GetTokenInfo(SLOT_ID, &raw mut token_info) },
+        CKR_OK
+    );
+    assert_ne!(token_info.flags & CKF_LOGIN_REQUIRED, 0);
+    assert_eq!(token_info.flags & CKF_PROTECTED_AUTHENTICATION_PATH, 0);
+
+    let session = test_open_session();
+    let mut session_info = CK_SESSION_INFO::default();
+    assert_eq!(
+        unsafe { C_GetSessionInfo(session, &raw mut session_info) },
+        CKR_OK
+    );
+    assert_eq!(session_info.state, CKS_RO_PUBLIC_SESSION);
+
+    // the objects cannot be used before login
+    assert_eq!(
+        unsafe { C_DestroyObject(session, 1) },
+        CKR_USER_NOT_LOGGED_IN
+    );
+    assert_eq!(C_Logout(session), CKR_USER_NOT_LOGGED_IN);
+
+    let mut pin = *b"1234";
+    let mut wrong_pin = *b"0000";
+    assert_eq!(
+        unsafe { C_Login(session, CKU_SO, pin.as_mut_ptr(), pin.len() as CK_ULONG) },
+        CKR_USER_TYPE_INVALID
+    );
+    assert_eq!(
+        unsafe {
+            C_Login(
+                session,
+                CKU_USER,
+                wrong_pin.as_mut_ptr(),
+                wrong_pin.len() as CK_ULONG,
+            )
+        },
+        CKR_PIN_INCORRECT
+    );
+    assert_eq!(
+        unsafe { C_Login(session, CKU_USER, pin.as_mut_ptr(), pin.len() as CK_ULONG) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe { C_Login(session, CKU_USER, pin.as_mut_ptr(), pin.len() as CK_ULONG) },
+        CKR_USER_ALREADY_LOGGED_IN
+    );
+    assert_eq!(
+        unsafe { C_GetSessionInfo(session, &raw mut session_info) },
+        CKR_OK
+    );
+    assert_eq!(session_info.state, CKS_RO_USER_FUNCTIONS);
+    let object = test_create_data_object(session, "login-object", true);
+    assert_eq!(unsafe { C_DestroyObject(session, object) }, CKR_OK);
+
+    assert_eq!(C_Logout(session), CKR_OK);
+    assert_eq!(
+        unsafe { C_DestroyObject(session, object) },
+        CKR_USER_NOT_LOGGED_IN
+    );
+
+    // closing the last session of the slot logs the user out
+    assert_eq!(
+        unsafe { C_Login(session, CKU_USER, pin.as_mut_ptr(), pin.len() as CK_ULONG) },
+        CKR_OK
+    );
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    let session = test_open_session();
+    assert_eq!(
+        unsafe { C_GetSessionInfo(session, &raw mut session_info) },
+        CKR_OK
+    );
+    assert_eq!(session_info.state, CKS_RO_PUBLIC_SESSION);
+
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    register_backend(test_backends);
+}
+
+#[test]
+#[serial]
+fn get_token_info() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    unsafe {
+        assert_eq!(
+            C_GetTokenInfo(SLOT_ID, &mut CK_TOKEN_INFO::default()),
+            CKR_OK
+        );
+        // Expect CKR_SLOT_ID_INVALID if slotID references a nonexistent slot.
+        assert_eq!(
+            C_GetTokenInfo(SLOT_ID + 1, ptr::null_mut()),
+            CKR_SLOT_ID_INVALID
+        );
+        // Expect CKR_ARGUMENTS_BAD if pInfo is null.
+        assert_eq!(C_GetSlotInfo(SLOT_ID, ptr::null_mut()), CKR_ARGUMENTS_BAD);
+        // Expect CKR_CRYPTOKI_NOT_INITIALIZED if token is not initialized.
+        assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+        assert_eq!(
+            C_GetTokenInfo(SLOT_ID, &mut CK_TOKEN_INFO::default()),
+            CKR_CRYPTOKI_NOT_INITIALIZED
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn get_mechanism_list() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut count = 0;
+    unsafe {
+        assert_eq!(
+            C_GetMechanismList(SLOT_ID, ptr::null_mut(), &raw mut count),
+            CKR_OK
+        );
+        assert_ne!(count, 0);
+        let mut mechanisms =
+            Vec::<CK_MECHANISM_TYPE>::with_capacity(usize::try_from(count).unwrap());
+        assert_eq!(
+            C_GetMechanismList(SLOT_ID, mechanisms.as_mut_ptr(), &raw mut count),
             CKR_OK
         );
         mechanisms.set_len(usize::try_from(count).unwrap());
-        assert_eq!(mechanisms, *SUPPORTED_SIGNATURE_MECHANISMS);
+        // The test backend narrows the registry down
+        let expected_mechanisms = SUPPORTED_MECHANISMS
+            .iter()
+            .map(|(mechanism, _)| *mechanism)
+            .filter(|mechanism| *mechanism != CKM_AES_KEY_WRAP_PAD)
+            .collect::<Vec<_>>();
+        assert_eq!(mechanisms, expected_mechanisms);
         // Expect CKR_SLOT_ID_INVALID if slotID references a nonexistent slot.
         assert_eq!(
             C_GetMechanismList(SLOT_ID + 1, ptr::null_mut(), &raw mut count),
@@ -422,9 +1027,22 @@ fn get_mechanism_info() {
     assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
     let mut info = CK_MECHANISM_INFO::default();
     assert_eq!(
-        unsafe { C_GetMechanismInfo(SLOT_ID, SUPPORTED_SIGNATURE_MECHANISMS[0], &raw mut info,) },
+        unsafe { C_GetMechanismInfo(SLOT_ID, CKM_AES_CBC_PAD, &raw mut info) },
+        CKR_OK
+    );
+    assert_eq!(info.flags, CKF_ENCRYPT | CKF_DECRYPT);
+    assert_eq!(info.ulMinKeySize, 16);
+    assert_eq!(info.ulMaxKeySize, 32);
+    assert_eq!(
+        unsafe { C_GetMechanismInfo(SLOT_ID, CKM_SHA256_RSA_PKCS, &raw mut info) },
         CKR_OK
     );
+    assert_eq!(info.flags, CKF_SIGN | CKF_VERIFY);
+    // Expect CKR_MECHANISM_INVALID if the backend does not support the mechanism.
+    assert_eq!(
+        unsafe { C_GetMechanismInfo(SLOT_ID, CKM_AES_KEY_WRAP_PAD, &raw mut info) },
+        CKR_MECHANISM_INVALID
+    );
     // Expect CKR_MECHANISM_INVALID if type is an unsupported mechanism.
     assert_eq!(
         unsafe { C_GetMechanismInfo(SLOT_ID, CKM_DSA, &raw mut info) },
@@ -432,13 +1050,13 @@ fn get_mechanism_info() {
     );
     // Expect CKR_ARGUMENTS_BAD if pInfo is null.
     assert_eq!(
-        unsafe { C_GetMechanismInfo(SLOT_ID, SUPPORTED_SIGNATURE_MECHANISMS[0], ptr::null_mut(),) },
+        unsafe { C_GetMechanismInfo(SLOT_ID, CKM_AES_CBC_PAD, ptr::null_mut()) },
         CKR_ARGUMENTS_BAD
     );
     // Expect CKR_CRYPTOKI_NOT_INITIALIZED if token is not initialized.
     assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
     assert_eq!(
-        unsafe { C_GetMechanismInfo(SLOT_ID, SUPPORTED_SIGNATURE_MECHANISMS[0], ptr::null_mut(),) },
+        unsafe { C_GetMechanismInfo(SLOT_ID, CKM_AES_CBC_PAD, ptr::null_mut()) },
         CKR_CRYPTOKI_NOT_INITIALIZED
     );
 }
@@ -647,6 +1265,38 @@ fn find_objects() {
         CKR_OK
     );
     assert_eq!(count, 0);
+
+    // An unknown CKA_ID matches no certificate
+    let certificate = CKO_CERTIFICATE.to_ne_bytes();
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &certificate),
+        test_attribute(CKA_ID, b"unknown certificate"),
+    ];
+    assert_eq!(
+        unsafe { C_FindObjectsInit(handle, template.as_mut_ptr(), template.len() as CK_ULONG) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe { C_FindObjects(handle, objects.as_mut_ptr(), 1, &raw mut count) },
+        CKR_OK
+    );
+    assert_eq!(count, 0);
+
+    // Nor does an unknown binary CKA_ID
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &certificate),
+        test_attribute(CKA_ID, &[0xde, 0xad, 0xbe, 0xef]),
+    ];
+    assert_eq!(
+        unsafe { C_FindObjectsInit(handle, template.as_mut_ptr(), template.len() as CK_ULONG) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe { C_FindObjects(handle, objects.as_mut_ptr(), 1, &raw mut count) },
+        CKR_OK
+    );
+    assert_eq!(count, 0);
+
     assert_eq!(C_CloseSession(handle), CKR_OK);
     assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
     assert_eq!(
@@ -657,7 +1307,29 @@ fn find_objects() {
 
 #[test]
 #[serial]
-fn find_objects_final() {
+fn find_objects_template() {
+    let attributes = Attributes::from(vec![
+        Attribute::Class(CKO_SECRET_KEY),
+        Attribute::KeyType(CKK_AES),
+        Attribute::Label("my_key".to_owned()),
+        Attribute::Token(true),
+        Attribute::Sign(false),
+        Attribute::ValueLen(32),
+    ]);
+    assert_eq!(
+        SearchTemplate::from(&attributes),
+        SearchTemplate {
+            class: Some(CKO_SECRET_KEY),
+            key_type: Some(CKK_AES),
+            label: Some("my_key".to_owned()),
+            id: None,
+            token: Some(true),
+            sign: Some(false),
+            decrypt: None,
+            value_len: Some(32),
+        }
+    );
+
     test_init();
     assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
     let mut handle = CK_INVALID_HANDLE;
@@ -673,26 +1345,115 @@ fn find_objects_final() {
         },
         CKR_OK
     );
-    let mut template = vec![CK_ATTRIBUTE {
-        type_: CKA_CLASS,
-        pValue: std::ptr::from_ref::<CK_ULONG>(&CKO_PRIVATE_KEY) as CK_VOID_PTR,
-        ulValueLen: std::mem::size_of_val(&CKO_PRIVATE_KEY) as CK_ULONG,
-    }];
+    for (value_len, expected_count) in [(32 as CK_ULONG, 1), (16, 0)] {
+        let mut template = vec![
+            CK_ATTRIBUTE {
+                type_: CKA_CLASS,
+                pValue: std::ptr::from_ref::<CK_ULONG>(&CKO_SECRET_KEY) as CK_VOID_PTR,
+                ulValueLen: std::mem::size_of_val(&CKO_SECRET_KEY) as CK_ULONG,
+            },
+            CK_ATTRIBUTE {
+                type_: CKA_VALUE_LEN,
+                pValue: std::ptr::from_ref::<CK_ULONG>(&value_len) as CK_VOID_PTR,
+                ulValueLen: std::mem::size_of_val(&value_len) as CK_ULONG,
+            },
+            CK_ATTRIBUTE {
+                type_: CKA_TOKEN,
+                pValue: std::ptr::from_ref(&CK_TRUE) as CK_VOID_PTR,
+                ulValueLen: std::mem::size_of_val(&CK_TRUE) as CK_ULONG,
+            },
+            CK_ATTRIBUTE {
+                type_: CKA_SIGN,
+                pValue: std::ptr::from_ref(&CK_FALSE) as CK_VOID_PTR,
+                ulValueLen: std::mem::size_of_val(&CK_FALSE) as CK_ULONG,
+            },
+        ];
+        assert_eq!(
+            unsafe { C_FindObjectsInit(handle, template.as_mut_ptr(), template.len() as CK_ULONG) },
+            CKR_OK
+        );
+        let mut objects = vec![CK_OBJECT_HANDLE::default(); 2];
+        let mut count = 0;
+        assert_eq!(
+            unsafe { C_FindObjects(handle, objects.as_mut_ptr(), 2, &raw mut count) },
+            CKR_OK
+        );
+        assert_eq!(count, expected_count);
+        assert_eq!(C_FindObjectsFinal(handle), CKR_OK);
+    }
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+/// Create a data object with the given label, as a session object when `token` is false
+fn test_create_data_object(
+    session: CK_SESSION_HANDLE,
+    label: &str,
+    token: bool,
+) -> CK_OBJECT_HANDLE {
+    let token = if token { CK_TRUE } else { CK_FALSE };
+    let value = b"data";
+    let mut template = vec![
+        CK_ATTRIBUTE {
+            type_: CKA_CLASS,
+            pValue: std::ptr::from_ref::<CK_ULONG>(&CKO_DATA) as CK_VOID_PTR,
+            ulValueLen: std::mem::size_of_val(&CKO_DATA) as CK_ULONG,
+        },
+        CK_ATTRIBUTE {
+            type_: CKA_LABEL,
+            pValue: label.as_ptr() as CK_VOID_PTR,
+            ulValueLen: label.len() as CK_ULONG,
+        },
+        CK_ATTRIBUTE {
+            type_: CKA_VALUE,
+            pValue: value.as_ptr() as CK_VOID_PTR,
+            ulValueLen: value.len() as CK_ULONG,
+        },
+        CK_ATTRIBUTE {
+            type_: CKA_TOKEN,
+            pValue: std::ptr::from_ref(&token) as CK_VOID_PTR,
+            ulValueLen: std::mem::size_of_val(&token) as CK_ULONG,
+        },
+    ];
+    let mut object = CK_INVALID_HANDLE;
     assert_eq!(
-        unsafe { C_FindObjectsInit(handle, template.as_mut_ptr(), template.len() as CK_ULONG) },
+        unsafe {
+            C_CreateObject(
+                session,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut object,
+            )
+        },
         CKR_OK
     );
-    assert_eq!(C_FindObjectsFinal(handle), CKR_OK);
-    assert_eq!(C_CloseSession(handle), CKR_OK);
-    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    object
 }
 
-#[test]
-#[serial]
-fn get_function_status() {
-    test_init();
-    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
-    let mut session_h = CK_INVALID_HANDLE;
+/// A template attribute pointing to `value`
+fn test_attribute(type_: CK_ATTRIBUTE_TYPE, value: &[u8]) -> CK_ATTRIBUTE {
+    CK_ATTRIBUTE {
+        type_,
+        pValue: value.as_ptr() as CK_VOID_PTR,
+        ulValueLen: value.len() as CK_ULONG,
+    }
+}
+
+/// Create an object from `template` and return the return value of `C_CreateObject`
+fn test_create_object(session: CK_SESSION_HANDLE, template: &mut [CK_ATTRIBUTE]) -> CK_RV {
+    let mut object = CK_INVALID_HANDLE;
+    unsafe {
+        C_CreateObject(
+            session,
+            template.as_mut_ptr(),
+            template.len() as CK_ULONG,
+            &raw mut object,
+        )
+    }
+}
+
+fn test_open_session() -> CK_SESSION_HANDLE {
+    let mut handle = CK_INVALID_HANDLE;
     assert_eq!(
         unsafe {
             C_OpenSession(
@@ -700,73 +1461,2075 @@ fn get_function_status() {
                 CKF_SERIAL_SESSION,
                 ptr::null_mut(),
                 None,
-                &raw mut session_h,
+                &raw mut handle,
             )
         },
         CKR_OK
     );
-    assert_eq!(
-        { C_GetFunctionStatus(session_h) },
-        CKR_FUNCTION_NOT_PARALLEL
-    );
-    assert_eq!(C_CloseSession(session_h), CKR_OK);
-    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    handle
 }
 
 #[test]
 #[serial]
-fn cancel_function() {
+fn create_key_and_certificate_objects() {
     test_init();
     assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
-    let mut session_h = CK_INVALID_HANDLE;
+    let session = test_open_session();
+
+    let secret_key = CKO_SECRET_KEY.to_ne_bytes();
+    let private_key = CKO_PRIVATE_KEY.to_ne_bytes();
+    let certificate = CKO_CERTIFICATE.to_ne_bytes();
+    let aes = CKK_AES.to_ne_bytes();
+    let rsa = CKK_RSA.to_ne_bytes();
+    let ec = CKK_EC.to_ne_bytes();
+    let x509 = CKC_X_509.to_ne_bytes();
+
+    // an AES key imported from its value
+    let aes_key = [0x2a_u8; 32];
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &secret_key),
+        test_attribute(CKA_KEY_TYPE, &aes),
+        test_attribute(CKA_VALUE, &aes_key),
+    ];
+    assert_eq!(test_create_object(session, &mut template), CKR_OK);
+    template[2] = test_attribute(CKA_VALUE, &aes_key[..31]);
+    assert_eq!(
+        test_create_object(session, &mut template),
+        CKR_ATTRIBUTE_VALUE_INVALID
+    );
+    template.remove(1);
+    assert_eq!(
+        test_create_object(session, &mut template),
+        CKR_TEMPLATE_INCOMPLETE
+    );
+
+    // an RSA key imported from its components (a toy key: 61 * 53)
+    let (modulus, public_exponent, private_exponent) =
+        (3233_u16.to_be_bytes(), [17_u8], 2753_u16.to_be_bytes());
+    let (prime_1, prime_2) = ([61_u8], [53_u8]);
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &private_key),
+        test_attribute(CKA_KEY_TYPE, &rsa),
+        test_attribute(CKA_ID, b"rsa-key"),
+        test_attribute(CKA_MODULUS, &modulus),
+        test_attribute(CKA_PUBLIC_EXPONENT, &public_exponent),
+        test_attribute(CKA_PRIVATE_EXPONENT, &private_exponent),
+        test_attribute(CKA_PRIME_1, &prime_1),
+        test_attribute(CKA_PRIME_2, &prime_2),
+    ];
+    assert_eq!(test_create_object(session, &mut template), CKR_OK);
+    template.truncate(5);
+    assert_eq!(
+        test_create_object(session, &mut template),
+        CKR_TEMPLATE_INCOMPLETE
+    );
+
+    // a P-256 key imported from its scalar
+    let p256_params = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+    let scalar = [1_u8; 32];
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &private_key),
+        test_attribute(CKA_KEY_TYPE, &ec),
+        test_attribute(CKA_EC_PARAMS, &p256_params),
+        test_attribute(CKA_VALUE, &scalar),
+    ];
+    assert_eq!(test_create_object(session, &mut template), CKR_OK);
+
+    // a certificate is linked to the private key with the same CKA_ID
+    let der = b"certificate DER";
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &certificate),
+        test_attribute(CKA_CERTIFICATE_TYPE, &x509),
+        test_attribute(CKA_VALUE, der),
+        test_attribute(CKA_ID, b"rsa-key"),
+    ];
+    let mut handle = CK_INVALID_HANDLE;
     assert_eq!(
         unsafe {
-            C_OpenSession(
-                SLOT_ID,
-                CKF_SERIAL_SESSION,
-                ptr::null_mut(),
-                None,
-                &raw mut session_h,
+            C_CreateObject(
+                session,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut handle,
             )
         },
         CKR_OK
     );
+    let object = OBJECTS_STORE
+        .read()
+        .unwrap()
+        .get_using_handle(SLOT_ID, handle)
+        .unwrap();
+    assert!(matches!(
+        &*object,
+        Object::Certificate(certificate) if certificate.private_key_id() == "rsa-key"
+    ));
+    // the CKA_ID of a certificate is optional
+    template.pop();
+    assert_eq!(test_create_object(session, &mut template), CKR_OK);
+    template.remove(2);
     assert_eq!(
-        { C_GetFunctionStatus(session_h) },
-        CKR_FUNCTION_NOT_PARALLEL
+        test_create_object(session, &mut template),
+        CKR_TEMPLATE_INCOMPLETE
     );
-    assert_eq!(C_CloseSession(session_h), CKR_OK);
+
+    assert_eq!(C_CloseSession(session), CKR_OK);
     assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
 }
 
+/// Apply `template` to the object `handle` and return the return value of `C_SetAttributeValue`
+fn test_set_attribute_value(
+    session: CK_SESSION_HANDLE,
+    handle: CK_OBJECT_HANDLE,
+    template: &mut [CK_ATTRIBUTE],
+) -> CK_RV {
+    unsafe {
+        C_SetAttributeValue(
+            session,
+            handle,
+            template.as_mut_ptr(),
+            template.len() as CK_ULONG,
+        )
+    }
+}
+
 #[test]
 #[serial]
-fn module_test_generate_key_encrypt_decrypt() -> ModuleResult<()> {
+fn set_attribute_value() {
     test_init();
     assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let session = test_open_session();
+    let data_object = test_create_data_object(session, "data", true);
+
+    // the label and the application of a data object can be changed
+    let mut template = vec![
+        test_attribute(CKA_LABEL, b"renamed"),
+        test_attribute(CKA_APPLICATION, b"application"),
+    ];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_OK
+    );
+    // attributes set to their current value are accepted
+    let data = CKO_DATA.to_ne_bytes();
+    let mut template = vec![test_attribute(CKA_CLASS, &data)];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_OK
+    );
+    // not its value, nor its class
+    let mut template = vec![test_attribute(CKA_VALUE, b"other data")];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_ATTRIBUTE_READ_ONLY
+    );
+    let secret_key = CKO_SECRET_KEY.to_ne_bytes();
+    let mut template = vec![test_attribute(CKA_CLASS, &secret_key)];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_ATTRIBUTE_READ_ONLY
+    );
+    let mut template = vec![test_attribute(CKA_START_DATE, b"20250101")];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_ATTRIBUTE_READ_ONLY
+    );
+    // malformed dates and unknown attributes are rejected
+    let mut template = vec![test_attribute(CKA_START_DATE, b"2025-1-1")];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_ATTRIBUTE_VALUE_INVALID
+    );
+    let mut template = vec![test_attribute(0x8000_0001, b"vendor")];
+    assert_eq!(
+        test_set_attribute_value(session, data_object, &mut template),
+        CKR_ATTRIBUTE_TYPE_INVALID
+    );
+    let mut template = vec![test_attribute(CKA_LABEL, b"renamed")];
+    assert_eq!(
+        test_set_attribute_value(session, CK_INVALID_HANDLE, &mut template),
+        CKR_OBJECT_HANDLE_INVALID
+    );
+
+    // the CKA_ID of a certificate links it to another private key,
+    // and the object of the store is refreshed
+    let certificate = CKO_CERTIFICATE.to_ne_bytes();
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &certificate),
+        test_attribute(CKA_VALUE, b"certificate DER"),
+        test_attribute(CKA_ID, b"rsa-key"),
+    ];
     let mut handle = CK_INVALID_HANDLE;
     assert_eq!(
         unsafe {
-            C_OpenSession(
-                SLOT_ID,
-                CKF_SERIAL_SESSION,
-                ptr::null_mut(),
-                None,
+            C_CreateObject(
+                session,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
                 &raw mut handle,
             )
         },
         CKR_OK
     );
+    let mut template = vec![test_attribute(CKA_ID, b"other-key")];
+    assert_eq!(
+        test_set_attribute_value(session, handle, &mut template),
+        CKR_OK
+    );
+    let object = OBJECTS_STORE
+        .read()
+        .unwrap()
+        .get_using_handle(SLOT_ID, handle)
+        .unwrap();
+    assert!(matches!(
+        &*object,
+        Object::Certificate(certificate) if certificate.private_key_id() == "other-key"
+    ));
 
-    let key_handle = test_generate_key(handle);
-    // call to encrypt() test function
-    let plaintext = vec![0_u8; 32];
-    let encrypted_data = test_encrypt(handle, key_handle, plaintext.clone());
-    // call to decrypt() test function
-    let decrypted_data = test_decrypt(handle, key_handle, encrypted_data);
-    assert_eq!(decrypted_data, plaintext);
+    // the CKA_ID of a key can be changed after its creation
+    let key = test_create_secret_key(session, None);
+    let mut template = vec![test_attribute(CKA_ID, b"aes-key")];
+    assert_eq!(
+        test_set_attribute_value(session, key, &mut template),
+        CKR_OK
+    );
+    let object = OBJECTS_STORE
+        .read()
+        .unwrap()
+        .get_using_handle(SLOT_ID, key)
+        .unwrap();
+    assert_eq!(
+        object.attribute(SLOT_ID, AttributeType::Id).unwrap(),
+        Some(Attribute::Id(b"aes-key".to_vec()))
+    );
 
-    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn key_attributes_from_metadata() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+
+    // the metadata unknown to the backend keep their default values
+    let key = Object::SymmetricKey(Arc::new(DummySymKey));
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::Label).unwrap(),
+        Some(Attribute::Label("Symmetric Key".to_owned()))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::Wrap).unwrap(),
+        Some(Attribute::Wrap(true))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::StartDate).unwrap(),
+        Some(Attribute::StartDate(vec![]))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::Local).unwrap(),
+        Some(Attribute::Local(false))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::KeyGenMechanism)
+            .unwrap(),
+        Some(Attribute::KeyGenMechanism(CK_UNAVAILABLE_INFORMATION))
+    );
+
+    let key = Object::SymmetricKey(Arc::new(DummyMetadataKey(
+        KeyAlgorithm::Aes256,
+        KeyMetadata {
+            label: Some("aes key".to_owned()),
+            usage: Some(KeyUsage {
+                encrypt: true,
+                decrypt: true,
+                ..KeyUsage::default()
+            }),
+            start_date: Some("20250101".to_owned()),
+            local: Some(true),
+            ..KeyMetadata::default()
+        },
+    )));
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::Label).unwrap(),
+        Some(Attribute::Label("aes key".to_owned()))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::Encrypt).unwrap(),
+        Some(Attribute::Encrypt(true))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::Wrap).unwrap(),
+        Some(Attribute::Wrap(false))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::StartDate).unwrap(),
+        Some(Attribute::StartDate(b"20250101".to_vec()))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::EndDate).unwrap(),
+        Some(Attribute::EndDate(vec![]))
+    );
+    assert_eq!(
+        key.attribute(SLOT_ID, AttributeType::KeyGenMechanism)
+            .unwrap(),
+        Some(Attribute::KeyGenMechanism(CKM_AES_KEY_GEN))
+    );
+    // only the AES mechanisms matching the usages are allowed
+    let mechanisms = match key
+        .attribute(SLOT_ID, AttributeType::AllowedMechanisms)
+        .unwrap()
+    {
+        Some(Attribute::AllowedMechanisms(mechanisms)) => mechanisms,
+        _ => vec![],
+    };
+    assert!(mechanisms.contains(&CKM_AES_GCM));
+    assert!(mechanisms.contains(&CKM_AES_CBC_PAD));
+    assert!(!mechanisms.contains(&CKM_AES_KEY_WRAP));
+    assert!(!mechanisms.contains(&CKM_RSA_PKCS_OAEP));
+
+    // HKDF derives from generic secrets
+    let key = Object::SymmetricKey(Arc::new(DummyMetadataKey(
+        KeyAlgorithm::GenericSecret,
+        KeyMetadata {
+            usage: Some(KeyUsage {
+                derive: true,
+                ..KeyUsage::default()
+            }),
+            ..KeyMetadata::default()
+        },
+    )));
+    let mechanisms = match key
+        .attribute(SLOT_ID, AttributeType::AllowedMechanisms)
+        .unwrap()
+    {
+        Some(Attribute::AllowedMechanisms(mechanisms)) => mechanisms,
+        _ => vec![],
+    };
+    assert_eq!(mechanisms, vec![CKM_HKDF_DERIVE]);
+
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+/// Create an AES secret key from its value, with the optional `CKA_EXTRACTABLE` of `extractable`
+fn test_create_secret_key(
+    session: CK_SESSION_HANDLE,
+    extractable: Option<&[u8]>,
+) -> CK_OBJECT_HANDLE {
+    let secret_key = CKO_SECRET_KEY.to_ne_bytes();
+    let aes = CKK_AES.to_ne_bytes();
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &secret_key),
+        test_attribute(CKA_KEY_TYPE, &aes),
+        test_attribute(CKA_VALUE, &[0x2a_u8; 32]),
+    ];
+    if let Some(extractable) = extractable {
+        template.push(test_attribute(CKA_EXTRACTABLE, extractable));
+    }
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_CreateObject(
+                session,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+    handle
+}
+
+#[test]
+#[serial]
+fn sensitive_key_attributes() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let session = test_open_session();
+
+    // Read the CKA_VALUE and the CKA_EXTRACTABLE of `key`
+    let get_value = |key: CK_OBJECT_HANDLE| {
+        let mut value = [0xff_u8; 32];
+        let mut extractable = [0xff_u8];
+        let mut template = vec![
+            CK_ATTRIBUTE {
+                type_: CKA_VALUE,
+                pValue: value.as_mut_ptr().cast(),
+                ulValueLen: value.len() as CK_ULONG,
+            },
+            CK_ATTRIBUTE {
+                type_: CKA_EXTRACTABLE,
+                pValue: extractable.as_mut_ptr().cast(),
+                ulValueLen: extractable.len() as CK_ULONG,
+            },
+        ];
+        let rv = unsafe {
+            C_GetAttributeValue(
+                session,
+                key,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+            )
+        };
+        (rv, template[0].ulValueLen, value, extractable[0])
+    };
+
+    // keys created without CKA_EXTRACTABLE are not extractable by default:
+    // their value is unavailable, the other attributes are returned
+    let key = test_create_secret_key(session, None);
+    let (rv, value_len, _, extractable) = get_value(key);
+    assert_eq!(rv, CKR_ATTRIBUTE_SENSITIVE);
+    assert_eq!(value_len, CK_UNAVAILABLE_INFORMATION);
+    assert_eq!(extractable, CK_FALSE);
+
+    let key = test_create_secret_key(session, Some(&[CK_TRUE][..]));
+    let (rv, value_len, value, extractable) = get_value(key);
+    assert_eq!(rv, CKR_OK);
+    assert_eq!(value_len, 32);
+    assert_eq!(value, [0_u8; 32]);
+    assert_eq!(extractable, CK_TRUE);
+
+    // a sensitive key does not reveal its value, even when extractable
+    let key = test_generate_key(session);
+    let (rv, value_len, _, extractable) = get_value(key);
+    assert_eq!(rv, CKR_ATTRIBUTE_SENSITIVE);
+    assert_eq!(value_len, CK_UNAVAILABLE_INFORMATION);
+    assert_eq!(extractable, CK_TRUE);
+
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn create_destroy_find_objects() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let handle = test_open_session();
+
+    // handles of destroyed objects are not reused
+    let first = test_create_data_object(handle, "first", true);
+    let second = test_create_data_object(handle, "second", true);
+    assert_eq!(unsafe { C_DestroyObject(handle, first) }, CKR_OK);
+    assert_eq!(
+        unsafe { C_DestroyObject(handle, first) },
+        CKR_OBJECT_HANDLE_INVALID
+    );
+    let third = test_create_data_object(handle, "third", true);
+    assert_ne!(third, first);
+    assert_ne!(third, second);
+    assert!(
+        OBJECTS_STORE
+            .read()
+            .unwrap()
+            .get_using_handle(SLOT_ID, second)
+            .is_some()
+    );
+
+    // session objects are evicted when their session is closed,
+    // token objects survive it
+    let other = test_open_session();
+    let session_object = test_create_data_object(other, "session", false);
+    let token_object = test_create_data_object(other, "token", true);
+    assert_eq!(C_CloseSession(other), CKR_OK);
+    {
+        let store = OBJECTS_STORE.read().unwrap();
+        assert!(store.get_using_handle(SLOT_ID, session_object).is_none());
+        assert!(store.get_using_handle(SLOT_ID, token_object).is_some());
+    }
+    assert_eq!(
+        unsafe { C_DestroyObject(handle, session_object) },
+        CKR_OBJECT_HANDLE_INVALID
+    );
+
+    // the handle of an object destroyed outside of the module is evicted when used
+    let stale = test_create_data_object(handle, "stale", true);
+    assert_eq!(
+        unsafe { C_DestroyObject(handle, stale) },
+        CKR_OBJECT_HANDLE_INVALID
+    );
+    assert!(
+        OBJECTS_STORE
+            .read()
+            .unwrap()
+            .get_using_handle(SLOT_ID, stale)
+            .is_none()
+    );
+
+    // a live object excluded by the filters of a search stays in the store
+    let label = "test-data-token";
+    let mut template = vec![
+        CK_ATTRIBUTE {
+            type_: CKA_CLASS,
+            pValue: std::ptr::from_ref::<CK_ULONG>(&CKO_DATA) as CK_VOID_PTR,
+            ulValueLen: std::mem::size_of_val(&CKO_DATA) as CK_ULONG,
+        },
+        CK_ATTRIBUTE {
+            type_: CKA_LABEL,
+            pValue: label.as_ptr() as CK_VOID_PTR,
+            ulValueLen: label.len() as CK_ULONG,
+        },
+    ];
+    assert_eq!(
+        unsafe { C_FindObjectsInit(handle, template.as_mut_ptr(), template.len() as CK_ULONG) },
+        CKR_OK
+    );
+    let mut objects = vec![CK_OBJECT_HANDLE::default(); 2];
+    let mut count = 0;
+    assert_eq!(
+        unsafe { C_FindObjects(handle, objects.as_mut_ptr(), 2, &raw mut count) },
+        CKR_OK
+    );
+    assert_eq!(count, 0);
+    assert_eq!(C_FindObjectsFinal(handle), CKR_OK);
+    assert!(
+        OBJECTS_STORE
+            .read()
+            .unwrap()
+            .get_using_handle(SLOT_ID, token_object)
+            .is_some()
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn find_objects_final() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+    let mut template = vec![CK_ATTRIBUTE {
+        type_: CKA_CLASS,
+        pValue: std::ptr::from_ref::<CK_ULONG>(&CKO_PRIVATE_KEY) as CK_VOID_PTR,
+        ulValueLen: std::mem::size_of_val(&CKO_PRIVATE_KEY) as CK_ULONG,
+    }];
+    assert_eq!(
+        unsafe { C_FindObjectsInit(handle, template.as_mut_ptr(), template.len() as CK_ULONG) },
+        CKR_OK
+    );
+    assert_eq!(C_FindObjectsFinal(handle), CKR_OK);
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn get_function_status() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut session_h = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut session_h,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(
+        { C_GetFunctionStatus(session_h) },
+        CKR_FUNCTION_NOT_PARALLEL
+    );
+    assert_eq!(C_CloseSession(session_h), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn cancel_function() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut session_h = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut session_h,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(
+        { C_GetFunctionStatus(session_h) },
+        CKR_FUNCTION_NOT_PARALLEL
+    );
+    assert_eq!(C_CloseSession(session_h), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn module_test_generate_key_encrypt_decrypt() -> ModuleResult<()> {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let key_handle = test_generate_key(handle);
+    // call to encrypt() test function
+    let plaintext = vec![0_u8; 32];
+    let encrypted_data = test_encrypt(handle, key_handle, plaintext.clone());
+    // call to decrypt() test function
+    let decrypted_data = test_decrypt(handle, key_handle, encrypted_data);
+    assert_eq!(decrypted_data, plaintext);
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn generate_key_pair() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let modulus_bits: CK_ULONG = 2048;
+    let label = b"key pair".to_vec();
+    let id = b"key-pair-id".to_vec();
+    let mut public_key_template = vec![CK_ATTRIBUTE {
+        type_: CKA_MODULUS_BITS,
+        pValue: std::ptr::from_ref::<CK_ULONG>(&modulus_bits) as CK_VOID_PTR,
+        ulValueLen: std::mem::size_of_val(&modulus_bits) as CK_ULONG,
+    }];
+    let mut private_key_template = vec![
+        CK_ATTRIBUTE {
+            type_: CKA_LABEL,
+            pValue: label.as_ptr() as CK_VOID_PTR,
+            ulValueLen: label.len() as CK_ULONG,
+        },
+        CK_ATTRIBUTE {
+            type_: CKA_ID,
+            pValue: id.as_ptr() as CK_VOID_PTR,
+            ulValueLen: id.len() as CK_ULONG,
+        },
+    ];
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_RSA_PKCS_KEY_PAIR_GEN,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let mut public_key_handle = CK_INVALID_HANDLE;
+    let mut private_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                handle,
+                &raw mut mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                private_key_template.as_mut_ptr(),
+                private_key_template.len() as CK_ULONG,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_OK
+    );
+    assert_ne!(public_key_handle, CK_INVALID_HANDLE);
+    assert_ne!(private_key_handle, CK_INVALID_HANDLE);
+    assert_ne!(public_key_handle, private_key_handle);
+
+    // DER encoding of the P-256 curve OID (1.2.840.10045.3.1.7)
+    let ec_params = vec![0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+    let mut public_key_template = vec![CK_ATTRIBUTE {
+        type_: CKA_EC_PARAMS,
+        pValue: ec_params.as_ptr() as CK_VOID_PTR,
+        ulValueLen: ec_params.len() as CK_ULONG,
+    }];
+    mechanism.mechanism = CKM_EC_KEY_PAIR_GEN;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                handle,
+                &raw mut mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                private_key_template.as_mut_ptr(),
+                private_key_template.len() as CK_ULONG,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_MECHANISM_INVALID for a symmetric key generation mechanism.
+    mechanism.mechanism = CKM_AES_KEY_GEN;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                handle,
+                &raw mut mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                private_key_template.as_mut_ptr(),
+                private_key_template.len() as CK_ULONG,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_MECHANISM_INVALID
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn sign_verify() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let modulus_bits: CK_ULONG = 2048;
+    let mut public_key_template = vec![CK_ATTRIBUTE {
+        type_: CKA_MODULUS_BITS,
+        pValue: std::ptr::from_ref::<CK_ULONG>(&modulus_bits) as CK_VOID_PTR,
+        ulValueLen: std::mem::size_of_val(&modulus_bits) as CK_ULONG,
+    }];
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_RSA_PKCS_KEY_PAIR_GEN,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let mut public_key_handle = CK_INVALID_HANDLE;
+    let mut private_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                handle,
+                &raw mut mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                public_key_template.as_mut_ptr(),
+                0,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_OK
+    );
+
+    mechanism.mechanism = CKM_SHA256_RSA_PKCS;
+    let mut data = b"data to sign".to_vec();
+    let mut signature = vec![0_u8; 64];
+    let mut signature_len = signature.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_SignInit(handle, &raw mut mechanism, private_key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Sign(
+                handle,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                &raw mut signature_len,
+            )
+        },
+        CKR_OK
+    );
+
+    // Single part verification
+    assert_eq!(
+        unsafe { C_VerifyInit(handle, &raw mut mechanism, public_key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Verify(
+                handle,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                signature_len,
+            )
+        },
+        CKR_OK
+    );
+    // Expect CKR_OPERATION_NOT_INITIALIZED since C_Verify terminated the operation.
+    assert_eq!(
+        unsafe {
+            C_Verify(
+                handle,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                signature_len,
+            )
+        },
+        CKR_OPERATION_NOT_INITIALIZED
+    );
+
+    // Multipart verification
+    assert_eq!(
+        unsafe { C_VerifyInit(handle, &raw mut mechanism, public_key_handle) },
+        CKR_OK
+    );
+    for part in data.chunks_mut(4) {
+        assert_eq!(
+            unsafe { C_VerifyUpdate(handle, part.as_mut_ptr(), part.len() as CK_ULONG) },
+            CKR_OK
+        );
+    }
+    assert_eq!(
+        unsafe { C_VerifyFinal(handle, signature.as_mut_ptr(), signature_len) },
+        CKR_OK
+    );
+
+    // Expect CKR_SIGNATURE_INVALID for a tampered signature.
+    signature[0] ^= 1;
+    assert_eq!(
+        unsafe { C_VerifyInit(handle, &raw mut mechanism, public_key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Verify(
+                handle,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                signature_len,
+            )
+        },
+        CKR_SIGNATURE_INVALID
+    );
+
+    // Expect CKR_KEY_HANDLE_INVALID when verifying with a private key.
+    assert_eq!(
+        unsafe { C_VerifyInit(handle, &raw mut mechanism, private_key_handle) },
+        CKR_KEY_HANDLE_INVALID
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn ec_edwards_key_pair() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let session = test_open_session();
+
+    // DER encodings of the curve name `edwards25519` and of the P-256 curve OID
+    let edwards25519 = b"\x13\x0cedwards25519";
+    let p256 = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+    let generate = |mechanism_type: CK_MECHANISM_TYPE, ec_params: &[u8]| {
+        let mut mechanism = CK_MECHANISM {
+            mechanism: mechanism_type,
+            pParameter: ptr::null_mut(),
+            ulParameterLen: 0,
+        };
+        let mut public_key_template = vec![test_attribute(CKA_EC_PARAMS, ec_params)];
+        let mut public_key_handle = CK_INVALID_HANDLE;
+        let mut private_key_handle = CK_INVALID_HANDLE;
+        let rv = unsafe {
+            C_GenerateKeyPair(
+                session,
+                &raw mut mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                public_key_template.as_mut_ptr(),
+                0,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        };
+        (rv, public_key_handle, private_key_handle)
+    };
+
+    // Expect CKR_ATTRIBUTE_VALUE_INVALID for a curve of another key type
+    assert_eq!(
+        generate(CKM_EC_EDWARDS_KEY_PAIR_GEN, &p256).0,
+        CKR_ATTRIBUTE_VALUE_INVALID
+    );
+    assert_eq!(
+        generate(CKM_EC_KEY_PAIR_GEN, edwards25519).0,
+        CKR_ATTRIBUTE_VALUE_INVALID
+    );
+
+    let (rv, public_key_handle, private_key_handle) =
+        generate(CKM_EC_EDWARDS_KEY_PAIR_GEN, edwards25519);
+    assert_eq!(rv, CKR_OK);
+
+    // CKA_EC_POINT is the DER OCTET STRING of the raw public key
+    let mut key_type: CK_KEY_TYPE = 0;
+    let mut ec_point = [0_u8; 34];
+    let mut template = vec![
+        CK_ATTRIBUTE {
+            type_: CKA_KEY_TYPE,
+            pValue: (&raw mut key_type).cast(),
+            ulValueLen: std::mem::size_of::<CK_KEY_TYPE>() as CK_ULONG,
+        },
+        CK_ATTRIBUTE {
+            type_: CKA_EC_POINT,
+            pValue: ec_point.as_mut_ptr().cast(),
+            ulValueLen: ec_point.len() as CK_ULONG,
+        },
+    ];
+    assert_eq!(
+        unsafe {
+            C_GetAttributeValue(
+                session,
+                public_key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(key_type, CKK_EC_EDWARDS);
+    assert_eq!(ec_point[..2], [0x04, 0x20]);
+    assert_eq!(ec_point[2..], [0x2a; 32]);
+
+    // EdDSA signature, without parameters
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_EDDSA,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let mut data = b"data to sign".to_vec();
+    let mut signature = vec![0_u8; 64];
+    let mut signature_len = signature.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_SignInit(session, &raw mut mechanism, private_key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Sign(
+                session,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                &raw mut signature_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(signature_len, 64);
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for the pre-hashed Ed25519ph
+    let mut params = CK_EDDSA_PARAMS {
+        phFlag: CK_TRUE,
+        ulContextDataLen: 0,
+        pContextData: ptr::null_mut(),
+    };
+    mechanism.pParameter = (&raw mut params).cast();
+    mechanism.ulParameterLen = std::mem::size_of::<CK_EDDSA_PARAMS>() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_SignInit(session, &raw mut mechanism, private_key_handle) },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn generic_secret_hmac() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let session = test_open_session();
+
+    let generate = |mechanism_type: CK_MECHANISM_TYPE, value_len: CK_ULONG| {
+        let mut mechanism = CK_MECHANISM {
+            mechanism: mechanism_type,
+            pParameter: ptr::null_mut(),
+            ulParameterLen: 0,
+        };
+        let value_len = value_len.to_ne_bytes();
+        let mut template = vec![
+            test_attribute(CKA_LABEL, b"secret key"),
+            test_attribute(CKA_SENSITIVE, &[CK_FALSE]),
+            test_attribute(CKA_VALUE_LEN, &value_len),
+        ];
+        let mut key_handle = CK_INVALID_HANDLE;
+        let rv = unsafe {
+            C_GenerateKey(
+                session,
+                &raw mut mechanism,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut key_handle,
+            )
+        };
+        (rv, key_handle)
+    };
+
+    // AES keys are 16, 24 or 32 bytes long
+    assert_eq!(generate(CKM_AES_KEY_GEN, 16).0, CKR_OK);
+    assert_eq!(generate(CKM_AES_KEY_GEN, 24).0, CKR_OK);
+    assert_eq!(generate(CKM_AES_KEY_GEN, 20).0, CKR_ATTRIBUTE_VALUE_INVALID);
+
+    let (rv, key_handle) = generate(CKM_GENERIC_SECRET_KEY_GEN, 32);
+    assert_eq!(rv, CKR_OK);
+    let mut key_type: CK_KEY_TYPE = 0;
+    let mut template = vec![CK_ATTRIBUTE {
+        type_: CKA_KEY_TYPE,
+        pValue: (&raw mut key_type).cast(),
+        ulValueLen: std::mem::size_of::<CK_KEY_TYPE>() as CK_ULONG,
+    }];
+    assert_eq!(
+        unsafe {
+            C_GetAttributeValue(
+                session,
+                key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(key_type, CKK_GENERIC_SECRET);
+
+    // HMAC signature and verification with the generic secret
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_SHA256_HMAC,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let mut data = b"data to sign".to_vec();
+    let mut signature = vec![0_u8; 64];
+    let mut signature_len = signature.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_SignInit(session, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Sign(
+                session,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                &raw mut signature_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe { C_VerifyInit(session, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Verify(
+                session,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                signature.as_mut_ptr(),
+                signature_len,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_KEY_HANDLE_INVALID for an HMAC with a private key
+    // and for an RSA signature with a generic secret
+    let mut edwards_mechanism = CK_MECHANISM {
+        mechanism: CKM_EC_EDWARDS_KEY_PAIR_GEN,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let edwards25519 = b"\x13\x0cedwards25519";
+    let mut public_key_template = vec![test_attribute(CKA_EC_PARAMS, edwards25519)];
+    let mut public_key_handle = CK_INVALID_HANDLE;
+    let mut private_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                session,
+                &raw mut edwards_mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                public_key_template.as_mut_ptr(),
+                0,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe { C_SignInit(session, &raw mut mechanism, private_key_handle) },
+        CKR_KEY_HANDLE_INVALID
+    );
+    mechanism.mechanism = CKM_SHA256_RSA_PKCS;
+    assert_eq!(
+        unsafe { C_SignInit(session, &raw mut mechanism, key_handle) },
+        CKR_KEY_HANDLE_INVALID
+    );
+
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn wrap_unwrap_key() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let key_handle = test_generate_key(handle);
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_AES_KEY_WRAP,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+
+    // Query the wrapped key length first
+    let mut wrapped_key_len: CK_ULONG = 0;
+    assert_eq!(
+        unsafe {
+            C_WrapKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                key_handle,
+                ptr::null_mut(),
+                &raw mut wrapped_key_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(wrapped_key_len, 40);
+
+    // Expect CKR_BUFFER_TOO_SMALL when the buffer cannot hold the wrapped key
+    let mut wrapped_key = vec![0_u8; 40];
+    let mut too_small_len: CK_ULONG = 16;
+    assert_eq!(
+        unsafe {
+            C_WrapKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                key_handle,
+                wrapped_key.as_mut_ptr(),
+                &raw mut too_small_len,
+            )
+        },
+        CKR_BUFFER_TOO_SMALL
+    );
+    assert_eq!(
+        unsafe {
+            C_WrapKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                key_handle,
+                wrapped_key.as_mut_ptr(),
+                &raw mut wrapped_key_len,
+            )
+        },
+        CKR_OK
+    );
+
+    let label = b"unwrapped key".to_vec();
+    let mut template = vec![CK_ATTRIBUTE {
+        type_: CKA_LABEL,
+        pValue: label.as_ptr() as CK_VOID_PTR,
+        ulValueLen: label.len() as CK_ULONG,
+    }];
+    let mut unwrapped_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_UnwrapKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                wrapped_key.as_mut_ptr(),
+                wrapped_key_len,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut unwrapped_key_handle,
+            )
+        },
+        CKR_OK
+    );
+    assert_ne!(unwrapped_key_handle, CK_INVALID_HANDLE);
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for an OAEP label, which the KMS does not support
+    let mut source_data = b"label".to_vec();
+    let mut oaep_params = CK_RSA_PKCS_OAEP_PARAMS {
+        hashAlg: CKM_SHA256,
+        mgf: CKG_MGF1_SHA256,
+        source: CKZ_DATA_SPECIFIED,
+        pSourceData: source_data.as_mut_ptr() as CK_VOID_PTR,
+        ulSourceDataLen: source_data.len() as CK_ULONG,
+    };
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_RSA_PKCS_OAEP,
+        pParameter: std::ptr::from_mut(&mut oaep_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_RSA_PKCS_OAEP_PARAMS>() as CK_ULONG,
+    };
+    assert_eq!(
+        unsafe {
+            C_WrapKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                key_handle,
+                ptr::null_mut(),
+                &raw mut wrapped_key_len,
+            )
+        },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn wrap_key_permissions() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let session = test_open_session();
+    let wrap = |wrapping_key_handle, key_handle| {
+        let mut mechanism = CK_MECHANISM {
+            mechanism: CKM_AES_KEY_WRAP,
+            pParameter: ptr::null_mut(),
+            ulParameterLen: 0,
+        };
+        let mut wrapped_key_len: CK_ULONG = 0;
+        unsafe {
+            C_WrapKey(
+                session,
+                &raw mut mechanism,
+                wrapping_key_handle,
+                key_handle,
+                ptr::null_mut(),
+                &raw mut wrapped_key_len,
+            )
+        }
+    };
+
+    let wrapping_key = test_create_secret_key(session, Some(&[CK_TRUE][..]));
+    let extractable_key = test_create_secret_key(session, Some(&[CK_TRUE][..]));
+    assert_eq!(wrap(wrapping_key, extractable_key), CKR_OK);
+
+    // a key which is not extractable cannot be wrapped
+    let non_extractable_key = test_create_secret_key(session, Some(&[CK_FALSE][..]));
+    assert_eq!(
+        wrap(wrapping_key, non_extractable_key),
+        CKR_KEY_UNEXTRACTABLE
+    );
+
+    // a generic secret does not allow wrapping (CKA_WRAP)
+    let secret_key = CKO_SECRET_KEY.to_ne_bytes();
+    let generic_secret = CKK_GENERIC_SECRET.to_ne_bytes();
+    let mut template = vec![
+        test_attribute(CKA_CLASS, &secret_key),
+        test_attribute(CKA_KEY_TYPE, &generic_secret),
+        test_attribute(CKA_VALUE, &[0x2a_u8; 32]),
+    ];
+    let mut generic_secret_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_CreateObject(
+                session,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut generic_secret_handle,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(
+        wrap(generic_secret_handle, extractable_key),
+        CKR_KEY_FUNCTION_NOT_PERMITTED
+    );
+
+    assert_eq!(C_CloseSession(session), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn derive_key() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let key_handle = test_generate_key(handle);
+    let label = b"derived key".to_vec();
+    let mut template = vec![CK_ATTRIBUTE {
+        type_: CKA_LABEL,
+        pValue: label.as_ptr() as CK_VOID_PTR,
+        ulValueLen: label.len() as CK_ULONG,
+    }];
+
+    // HKDF from a symmetric key
+    let mut salt = b"salt".to_vec();
+    let mut info = b"info".to_vec();
+    let mut hkdf_params = CK_HKDF_PARAMS {
+        bExtract: CK_TRUE,
+        bExpand: CK_TRUE,
+        prfHashMechanism: CKM_SHA256,
+        ulSaltType: CKF_HKDF_SALT_DATA,
+        pSalt: salt.as_mut_ptr(),
+        ulSaltLen: salt.len() as CK_ULONG,
+        hSaltKey: CK_INVALID_HANDLE,
+        pInfo: info.as_mut_ptr(),
+        ulInfoLen: info.len() as CK_ULONG,
+    };
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_HKDF_DERIVE,
+        pParameter: std::ptr::from_mut(&mut hkdf_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_HKDF_PARAMS>() as CK_ULONG,
+    };
+    let mut derived_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_DeriveKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut derived_key_handle,
+            )
+        },
+        CKR_OK
+    );
+    assert_ne!(derived_key_handle, CK_INVALID_HANDLE);
+
+    // Expect CKR_ATTRIBUTE_VALUE_INVALID for an AES key of an invalid length
+    let aes = CKK_AES.to_ne_bytes();
+    let value_len = (20 as CK_ULONG).to_ne_bytes();
+    let mut aes_template = vec![
+        test_attribute(CKA_KEY_TYPE, &aes),
+        test_attribute(CKA_VALUE_LEN, &value_len),
+    ];
+    assert_eq!(
+        unsafe {
+            C_DeriveKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                aes_template.as_mut_ptr(),
+                aes_template.len() as CK_ULONG,
+                &raw mut derived_key_handle,
+            )
+        },
+        CKR_ATTRIBUTE_VALUE_INVALID
+    );
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for an HKDF without the expand step
+    hkdf_params.bExpand = CK_FALSE;
+    assert_eq!(
+        unsafe {
+            C_DeriveKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut derived_key_handle,
+            )
+        },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    // ECDH from an EC private key
+    let ec_params = vec![0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+    let mut public_key_template = vec![CK_ATTRIBUTE {
+        type_: CKA_EC_PARAMS,
+        pValue: ec_params.as_ptr() as CK_VOID_PTR,
+        ulValueLen: ec_params.len() as CK_ULONG,
+    }];
+    let mut key_pair_mechanism = CK_MECHANISM {
+        mechanism: CKM_EC_KEY_PAIR_GEN,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let mut public_key_handle = CK_INVALID_HANDLE;
+    let mut private_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                handle,
+                &raw mut key_pair_mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_OK
+    );
+    let mut public_data = vec![0x04_u8; 65];
+    let mut ecdh_params = CK_ECDH1_DERIVE_PARAMS {
+        kdf: CKD_NULL,
+        ulSharedDataLen: 0,
+        pSharedData: ptr::null_mut(),
+        ulPublicDataLen: public_data.len() as CK_ULONG,
+        pPublicData: public_data.as_mut_ptr(),
+    };
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_ECDH1_DERIVE,
+        pParameter: std::ptr::from_mut(&mut ecdh_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_ECDH1_DERIVE_PARAMS>() as CK_ULONG,
+    };
+    assert_eq!(
+        unsafe {
+            C_DeriveKey(
+                handle,
+                &raw mut mechanism,
+                private_key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut derived_key_handle,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_KEY_HANDLE_INVALID for ECDH with a symmetric base key
+    assert_eq!(
+        unsafe {
+            C_DeriveKey(
+                handle,
+                &raw mut mechanism,
+                key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut derived_key_handle,
+            )
+        },
+        CKR_KEY_HANDLE_INVALID
+    );
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for an ECDH KDF
+    ecdh_params.kdf = CKD_SHA1_KDF;
+    assert_eq!(
+        unsafe {
+            C_DeriveKey(
+                handle,
+                &raw mut mechanism,
+                private_key_handle,
+                template.as_mut_ptr(),
+                template.len() as CK_ULONG,
+                &raw mut derived_key_handle,
+            )
+        },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn encrypt_decrypt_aes_gcm_ctr() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let key_handle = test_generate_key(handle);
+    let mut iv = [0_u8; 12];
+    let mut aad = b"additional data".to_vec();
+    let mut gcm_params = CK_GCM_PARAMS {
+        pIv: iv.as_mut_ptr(),
+        ulIvLen: iv.len() as CK_ULONG,
+        ulIvBits: 96,
+        pAAD: aad.as_mut_ptr(),
+        ulAADLen: aad.len() as CK_ULONG,
+        ulTagBits: 128,
+    };
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_AES_GCM,
+        pParameter: std::ptr::from_mut(&mut gcm_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_GCM_PARAMS>() as CK_ULONG,
+    };
+
+    let mut plaintext = vec![0_u8; 32];
+    let mut ciphertext = vec![0_u8; 64];
+    let mut ciphertext_len = ciphertext.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Encrypt(
+                handle,
+                plaintext.as_mut_ptr(),
+                plaintext.len() as CK_ULONG,
+                ciphertext.as_mut_ptr(),
+                &raw mut ciphertext_len,
+            )
+        },
+        CKR_OK
+    );
+
+    let mut decrypted = vec![0_u8; 64];
+    let mut decrypted_len = decrypted.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_DecryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Decrypt(
+                handle,
+                ciphertext.as_mut_ptr(),
+                ciphertext_len,
+                decrypted.as_mut_ptr(),
+                &raw mut decrypted_len,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for a tag length which is not a whole number of bytes
+    gcm_params.ulTagBits = 100;
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    let mut ctr_params = CK_AES_CTR_PARAMS {
+        ulCounterBits: 128,
+        cb: [0_u8; AES_IV_SIZE],
+    };
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_AES_CTR,
+        pParameter: std::ptr::from_mut(&mut ctr_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_AES_CTR_PARAMS>() as CK_ULONG,
+    };
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+    // Query the length, then provide a buffer too small: the operation stays active
+    ciphertext_len = 0;
+    assert_eq!(
+        unsafe {
+            C_Encrypt(
+                handle,
+                plaintext.as_mut_ptr(),
+                plaintext.len() as CK_ULONG,
+                ptr::null_mut(),
+                &raw mut ciphertext_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(ciphertext_len, (plaintext.len() + AES_IV_SIZE) as CK_ULONG);
+    let mut short_len = ciphertext_len - 1;
+    assert_eq!(
+        unsafe {
+            C_Encrypt(
+                handle,
+                plaintext.as_mut_ptr(),
+                plaintext.len() as CK_ULONG,
+                ciphertext.as_mut_ptr(),
+                &raw mut short_len,
+            )
+        },
+        CKR_BUFFER_TOO_SMALL
+    );
+    assert_eq!(
+        unsafe {
+            C_Encrypt(
+                handle,
+                plaintext.as_mut_ptr(),
+                plaintext.len() as CK_ULONG,
+                ciphertext.as_mut_ptr(),
+                &raw mut ciphertext_len,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for a counter smaller than the block
+    ctr_params.ulCounterBits = 64;
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn encrypt_decrypt_rsa_oaep() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let modulus_bits: CK_ULONG = 2048;
+    let mut public_key_template = vec![CK_ATTRIBUTE {
+        type_: CKA_MODULUS_BITS,
+        pValue: std::ptr::from_ref::<CK_ULONG>(&modulus_bits) as CK_VOID_PTR,
+        ulValueLen: std::mem::size_of_val(&modulus_bits) as CK_ULONG,
+    }];
+    let mut key_pair_mechanism = CK_MECHANISM {
+        mechanism: CKM_RSA_PKCS_KEY_PAIR_GEN,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+    let mut public_key_handle = CK_INVALID_HANDLE;
+    let mut private_key_handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_GenerateKeyPair(
+                handle,
+                &raw mut key_pair_mechanism,
+                public_key_template.as_mut_ptr(),
+                public_key_template.len() as CK_ULONG,
+                public_key_template.as_mut_ptr(),
+                0,
+                &raw mut public_key_handle,
+                &raw mut private_key_handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let mut oaep_params = CK_RSA_PKCS_OAEP_PARAMS {
+        hashAlg: CKM_SHA256,
+        mgf: CKG_MGF1_SHA256,
+        source: CKZ_DATA_SPECIFIED,
+        pSourceData: ptr::null_mut(),
+        ulSourceDataLen: 0,
+    };
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_RSA_PKCS_OAEP,
+        pParameter: std::ptr::from_mut(&mut oaep_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_RSA_PKCS_OAEP_PARAMS>() as CK_ULONG,
+    };
+
+    let mut plaintext = vec![0_u8; 32];
+    let mut ciphertext = vec![0_u8; 256];
+    let mut ciphertext_len = ciphertext.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, public_key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Encrypt(
+                handle,
+                plaintext.as_mut_ptr(),
+                plaintext.len() as CK_ULONG,
+                ciphertext.as_mut_ptr(),
+                &raw mut ciphertext_len,
+            )
+        },
+        CKR_OK
+    );
+
+    let mut decrypted = vec![0_u8; 256];
+    let mut decrypted_len = decrypted.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_DecryptInit(handle, &raw mut mechanism, private_key_handle) },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_Decrypt(
+                handle,
+                ciphertext.as_mut_ptr(),
+                ciphertext_len,
+                decrypted.as_mut_ptr(),
+                &raw mut decrypted_len,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for an OAEP label, which the KMS does not support
+    let mut source_data = b"label".to_vec();
+    oaep_params.pSourceData = source_data.as_mut_ptr() as CK_VOID_PTR;
+    oaep_params.ulSourceDataLen = source_data.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, public_key_handle) },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    // Expect CKR_MECHANISM_PARAM_INVALID for an unknown source of the encoding parameter
+    oaep_params.pSourceData = ptr::null_mut();
+    oaep_params.ulSourceDataLen = 0;
+    oaep_params.source = 2;
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, public_key_handle) },
+        CKR_MECHANISM_PARAM_INVALID
+    );
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn encrypt_decrypt_multipart() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    let key_handle = test_generate_key(handle);
+    let mut iv = [0_u8; AES_IV_SIZE];
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_AES_CBC_PAD,
+        pParameter: iv.as_mut_ptr() as CK_VOID_PTR,
+        ulParameterLen: iv.len() as CK_ULONG,
+    };
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+
+    // Query the length of the encrypted part first
+    let mut part = vec![0_u8; 32];
+    let mut encrypted_part = vec![0_u8; 32];
+    let mut encrypted_part_len: CK_ULONG = 0;
+    assert_eq!(
+        unsafe {
+            C_EncryptUpdate(
+                handle,
+                part.as_mut_ptr(),
+                part.len() as CK_ULONG,
+                ptr::null_mut(),
+                &raw mut encrypted_part_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(encrypted_part_len, 32);
+    assert_eq!(
+        unsafe {
+            C_EncryptUpdate(
+                handle,
+                part.as_mut_ptr(),
+                part.len() as CK_ULONG,
+                encrypted_part.as_mut_ptr(),
+                &raw mut encrypted_part_len,
+            )
+        },
+        CKR_OK
+    );
+
+    // Expect CKR_BUFFER_TOO_SMALL when the buffer cannot hold the last part
+    let mut last_part = vec![0_u8; AES_IV_SIZE];
+    let mut last_part_len: CK_ULONG = 8;
+    assert_eq!(
+        unsafe { C_EncryptFinal(handle, last_part.as_mut_ptr(), &raw mut last_part_len) },
+        CKR_BUFFER_TOO_SMALL
+    );
+    last_part_len = last_part.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_EncryptFinal(handle, last_part.as_mut_ptr(), &raw mut last_part_len) },
+        CKR_OK
+    );
+    assert_eq!(last_part_len, AES_IV_SIZE as CK_ULONG);
+
+    // The operation is terminated
+    assert_eq!(
+        unsafe { C_EncryptFinal(handle, last_part.as_mut_ptr(), &raw mut last_part_len) },
+        CKR_OPERATION_NOT_INITIALIZED
+    );
+
+    // Without any part nor padding, there is nothing left to encrypt
+    let mut ctr_params = CK_AES_CTR_PARAMS {
+        ulCounterBits: 128,
+        cb: [0_u8; AES_IV_SIZE],
+    };
+    let mut ctr_mechanism = CK_MECHANISM {
+        mechanism: CKM_AES_CTR,
+        pParameter: std::ptr::from_mut(&mut ctr_params) as CK_VOID_PTR,
+        ulParameterLen: std::mem::size_of::<CK_AES_CTR_PARAMS>() as CK_ULONG,
+    };
+    assert_eq!(
+        unsafe { C_EncryptInit(handle, &raw mut ctr_mechanism, key_handle) },
+        CKR_OK
+    );
+    last_part_len = last_part.len() as CK_ULONG;
+    assert_eq!(
+        unsafe { C_EncryptFinal(handle, last_part.as_mut_ptr(), &raw mut last_part_len) },
+        CKR_OK
+    );
+    assert_eq!(last_part_len, 0);
+
+    assert_eq!(
+        unsafe { C_DecryptInit(handle, &raw mut mechanism, key_handle) },
+        CKR_OK
+    );
+    let mut decrypted_part = vec![0_u8; 32];
+    let mut decrypted_part_len = decrypted_part.len() as CK_ULONG;
+    assert_eq!(
+        unsafe {
+            C_DecryptUpdate(
+                handle,
+                encrypted_part.as_mut_ptr(),
+                encrypted_part_len,
+                decrypted_part.as_mut_ptr(),
+                &raw mut decrypted_part_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(
+        unsafe {
+            C_DecryptFinal(
+                handle,
+                decrypted_part.as_mut_ptr(),
+                &raw mut decrypted_part_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(decrypted_part_len, 0);
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
+    assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
+}
+
+#[test]
+#[serial]
+fn digest() {
+    test_init();
+    assert_eq!(C_Initialize(ptr::null_mut()), CKR_OK);
+    let mut handle = CK_INVALID_HANDLE;
+    assert_eq!(
+        unsafe {
+            C_OpenSession(
+                SLOT_ID,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                None,
+                &raw mut handle,
+            )
+        },
+        CKR_OK
+    );
+
+    // SHA-256 of "abc" from FIPS 180-2
+    let expected_digest =
+        hex::decode("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap();
+    let mut mechanism = CK_MECHANISM {
+        mechanism: CKM_SHA256,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    };
+
+    // Single part, querying the digest length first
+    let mut data = b"abc".to_vec();
+    let mut digest = vec![0_u8; 32];
+    let mut digest_len: CK_ULONG = 0;
+    assert_eq!(unsafe { C_DigestInit(handle, &raw mut mechanism) }, CKR_OK);
+    assert_eq!(
+        unsafe {
+            C_Digest(
+                handle,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                ptr::null_mut(),
+                &raw mut digest_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(digest_len, 32);
+    assert_eq!(
+        unsafe {
+            C_Digest(
+                handle,
+                data.as_mut_ptr(),
+                data.len() as CK_ULONG,
+                digest.as_mut_ptr(),
+                &raw mut digest_len,
+            )
+        },
+        CKR_OK
+    );
+    assert_eq!(digest, expected_digest);
+
+    // Multipart
+    assert_eq!(unsafe { C_DigestInit(handle, &raw mut mechanism) }, CKR_OK);
+    for part in [b"a", b"b", b"c"] {
+        let mut part = part.to_vec();
+        assert_eq!(
+            unsafe { C_DigestUpdate(handle, part.as_mut_ptr(), part.len() as CK_ULONG) },
+            CKR_OK
+        );
+    }
+    let mut too_small_len: CK_ULONG = 16;
+    assert_eq!(
+        unsafe { C_DigestFinal(handle, digest.as_mut_ptr(), &raw mut too_small_len) },
+        CKR_BUFFER_TOO_SMALL
+    );
+    digest.fill(0);
+    assert_eq!(
+        unsafe { C_DigestFinal(handle, digest.as_mut_ptr(), &raw mut digest_len) },
+        CKR_OK
+    );
+    assert_eq!(digest, expected_digest);
+    assert_eq!(
+        unsafe { C_DigestFinal(handle, digest.as_mut_ptr(), &raw mut digest_len) },
+        CKR_OPERATION_NOT_INITIALIZED
+    );
+
+    // The secret keys are digested by the KMS, unless their value is protected
+    let sensitive_key_handle = test_generate_key(handle);
+    let non_extractable_key_handle = test_create_secret_key(handle, None);
+    let key_handle = test_create_secret_key(handle, Some(&[CK_TRUE][..]));
+    mechanism.mechanism = CKM_SHA_1;
+    assert_eq!(unsafe { C_DigestInit(handle, &raw mut mechanism) }, CKR_OK);
+    assert_eq!(
+        unsafe { C_DigestKey(handle, sensitive_key_handle) },
+        CKR_KEY_INDIGESTIBLE
+    );
+    assert_eq!(
+        unsafe { C_DigestKey(handle, non_extractable_key_handle) },
+        CKR_KEY_INDIGESTIBLE
+    );
+    assert_eq!(unsafe { C_DigestKey(handle, key_handle) }, CKR_OK);
+    // The KMS digest cannot be combined with other parts
+    assert_eq!(
+        unsafe { C_DigestKey(handle, key_handle) },
+        CKR_KEY_INDIGESTIBLE
+    );
+    assert_eq!(
+        unsafe { C_DigestFinal(handle, digest.as_mut_ptr(), &raw mut digest_len) },
+        CKR_OK
+    );
+    assert_eq!(digest_len, 20);
+
+    assert_eq!(C_CloseSession(handle), CKR_OK);
     assert_eq!(C_Finalize(ptr::null_mut()), CKR_OK);
-    Ok(())
 }
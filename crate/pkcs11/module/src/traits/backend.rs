@@ -1,23 +1,40 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, PoisonError, RwLock};
 
+use cosmian_logger::error;
+use pkcs11_sys::{CK_MECHANISM_INFO, CK_MECHANISM_TYPE, CK_SLOT_ID};
 use zeroize::Zeroizing;
 
 use super::{SignatureAlgorithm, SymmetricKey};
 use crate::{
-    ModuleResult,
-    core::object::Object,
+    ModuleError, ModuleResult,
+    core::{attribute::Attributes, object::Object},
+    pkcs11::SLOT_ID,
     traits::{
-        Certificate, DataObject, EncryptionAlgorithm, KeyAlgorithm, PrivateKey, PublicKey,
-        SearchOptions, Version,
+        Certificate, DataObject, DigestType, EncryptionAlgorithm, KeyAlgorithm,
+        KeyDerivationAlgorithm, KeyWrappingAlgorithm, PrivateKey, PublicKey, SearchOptions,
+        SearchTemplate, Version,
     },
 };
 
 #[derive(Debug)]
 pub struct SignContext {
+    /// The private key, or the generic secret of an HMAC
+    pub remote_object_id: String,
     pub algorithm: SignatureAlgorithm,
-    pub private_key: Arc<dyn PrivateKey>,
     /// Payload stored for multipart `C_SignUpdate` operations.
     pub payload: Option<Vec<u8>>,
+    /// Signature computed by a `C_Sign` call made to query the signature
+    /// length, returned by the following call instead of signing again.
+    pub signature: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct VerifyContext {
+    /// The public key, or the generic secret of an HMAC
+    pub remote_object_id: String,
+    pub algorithm: SignatureAlgorithm,
+    /// Payload stored for multipart `C_VerifyUpdate` operations.
+    pub payload: Option<Vec<u8>>,
 }
 
 #[derive(Debug)]
@@ -25,6 +42,14 @@ pub struct DecryptContext {
     pub remote_object_id: String,
     pub algorithm: EncryptionAlgorithm,
     pub iv: Option<Vec<u8>>,
+    /// Additional authenticated data of AES GCM
+    pub aad: Option<Vec<u8>>,
+    /// Correlation value returned by the KMS between the parts of multipart
+    /// `C_DecryptUpdate` operations.
+    pub correlation_value: Option<Vec<u8>>,
+    /// Output of a multipart operation kept for the next call when the caller
+    /// only queried its length or provided a buffer too small.
+    pub pending_output: Option<Zeroizing<Vec<u8>>>,
 }
 
 #[derive(Debug)]
@@ -32,30 +57,87 @@ pub struct EncryptContext {
     pub remote_object_id: String,
     pub algorithm: EncryptionAlgorithm,
     pub iv: Option<Vec<u8>>,
+    /// Additional authenticated data of AES GCM
+    pub aad: Option<Vec<u8>>,
+    /// Correlation value returned by the KMS between the parts of multipart
+    /// `C_EncryptUpdate` operations.
+    pub correlation_value: Option<Vec<u8>>,
+    /// Output of a `C_Encrypt` call or of a multipart operation kept for the
+    /// next call when the caller only queried its length or provided a buffer
+    /// too small.
+    pub pending_output: Option<Zeroizing<Vec<u8>>>,
 }
 
-//  The Backend is first staged so it can be stored in a Box<dyn Backend>. This
-//  allows the Backend to be reference with `&'static`.
-static STAGED_BACKEND: RwLock<Option<Box<dyn Backend>>> = RwLock::new(None);
-#[expect(clippy::expect_used)]
-static BACKEND: std::sync::LazyLock<Box<dyn Backend>> = std::sync::LazyLock::new(|| {
-    STAGED_BACKEND
+/// Builds the backends of the slots; called by `C_Initialize`.
+///
+/// Each backend is bound to its own slot: the first one to `SLOT_ID`,
+/// the next ones to the following slot ids.
+pub type BackendFactory = fn() -> ModuleResult<Vec<Box<dyn Backend>>>;
+
+static BACKEND_FACTORY: RwLock<Option<BackendFactory>> = RwLock::new(None);
+static BACKENDS: RwLock<Vec<Arc<dyn Backend>>> = RwLock::new(Vec::new());
+
+/// Stores the function building the backends later returned by
+/// `crate::backend_for()`.
+///
+/// The backends are only built by `C_Initialize`, so that a broken configuration
+/// or an unreachable server is reported to the caller as an error code.
+pub fn register_backend(factory: BackendFactory) {
+    *BACKEND_FACTORY
         .write()
-        .expect("Failed to acquire write lock")
-        .take()
-        .expect("Backend not initialized")
-});
-
-/// Stores a backend to later be returned by all calls `crate::backend()`.
-#[expect(clippy::expect_used, clippy::missing_panics_doc)]
-pub fn register_backend(backend: Box<dyn Backend>) {
-    *STAGED_BACKEND
+        .unwrap_or_else(PoisonError::into_inner) = Some(factory);
+}
+
+/// Build the backends with the registered factory.
+/// On failure, the next `C_Initialize` call tries again.
+pub(crate) fn initialize_backends() -> ModuleResult<()> {
+    let factory = BACKEND_FACTORY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .ok_or_else(|| ModuleError::Default("no backend has been registered".to_owned()))?;
+    let backends = factory().inspect_err(|e| error!("failed instantiating the backends: {e}"))?;
+    if backends.is_empty() {
+        return Err(ModuleError::Default(
+            "the backend factory returned no slot".to_owned(),
+        ));
+    }
+    *BACKENDS.write().unwrap_or_else(PoisonError::into_inner) =
+        backends.into_iter().map(Arc::from).collect();
+    Ok(())
+}
+
+/// Drop the backends; called by `C_Finalize`.
+pub(crate) fn release_backends() {
+    BACKENDS
         .write()
-        .expect("Failed to acquire write lock") = Some(backend);
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// The ids of the slots, one per backend
+pub(crate) fn slot_ids() -> Vec<CK_SLOT_ID> {
+    let count = BACKENDS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .len();
+    (SLOT_ID..).take(count).collect()
 }
 
-pub fn backend() -> &'static dyn Backend {
-    BACKEND.as_ref()
+/// The backend bound to the slot `slot`
+///
+/// # Errors
+/// `CryptokiNotInitialized` when the module has not been initialized,
+/// `SlotIdInvalid` when there is no such slot.
+pub fn backend_for(slot: CK_SLOT_ID) -> ModuleResult<Arc<dyn Backend>> {
+    let backends = BACKENDS.read().unwrap_or_else(PoisonError::into_inner);
+    if backends.is_empty() {
+        return Err(ModuleError::CryptokiNotInitialized);
+    }
+    (SLOT_ID..)
+        .zip(backends.iter())
+        .find(|(slot_id, _)| *slot_id == slot)
+        .map(|(_, backend)| backend.clone())
+        .ok_or(ModuleError::SlotIdInvalid(slot))
 }
 
 pub trait Backend: Send + Sync {
@@ -74,6 +156,29 @@ pub trait Backend: Send + Sync {
     /// The version of this library
     fn library_version(&self) -> Version;
 
+    /// Whether the user must log in with `C_Login` before using the objects
+    /// of the token. Otherwise, the token has a protected authentication path
+    /// and `C_Login` does not need a PIN.
+    fn login_required(&self) -> bool {
+        false
+    }
+    /// Unlock the credentials of the token with the user `pin`.
+    /// A wrong PIN is reported as
+    /// [`ModuleError::PinIncorrect`](crate::ModuleError::PinIncorrect).
+    fn login(&self, _pin: &[u8]) -> ModuleResult<()> {
+        Ok(())
+    }
+    /// Forget the credentials unlocked by `login`.
+    fn logout(&self) {}
+
+    /// Whether the keys created through the token are extractable
+    /// when their template does not set `CKA_EXTRACTABLE`.
+    fn extractable_by_default(&self) -> bool {
+        false
+    }
+
+    /// Find the certificate of this ID, or linked to the private key of this ID.
+    /// An unknown ID matches no certificate: `None` is returned.
     fn find_certificate(&self, query: SearchOptions) -> ModuleResult<Option<Arc<dyn Certificate>>>;
     fn find_all_certificates(&self) -> ModuleResult<Vec<Arc<dyn Certificate>>>;
 
@@ -89,20 +194,154 @@ pub trait Backend: Send + Sync {
     fn find_data_object(&self, query: SearchOptions) -> ModuleResult<Option<Arc<dyn DataObject>>>;
     fn find_all_data_objects(&self) -> ModuleResult<Vec<Arc<dyn DataObject>>>;
 
-    fn find_all_objects(&self) -> ModuleResult<Vec<Arc<Object>>>;
+    /// Find the objects matching the filters of a `C_FindObjectsInit` template.
+    /// The filters should be resolved by the backend rather than by listing
+    /// every object.
+    fn find_objects(&self, template: &SearchTemplate) -> ModuleResult<Vec<Arc<Object>>>;
 
     fn generate_key(
         &self,
         algorithm: KeyAlgorithm,
         key_length: usize,
         sensitive: bool,
+        extractable: bool,
         label: Option<&str>,
     ) -> ModuleResult<Arc<dyn SymmetricKey>>;
 
+    /// Generate an asymmetric key pair.
+    ///
+    /// `key_length` is the modulus size in bits for RSA keys and is `None`
+    /// for elliptic curve keys, whose size is given by the curve.
+    /// The backend identifies the keys by ids of its own: `id`, the `CKA_ID`
+    /// of the key pair, is kept as metadata of both keys.
+    fn generate_key_pair(
+        &self,
+        algorithm: KeyAlgorithm,
+        key_length: Option<usize>,
+        sensitive: bool,
+        extractable: bool,
+        id: Option<&[u8]>,
+        label: Option<&str>,
+    ) -> ModuleResult<(Arc<dyn PublicKey>, Arc<dyn PrivateKey>)>;
+
     fn create_object(&self, label: &str, data: &[u8]) -> ModuleResult<Arc<dyn DataObject>>;
+
+    /// Import the raw `value` of a secret key created by `C_CreateObject`.
+    fn import_symmetric_key(
+        &self,
+        algorithm: KeyAlgorithm,
+        value: Zeroizing<Vec<u8>>,
+        sensitive: bool,
+        extractable: bool,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>>;
+
+    /// Import a private key created by `C_CreateObject`, encoded as PKCS#8 DER.
+    ///
+    /// The backend identifies the key by an id of its own: `id`, the `CKA_ID` of the key,
+    /// is kept as metadata so that the key is found by it and that the certificates
+    /// created with the same `CKA_ID` are linked to it.
+    fn import_private_key(
+        &self,
+        algorithm: KeyAlgorithm,
+        pkcs8: Zeroizing<Vec<u8>>,
+        sensitive: bool,
+        extractable: bool,
+        id: Option<&[u8]>,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn PrivateKey>>;
+
+    /// Import an X.509 certificate created by `C_CreateObject`, encoded as DER.
+    /// When given, `id` is its `CKA_ID`: the certificate is linked to the private key
+    /// with the same `CKA_ID`.
+    fn import_certificate(
+        &self,
+        der: &[u8],
+        id: Option<&[u8]>,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn Certificate>>;
+
+    /// Apply the `attributes` of a `C_SetAttributeValue` template to the remote `object`
+    /// and return the object refreshed from the backend.
+    /// The module has checked that these attributes are modifiable for this object.
+    fn set_attributes(&self, object: &Object, attributes: &Attributes)
+    -> ModuleResult<Arc<Object>>;
+
     fn revoke_object(&self, remote_id: &str) -> ModuleResult<()>;
     fn destroy_object(&self, remote_id: &str) -> ModuleResult<()>;
 
+    /// Sign `data` with the private key identified by `remote_id`.
+    fn sign(
+        &self,
+        remote_id: &str,
+        algorithm: &SignatureAlgorithm,
+        data: &[u8],
+    ) -> ModuleResult<Vec<u8>>;
+
+    /// Verify the `signature` of `data` with the public key identified by
+    /// `remote_id`. An invalid signature is reported as
+    /// [`ModuleError::SignatureInvalid`](crate::ModuleError::SignatureInvalid).
+    fn verify(
+        &self,
+        remote_id: &str,
+        algorithm: &SignatureAlgorithm,
+        data: &[u8],
+        signature: &[u8],
+    ) -> ModuleResult<()>;
+
+    /// Wrap (encrypt) the key identified by `key_id` with the key
+    /// identified by `wrapping_key_id` and return the wrapped key bytes.
+    fn wrap_key(
+        &self,
+        wrapping_key_id: &str,
+        key_id: &str,
+        algorithm: &KeyWrappingAlgorithm,
+    ) -> ModuleResult<Vec<u8>>;
+
+    /// Unwrap (decrypt) `wrapped_key` with the key identified by
+    /// `unwrapping_key_id` and store the resulting symmetric key.
+    fn unwrap_key(
+        &self,
+        unwrapping_key_id: &str,
+        algorithm: &KeyWrappingAlgorithm,
+        wrapped_key: &[u8],
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>>;
+
+    /// Derive a new secret key of type `key_algorithm` from the key identified by `base_key_id`.
+    ///
+    /// `key_length` is the length in bytes of the derived key; when `None`,
+    /// the natural length of the derivation output is used.
+    /// The derivation must happen in the backend: the base key never leaves it.
+    #[expect(clippy::too_many_arguments)]
+    fn derive_key(
+        &self,
+        base_key_id: &str,
+        algorithm: &KeyDerivationAlgorithm,
+        key_algorithm: KeyAlgorithm,
+        key_length: Option<usize>,
+        sensitive: bool,
+        extractable: bool,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>>;
+
+    /// Narrow the capabilities of a mechanism of the module registry,
+    /// or return `None` when the backend does not support it at all.
+    fn mechanism_info(
+        &self,
+        _mechanism: CK_MECHANISM_TYPE,
+        info: CK_MECHANISM_INFO,
+    ) -> Option<CK_MECHANISM_INFO> {
+        Some(info)
+    }
+
+    /// Digest the value of the secret key `key_id`.
+    ///
+    /// The key value must not leave the backend: a backend which cannot digest the key
+    /// where it is stored refuses it with `KeyIndigestible`.
+    /// The module only calls it for keys which are neither sensitive nor non-extractable.
+    fn digest_key(&self, key_id: &str, digest_type: &DigestType) -> ModuleResult<Vec<u8>>;
+
     fn encrypt(&self, ctx: &EncryptContext, cleartext: Vec<u8>) -> ModuleResult<Vec<u8>>;
 
     fn decrypt(
@@ -110,4 +349,20 @@ pub trait Backend: Send + Sync {
         ctx: &DecryptContext,
         ciphertext: Vec<u8>,
     ) -> ModuleResult<Zeroizing<Vec<u8>>>;
+
+    /// Encrypt a part of a multipart encryption, or terminate it when `part` is `None`.
+    /// The state of the operation is kept in the context between the calls.
+    fn encrypt_multipart(
+        &self,
+        ctx: &mut EncryptContext,
+        part: Option<Vec<u8>>,
+    ) -> ModuleResult<Vec<u8>>;
+
+    /// Decrypt a part of a multipart decryption, or terminate it when `part` is `None`.
+    /// The state of the operation is kept in the context between the calls.
+    fn decrypt_multipart(
+        &self,
+        ctx: &mut DecryptContext,
+        part: Option<Vec<u8>>,
+    ) -> ModuleResult<Zeroizing<Vec<u8>>>;
 }
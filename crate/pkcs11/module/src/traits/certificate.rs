@@ -2,7 +2,7 @@ use std::hash::Hash;
 
 use crate::{
     ModuleResult,
-    traits::{KeyAlgorithm, PublicKey},
+    traits::{KeyAlgorithm, KeyMetadata, PublicKey},
 };
 
 pub trait Certificate: Send + Sync + std::fmt::Debug {
@@ -19,8 +19,13 @@ pub trait Certificate: Send + Sync + std::fmt::Debug {
     fn subject(&self) -> ModuleResult<Vec<u8>>;
 
     /// This returns the private key ID associated with the certificate
-    /// which the `CKA_ID`
+    /// which is the `CKA_ID`, unless the metadata of the certificate holds one
     fn private_key_id(&self) -> String;
+
+    /// The metadata of the certificate held by the backend
+    fn metadata(&self) -> KeyMetadata {
+        KeyMetadata::default()
+    }
 }
 
 impl PartialEq for dyn Certificate {
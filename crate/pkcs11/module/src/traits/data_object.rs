@@ -9,14 +9,21 @@ use std::hash::Hash;
 
 use zeroize::{Zeroize, Zeroizing};
 
+use crate::{ModuleResult, traits::KeyMetadata};
+
 pub trait DataObject: Zeroize + Send + Sync {
     /// The unique identifier of the object (in the KMS)
     fn remote_id(&self) -> String;
-    /// The value of the object which may be a secret
-    fn value(&self) -> Zeroizing<Vec<u8>>;
+    /// The value of the object which may be a secret.
+    /// It may be fetched from the KMS when first requested.
+    fn value(&self) -> ModuleResult<Zeroizing<Vec<u8>>>;
     /// The application that manages the object
     fn application(&self) -> Vec<u8>;
-    fn data_hash(&self) -> Vec<u8>;
+    fn data_hash(&self) -> ModuleResult<Vec<u8>>;
+    /// The metadata of the object held by the backend
+    fn metadata(&self) -> KeyMetadata {
+        KeyMetadata::default()
+    }
 }
 
 impl std::fmt::Debug for dyn DataObject {
@@ -1,7 +1,29 @@
-#[derive(Debug, Clone, Copy)]
+use crate::traits::DigestType;
+
+#[derive(Debug, Clone)]
 pub enum EncryptionAlgorithm {
     // CKM_RSA_PKCS
     RsaPkcs1v15,
+    // CKM_RSA_PKCS_OAEP
+    RsaOaep {
+        digest: DigestType,
+        mask_generation_function: DigestType,
+    },
     AesCbcPad,
     AesCbc,
+    AesCtr,
+    /// The authentication tag of `tag_length` bytes is appended to the ciphertext
+    AesGcm {
+        tag_length: usize,
+    },
+}
+
+impl EncryptionAlgorithm {
+    /// Whether the algorithm can be streamed in multipart operations.
+    /// The AES GCM tag must be verified before releasing any plaintext,
+    /// and RSA operates on a single block.
+    #[must_use]
+    pub const fn is_multipart(&self) -> bool {
+        matches!(self, Self::AesCbcPad | Self::AesCbc | Self::AesCtr)
+    }
 }
@@ -1,13 +1,22 @@
 use std::str::FromStr;
 
-use pkcs1::ObjectIdentifier;
-use pkcs11_sys::{CK_KEY_TYPE, CKK_AES, CKK_EC, CKK_RSA};
+use pkcs1::{
+    ObjectIdentifier,
+    der::{Decode, asn1::PrintableStringRef},
+};
+use pkcs11_sys::{
+    CK_KEY_TYPE, CKK_AES, CKK_EC, CKK_EC_EDWARDS, CKK_EC_MONTGOMERY, CKK_GENERIC_SECRET, CKK_RSA,
+};
 
-use crate::ModuleResult;
+use crate::{ModuleError, ModuleResult};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyAlgorithm {
+    Aes128,
+    Aes192,
     Aes256,
+    /// A generic secret, used as an HMAC key
+    GenericSecret,
     Rsa,
     EccP256,
     EccP384,
@@ -24,20 +33,33 @@ impl KeyAlgorithm {
     #[must_use]
     pub const fn to_ck_key_type(&self) -> CK_KEY_TYPE {
         match self {
-            Self::Aes256 => CKK_AES,
+            Self::Aes128 | Self::Aes192 | Self::Aes256 => CKK_AES,
+            Self::GenericSecret => CKK_GENERIC_SECRET,
             Self::Rsa => CKK_RSA,
-            Self::EccP256
-            | Self::Secp224k1
-            | Self::Secp256k1
-            | Self::EccP384
-            | Self::EccP521
-            | Self::Ed448
-            | Self::Ed25519
-            | Self::X448
-            | Self::X25519 => CKK_EC,
+            Self::EccP256 | Self::Secp224k1 | Self::Secp256k1 | Self::EccP384 | Self::EccP521 => {
+                CKK_EC
+            }
+            Self::Ed448 | Self::Ed25519 => CKK_EC_EDWARDS,
+            Self::X448 | Self::X25519 => CKK_EC_MONTGOMERY,
         }
     }
 
+    /// The AES algorithm of a key of `key_length` bytes
+    #[must_use]
+    pub const fn aes(key_length: usize) -> Option<Self> {
+        match key_length {
+            16 => Some(Self::Aes128),
+            24 => Some(Self::Aes192),
+            32 => Some(Self::Aes256),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_aes(&self) -> bool {
+        matches!(self, Self::Aes128 | Self::Aes192 | Self::Aes256)
+    }
+
     #[must_use]
     pub const fn is_rsa(&self) -> bool {
         matches!(self, Self::Rsa)
@@ -59,10 +81,14 @@ impl KeyAlgorithm {
         )
     }
 
+    /// The OID of the algorithm; a generic secret has none
     #[must_use]
-    pub const fn to_oid_str(&self) -> &'static str {
-        match self {
+    pub const fn to_oid_str(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Aes128 => "2.16.840.1.101.3.4.1.1",
+            Self::Aes192 => "2.16.840.1.101.3.4.1.21",
             Self::Aes256 => "2.16.840.1.101.3.4.1.41",
+            Self::GenericSecret => return None,
             Self::Rsa => "1.2.840.113549.1.1.1",
             Self::EccP256 => "1.2.840.10045.3.1.7",
             Self::EccP384 => "1.3.132.0.34",
@@ -73,16 +99,21 @@ impl KeyAlgorithm {
             Self::Ed448 => "1.3.101.113",
             Self::Secp224k1 => "1.3.132.0.32",
             Self::Secp256k1 => "1.3.132.0.33",
-        }
+        })
     }
 
     pub fn to_oid(&self) -> ModuleResult<ObjectIdentifier> {
-        Ok(ObjectIdentifier::from_str(self.to_oid_str())?)
+        let oid = self
+            .to_oid_str()
+            .ok_or_else(|| ModuleError::AlgorithmNotSupported(format!("no OID for {self:?}")))?;
+        Ok(ObjectIdentifier::from_str(oid)?)
     }
 
     #[must_use]
     pub fn from_oid_str(oid: &str) -> Option<Self> {
         match oid {
+            "2.16.840.1.101.3.4.1.1" => Some(Self::Aes128),
+            "2.16.840.1.101.3.4.1.21" => Some(Self::Aes192),
             "2.16.840.1.101.3.4.1.41" => Some(Self::Aes256),
             "1.2.840.113549.1.1.1" => Some(Self::Rsa),
             "1.2.840.10045.3.1.7" => Some(Self::EccP256),
@@ -102,4 +133,20 @@ impl KeyAlgorithm {
     pub fn from_oid(oid: &ObjectIdentifier) -> Option<Self> {
         Self::from_oid_str(&oid.to_string())
     }
+
+    /// The curve of a `CKA_EC_PARAMS` value: the DER encoding of the curve OID,
+    /// or of the curve name for the Edwards and Montgomery curves.
+    #[must_use]
+    pub fn from_ec_params(ec_params: &[u8]) -> Option<Self> {
+        if let Ok(oid) = ObjectIdentifier::from_der(ec_params) {
+            return Self::from_oid(&oid);
+        }
+        match PrintableStringRef::from_der(ec_params).ok()?.as_str() {
+            "edwards25519" => Some(Self::Ed25519),
+            "edwards448" => Some(Self::Ed448),
+            "curve25519" => Some(Self::X25519),
+            "curve448" => Some(Self::X448),
+            _ => None,
+        }
+    }
 }
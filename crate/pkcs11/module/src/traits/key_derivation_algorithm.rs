@@ -0,0 +1,19 @@
+use crate::traits::DigestType;
+
+/// The algorithms used to derive a secret key from a base key
+#[derive(Debug, Clone)]
+pub enum KeyDerivationAlgorithm {
+    /// ECDH with the peer public key, given as an EC point. The raw shared
+    /// secret is the derived key.
+    Ecdh { public_data: Vec<u8> },
+    Hkdf {
+        digest: DigestType,
+        salt: Vec<u8>,
+        info: Vec<u8>,
+    },
+    Pbkdf2 {
+        digest: DigestType,
+        salt: Vec<u8>,
+        iterations: u64,
+    },
+}
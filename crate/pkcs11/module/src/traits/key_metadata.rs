@@ -0,0 +1,88 @@
+use pkcs11_sys::{
+    CK_FLAGS, CKF_DECRYPT, CKF_DERIVE, CKF_ENCRYPT, CKF_SIGN, CKF_UNWRAP, CKF_VERIFY, CKF_WRAP,
+};
+
+/// The metadata that the backend holds for an object, reported as its PKCS#11 attributes.
+/// A field left to `None` is unknown to the backend: the attribute keeps its default value.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KeyMetadata {
+    /// `CKA_LABEL`
+    pub label: Option<String>,
+    /// `CKA_ID`, when the backend holds one distinct from the remote id of the key
+    pub id: Option<Vec<u8>>,
+    /// `CKA_ENCRYPT`, `CKA_DECRYPT`, `CKA_SIGN`... the operations the key may be used for.
+    /// None of them is allowed for a key which is no longer active.
+    pub usage: Option<KeyUsage>,
+    /// `CKA_START_DATE`, as the 8 digits of `YYYYMMDD`
+    pub start_date: Option<String>,
+    /// `CKA_END_DATE`, as the 8 digits of `YYYYMMDD`
+    pub end_date: Option<String>,
+    /// `CKA_LOCAL`: whether the key was generated by `C_GenerateKey` or `C_GenerateKeyPair`
+    pub local: Option<bool>,
+    /// `CKA_SENSITIVE`: the key material of a sensitive key cannot be revealed
+    pub sensitive: Option<bool>,
+    /// `CKA_EXTRACTABLE`: the key material of a non-extractable key cannot be revealed
+    pub extractable: Option<bool>,
+}
+
+impl KeyMetadata {
+    /// Whether the key is sensitive; a key is not unless the backend says so
+    #[must_use]
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive.unwrap_or(false)
+    }
+
+    /// Whether the key is extractable; a key is unless the backend says otherwise
+    #[must_use]
+    pub fn is_extractable(&self) -> bool {
+        self.extractable.unwrap_or(true)
+    }
+
+    /// Whether `C_GetAttributeValue` must not reveal the key material
+    #[must_use]
+    pub fn protects_value(&self) -> bool {
+        self.is_sensitive() || !self.is_extractable()
+    }
+}
+
+/// The operations a key may be used for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUsage {
+    pub encrypt: bool,
+    pub decrypt: bool,
+    pub sign: bool,
+    pub verify: bool,
+    pub wrap: bool,
+    pub unwrap: bool,
+    pub derive: bool,
+}
+
+impl KeyUsage {
+    /// The mechanism flags of these operations, to select the allowed mechanisms
+    #[must_use]
+    pub const fn flags(&self) -> CK_FLAGS {
+        let mut flags = 0;
+        if self.encrypt {
+            flags |= CKF_ENCRYPT;
+        }
+        if self.decrypt {
+            flags |= CKF_DECRYPT;
+        }
+        if self.sign {
+            flags |= CKF_SIGN;
+        }
+        if self.verify {
+            flags |= CKF_VERIFY;
+        }
+        if self.wrap {
+            flags |= CKF_WRAP;
+        }
+        if self.unwrap {
+            flags |= CKF_UNWRAP;
+        }
+        if self.derive {
+            flags |= CKF_DERIVE;
+        }
+        flags
+    }
+}
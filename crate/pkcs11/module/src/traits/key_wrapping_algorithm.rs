@@ -0,0 +1,14 @@
+use crate::traits::DigestType;
+
+/// The algorithms used to wrap (encrypt) a key with another key
+#[derive(Debug, Clone)]
+pub enum KeyWrappingAlgorithm {
+    /// AES Key Wrap (RFC 3394)
+    AesKeyWrap,
+    /// AES Key Wrap with Padding (RFC 5649)
+    AesKeyWrapPad,
+    RsaOaep {
+        digest: DigestType,
+        mask_generation_function: DigestType,
+    },
+}
@@ -18,13 +18,19 @@
 // limitations under the License.
 
 pub use backend::{
-    Backend, DecryptContext, EncryptContext, SignContext, backend, register_backend,
+    Backend, BackendFactory, DecryptContext, EncryptContext, SignContext, VerifyContext,
+    backend_for, register_backend,
 };
+pub(crate) use backend::{initialize_backends, release_backends, slot_ids};
 pub use certificate::Certificate;
 pub use data_object::DataObject;
 pub use encryption_algorithms::EncryptionAlgorithm;
 pub use key_algorithm::KeyAlgorithm;
+pub use key_derivation_algorithm::KeyDerivationAlgorithm;
+pub use key_metadata::{KeyMetadata, KeyUsage};
+pub use key_wrapping_algorithm::KeyWrappingAlgorithm;
 pub use once_cell;
+use pkcs11_sys::{CK_KEY_TYPE, CK_OBJECT_CLASS, CK_ULONG};
 pub use private_key::PrivateKey;
 pub use public_key::PublicKey;
 pub use signature_algorithm::SignatureAlgorithm;
@@ -40,6 +46,9 @@ mod certificate;
 mod data_object;
 mod encryption_algorithms;
 mod key_algorithm;
+mod key_derivation_algorithm;
+mod key_metadata;
+mod key_wrapping_algorithm;
 mod private_key;
 mod public_key;
 mod signature_algorithm;
@@ -90,6 +99,59 @@ impl TryFrom<&Attributes> for SearchOptions {
     }
 }
 
+/// The filters of a `C_FindObjectsInit` template that the backend applies
+/// when searching for objects. A filter left to `None` matches any object.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchTemplate {
+    /// `CKA_CLASS`
+    pub class: Option<CK_OBJECT_CLASS>,
+    /// `CKA_KEY_TYPE`
+    pub key_type: Option<CK_KEY_TYPE>,
+    /// `CKA_LABEL`
+    pub label: Option<String>,
+    /// `CKA_ID`
+    pub id: Option<Vec<u8>>,
+    /// `CKA_TOKEN`
+    pub token: Option<bool>,
+    /// `CKA_SIGN`
+    pub sign: Option<bool>,
+    /// `CKA_DECRYPT`
+    pub decrypt: Option<bool>,
+    /// `CKA_VALUE_LEN`, in bytes
+    pub value_len: Option<CK_ULONG>,
+}
+
+impl From<&Attributes> for SearchTemplate {
+    fn from(attributes: &Attributes) -> Self {
+        let mut template = Self::default();
+        if let Some(Attribute::Class(class)) = attributes.get(AttributeType::Class) {
+            template.class = Some(*class);
+        }
+        if let Some(Attribute::KeyType(key_type)) = attributes.get(AttributeType::KeyType) {
+            template.key_type = Some(*key_type);
+        }
+        if let Some(Attribute::Label(label)) = attributes.get(AttributeType::Label) {
+            template.label = Some(label.clone());
+        }
+        if let Some(Attribute::Id(id)) = attributes.get(AttributeType::Id) {
+            template.id = Some(id.clone());
+        }
+        if let Some(Attribute::Token(token)) = attributes.get(AttributeType::Token) {
+            template.token = Some(*token);
+        }
+        if let Some(Attribute::Sign(sign)) = attributes.get(AttributeType::Sign) {
+            template.sign = Some(*sign);
+        }
+        if let Some(Attribute::Decrypt(decrypt)) = attributes.get(AttributeType::Decrypt) {
+            template.decrypt = Some(*decrypt);
+        }
+        if let Some(Attribute::ValueLen(value_len)) = attributes.get(AttributeType::ValueLen) {
+            template.value_len = Some(*value_len);
+        }
+        template
+    }
+}
+
 pub struct Version {
     pub major: u8,
     pub minor: u8,
@@ -17,21 +17,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::hash::Hash;
+use std::{hash::Hash, sync::Arc};
 
 use zeroize::Zeroizing;
 
 use crate::{
     ModuleResult,
-    traits::{KeyAlgorithm, SignatureAlgorithm},
+    traits::{KeyAlgorithm, KeyMetadata, PublicKey},
 };
 
 pub trait PrivateKey: Send + Sync {
     /// The unique identifier of the key (in the KMS)
     fn remote_id(&self) -> String;
 
-    fn sign(&self, algorithm: &SignatureAlgorithm, data: &[u8]) -> ModuleResult<Vec<u8>>;
-
     /// Returns the algorithm of the key; will fail if only the remote part is known
     fn algorithm(&self) -> KeyAlgorithm;
 
@@ -45,6 +43,15 @@ pub trait PrivateKey: Send + Sync {
     /// Return the RSA public exponent if the key is an RSA key
     /// In big endian
     fn rsa_public_exponent(&self) -> ModuleResult<Vec<u8>>;
+
+    /// Return the public key of the key pair, which holds the public components
+    /// of the key without exporting it
+    fn public_key(&self) -> ModuleResult<Arc<dyn PublicKey>>;
+
+    /// The metadata of the key held by the backend
+    fn metadata(&self) -> KeyMetadata {
+        KeyMetadata::default()
+    }
 }
 
 impl std::fmt::Debug for dyn PrivateKey {
@@ -1,22 +1,21 @@
 use std::{hash::Hash, sync::Arc};
 
-use pkcs1::RsaPublicKey;
+use pkcs1::{
+    RsaPublicKey,
+    der::{Encode, asn1::OctetStringRef},
+};
 
 use crate::{
-    ModuleResult,
-    traits::{KeyAlgorithm, SignatureAlgorithm},
+    ModuleError, ModuleResult,
+    traits::{KeyAlgorithm, KeyMetadata},
 };
 
 pub trait PublicKey: Send + Sync {
     /// The unique ID of the key (in the KMS)
     fn remote_id(&self) -> String;
-    fn fingerprint(&self) -> &[u8];
-    fn verify(
-        &self,
-        algorithm: &SignatureAlgorithm,
-        data: &[u8],
-        signature: &[u8],
-    ) -> ModuleResult<()>;
+    /// The SHA3-256 fingerprint of the DER encoding of the key.
+    /// The key material may be fetched from the KMS when first requested.
+    fn fingerprint(&self) -> ModuleResult<&[u8]>;
     fn delete(self: Arc<Self>);
     fn algorithm(&self) -> KeyAlgorithm;
     /// Return the RSA public key if the key is an RSA key
@@ -31,8 +30,29 @@ pub trait PublicKey: Send + Sync {
     fn rsa_public_exponent(&self) -> ModuleResult<Vec<u8>> {
         Ok(self.rsa_public_key()?.public_exponent.as_bytes().to_vec())
     }
-    /// Return the EC P256 public key if the key is an EC key
-    fn ec_p256_public_key(&self) -> ModuleResult<p256::PublicKey>;
+    /// Return the public point if the key is an EC key, as in the `subjectPublicKey`
+    /// of its `SubjectPublicKeyInfo`: the SEC1 uncompressed point on a Weierstrass curve,
+    /// the raw public key on an Edwards or Montgomery curve
+    fn ec_public_point(&self) -> ModuleResult<Vec<u8>>;
+    /// Return the `CKA_EC_POINT` of an EC key: its public point as a DER OCTET STRING
+    fn ec_point(&self) -> ModuleResult<Vec<u8>> {
+        Ok(OctetStringRef::new(&self.ec_public_point()?)?.to_der()?)
+    }
+    /// Return the EC P256 public key if the key is an EC P256 key
+    fn ec_p256_public_key(&self) -> ModuleResult<p256::PublicKey> {
+        if self.algorithm() != KeyAlgorithm::EccP256 {
+            return Err(ModuleError::Cryptography(
+                "Public key is not an EC P256 key".to_owned(),
+            ));
+        }
+        p256::PublicKey::from_sec1_bytes(&self.ec_public_point()?).map_err(|e| {
+            ModuleError::Cryptography(format!("Failed to parse EC P256 public key: {e:?}"))
+        })
+    }
+    /// The metadata of the key held by the backend
+    fn metadata(&self) -> KeyMetadata {
+        KeyMetadata::default()
+    }
 }
 
 impl PartialEq for dyn PublicKey {
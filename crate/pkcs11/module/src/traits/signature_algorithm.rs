@@ -3,6 +3,8 @@ use crate::traits::DigestType;
 #[derive(Debug, Clone)]
 pub enum SignatureAlgorithm {
     Ecdsa,
+    /// Pure `EdDSA`, without pre-hashing or context, on Ed25519 or Ed448
+    EdDsa,
     RsaRaw,
     RsaPkcs1v15Raw,
     RsaPkcs1v15Sha1,
@@ -14,4 +16,16 @@ pub enum SignatureAlgorithm {
         mask_generation_function: DigestType,
         salt_length: u64,
     },
+    /// HMAC, computed with a generic secret
+    Hmac {
+        digest: DigestType,
+    },
+}
+
+impl SignatureAlgorithm {
+    /// Whether the algorithm signs with a secret key rather than a private key
+    #[must_use]
+    pub const fn is_hmac(&self) -> bool {
+        matches!(self, Self::Hmac { .. })
+    }
 }
@@ -6,7 +6,10 @@ use std::hash::Hash;
 
 use zeroize::Zeroizing;
 
-use crate::{ModuleResult, traits::KeyAlgorithm};
+use crate::{
+    ModuleResult,
+    traits::{KeyAlgorithm, KeyMetadata},
+};
 
 pub trait SymmetricKey: Send + Sync {
     /// The unique identifier of the key (in the KMS)
@@ -20,6 +23,11 @@ pub trait SymmetricKey: Send + Sync {
 
     /// Return raw bytes
     fn raw_bytes(&self) -> ModuleResult<Zeroizing<Vec<u8>>>;
+
+    /// The metadata of the key held by the backend
+    fn metadata(&self) -> KeyMetadata {
+        KeyMetadata::default()
+    }
 }
 
 impl std::fmt::Debug for dyn SymmetricKey {
@@ -1,28 +1,56 @@
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
 
-use cosmian_cli::reexport::cosmian_kms_cli::reexport::{
-    cosmian_kmip::kmip_2_1::{
-        kmip_attributes::Attributes, kmip_objects::ObjectType, kmip_types::KeyFormatType,
+use cosmian_cli::{
+    config::Pkcs11Config,
+    reexport::cosmian_kms_cli::reexport::{
+        cosmian_kmip::kmip_2_1::{
+            kmip_attributes::Attributes,
+            kmip_objects::ObjectType,
+            kmip_types::{KeyFormatType, LinkType},
+        },
+        cosmian_kms_client::{KmsClient, KmsClientConfig},
     },
-    cosmian_kms_client::KmsClient,
 };
 use cosmian_logger::{debug, trace, warn};
 use cosmian_pkcs11_module::{
     ModuleError, ModuleResult,
-    core::object::Object,
+    core::{
+        attribute::{AttributeType, Attributes as Template},
+        object::Object,
+    },
     traits::{
-        Backend, Certificate, DataObject, DecryptContext, EncryptContext, KeyAlgorithm, PrivateKey,
-        PublicKey, SearchOptions, SymmetricKey, Version,
+        Backend, Certificate, DataObject, DecryptContext, DigestType, EncryptContext, KeyAlgorithm,
+        KeyDerivationAlgorithm, KeyMetadata, KeyWrappingAlgorithm, PrivateKey, PublicKey,
+        SearchOptions, SearchTemplate, SignatureAlgorithm, SymmetricKey, Version,
     },
 };
+use openssl::pkey::PKey;
+use pkcs11_sys::{
+    CK_INVALID_HANDLE, CK_MECHANISM_INFO, CK_MECHANISM_TYPE, CK_OBJECT_CLASS, CK_SLOT_ID,
+    CKM_ECDH1_DERIVE, CKO_CERTIFICATE, CKO_DATA, CKO_PRIVATE_KEY, CKO_PUBLIC_KEY,
+};
+#[cfg(not(feature = "non-fips"))]
+use pkcs11_sys::{CKF_SIGN, CKF_VERIFY, CKM_RSA_PKCS};
+use tokio::runtime::Runtime;
+use x509_cert::{Certificate as X509Certificate, der::Decode};
 use zeroize::Zeroizing;
 
 use crate::{
+    error::{Pkcs11Error, result::Pkcs11Result},
     kms_object::{
-        get_kms_object, get_kms_object_attributes, get_kms_objects, key_algorithm_from_attributes,
-        kms_decrypt, kms_destroy_object, kms_encrypt, kms_import_object, kms_import_symmetric_key,
-        kms_revoke_object, locate_kms_objects,
+        get_kms_object_async, get_kms_object_attributes_async, get_kms_objects_attributes_async,
+        get_located_objects_attributes_async, id_tag, key_algorithm_from_attributes,
+        kmip_attributes_from_template, kms_create_key_pair_async, kms_decrypt_async,
+        kms_decrypt_multipart_async, kms_derive_key_async, kms_destroy_object_async,
+        kms_encrypt_async, kms_encrypt_multipart_async, kms_import_certificate_async,
+        kms_import_object_async, kms_import_private_key_async, kms_import_public_key_async,
+        kms_import_symmetric_key_async, kms_import_symmetric_key_bytes_async,
+        kms_revoke_object_async, kms_set_attributes_async, kms_set_link_async, kms_sign_async,
+        kms_unwrap_key_async, kms_verify_async, kms_wrap_key_async, locate_kms_objects_async,
+        locate_kms_objects_by_template_async, metadata_from_attributes,
+        template_matches_attributes,
     },
+    pin::{check_pkcs12_password, decrypt_with_pin},
     pkcs11_certificate::Pkcs11Certificate,
     pkcs11_data_object::Pkcs11DataObject,
     pkcs11_error,
@@ -34,13 +62,147 @@ use crate::{
 pub(crate) const COSMIAN_PKCS11_DISK_ENCRYPTION_TAG: &str = "disk-encryption";
 
 pub(crate) struct CliBackend {
-    kms_rest_client: KmsClient,
+    /// The slot the backend is bound to, held by the objects it returns
+    slot_id: CK_SLOT_ID,
+    /// The connection to the KMS. When the token requires a login, it is only
+    /// opened by `login`, with the credentials unlocked by the PIN.
+    kms_connection: RwLock<Option<KmsConnection>>,
+    /// The configuration of the KMS client, without the credentials protected by the PIN
+    kms_config: KmsClientConfig,
+    /// The runtime driving the KMS requests. It lives as long as the backend
+    /// so that the connections of the KMS client are reused across calls.
+    runtime: Runtime,
+    /// The token identity and the tags selecting the objects it exposes
+    config: Pkcs11Config,
+}
+
+/// The KMS client and the configuration it is built from
+struct KmsConnection {
+    config: KmsClientConfig,
+    /// Dropped when a request fails at the transport level:
+    /// the next request builds it again from `config`
+    client: Option<Arc<KmsClient>>,
+}
+
+impl KmsConnection {
+    fn open(config: KmsClientConfig) -> ModuleResult<Self> {
+        let client = Arc::new(build_kms_client(config.clone())?);
+        Ok(Self {
+            config,
+            client: Some(client),
+        })
+    }
+}
+
+fn build_kms_client(kms_config: KmsClientConfig) -> ModuleResult<KmsClient> {
+    KmsClient::new_with_config(kms_config).map_err(|e| ModuleError::DeviceError(e.to_string()))
+}
+
+/// Pad with spaces, or truncate, a token information field
+fn token_field<const N: usize>(value: &str) -> [u8; N] {
+    let mut field = [b' '; N];
+    for (dst, src) in field.iter_mut().zip(value.bytes()) {
+        *dst = src;
+    }
+    field
 }
 
 impl CliBackend {
-    /// Instantiate a new `CliBackend` using the
-    pub(crate) const fn instantiate(kms_rest_client: KmsClient) -> Self {
-        Self { kms_rest_client }
+    /// Instantiate a new `CliBackend` for the slot `slot_id` using the given KMS client
+    /// configuration and the `pkcs11_config` section of the configuration.
+    ///
+    /// Unless the token requires a login, the KMS client is built right away:
+    /// a KMS client which cannot be built is reported as `CKR_DEVICE_ERROR`.
+    pub(crate) fn instantiate(
+        slot_id: CK_SLOT_ID,
+        kms_config: KmsClientConfig,
+        config: Pkcs11Config,
+    ) -> ModuleResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("cosmian-pkcs11")
+            .build()
+            .map_err(Pkcs11Error::from)?;
+        let kms_connection = if config.login_required {
+            None
+        } else {
+            Some(KmsConnection::open(kms_config.clone())?)
+        };
+        Ok(Self {
+            slot_id,
+            kms_connection: RwLock::new(kms_connection),
+            kms_config,
+            runtime,
+            config,
+        })
+    }
+
+    /// The KMS client, only available once logged in when the token requires a login.
+    /// The client dropped by a transport failure is built again.
+    pub(crate) fn kms_client(&self) -> ModuleResult<Arc<KmsClient>> {
+        if let Some(client) = self
+            .kms_connection
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .ok_or(ModuleError::UserNotLoggedIn)?
+            .client
+            .clone()
+        {
+            return Ok(client);
+        }
+        let mut kms_connection = self
+            .kms_connection
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let kms_connection = kms_connection
+            .as_mut()
+            .ok_or(ModuleError::UserNotLoggedIn)?;
+        // another request may have reconnected meanwhile
+        if let Some(client) = &kms_connection.client {
+            return Ok(client.clone());
+        }
+        debug!("kms_client: reconnecting to the KMS");
+        let client = Arc::new(build_kms_client(kms_connection.config.clone())?);
+        kms_connection.client = Some(client.clone());
+        Ok(client)
+    }
+
+    /// The tag selecting the KMS objects of this class exposed by the token:
+    /// the tag of the class in the configuration, then the configuration tag,
+    /// then the `COSMIAN_PKCS11_DISK_ENCRYPTION_TAG` environment variable.
+    pub(crate) fn object_tag(&self, class: CK_OBJECT_CLASS) -> String {
+        let class_tag = match class {
+            CKO_CERTIFICATE => self.config.certificate_tag.as_deref(),
+            CKO_PRIVATE_KEY => self.config.private_key_tag.as_deref(),
+            CKO_PUBLIC_KEY => self.config.public_key_tag.as_deref(),
+            CKO_DATA => self.config.data_object_tag.as_deref(),
+            _ => None,
+        };
+        class_tag
+            .or(self.config.tag.as_deref())
+            .map(ToOwned::to_owned)
+            .or_else(|| std::env::var("COSMIAN_PKCS11_DISK_ENCRYPTION_TAG").ok())
+            .unwrap_or_else(|| COSMIAN_PKCS11_DISK_ENCRYPTION_TAG.to_owned())
+    }
+
+    /// Run a KMS request to completion on the backend runtime.
+    /// A request which fails at the transport level drops the KMS client,
+    /// so that the next request reconnects to the KMS.
+    fn block_on<T>(&self, future: impl Future<Output = Pkcs11Result<T>>) -> Pkcs11Result<T> {
+        let result = self.runtime.block_on(future);
+        if let Err(Pkcs11Error::KmsUnreachable(e)) = &result {
+            warn!("block_on: the KMS is unreachable, reconnecting on the next request: {e}");
+            if let Some(kms_connection) = self
+                .kms_connection
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_mut()
+            {
+                kms_connection.client = None;
+            }
+        }
+        result
     }
 
     fn get_key_size_and_algorithm(attributes: &Attributes) -> ModuleResult<(usize, KeyAlgorithm)> {
@@ -53,7 +215,12 @@ impl CliBackend {
 
     /// Helper function to create a private key from an ID
     fn create_private_key_from_id(&self, id: &str) -> Option<Arc<dyn PrivateKey>> {
-        let attributes = get_kms_object_attributes(&self.kms_rest_client, id).ok()?;
+        let attributes = self
+            .block_on(get_kms_object_attributes_async(
+                &*self.kms_client().ok()?,
+                id,
+            ))
+            .ok()?;
         let (key_size, algorithm) = match Self::get_key_size_and_algorithm(&attributes) {
             Ok(result) => result,
             Err(e) => {
@@ -65,15 +232,22 @@ impl CliBackend {
             }
         };
         Some(Arc::new(Pkcs11PrivateKey::new(
+            self.slot_id,
             id.to_owned(),
             algorithm,
             key_size,
+            metadata_from_attributes(&attributes),
         )))
     }
 
     /// Helper function to create a symmetric key from an ID
     fn create_symmetric_key_from_id(&self, id: &str) -> Option<Arc<dyn SymmetricKey>> {
-        let attributes = get_kms_object_attributes(&self.kms_rest_client, id).ok()?;
+        let attributes = self
+            .block_on(get_kms_object_attributes_async(
+                &*self.kms_client().ok()?,
+                id,
+            ))
+            .ok()?;
         let (key_size, algorithm) = match Self::get_key_size_and_algorithm(&attributes) {
             Ok(result) => result,
             Err(e) => {
@@ -85,22 +259,24 @@ impl CliBackend {
             }
         };
         Some(Arc::new(Pkcs11SymmetricKey::new(
+            self.slot_id,
             id.to_owned(),
             algorithm,
             key_size,
+            metadata_from_attributes(&attributes),
         )))
     }
 
     /// Helper function to create an object from ID and attributes
-    fn create_object_from_attributes(id: &str, attributes: &Attributes) -> Option<Object> {
+    fn create_object_from_attributes(&self, id: &str, attributes: &Attributes) -> Option<Object> {
         let object_type = attributes.object_type?;
         match object_type {
-            ObjectType::SymmetricKey => Self::create_symmetric_key_object(id, attributes),
-            ObjectType::PrivateKey => Self::create_private_key_object(id, attributes),
-            ObjectType::PublicKey => Self::create_public_key_object(id, attributes),
-            ObjectType::SecretData => Some(Object::DataObject(Arc::new(Pkcs11DataObject::new(
-                id.to_owned(),
-            )))),
+            ObjectType::SymmetricKey => self.create_symmetric_key_object(id, attributes),
+            ObjectType::PrivateKey => self.create_private_key_object(id, attributes),
+            ObjectType::PublicKey => self.create_public_key_object(id, attributes),
+            ObjectType::SecretData => Some(Object::DataObject(Arc::new(
+                Pkcs11DataObject::from_attributes(self.slot_id, id.to_owned(), attributes),
+            ))),
             other => {
                 warn!(
                     "create_object_from_attributes: unsupported object type: {other}, skipping \
@@ -111,8 +287,18 @@ impl CliBackend {
         }
     }
 
+    /// Helper function to create an object, certificates included, from ID and attributes
+    fn object_from_attributes(&self, id: String, attributes: &Attributes) -> Option<Object> {
+        if attributes.object_type == Some(ObjectType::Certificate) {
+            return Some(Object::Certificate(Arc::new(
+                Pkcs11Certificate::from_attributes(self.slot_id, id, attributes),
+            )));
+        }
+        self.create_object_from_attributes(&id, attributes)
+    }
+
     /// Helper to create symmetric key object
-    fn create_symmetric_key_object(id: &str, attributes: &Attributes) -> Option<Object> {
+    fn create_symmetric_key_object(&self, id: &str, attributes: &Attributes) -> Option<Object> {
         let (key_size, key_algorithm) = match Self::get_key_size_and_algorithm(attributes) {
             Ok(result) => result,
             Err(e) => {
@@ -124,14 +310,16 @@ impl CliBackend {
             }
         };
         Some(Object::SymmetricKey(Arc::new(Pkcs11SymmetricKey::new(
+            self.slot_id,
             id.to_owned(),
             key_algorithm,
             key_size,
+            metadata_from_attributes(attributes),
         ))))
     }
 
     /// Helper to create private key object
-    fn create_private_key_object(id: &str, attributes: &Attributes) -> Option<Object> {
+    fn create_private_key_object(&self, id: &str, attributes: &Attributes) -> Option<Object> {
         let (key_size, key_algorithm) = match Self::get_key_size_and_algorithm(attributes) {
             Ok(result) => result,
             Err(e) => {
@@ -143,14 +331,16 @@ impl CliBackend {
             }
         };
         Some(Object::PrivateKey(Arc::new(Pkcs11PrivateKey::new(
+            self.slot_id,
             id.to_owned(),
             key_algorithm,
             key_size,
+            metadata_from_attributes(attributes),
         ))))
     }
 
     /// Helper to create public key object
-    fn create_public_key_object(id: &str, attributes: &Attributes) -> Option<Object> {
+    fn create_public_key_object(&self, id: &str, attributes: &Attributes) -> Option<Object> {
         let (_key_size, key_algorithm) = match Self::get_key_size_and_algorithm(attributes) {
             Ok(result) => result,
             Err(e) => {
@@ -162,32 +352,39 @@ impl CliBackend {
             }
         };
         Some(Object::PublicKey(Arc::new(Pkcs11PublicKey::new(
+            self.slot_id,
             id.to_owned(),
             key_algorithm,
+            metadata_from_attributes(attributes),
         ))))
     }
 }
 
 impl Backend for CliBackend {
     fn token_label(&self) -> [u8; 32] {
-        *b"Cosmian-KMS                     "
+        token_field(self.config.token_label.as_deref().unwrap_or("Cosmian-KMS"))
     }
 
     fn token_manufacturer_id(&self) -> [u8; 32] {
-        *b"Cosmian                         "
+        token_field(
+            self.config
+                .token_manufacturer
+                .as_deref()
+                .unwrap_or("Cosmian"),
+        )
     }
 
     fn token_model(&self) -> [u8; 16] {
-        *b"software        "
+        token_field(self.config.token_model.as_deref().unwrap_or("software"))
     }
 
-    #[expect(clippy::indexing_slicing)]
     fn token_serial_number(&self) -> [u8; 16] {
-        let version = env!("CARGO_PKG_VERSION").as_bytes();
-        let len = version.len().min(16);
-        let mut sn = [0x20; 16];
-        sn[0..len].copy_from_slice(&version[..len]);
-        sn
+        token_field(
+            self.config
+                .token_serial
+                .as_deref()
+                .unwrap_or(env!("CARGO_PKG_VERSION")),
+        )
     }
 
     fn library_description(&self) -> [u8; 32] {
@@ -202,27 +399,149 @@ impl Backend for CliBackend {
         Version { major, minor }
     }
 
-    fn find_certificate(
-        &self,
-        _query: SearchOptions,
-    ) -> ModuleResult<Option<Arc<dyn Certificate>>> {
-        trace!("find_certificate");
-        Ok(None)
+    fn login_required(&self) -> bool {
+        self.config.login_required
+    }
+
+    /// Build the KMS client with the credentials unlocked by the PIN:
+    /// the PIN decrypts the protected access token and PKCS#12 password of the
+    /// configuration, or is itself the password of the client PKCS#12.
+    fn login(&self, pin: &[u8]) -> ModuleResult<()> {
+        let mut kms_config = self.kms_config.clone();
+        let http_config = &mut kms_config.http_config;
+        let protected_access_token = self.config.pin_protected_access_token.as_deref();
+        let protected_pkcs12_password = self.config.pin_protected_pkcs12_password.as_deref();
+        if let Some(access_token) = protected_access_token {
+            http_config.access_token = Some(decrypt_with_pin(access_token, pin)?);
+        }
+        if let Some(password) = protected_pkcs12_password {
+            http_config.ssl_client_pkcs12_password = Some(decrypt_with_pin(password, pin)?);
+        }
+        if protected_access_token.is_none() && protected_pkcs12_password.is_none() {
+            let password = String::from_utf8(pin.to_vec()).map_err(|e| {
+                debug!("login: the PIN is not valid UTF-8: {e}");
+                ModuleError::PinIncorrect
+            })?;
+            check_pkcs12_password(http_config.ssl_client_pkcs12_path.as_deref(), &password)?;
+            http_config.ssl_client_pkcs12_password = Some(password);
+        }
+        let kms_connection = KmsConnection::open(kms_config)?;
+        *self
+            .kms_connection
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(kms_connection);
+        Ok(())
+    }
+
+    fn logout(&self) {
+        if self.config.login_required {
+            *self
+                .kms_connection
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) = None;
+        }
+    }
+
+    fn extractable_by_default(&self) -> bool {
+        self.config.extractable_keys
+    }
+
+    fn find_certificate(&self, query: SearchOptions) -> ModuleResult<Option<Arc<dyn Certificate>>> {
+        trace!("find_certificate: {:?}", query);
+        let id = match query {
+            SearchOptions::Id(id) => id,
+            SearchOptions::All => {
+                return Err(ModuleError::Backend(Box::new(pkcs11_error!(
+                    "find_certificate: find must be made using an ID"
+                ))));
+            }
+        };
+        let kms_client = self.kms_client()?;
+        // The ID is either the one of the certificate, the one of the private key
+        // linked to the certificate or the CKA_ID of the certificate;
+        // the KMS identifiers are UTF-8 strings, a binary CKA_ID is only a CKA_ID
+        let remote_id = String::from_utf8(id.clone()).ok();
+        let attributes = match &remote_id {
+            Some(remote_id) => {
+                match self.block_on(get_kms_object_attributes_async(&kms_client, remote_id)) {
+                    Ok(attributes) => Some((remote_id.clone(), attributes)),
+                    Err(Pkcs11Error::ObjectNotFound(e)) => {
+                        debug!("find_certificate: {e}");
+                        None
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            None => None,
+        };
+        let attributes = match attributes {
+            Some(attributes) => Some(attributes),
+            None => self
+                .block_on(get_kms_objects_attributes_async(
+                    &kms_client,
+                    &[id_tag(&id), "_cert".to_owned()],
+                ))?
+                .into_iter()
+                .next(),
+        };
+        let certificate = match attributes {
+            Some((certificate_id, attributes))
+                if attributes.object_type == Some(ObjectType::Certificate) =>
+            {
+                Some((certificate_id, attributes))
+            }
+            Some((_, attributes)) if attributes.object_type == Some(ObjectType::PrivateKey) => {
+                match attributes.get_link(LinkType::CertificateLink) {
+                    Some(certificate_id) => {
+                        let certificate_id = certificate_id.to_string();
+                        let attributes = self.block_on(get_kms_object_attributes_async(
+                            &kms_client,
+                            &certificate_id,
+                        ))?;
+                        Some((certificate_id, attributes))
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some((certificate_id, attributes)) = certificate {
+            let mut kms_object = self.block_on(get_kms_object_async(
+                &kms_client,
+                &certificate_id,
+                KeyFormatType::X509,
+            ))?;
+            // the export does not return the links of the certificate
+            kms_object.attributes = attributes;
+            return Ok(Some(Arc::new(Pkcs11Certificate::try_from_kms_object(
+                self.slot_id,
+                kms_object,
+            )?)));
+        }
+        let Some(remote_id) = remote_id else {
+            return Ok(None);
+        };
+        Ok(self
+            .find_all_certificates()?
+            .into_iter()
+            .find(|certificate| certificate.private_key_id() == remote_id))
     }
 
     fn find_all_certificates(&self) -> ModuleResult<Vec<Arc<dyn Certificate>>> {
         trace!("find_all_certificates");
-        let disk_encryption_tag = std::env::var("COSMIAN_PKCS11_DISK_ENCRYPTION_TAG")
-            .unwrap_or_else(|_| COSMIAN_PKCS11_DISK_ENCRYPTION_TAG.to_owned());
-        let kms_objects = get_kms_objects(
-            &self.kms_rest_client,
+        let disk_encryption_tag = self.object_tag(CKO_CERTIFICATE);
+        let kms_objects = self.block_on(get_kms_objects_attributes_async(
+            &*self.kms_client()?,
             &[disk_encryption_tag, "_cert".to_owned()],
-            Some(KeyFormatType::X509),
-        )?;
+        ))?;
         let mut result = Vec::with_capacity(kms_objects.len());
-        for dao in kms_objects {
-            let data_object: Arc<dyn Certificate> = Arc::new(Pkcs11Certificate::try_from(dao)?);
-            result.push(data_object);
+        for (id, attributes) in kms_objects {
+            let certificate: Arc<dyn Certificate> = Arc::new(Pkcs11Certificate::from_attributes(
+                self.slot_id,
+                id,
+                &attributes,
+            ));
+            result.push(certificate);
         }
         Ok(result)
     }
@@ -238,19 +557,25 @@ impl Backend for CliBackend {
             }
         };
         let id = String::from_utf8(id)?;
-        let kms_object = get_kms_object(&self.kms_rest_client, &id, KeyFormatType::PKCS8)?;
-        Ok(Arc::new(Pkcs11PrivateKey::try_from_kms_object(kms_object)?))
+        let kms_object = self.block_on(get_kms_object_async(
+            &*self.kms_client()?,
+            &id,
+            KeyFormatType::PKCS8,
+        ))?;
+        Ok(Arc::new(Pkcs11PrivateKey::try_from_kms_object(
+            self.slot_id,
+            kms_object,
+        )?))
     }
 
     fn find_all_private_keys(&self) -> ModuleResult<Vec<Arc<dyn PrivateKey>>> {
         trace!("find_all_private_keys");
-        let disk_encryption_tag = std::env::var("COSMIAN_PKCS11_DISK_ENCRYPTION_TAG")
-            .unwrap_or_else(|_| COSMIAN_PKCS11_DISK_ENCRYPTION_TAG.to_owned());
+        let disk_encryption_tag = self.object_tag(CKO_PRIVATE_KEY);
         let mut private_keys = vec![];
-        let ids = locate_kms_objects(
-            &self.kms_rest_client,
+        let ids = self.block_on(locate_kms_objects_async(
+            &*self.kms_client()?,
             &[disk_encryption_tag, "_sk".to_owned()],
-        )?;
+        ))?;
         for id in ids {
             if let Some(private_key) = self.create_private_key_from_id(&id) {
                 private_keys.push(private_key);
@@ -262,30 +587,100 @@ impl Backend for CliBackend {
 
     fn find_public_key(&self, query: SearchOptions) -> ModuleResult<Arc<dyn PublicKey>> {
         trace!("find_public_key: {:?}", query);
-        Err(ModuleError::Backend(Box::new(pkcs11_error!(
-            "find_public_key: not implemented"
-        ))))
+        let id = match query {
+            SearchOptions::Id(id) => id,
+            SearchOptions::All => {
+                return Err(ModuleError::Backend(Box::new(pkcs11_error!(
+                    "find_public_key: find must be made using an ID"
+                ))));
+            }
+        };
+        let kms_client = self.kms_client()?;
+        // The ID is either the one of the public key, the one of the matching
+        // private key or the CKA_ID of the key pair
+        let attributes = match String::from_utf8(id.clone()) {
+            Ok(remote_id) => {
+                match self.block_on(get_kms_object_attributes_async(&kms_client, &remote_id)) {
+                    Ok(attributes) => Some((remote_id, attributes)),
+                    Err(Pkcs11Error::ObjectNotFound(e)) => {
+                        debug!("find_public_key: {e}");
+                        None
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            // the KMS identifiers are UTF-8 strings: a binary id is a CKA_ID
+            Err(_) => None,
+        };
+        let public_key_id = match attributes {
+            Some((remote_id, attributes))
+                if attributes.object_type == Some(ObjectType::PrivateKey) =>
+            {
+                attributes
+                    .get_link(LinkType::PublicKeyLink)
+                    .ok_or_else(|| {
+                        ModuleError::Backend(Box::new(pkcs11_error!(
+                            "find_public_key: no public key linked to the private key {remote_id}"
+                        )))
+                    })?
+                    .to_string()
+            }
+            Some((remote_id, _)) => remote_id,
+            None => self
+                .block_on(locate_kms_objects_async(
+                    &kms_client,
+                    &[id_tag(&id), "_pk".to_owned()],
+                ))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    ModuleError::ObjectNotFound(format!(
+                        "find_public_key: no public key for {id:?}"
+                    ))
+                })?,
+        };
+        let kms_object = self.block_on(get_kms_object_async(
+            &kms_client,
+            &public_key_id,
+            KeyFormatType::PKCS8,
+        ))?;
+        Ok(Arc::new(Pkcs11PublicKey::try_from_kms_object(
+            self.slot_id,
+            kms_object,
+        )?))
     }
 
     fn find_all_public_keys(&self) -> ModuleResult<Vec<Arc<dyn PublicKey>>> {
-        warn!("find_all_public_keys not implemented");
-        Ok(vec![])
+        trace!("find_all_public_keys");
+        let disk_encryption_tag = self.object_tag(CKO_PUBLIC_KEY);
+        let kms_objects = self.block_on(get_kms_objects_attributes_async(
+            &*self.kms_client()?,
+            &[disk_encryption_tag, "_pk".to_owned()],
+        ))?;
+        let mut result = Vec::with_capacity(kms_objects.len());
+        for (id, attributes) in kms_objects {
+            if let Some(Object::PublicKey(public_key)) =
+                self.create_public_key_object(&id, &attributes)
+            {
+                result.push(public_key);
+            }
+        }
+        Ok(result)
     }
 
     fn find_all_data_objects(&self) -> ModuleResult<Vec<Arc<dyn DataObject>>> {
         trace!("find_all_data_objects: entering");
-        let disk_encryption_tag = std::env::var("COSMIAN_PKCS11_DISK_ENCRYPTION_TAG")
-            .unwrap_or_else(|_| COSMIAN_PKCS11_DISK_ENCRYPTION_TAG.to_owned());
-        let kms_objects = get_kms_objects(
-            &self.kms_rest_client,
+        let disk_encryption_tag = self.object_tag(CKO_DATA);
+        let kms_ids = self.block_on(locate_kms_objects_async(
+            &*self.kms_client()?,
             &[disk_encryption_tag, "_sd".to_owned()],
-            Some(KeyFormatType::Raw),
-        )?;
-        trace!("find_all_data_objects: found {} objects", kms_objects.len());
+        ))?;
+        trace!("find_all_data_objects: found {} objects", kms_ids.len());
 
-        let mut result = Vec::with_capacity(kms_objects.len());
-        for dao in kms_objects {
-            let data_object: Arc<dyn DataObject> = Arc::new(Pkcs11DataObject::try_from(dao)?);
+        let mut result = Vec::with_capacity(kms_ids.len());
+        for id in kms_ids {
+            let data_object: Arc<dyn DataObject> =
+                Arc::new(Pkcs11DataObject::new(self.slot_id, id));
             result.push(data_object);
         }
         Ok(result)
@@ -302,19 +697,23 @@ impl Backend for CliBackend {
             }
         };
         let id = String::from_utf8(id)?;
-        let kms_object = get_kms_object(
-            &self.kms_rest_client,
+        let kms_object = self.block_on(get_kms_object_async(
+            &*self.kms_client()?,
             &id,
             KeyFormatType::TransparentSymmetricKey,
-        )?;
+        ))?;
         Ok(Arc::new(Pkcs11SymmetricKey::try_from_kms_object(
+            self.slot_id,
             kms_object,
         )?))
     }
 
     fn find_all_symmetric_keys(&self) -> ModuleResult<Vec<Arc<dyn SymmetricKey>>> {
         trace!("find_all_symmetric_keys");
-        let kms_ids = locate_kms_objects(&self.kms_rest_client, &["_kk".to_owned()])?;
+        let kms_ids = self.block_on(locate_kms_objects_async(
+            &*self.kms_client()?,
+            &["_kk".to_owned()],
+        ))?;
         let mut symmetric_keys = Vec::with_capacity(kms_ids.len());
 
         for id in kms_ids {
@@ -327,23 +726,68 @@ impl Backend for CliBackend {
     }
 
     fn find_data_object(&self, query: SearchOptions) -> ModuleResult<Option<Arc<dyn DataObject>>> {
-        warn!("find_data_object: {:?}, not implemented", query);
-        Ok(None)
+        trace!("find_data_object: {:?}", query);
+        let id = match query {
+            SearchOptions::Id(id) => id,
+            SearchOptions::All => {
+                return Err(ModuleError::Backend(Box::new(pkcs11_error!(
+                    "find_data_object: find must be made using an ID"
+                ))));
+            }
+        };
+        let id = String::from_utf8(id)?;
+        let kms_object = self.block_on(get_kms_object_async(
+            &*self.kms_client()?,
+            &id,
+            KeyFormatType::Raw,
+        ))?;
+        Ok(Some(Arc::new(Pkcs11DataObject::try_from_kms_object(
+            self.slot_id,
+            kms_object,
+        )?)))
     }
 
-    fn find_all_objects(&self) -> ModuleResult<Vec<Arc<Object>>> {
-        trace!("find_all_objects: entering");
-        let kms_ids = locate_kms_objects(&self.kms_rest_client, &[])?;
-        let mut objects = Vec::with_capacity(kms_ids.len());
-        for id in kms_ids {
-            if let Ok(attributes) = get_kms_object_attributes(&self.kms_rest_client, &id) {
-                if let Some(object) = Self::create_object_from_attributes(&id, &attributes) {
-                    objects.push(Arc::new(object));
-                }
-            }
-        }
+    fn find_objects(&self, template: &SearchTemplate) -> ModuleResult<Vec<Arc<Object>>> {
+        trace!("find_objects: {template:?}");
+        let ids = if let Some(id) = &template.id {
+            // The CKA_ID is the one held in the tags of the object, or its identifier:
+            // the KMS identifiers are UTF-8 strings
+            let mut ids = self.block_on(locate_kms_objects_async(
+                &*self.kms_client()?,
+                &[id_tag(id)],
+            ))?;
+            ids.extend(String::from_utf8(id.clone()).ok());
+            ids
+        } else {
+            // Without a label, the certificates, key pairs and data objects are
+            // restricted to the ones tagged for this token
+            let tags =
+                match template.class {
+                    Some(
+                        class @ (CKO_CERTIFICATE | CKO_PUBLIC_KEY | CKO_PRIVATE_KEY | CKO_DATA),
+                    ) if template.label.is_none() => vec![self.object_tag(class)],
+                    _ => vec![],
+                };
+            // The objects created through the token carry their label as a tag
+            self.block_on(locate_kms_objects_by_template_async(
+                &*self.kms_client()?,
+                template,
+                &tags,
+            ))?
+        };
+
+        let objects = self
+            .block_on(get_located_objects_attributes_async(
+                &*self.kms_client()?,
+                ids,
+            ))?
+            .into_iter()
+            .filter(|(id, attributes)| template_matches_attributes(template, id, attributes))
+            .filter_map(|(id, attributes)| self.object_from_attributes(id, &attributes))
+            .map(Arc::new)
+            .collect::<Vec<_>>();
 
-        trace!("find_all_objects: found {} keys", objects.len());
+        trace!("find_objects: found {} objects", objects.len());
         Ok(objects)
     }
 
@@ -352,45 +796,427 @@ impl Backend for CliBackend {
         algorithm: KeyAlgorithm,
         key_length: usize,
         sensitive: bool,
+        extractable: bool,
         label: Option<&str>,
     ) -> ModuleResult<Arc<dyn SymmetricKey>> {
         trace!("generate_key: {algorithm:?}-{key_length}, {label:?}");
 
-        if algorithm != KeyAlgorithm::Aes256 {
+        if !algorithm.is_aes() && algorithm != KeyAlgorithm::GenericSecret {
             return Err(ModuleError::Backend(Box::new(pkcs11_error!(
-                "generate_key: only support AES-256 algorithm"
+                "generate_key: only support AES and generic secret algorithms"
             ))));
         }
 
-        let kms_object = kms_import_symmetric_key(
-            &self.kms_rest_client,
+        let kms_object = self.block_on(kms_import_symmetric_key_async(
+            &*self.kms_client()?,
             algorithm,
             key_length,
             sensitive,
+            extractable,
             label,
-        )?;
+        ))?;
         Ok(Arc::new(Pkcs11SymmetricKey::try_from_kms_object(
+            self.slot_id,
             kms_object,
         )?))
     }
 
+    fn generate_key_pair(
+        &self,
+        algorithm: KeyAlgorithm,
+        key_length: Option<usize>,
+        sensitive: bool,
+        extractable: bool,
+        id: Option<&[u8]>,
+        label: Option<&str>,
+    ) -> ModuleResult<(Arc<dyn PublicKey>, Arc<dyn PrivateKey>)> {
+        trace!("generate_key_pair: {algorithm:?}-{key_length:?}, id: {id:?}, label: {label:?}");
+
+        if !algorithm.is_rsa() && !algorithm.is_ecc() {
+            return Err(ModuleError::Backend(Box::new(pkcs11_error!(
+                "generate_key_pair: unsupported algorithm: {algorithm:?}"
+            ))));
+        }
+
+        // Tag the key pair with the tags of the token so that it is found
+        // by `find_all_private_keys` and `find_all_public_keys`, and with its CKA_ID:
+        // the KMS generates the identifiers of the keys
+        let mut tags = vec![self.object_tag(CKO_PRIVATE_KEY)];
+        let public_key_tag = self.object_tag(CKO_PUBLIC_KEY);
+        if !tags.contains(&public_key_tag) {
+            tags.push(public_key_tag);
+        }
+        if let Some(label) = label {
+            tags.push(label.to_owned());
+        }
+        tags.extend(id.map(id_tag));
+
+        let (private_key_id, public_key_id) = self.block_on(kms_create_key_pair_async(
+            &*self.kms_client()?,
+            algorithm,
+            key_length,
+            sensitive,
+            extractable,
+            label,
+            &tags,
+        ))?;
+        let private_key = self
+            .create_private_key_from_id(&private_key_id)
+            .ok_or_else(|| {
+                ModuleError::Backend(Box::new(pkcs11_error!(
+                    "generate_key_pair: failed to fetch the attributes of private key \
+                     {private_key_id}"
+                )))
+            })?;
+        // The public key is not fetched: its metadata is that of its creation
+        let metadata = KeyMetadata {
+            label: label.map(ToOwned::to_owned),
+            id: id.map(<[u8]>::to_vec),
+            local: Some(true),
+            ..KeyMetadata::default()
+        };
+        let public_key: Arc<dyn PublicKey> = Arc::new(Pkcs11PublicKey::new(
+            self.slot_id,
+            public_key_id,
+            algorithm,
+            metadata,
+        ));
+
+        Ok((public_key, private_key))
+    }
+
     fn create_object(&self, label: &str, data: &[u8]) -> ModuleResult<Arc<dyn DataObject>> {
         trace!("create_object: {label:?}");
-        let kms_object = kms_import_object(&self.kms_rest_client, label, data)?;
-        Ok(Arc::new(Pkcs11DataObject::try_from_kms_object(kms_object)?))
+        let kms_object =
+            self.block_on(kms_import_object_async(&*self.kms_client()?, label, data))?;
+        Ok(Arc::new(Pkcs11DataObject::try_from_kms_object(
+            self.slot_id,
+            kms_object,
+        )?))
+    }
+
+    fn import_symmetric_key(
+        &self,
+        algorithm: KeyAlgorithm,
+        value: Zeroizing<Vec<u8>>,
+        sensitive: bool,
+        extractable: bool,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        trace!("import_symmetric_key: {algorithm:?}, {label:?}");
+        let kms_object = self.block_on(kms_import_symmetric_key_bytes_async(
+            &*self.kms_client()?,
+            algorithm,
+            value,
+            sensitive,
+            extractable,
+            label,
+            false,
+        ))?;
+        Ok(Arc::new(Pkcs11SymmetricKey::try_from_kms_object(
+            self.slot_id,
+            kms_object,
+        )?))
+    }
+
+    fn import_private_key(
+        &self,
+        algorithm: KeyAlgorithm,
+        pkcs8: Zeroizing<Vec<u8>>,
+        sensitive: bool,
+        extractable: bool,
+        id: Option<&[u8]>,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn PrivateKey>> {
+        trace!("import_private_key: {algorithm:?}, id: {id:?}, label: {label:?}");
+        let spki = PKey::private_key_from_pkcs8(&pkcs8)
+            .and_then(|private_key| private_key.public_key_to_der())
+            .map_err(|e| {
+                Pkcs11Error::Conversion(format!(
+                    "import_private_key: cannot derive the public key: {e}"
+                ))
+            })?;
+        // Tag the keys with the tag of the token so that they are found by
+        // `find_all_private_keys` and `find_all_public_keys`, and with their CKA_ID
+        let mut private_key_tags = vec![self.object_tag(CKO_PRIVATE_KEY)];
+        let mut public_key_tags = vec![self.object_tag(CKO_PUBLIC_KEY)];
+        for tag in label
+            .map(ToOwned::to_owned)
+            .into_iter()
+            .chain(id.map(id_tag))
+        {
+            private_key_tags.push(tag.clone());
+            public_key_tags.push(tag);
+        }
+        let kms_client = self.kms_client()?;
+        let private_key_id = self.block_on(kms_import_private_key_async(
+            &kms_client,
+            pkcs8,
+            sensitive,
+            extractable,
+            label,
+            &private_key_tags,
+        ))?;
+        let public_key_id = self.block_on(kms_import_public_key_async(
+            &kms_client,
+            &spki,
+            &private_key_id,
+            label,
+            &public_key_tags,
+        ))?;
+        self.block_on(kms_set_link_async(
+            &kms_client,
+            &private_key_id,
+            LinkType::PublicKeyLink,
+            &public_key_id,
+        ))?;
+        let attributes = self.block_on(get_kms_object_attributes_async(
+            &kms_client,
+            &private_key_id,
+        ))?;
+        let (key_size, algorithm) = Self::get_key_size_and_algorithm(&attributes)?;
+        Ok(Arc::new(Pkcs11PrivateKey::new(
+            self.slot_id,
+            private_key_id,
+            algorithm,
+            key_size,
+            metadata_from_attributes(&attributes),
+        )))
+    }
+
+    fn import_certificate(
+        &self,
+        der: &[u8],
+        id: Option<&[u8]>,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn Certificate>> {
+        trace!("import_certificate: id: {id:?}, label: {label:?}");
+        X509Certificate::from_der(der).map_err(|e| {
+            debug!("import_certificate: invalid X509 certificate DER bytes: {e:?}");
+            ModuleError::AttributeValueInvalid(AttributeType::Value)
+        })?;
+        // Tag the certificate with the tag of the token so that it is found by `find_all_certificates`
+        // and with its CKA_ID
+        let mut tags = vec![self.object_tag(CKO_CERTIFICATE)];
+        tags.extend(label.map(ToOwned::to_owned));
+        tags.extend(id.map(id_tag));
+        let kms_client = self.kms_client()?;
+        // The private key with the same CKA_ID, held in its tags;
+        // the CKA_ID may also be the identifier of the private key
+        let private_key_id = match id {
+            Some(id) => self
+                .block_on(locate_kms_objects_async(
+                    &kms_client,
+                    &[id_tag(id), "_sk".to_owned()],
+                ))?
+                .into_iter()
+                .next()
+                .or_else(|| {
+                    let remote_id = String::from_utf8(id.to_vec()).ok()?;
+                    self.block_on(get_kms_object_attributes_async(&kms_client, &remote_id))
+                        .ok()
+                        .filter(|attributes| attributes.object_type == Some(ObjectType::PrivateKey))
+                        .map(|_| remote_id)
+                }),
+            None => None,
+        };
+        let kms_object = self.block_on(kms_import_certificate_async(
+            &kms_client,
+            der,
+            private_key_id.as_deref(),
+            label,
+            &tags,
+        ))?;
+        if let Some(private_key_id) = &private_key_id {
+            self.block_on(kms_set_link_async(
+                &kms_client,
+                private_key_id,
+                LinkType::CertificateLink,
+                &kms_object.remote_id,
+            ))?;
+        }
+        Ok(Arc::new(Pkcs11Certificate::try_from_kms_object(
+            self.slot_id,
+            kms_object,
+        )?))
+    }
+
+    fn set_attributes(&self, object: &Object, attributes: &Template) -> ModuleResult<Arc<Object>> {
+        let remote_id = object.remote_id();
+        trace!("set_attributes: {remote_id}, {attributes:?}");
+        let kms_client = self.kms_client()?;
+        let current = self.block_on(get_kms_object_attributes_async(&kms_client, &remote_id))?;
+        let kmip_attributes = kmip_attributes_from_template(&current, attributes)?;
+        self.block_on(kms_set_attributes_async(
+            &kms_client,
+            &remote_id,
+            &current,
+            kmip_attributes,
+        ))?;
+        // Refresh the object from its new attributes
+        let attributes = self.block_on(get_kms_object_attributes_async(&kms_client, &remote_id))?;
+        self.object_from_attributes(remote_id.clone(), &attributes)
+            .map(Arc::new)
+            .ok_or_else(|| {
+                ModuleError::Backend(Box::new(pkcs11_error!(
+                    "set_attributes: unsupported object {remote_id}"
+                )))
+            })
     }
 
     fn revoke_object(&self, remote_id: &str) -> ModuleResult<()> {
-        Ok(kms_revoke_object(&self.kms_rest_client, remote_id)?)
+        Ok(self.block_on(kms_revoke_object_async(&*self.kms_client()?, remote_id))?)
     }
 
     fn destroy_object(&self, remote_id: &str) -> ModuleResult<()> {
-        Ok(kms_destroy_object(&self.kms_rest_client, remote_id)?)
+        Ok(self.block_on(kms_destroy_object_async(&*self.kms_client()?, remote_id))?)
+    }
+
+    fn sign(
+        &self,
+        remote_id: &str,
+        algorithm: &SignatureAlgorithm,
+        data: &[u8],
+    ) -> ModuleResult<Vec<u8>> {
+        debug!("sign: remote_id: {remote_id}, algorithm: {algorithm:?}");
+        self.block_on(kms_sign_async(
+            &*self.kms_client()?,
+            remote_id,
+            algorithm,
+            data,
+        ))
+        .map_err(Into::into)
+    }
+
+    fn verify(
+        &self,
+        remote_id: &str,
+        algorithm: &SignatureAlgorithm,
+        data: &[u8],
+        signature: &[u8],
+    ) -> ModuleResult<()> {
+        debug!("verify: remote_id: {remote_id}, algorithm: {algorithm:?}");
+        if self.block_on(kms_verify_async(
+            &*self.kms_client()?,
+            remote_id,
+            algorithm,
+            data,
+            signature,
+        ))? {
+            Ok(())
+        } else {
+            Err(ModuleError::SignatureInvalid)
+        }
+    }
+
+    fn wrap_key(
+        &self,
+        wrapping_key_id: &str,
+        key_id: &str,
+        algorithm: &KeyWrappingAlgorithm,
+    ) -> ModuleResult<Vec<u8>> {
+        debug!("wrap_key: wrapping_key_id: {wrapping_key_id}, key_id: {key_id}, {algorithm:?}");
+        self.block_on(kms_wrap_key_async(
+            &*self.kms_client()?,
+            wrapping_key_id,
+            key_id,
+            algorithm,
+        ))
+        .map_err(Into::into)
+    }
+
+    fn unwrap_key(
+        &self,
+        unwrapping_key_id: &str,
+        algorithm: &KeyWrappingAlgorithm,
+        wrapped_key: &[u8],
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        debug!("unwrap_key: unwrapping_key_id: {unwrapping_key_id}, {algorithm:?}, {label:?}");
+        let tags = label.map(|l| vec![l.to_owned()]).unwrap_or_default();
+        let key_id = self.block_on(kms_unwrap_key_async(
+            &*self.kms_client()?,
+            unwrapping_key_id,
+            algorithm,
+            wrapped_key,
+            &tags,
+        ))?;
+        self.create_symmetric_key_from_id(&key_id).ok_or_else(|| {
+            ModuleError::Backend(Box::new(pkcs11_error!(
+                "unwrap_key: failed to fetch the attributes of the unwrapped key {key_id}"
+            )))
+        })
+    }
+
+    fn derive_key(
+        &self,
+        base_key_id: &str,
+        algorithm: &KeyDerivationAlgorithm,
+        key_algorithm: KeyAlgorithm,
+        key_length: Option<usize>,
+        sensitive: bool,
+        extractable: bool,
+        label: Option<&str>,
+    ) -> ModuleResult<Arc<dyn SymmetricKey>> {
+        debug!(
+            "derive_key: base_key_id: {base_key_id}, {algorithm:?}, {key_algorithm:?}, \
+             {key_length:?}, {label:?}"
+        );
+        if matches!(algorithm, KeyDerivationAlgorithm::Ecdh { .. }) {
+            // KMIP has no ECDH derivation, and the private key must not leave the KMS
+            return Err(ModuleError::MechanismInvalid(CKM_ECDH1_DERIVE));
+        }
+
+        let tags = label.map(|l| vec![l.to_owned()]).unwrap_or_default();
+        let key_id = self.block_on(kms_derive_key_async(
+            &*self.kms_client()?,
+            base_key_id,
+            algorithm,
+            key_algorithm,
+            key_length.unwrap_or(32),
+            sensitive,
+            extractable,
+            &tags,
+        ))?;
+        self.create_symmetric_key_from_id(&key_id).ok_or_else(|| {
+            ModuleError::Backend(Box::new(pkcs11_error!(
+                "derive_key: failed to fetch the attributes of the derived key {key_id}"
+            )))
+        })
+    }
+
+    /// The KMS cannot derive ECDH shared secrets.
+    /// It only supports RSA PKCS#1 v1.5 encryption in non-FIPS mode.
+    fn mechanism_info(
+        &self,
+        mechanism: CK_MECHANISM_TYPE,
+        info: CK_MECHANISM_INFO,
+    ) -> Option<CK_MECHANISM_INFO> {
+        if mechanism == CKM_ECDH1_DERIVE {
+            return None;
+        }
+        #[cfg(not(feature = "non-fips"))]
+        if mechanism == CKM_RSA_PKCS {
+            return Some(CK_MECHANISM_INFO {
+                flags: info.flags & (CKF_SIGN | CKF_VERIFY),
+                ..info
+            });
+        }
+        Some(info)
+    }
+
+    fn digest_key(&self, key_id: &str, digest_type: &DigestType) -> ModuleResult<Vec<u8>> {
+        debug!("digest_key: key_id: {key_id}, digest_type: {digest_type:?}");
+        // KMIP `Hash` cannot reference a key and no other KMS operation digests one:
+        // the key value would have to leave the KMS, so no key is digested.
+        // The handle of the key is only known to the module.
+        Err(ModuleError::KeyIndigestible(CK_INVALID_HANDLE))
     }
 
     fn encrypt(&self, ctx: &EncryptContext, cleartext: Vec<u8>) -> ModuleResult<Vec<u8>> {
         debug!("encrypt: ctx: {ctx:?}");
-        kms_encrypt(&self.kms_rest_client, ctx, cleartext).map_err(Into::into)
+        self.block_on(kms_encrypt_async(&*self.kms_client()?, ctx, cleartext))
+            .map_err(Into::into)
     }
 
     fn decrypt(
@@ -399,6 +1225,27 @@ impl Backend for CliBackend {
         ciphertext: Vec<u8>,
     ) -> ModuleResult<Zeroizing<Vec<u8>>> {
         debug!("decrypt: decrypt_ctx: {ctx:?}");
-        kms_decrypt(&self.kms_rest_client, ctx, ciphertext).map_err(Into::into)
+        self.block_on(kms_decrypt_async(&*self.kms_client()?, ctx, ciphertext))
+            .map_err(Into::into)
+    }
+
+    fn encrypt_multipart(
+        &self,
+        ctx: &mut EncryptContext,
+        part: Option<Vec<u8>>,
+    ) -> ModuleResult<Vec<u8>> {
+        debug!("encrypt_multipart: ctx: {ctx:?}");
+        self.block_on(kms_encrypt_multipart_async(&*self.kms_client()?, ctx, part))
+            .map_err(Into::into)
+    }
+
+    fn decrypt_multipart(
+        &self,
+        ctx: &mut DecryptContext,
+        part: Option<Vec<u8>>,
+    ) -> ModuleResult<Zeroizing<Vec<u8>>> {
+        debug!("decrypt_multipart: ctx: {ctx:?}");
+        self.block_on(kms_decrypt_multipart_async(&*self.kms_client()?, ctx, part))
+            .map_err(Into::into)
     }
 }
@@ -23,9 +23,15 @@ pub enum Pkcs11Error {
     // When the KMS client returns an error
     #[error("{0}")]
     KmsClientError(String),
+    // When the request fails at the transport level, before the KMS could answer
+    #[error("KMS unreachable: {0}")]
+    KmsUnreachable(String),
     // When a user requests something not supported by the server
     #[error("Not Supported: {0}")]
     NotSupported(String),
+    // When the KMS does not know the requested object
+    #[error("Object not found: {0}")]
+    ObjectNotFound(String),
     // Any errors related to a bad behavior of the server but not related to the user input
     #[error("Server error: {0}")]
     ServerError(String),
@@ -55,8 +61,8 @@ impl From<KmipError> for Pkcs11Error {
             | KmipError::InvalidTag(s)
             | KmipError::Derivation(s)
             | KmipError::ConversionError(s)
-            | KmipError::IndexingSlicing(s)
-            | KmipError::ObjectNotFound(s) => Self::NotSupported(s),
+            | KmipError::IndexingSlicing(s) => Self::NotSupported(s),
+            KmipError::ObjectNotFound(s) => Self::ObjectNotFound(s),
             KmipError::TryFromSliceError(e) => Self::Conversion(e.to_string()),
             KmipError::SerdeJsonError(e) => Self::Conversion(e.to_string()),
             KmipError::Deserialization(_) | KmipError::Serialization(_) => {
@@ -81,7 +87,11 @@ impl From<cosmian_pkcs11_module::ModuleError> for Pkcs11Error {
 
 impl From<Pkcs11Error> for cosmian_pkcs11_module::ModuleError {
     fn from(e: Pkcs11Error) -> Self {
-        Self::Backend(Box::new(e))
+        match e {
+            Pkcs11Error::ObjectNotFound(s) => Self::ObjectNotFound(s),
+            Pkcs11Error::KmsUnreachable(s) => Self::DeviceError(s),
+            e => Self::Backend(Box::new(e)),
+        }
     }
 }
 
@@ -117,10 +127,25 @@ impl From<std::string::FromUtf8Error> for Pkcs11Error {
 
 impl From<KmsClientError> for Pkcs11Error {
     fn from(e: KmsClientError) -> Self {
-        Self::KmsClientError(e.to_string())
+        // The KMS reports unknown objects with the KMIP `Item_Not_Found` reason
+        let message = e.to_string();
+        if message.contains(&ErrorReason::Item_Not_Found.to_string()) {
+            Self::ObjectNotFound(message)
+        } else if failed_at_transport_level(&e) {
+            Self::KmsUnreachable(message)
+        } else {
+            Self::KmsClientError(message)
+        }
     }
 }
 
+/// Whether a KMS request failed at the transport level: an I/O error,
+/// such as a refused or reset connection, caused the failure
+fn failed_at_transport_level(e: &KmsClientError) -> bool {
+    std::iter::successors(std::error::Error::source(e), |source| source.source())
+        .any(|source| source.is::<std::io::Error>())
+}
+
 impl From<std::fmt::Error> for Pkcs11Error {
     fn from(e: std::fmt::Error) -> Self {
         Self::Default(e.to_string())
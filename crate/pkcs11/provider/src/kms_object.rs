@@ -6,23 +6,34 @@ use cosmian_cli::{
         cosmian_kmip::{
             self,
             kmip_0::kmip_types::{
-                BlockCipherMode, CryptographicUsageMask, PaddingMethod, RevocationReason,
-                RevocationReasonCode, SecretDataType,
+                BlockCipherMode, CertificateType, CryptographicUsageMask, EncodingOption,
+                HashingAlgorithm, KeyWrapType, MaskGenerator, PaddingMethod, RevocationReason,
+                RevocationReasonCode, SecretDataType, State, ValidityIndicator, WrappingMethod,
             },
             kmip_2_1::{
-                kmip_attributes::Attributes,
-                kmip_data_structures::{KeyBlock, KeyMaterial, KeyValue},
-                kmip_objects::{Object, ObjectType, SecretData, SymmetricKey},
+                extra::{VENDOR_ID_COSMIAN, tagging::VENDOR_ATTR_TAG},
+                kmip_attributes::{Attribute as KmipAttribute, Attributes},
+                kmip_data_structures::{
+                    EncryptionKeyInformation, KeyBlock, KeyMaterial, KeyValue, KeyWrappingData,
+                },
+                kmip_objects::{
+                    Certificate, Object, ObjectType, PrivateKey, PublicKey, SecretData,
+                    SymmetricKey,
+                },
                 kmip_operations::{
-                    Decrypt, Destroy, Encrypt, GetAttributes, Import, Locate, Revoke,
+                    Decrypt, DeleteAttribute, DeriveKey, Destroy, Encrypt, GetAttributes, Import,
+                    Locate, Mac, Revoke, SetAttribute, Sign, SignatureVerify,
                 },
                 kmip_types::{
-                    CryptographicAlgorithm, CryptographicParameters, KeyFormatType,
-                    RecommendedCurve, UniqueIdentifier,
+                    CryptographicAlgorithm, CryptographicParameters, DerivationMethod,
+                    DerivationParameters, KeyFormatType, Link, LinkType, LinkedObjectIdentifier,
+                    Name, NameType, RecommendedCurve, UniqueIdentifier, VendorAttribute,
+                    VendorAttributeValue,
                 },
+                requests::{create_ec_key_pair_request, create_rsa_key_pair_request},
             },
         },
-        cosmian_kms_client::{ExportObjectParams, KmsClient, batch_export_objects, export_object},
+        cosmian_kms_client::{ExportObjectParams, KmsClient, export_object},
         cosmian_kms_crypto::reexport::cosmian_crypto_core::{
             CsRng,
             reexport::rand_core::{RngCore, SeedableRng},
@@ -30,9 +41,19 @@ use cosmian_cli::{
     },
 };
 use cosmian_logger::{debug, error, trace};
-use cosmian_pkcs11_module::traits::{
-    DecryptContext, EncryptContext, EncryptionAlgorithm, KeyAlgorithm,
+use cosmian_pkcs11_module::{
+    core::attribute::{Attribute, Attributes as Template},
+    traits::{
+        DecryptContext, DigestType, EncryptContext, EncryptionAlgorithm, KeyAlgorithm,
+        KeyDerivationAlgorithm, KeyMetadata, KeyUsage, KeyWrappingAlgorithm, SearchTemplate,
+        SignatureAlgorithm,
+    },
+};
+use pkcs11_sys::{
+    CK_OBJECT_CLASS, CK_ULONG, CKK_AES, CKK_EC, CKK_GENERIC_SECRET, CKK_RSA, CKO_CERTIFICATE,
+    CKO_DATA, CKO_PRIVATE_KEY, CKO_PUBLIC_KEY, CKO_SECRET_KEY,
 };
+use time::{Date, Month, OffsetDateTime, UtcOffset};
 use zeroize::Zeroizing;
 
 use crate::error::{Pkcs11Error, result::Pkcs11Result};
@@ -46,16 +67,17 @@ pub(crate) struct KmsObject {
     pub other_tags: Vec<String>,
 }
 
-pub(crate) fn get_kms_client() -> Pkcs11Result<KmsClient> {
-    let config = ClientConfig::load(None)?;
-    Ok(KmsClient::new_with_config(config.kms_config)?)
-}
+/// The environment variable pointing to a configuration file dedicated
+/// to the PKCS#11 provider, instead of the one of the CLI
+pub(crate) const COSMIAN_PKCS11_CONF_ENV: &str = "COSMIAN_PKCS11_CONF";
 
-pub(crate) fn locate_kms_objects(
-    kms_rest_client: &KmsClient,
-    tags: &[String],
-) -> Pkcs11Result<Vec<String>> {
-    tokio::runtime::Runtime::new()?.block_on(locate_kms_objects_async(kms_rest_client, tags))
+/// Load the client configuration, from the file pointed to by
+/// `COSMIAN_PKCS11_CONF` when it is set
+pub(crate) fn load_client_config() -> Pkcs11Result<ClientConfig> {
+    let conf_path = std::env::var(COSMIAN_PKCS11_CONF_ENV)
+        .ok()
+        .map(std::path::PathBuf::from);
+    Ok(ClientConfig::load(conf_path)?)
 }
 
 pub(crate) async fn locate_kms_objects_async(
@@ -65,69 +87,40 @@ pub(crate) async fn locate_kms_objects_async(
     locate_objects(kms_rest_client, tags).await
 }
 
-pub(crate) fn get_kms_objects(
+/// Locate the KMS objects having all the given tags and fetch their attributes.
+/// The key material of the objects is not exported.
+pub(crate) async fn get_kms_objects_attributes_async(
     kms_rest_client: &KmsClient,
     tags: &[String],
-    key_format_type: Option<KeyFormatType>,
-) -> Pkcs11Result<Vec<KmsObject>> {
-    tokio::runtime::Runtime::new()?.block_on(get_kms_objects_async(
-        kms_rest_client,
-        tags,
-        key_format_type,
-    ))
+) -> Pkcs11Result<Vec<(String, Attributes)>> {
+    let ids = locate_objects(kms_rest_client, tags).await?;
+    let results = get_located_objects_attributes_async(kms_rest_client, ids).await?;
+    trace!(
+        "get_kms_objects_attributes_async: found {} objects for tags: {tags:?}",
+        results.len()
+    );
+    Ok(results)
 }
 
-pub(crate) async fn get_kms_objects_async(
+/// Get the attributes of the located KMS objects `ids`.
+/// An object destroyed since it was located is skipped; the other errors are returned.
+pub(crate) async fn get_located_objects_attributes_async(
     kms_rest_client: &KmsClient,
-    tags: &[String],
-    key_format_type: Option<KeyFormatType>,
-) -> Pkcs11Result<Vec<KmsObject>> {
-    let key_ids = locate_objects(kms_rest_client, tags).await?;
-    let export_object_params = ExportObjectParams {
-        unwrap: true,
-        key_format_type,
-        ..Default::default()
-    };
-    if key_ids.is_empty() {
-        trace!(
-            "get_kms_objects_async: no objects found for tags: {:?}",
-            tags
-        );
-        return Ok(vec![]);
-    }
-
-    let responses = batch_export_objects(kms_rest_client, key_ids, export_object_params).await?;
-    trace!("Found {} objects", responses.len());
-
-    let mut results = vec![];
-    for (id, object, attributes) in responses {
-        let other_tags = attributes
-            .get_tags()
-            .into_iter()
-            .filter(|t| !t.is_empty() && !tags.contains(t) && !t.starts_with('_'))
-            .collect::<Vec<String>>();
-        results.push(KmsObject {
-            remote_id: id.to_string(),
-            object,
-            attributes,
-            other_tags,
-        });
+    ids: Vec<String>,
+) -> Pkcs11Result<Vec<(String, Attributes)>> {
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        match get_kms_object_attributes_async(kms_rest_client, &id).await {
+            Ok(attributes) => results.push((id, attributes)),
+            Err(Pkcs11Error::ObjectNotFound(e)) => {
+                debug!("get_located_objects_attributes_async: skipping {id}: {e}");
+            }
+            Err(e) => return Err(e),
+        }
     }
     Ok(results)
 }
 
-pub(crate) fn get_kms_object(
-    kms_client: &KmsClient,
-    object_id_or_tags: &str,
-    key_format_type: KeyFormatType,
-) -> Pkcs11Result<KmsObject> {
-    tokio::runtime::Runtime::new()?.block_on(get_kms_object_async(
-        kms_client,
-        object_id_or_tags,
-        key_format_type,
-    ))
-}
-
 pub(crate) async fn get_kms_object_async(
     kms_client: &KmsClient,
     object_id_or_tags: &str,
@@ -162,7 +155,131 @@ pub(crate) async fn get_kms_object_async(
 async fn locate_objects(kms_rest_client: &KmsClient, tags: &[String]) -> Pkcs11Result<Vec<String>> {
     let mut attributes = Attributes::default();
     attributes.set_tags(tags)?;
+    locate_objects_with_attributes(kms_rest_client, attributes).await
+}
+
+/// Locate the KMS objects matching the filters of a `C_FindObjectsInit` template,
+/// in addition to the given tags.
+pub(crate) async fn locate_kms_objects_by_template_async(
+    kms_rest_client: &KmsClient,
+    template: &SearchTemplate,
+    tags: &[String],
+) -> Pkcs11Result<Vec<String>> {
+    let Some(attributes) = locate_attributes_from_template(template, tags)? else {
+        debug!("Located objects: no KMS object can match the template {template:?}");
+        return Ok(vec![]);
+    };
+    locate_objects_with_attributes(kms_rest_client, attributes).await
+}
+
+/// Translate a `C_FindObjectsInit` template into KMIP Locate attributes.
+/// Returns `None` when the template cannot match any KMS object.
+fn locate_attributes_from_template(
+    template: &SearchTemplate,
+    tags: &[String],
+) -> Pkcs11Result<Option<Attributes>> {
+    let mut attributes = Attributes::default();
+    if let Some(class) = template.class {
+        let Some(object_type) = object_type_from_class(class) else {
+            return Ok(None);
+        };
+        attributes.object_type = Some(object_type);
+    }
+    match template.key_type {
+        Some(CKK_AES) => attributes.cryptographic_algorithm = Some(CryptographicAlgorithm::AES),
+        Some(CKK_RSA) => attributes.cryptographic_algorithm = Some(CryptographicAlgorithm::RSA),
+        // EC keys may be stored with the EC or the ECDH algorithm
+        // and generic secrets with any of the HMAC algorithms:
+        // they are filtered on the returned attributes
+        Some(CKK_EC | CKK_GENERIC_SECRET) | None => {}
+        Some(_) => return Ok(None),
+    }
+    if let Some(value_len) = template.value_len {
+        attributes.cryptographic_length = Some(i32::try_from(value_len.saturating_mul(8))?);
+    }
+    let mut usage_mask = CryptographicUsageMask::empty();
+    if template.sign == Some(true) {
+        usage_mask |= CryptographicUsageMask::Sign;
+    }
+    if template.decrypt == Some(true) {
+        usage_mask |= CryptographicUsageMask::Decrypt;
+    }
+    if !usage_mask.is_empty() {
+        attributes.cryptographic_usage_mask = Some(usage_mask);
+    }
+    let mut tags = tags.to_vec();
+    if let Some(label) = &template.label {
+        tags.push(label.clone());
+    }
+    attributes.set_tags(tags)?;
+    Ok(Some(attributes))
+}
+
+/// Check the attributes of the KMS object `id` against the filters
+/// of a `C_FindObjectsInit` template.
+pub(crate) fn template_matches_attributes(
+    template: &SearchTemplate,
+    id: &str,
+    attributes: &Attributes,
+) -> bool {
+    if template.token == Some(false) {
+        // all the KMS objects are token objects
+        return false;
+    }
+    if template.class.is_some_and(|class| {
+        attributes.object_type.is_none() || attributes.object_type != object_type_from_class(class)
+    }) {
+        return false;
+    }
+    if template.key_type.is_some_and(|key_type| {
+        !key_algorithm_from_attributes(attributes)
+            .is_ok_and(|algorithm| algorithm.to_ck_key_type() == key_type)
+    }) {
+        return false;
+    }
+    if template
+        .label
+        .as_ref()
+        .is_some_and(|label| id != label && !attributes.get_tags().contains(label))
+    {
+        return false;
+    }
+    let length = attributes
+        .cryptographic_length
+        .and_then(|length| CK_ULONG::try_from(length).ok());
+    if template
+        .value_len
+        .is_some_and(|value_len| length != Some(value_len.saturating_mul(8)))
+    {
+        return false;
+    }
+    let usage_mask = attributes
+        .cryptographic_usage_mask
+        .unwrap_or(CryptographicUsageMask::empty());
+    [
+        (template.sign, CryptographicUsageMask::Sign),
+        (template.decrypt, CryptographicUsageMask::Decrypt),
+    ]
+    .into_iter()
+    .all(|(expected, usage)| expected.is_none_or(|expected| usage_mask.contains(usage) == expected))
+}
 
+const fn object_type_from_class(class: CK_OBJECT_CLASS) -> Option<ObjectType> {
+    match class {
+        CKO_SECRET_KEY => Some(ObjectType::SymmetricKey),
+        CKO_PRIVATE_KEY => Some(ObjectType::PrivateKey),
+        CKO_PUBLIC_KEY => Some(ObjectType::PublicKey),
+        CKO_CERTIFICATE => Some(ObjectType::Certificate),
+        CKO_DATA => Some(ObjectType::SecretData),
+        _ => None,
+    }
+}
+
+async fn locate_objects_with_attributes(
+    kms_rest_client: &KmsClient,
+    attributes: Attributes,
+) -> Pkcs11Result<Vec<String>> {
+    let tags = attributes.get_tags();
     let locate = Locate {
         attributes,
         ..Default::default()
@@ -180,76 +297,106 @@ async fn locate_objects(kms_rest_client: &KmsClient, tags: &[String]) -> Pkcs11R
     Ok(uniques_identifiers)
 }
 
-pub(crate) fn kms_import_symmetric_key(
+/// Creates a new KMS key.
+/// At first, the key is locally created and then imported to the KMS. There are 2 reasons why:
+/// - 1/ a key with `sensitive` flag cannot be extracted and then cannot be exported afterwards
+/// - 2/ is that the content of the key must be kept in cache to be reused later.
+pub(crate) async fn kms_import_symmetric_key_async(
     kms_rest_client: &KmsClient,
     algorithm: KeyAlgorithm,
     key_length: usize,
     sensitive: bool,
+    extractable: bool,
     label: Option<&str>,
 ) -> Pkcs11Result<KmsObject> {
-    tokio::runtime::Runtime::new()?.block_on(kms_import_symmetric_key_async(
+    let mut rng = CsRng::from_entropy();
+    let mut key = vec![0_u8; key_length];
+    rng.fill_bytes(&mut key);
+
+    kms_import_symmetric_key_bytes_async(
         kms_rest_client,
         algorithm,
-        key_length,
+        Zeroizing::new(key),
         sensitive,
+        extractable,
         label,
-    ))
+        true,
+    )
+    .await
 }
 
-/// Creates a new KMS key.
-/// At first, the key is locally created and then imported to the KMS. There are 2 reasons why:
-/// - 1/ a key with `sensitive` flag cannot be extracted and then cannot be exported afterwards
-/// - 2/ is that the content of the key must be kept in cache to be reused later.
-pub(crate) async fn kms_import_symmetric_key_async(
-    kms_rest_client: &KmsClient,
+/// Maps the algorithm of a secret key of `key_length` bytes to its KMIP algorithm and usage mask.
+/// KMIP has no generic secret algorithm: generic secrets are stored as
+/// HMAC keys, usable for MAC generation and verification only.
+fn symmetric_key_algorithm_and_usage(
     algorithm: KeyAlgorithm,
     key_length: usize,
-    sensitive: bool,
-    label: Option<&str>,
-) -> Pkcs11Result<KmsObject> {
-    let cryptographic_algorithm = if algorithm == KeyAlgorithm::Aes256 {
-        CryptographicAlgorithm::AES
+) -> Pkcs11Result<(CryptographicAlgorithm, CryptographicUsageMask)> {
+    if algorithm.is_aes() {
+        if KeyAlgorithm::aes(key_length) != Some(algorithm) {
+            return Err(Pkcs11Error::Default(format!(
+                "invalid {algorithm:?} key length: {key_length} bytes"
+            )));
+        }
+        Ok((
+            CryptographicAlgorithm::AES,
+            CryptographicUsageMask::Encrypt
+                | CryptographicUsageMask::Decrypt
+                | CryptographicUsageMask::WrapKey
+                | CryptographicUsageMask::UnwrapKey
+                | CryptographicUsageMask::KeyAgreement,
+        ))
+    } else if algorithm == KeyAlgorithm::GenericSecret {
+        Ok((
+            CryptographicAlgorithm::HMACSHA256,
+            CryptographicUsageMask::MACGenerate | CryptographicUsageMask::MACVerify,
+        ))
     } else {
         error!("Unsupported key algorithm: {:?}", algorithm);
-        return Err(Pkcs11Error::Default(format!(
+        Err(Pkcs11Error::Default(format!(
             "unsupported key algorithm: {algorithm:?}"
-        )));
-    };
-    let tags = label.map(|l| vec![l.to_owned()]).unwrap_or_default();
+        )))
+    }
+}
 
-    let mut rng = CsRng::from_entropy();
-    let mut key = vec![0_u8; key_length];
-    rng.fill_bytes(&mut key);
+/// Imports the given key bytes as a new KMS symmetric key.
+/// A `local` key is reported as generated by the token (`CKA_LOCAL`).
+pub(crate) async fn kms_import_symmetric_key_bytes_async(
+    kms_rest_client: &KmsClient,
+    algorithm: KeyAlgorithm,
+    key: Zeroizing<Vec<u8>>,
+    sensitive: bool,
+    extractable: bool,
+    label: Option<&str>,
+    local: bool,
+) -> Pkcs11Result<KmsObject> {
+    let (cryptographic_algorithm, cryptographic_usage_mask) =
+        symmetric_key_algorithm_and_usage(algorithm, key.len())?;
+    let tags = label.map(|l| vec![l.to_owned()]).unwrap_or_default();
 
-    let cryptographic_length = Some(i32::try_from(key_length * 8)?);
+    let cryptographic_length = Some(i32::try_from(key.len() * 8)?);
 
     let mut attributes = Attributes {
         cryptographic_algorithm: Some(cryptographic_algorithm),
         cryptographic_length,
         cryptographic_parameters: None,
-        cryptographic_usage_mask: Some(
-            CryptographicUsageMask::Encrypt
-                | CryptographicUsageMask::Decrypt
-                | CryptographicUsageMask::WrapKey
-                | CryptographicUsageMask::UnwrapKey
-                | CryptographicUsageMask::KeyAgreement,
-        ),
+        cryptographic_usage_mask: Some(cryptographic_usage_mask),
         key_format_type: Some(KeyFormatType::TransparentSymmetricKey),
         object_type: Some(ObjectType::SymmetricKey),
         unique_identifier: label.map(|l| UniqueIdentifier::TextString(l.to_owned())),
         sensitive: if sensitive { Some(true) } else { None },
+        extractable: Some(extractable),
         ..Attributes::default()
     };
     attributes.set_tags(tags.clone())?;
+    set_pkcs11_attributes(&mut attributes, label, local);
     let object = Object::SymmetricKey(SymmetricKey {
         key_block: KeyBlock {
             cryptographic_algorithm: Some(cryptographic_algorithm),
             key_format_type: KeyFormatType::TransparentSymmetricKey,
             key_compression_type: None,
             key_value: Some(KeyValue::Structure {
-                key_material: KeyMaterial::TransparentSymmetricKey {
-                    key: Zeroizing::new(key),
-                },
+                key_material: KeyMaterial::TransparentSymmetricKey { key },
                 attributes: Some(attributes.clone()),
             }),
             cryptographic_length,
@@ -279,14 +426,6 @@ pub(crate) async fn kms_import_symmetric_key_async(
     Ok(res)
 }
 
-pub(crate) fn kms_import_object(
-    kms_rest_client: &KmsClient,
-    label: &str,
-    data: &[u8],
-) -> Pkcs11Result<KmsObject> {
-    tokio::runtime::Runtime::new()?.block_on(kms_import_object_async(kms_rest_client, label, data))
-}
-
 pub(crate) async fn kms_import_object_async(
     kms_rest_client: &KmsClient,
     label: &str,
@@ -305,6 +444,7 @@ pub(crate) async fn kms_import_object_async(
 
     let mut attributes = Attributes::default();
     attributes.set_tags(tags.clone())?;
+    set_pkcs11_attributes(&mut attributes, Some(label), false);
 
     let object = Object::SecretData(SecretData {
         secret_data_type: SecretDataType::Password,
@@ -342,86 +482,653 @@ pub(crate) async fn kms_import_object_async(
     Ok(res)
 }
 
-pub(crate) fn kms_revoke_object(
+/// Imports a private key, given as PKCS#8 DER bytes, in the KMS.
+/// The key algorithm and size are recovered by the KMS from the PKCS#8 encoding.
+/// Returns the unique identifier of the private key, generated by the KMS.
+pub(crate) async fn kms_import_private_key_async(
     kms_rest_client: &KmsClient,
-    unique_identifier: &str,
-) -> Pkcs11Result<()> {
-    tokio::runtime::Runtime::new()?
-        .block_on(kms_revoke_object_async(kms_rest_client, unique_identifier))
+    pkcs8: Zeroizing<Vec<u8>>,
+    sensitive: bool,
+    extractable: bool,
+    label: Option<&str>,
+    tags: &[String],
+) -> Pkcs11Result<String> {
+    debug!("kms_import_private_key_async: tags: {tags:?}");
+    let mut attributes = Attributes {
+        cryptographic_usage_mask: Some(
+            CryptographicUsageMask::Sign
+                | CryptographicUsageMask::Decrypt
+                | CryptographicUsageMask::UnwrapKey
+                | CryptographicUsageMask::KeyAgreement,
+        ),
+        key_format_type: Some(KeyFormatType::PKCS8),
+        object_type: Some(ObjectType::PrivateKey),
+        sensitive: if sensitive { Some(true) } else { None },
+        extractable: Some(extractable),
+        ..Attributes::default()
+    };
+    attributes.set_tags(tags.to_vec())?;
+    set_pkcs11_attributes(&mut attributes, label, false);
+    let object = Object::PrivateKey(PrivateKey {
+        key_block: KeyBlock {
+            cryptographic_algorithm: None,
+            key_format_type: KeyFormatType::PKCS8,
+            key_compression_type: None,
+            key_value: Some(KeyValue::Structure {
+                key_material: KeyMaterial::ByteString(pkcs8),
+                attributes: Some(attributes.clone()),
+            }),
+            cryptographic_length: None,
+            key_wrapping_data: None,
+        },
+    });
+    let response = kms_rest_client
+        .import(Import {
+            unique_identifier: UniqueIdentifier::default(),
+            object_type: ObjectType::PrivateKey,
+            replace_existing: Some(false),
+            key_wrap_type: None,
+            attributes,
+            object,
+        })
+        .await?;
+
+    Ok(response.unique_identifier.to_string())
 }
 
-pub(crate) async fn kms_revoke_object_async(
+/// Imports the public key of an imported private key, given as `SubjectPublicKeyInfo`
+/// DER bytes, in the KMS. The public key is linked to the private key `private_key_id`.
+/// Returns the unique identifier of the public key, generated by the KMS.
+pub(crate) async fn kms_import_public_key_async(
     kms_rest_client: &KmsClient,
-    unique_identifier: &str,
-) -> Pkcs11Result<()> {
-    kms_rest_client
-        .revoke(Revoke {
-            unique_identifier: Some(UniqueIdentifier::TextString(unique_identifier.to_owned())),
-            revocation_reason: RevocationReason {
-                revocation_reason_code: RevocationReasonCode::CessationOfOperation,
-                revocation_message: None,
-            },
-            compromise_occurrence_date: None,
+    spki: &[u8],
+    private_key_id: &str,
+    label: Option<&str>,
+    tags: &[String],
+) -> Pkcs11Result<String> {
+    debug!("kms_import_public_key_async: private key id: {private_key_id}, tags: {tags:?}");
+    let mut attributes = Attributes {
+        cryptographic_usage_mask: Some(
+            CryptographicUsageMask::Verify
+                | CryptographicUsageMask::Encrypt
+                | CryptographicUsageMask::WrapKey,
+        ),
+        key_format_type: Some(KeyFormatType::PKCS8),
+        object_type: Some(ObjectType::PublicKey),
+        ..Attributes::default()
+    };
+    attributes.set_tags(tags.to_vec())?;
+    set_pkcs11_attributes(&mut attributes, label, false);
+    attributes.set_link(
+        LinkType::PrivateKeyLink,
+        LinkedObjectIdentifier::TextString(private_key_id.to_owned()),
+    );
+    let object = Object::PublicKey(PublicKey {
+        key_block: KeyBlock {
+            cryptographic_algorithm: None,
+            key_format_type: KeyFormatType::PKCS8,
+            key_compression_type: None,
+            key_value: Some(KeyValue::Structure {
+                key_material: KeyMaterial::ByteString(Zeroizing::new(spki.to_vec())),
+                attributes: Some(attributes.clone()),
+            }),
+            cryptographic_length: None,
+            key_wrapping_data: None,
+        },
+    });
+    let response = kms_rest_client
+        .import(Import {
+            unique_identifier: UniqueIdentifier::default(),
+            object_type: ObjectType::PublicKey,
+            replace_existing: Some(false),
+            key_wrap_type: None,
+            attributes,
+            object,
         })
         .await?;
 
-    Ok(())
+    Ok(response.unique_identifier.to_string())
 }
 
-pub(crate) fn kms_destroy_object(
+/// Imports an X.509 certificate, given as DER bytes, in the KMS.
+/// The certificate is linked to the private key `private_key_id`, when given.
+pub(crate) async fn kms_import_certificate_async(
     kms_rest_client: &KmsClient,
-    unique_identifier: &str,
-) -> Pkcs11Result<()> {
-    tokio::runtime::Runtime::new()?
-        .block_on(kms_destroy_object_async(kms_rest_client, unique_identifier))
+    der: &[u8],
+    private_key_id: Option<&str>,
+    label: Option<&str>,
+    tags: &[String],
+) -> Pkcs11Result<KmsObject> {
+    debug!(
+        "kms_import_certificate_async: private key id: {private_key_id:?}, tags: {tags:?}, der \
+         (length): {}",
+        der.len()
+    );
+    let mut attributes = Attributes {
+        object_type: Some(ObjectType::Certificate),
+        ..Attributes::default()
+    };
+    attributes.set_tags(tags.to_vec())?;
+    set_pkcs11_attributes(&mut attributes, label, false);
+    if let Some(private_key_id) = private_key_id {
+        attributes.set_link(
+            LinkType::PrivateKeyLink,
+            LinkedObjectIdentifier::TextString(private_key_id.to_owned()),
+        );
+    }
+    let object = Object::Certificate(Certificate {
+        certificate_type: CertificateType::X509,
+        certificate_value: der.to_vec(),
+    });
+    let response = kms_rest_client
+        .import(Import {
+            unique_identifier: UniqueIdentifier::default(),
+            object_type: ObjectType::Certificate,
+            replace_existing: Some(false),
+            key_wrap_type: None,
+            attributes: attributes.clone(),
+            object: object.clone(),
+        })
+        .await?;
+
+    Ok(KmsObject {
+        remote_id: response.unique_identifier.to_string(),
+        object,
+        attributes,
+        other_tags: tags.to_vec(),
+    })
 }
 
-pub(crate) async fn kms_destroy_object_async(
-    kms_rest_client: &KmsClient,
-    unique_identifier: &str,
-) -> Pkcs11Result<()> {
-    kms_rest_client
-        .destroy(Destroy {
-            unique_identifier: Some(UniqueIdentifier::TextString(unique_identifier.to_owned())),
-            remove: false,
+/// The prefix of the tag holding the `CKA_ID` of an object: the KMS generates
+/// the unique identifiers of the objects imported by `C_CreateObject`
+const PKCS11_ID_TAG_PREFIX: &str = "pkcs11_id:";
+
+/// The tag holding the `CKA_ID` `id`, hex encoded since it is a byte array
+pub(crate) fn id_tag(id: &[u8]) -> String {
+    format!("{PKCS11_ID_TAG_PREFIX}{}", hex::encode(id))
+}
+
+/// The `CKA_ID` held in the tags of a KMS object
+pub(crate) fn id_from_attributes(attributes: &Attributes) -> Option<Vec<u8>> {
+    attributes.get_tags().into_iter().find_map(|tag| {
+        tag.strip_prefix(PKCS11_ID_TAG_PREFIX)
+            .and_then(|id| hex::decode(id).ok())
+    })
+}
+
+/// The name of the vendor attribute holding the `CKA_APPLICATION` of a data object
+pub(crate) const VENDOR_ATTR_PKCS11_APPLICATION: &str = "pkcs11_application";
+
+/// The name of the vendor attribute marking the keys generated through the token (`CKA_LOCAL`)
+const VENDOR_ATTR_PKCS11_LOCAL: &str = "pkcs11_local";
+
+/// Set the KMIP attributes of an object created through the token:
+/// its label is its KMIP Name, and a `local` key is marked as such.
+fn set_pkcs11_attributes(attributes: &mut Attributes, label: Option<&str>, local: bool) {
+    if let Some(label) = label {
+        attributes.name = Some(vec![Name {
+            name_value: label.to_owned(),
+            name_type: NameType::UninterpretedTextString,
+        }]);
+    }
+    if local {
+        attributes
+            .vendor_attributes
+            .get_or_insert_with(Vec::new)
+            .push(VendorAttribute {
+                vendor_identification: VENDOR_ID_COSMIAN.to_owned(),
+                attribute_name: VENDOR_ATTR_PKCS11_LOCAL.to_owned(),
+                attribute_value: VendorAttributeValue::TextString("true".to_owned()),
+            });
+    }
+}
+
+/// The value of the Cosmian vendor attribute `attribute_name` of a KMS object
+fn vendor_attribute<'a>(
+    attributes: &'a Attributes,
+    attribute_name: &str,
+) -> Option<&'a VendorAttributeValue> {
+    attributes
+        .vendor_attributes
+        .iter()
+        .flatten()
+        .find(|attribute| {
+            attribute.vendor_identification == VENDOR_ID_COSMIAN
+                && attribute.attribute_name == attribute_name
         })
-        .await?;
+        .map(|attribute| &attribute.attribute_value)
+}
 
-    Ok(())
+/// The `CKA_APPLICATION` of a data object, when it was set by `C_SetAttributeValue`
+pub(crate) fn application_from_attributes(attributes: &Attributes) -> Option<Vec<u8>> {
+    match vendor_attribute(attributes, VENDOR_ATTR_PKCS11_APPLICATION)? {
+        VendorAttributeValue::ByteString(application) => Some(application.clone()),
+        VendorAttributeValue::TextString(application) => Some(application.as_bytes().to_vec()),
+        _ => None,
+    }
 }
 
-pub(crate) fn kms_encrypt(
-    kms_rest_client: &KmsClient,
-    encrypt_ctx: &EncryptContext,
-    data: Vec<u8>,
-) -> Pkcs11Result<Vec<u8>> {
-    tokio::runtime::Runtime::new()?.block_on(kms_encrypt_async(kms_rest_client, encrypt_ctx, data))
+/// The PKCS#11 metadata of a KMS object, derived from its KMIP attributes:
+/// - the label is its first KMIP Name,
+/// - the `CKA_ID` is the one held in its tags,
+/// - the usages are its cryptographic usage mask, none of them while the object is not active,
+/// - the start and end dates are its activation and deactivation dates.
+pub(crate) fn metadata_from_attributes(attributes: &Attributes) -> KeyMetadata {
+    let active = attributes.state.is_none_or(|state| state == State::Active);
+    let usage = attributes.cryptographic_usage_mask.map(|mask| {
+        if !active {
+            return KeyUsage::default();
+        }
+        KeyUsage {
+            encrypt: mask.contains(CryptographicUsageMask::Encrypt),
+            decrypt: mask.contains(CryptographicUsageMask::Decrypt),
+            sign: mask
+                .intersects(CryptographicUsageMask::Sign | CryptographicUsageMask::MACGenerate),
+            verify: mask
+                .intersects(CryptographicUsageMask::Verify | CryptographicUsageMask::MACVerify),
+            wrap: mask.contains(CryptographicUsageMask::WrapKey),
+            unwrap: mask.contains(CryptographicUsageMask::UnwrapKey),
+            derive: mask.intersects(
+                CryptographicUsageMask::DeriveKey | CryptographicUsageMask::KeyAgreement,
+            ),
+        }
+    });
+    KeyMetadata {
+        label: attributes
+            .name
+            .iter()
+            .flatten()
+            .next()
+            .map(|name| name.name_value.clone()),
+        id: id_from_attributes(attributes),
+        usage,
+        start_date: attributes.activation_date.map(ck_date_from_date),
+        end_date: attributes.deactivation_date.map(ck_date_from_date),
+        local: Some(vendor_attribute(attributes, VENDOR_ATTR_PKCS11_LOCAL).is_some()),
+        sensitive: attributes.sensitive,
+        extractable: attributes.extractable,
+    }
 }
 
-pub(crate) async fn kms_encrypt_async(
-    kms_rest_client: &KmsClient,
-    encrypt_ctx: &EncryptContext,
-    data: Vec<u8>,
-) -> Pkcs11Result<Vec<u8>> {
-    let cryptographic_parameters = match encrypt_ctx.algorithm {
-        EncryptionAlgorithm::AesCbcPad => CryptographicParameters {
-            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
-            block_cipher_mode: Some(BlockCipherMode::CBC),
-            padding_method: Some(PaddingMethod::PKCS5),
-            ..Default::default()
-        },
-        EncryptionAlgorithm::AesCbc => CryptographicParameters {
-            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
-            block_cipher_mode: Some(BlockCipherMode::CBC),
-            padding_method: Some(PaddingMethod::None),
-            ..Default::default()
-        },
-        EncryptionAlgorithm::RsaPkcs1v15 => CryptographicParameters {
-            cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
-            padding_method: Some(PaddingMethod::PKCS1v15),
-            ..Default::default()
-        },
+/// Format a date as a `CK_DATE`, the 8 digits of `YYYYMMDD`, in UTC
+pub(crate) fn ck_date_from_date(date: OffsetDateTime) -> String {
+    let date = date.to_offset(UtcOffset::UTC);
+    format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+/// Translate the attributes of a `C_SetAttributeValue` template into the KMIP
+/// attributes to set on the KMS object which currently has the `current` attributes.
+///
+/// The label is the KMIP Name of the object; it also replaces the previous label
+/// in the tags, so that the object is found by its new label.
+/// The `CKA_ID` replaces the previous one in the tags.
+pub(crate) fn kmip_attributes_from_template(
+    current: &Attributes,
+    template: &Template,
+) -> Pkcs11Result<Vec<KmipAttribute>> {
+    let mut kmip_attributes = Vec::with_capacity(template.len());
+    let current_usage_mask = current
+        .cryptographic_usage_mask
+        .unwrap_or(CryptographicUsageMask::empty());
+    let mut usage_mask = current_usage_mask;
+    // the signatures of an HMAC key are MACs
+    let (sign_usage, verify_usage) = if matches!(
+        current.cryptographic_algorithm,
+        Some(
+            CryptographicAlgorithm::HMACSHA1
+                | CryptographicAlgorithm::HMACSHA224
+                | CryptographicAlgorithm::HMACSHA256
+                | CryptographicAlgorithm::HMACSHA384
+                | CryptographicAlgorithm::HMACSHA512
+        )
+    ) {
+        (
+            CryptographicUsageMask::MACGenerate,
+            CryptographicUsageMask::MACVerify,
+        )
+    } else {
+        (CryptographicUsageMask::Sign, CryptographicUsageMask::Verify)
     };
+    let current_tags = current.get_tags().into_iter().collect::<Vec<_>>();
+    let mut tags = current_tags.clone();
+    for attribute in template.iter() {
+        match attribute {
+            Attribute::Label(label) => {
+                let previous_labels = current
+                    .name
+                    .iter()
+                    .flatten()
+                    .map(|name| name.name_value.clone())
+                    .collect::<Vec<_>>();
+                tags.retain(|tag| !previous_labels.contains(tag));
+                tags.push(label.clone());
+                kmip_attributes.push(KmipAttribute::Name(Name {
+                    name_value: label.clone(),
+                    name_type: NameType::UninterpretedTextString,
+                }));
+            }
+            Attribute::Id(id) => {
+                tags.retain(|tag| !tag.starts_with(PKCS11_ID_TAG_PREFIX));
+                tags.push(id_tag(id));
+            }
+            Attribute::Application(application) => {
+                kmip_attributes.push(KmipAttribute::VendorAttribute(VendorAttribute {
+                    vendor_identification: VENDOR_ID_COSMIAN.to_owned(),
+                    attribute_name: VENDOR_ATTR_PKCS11_APPLICATION.to_owned(),
+                    attribute_value: VendorAttributeValue::ByteString(application.clone()),
+                }));
+            }
+            Attribute::StartDate(date) => {
+                kmip_attributes.push(KmipAttribute::ActivationDate(date_from_ck_date(date)?));
+            }
+            Attribute::EndDate(date) => {
+                kmip_attributes.push(KmipAttribute::DeactivationDate(date_from_ck_date(date)?));
+            }
+            Attribute::Encrypt(set) => usage_mask.set(CryptographicUsageMask::Encrypt, *set),
+            Attribute::Decrypt(set) => usage_mask.set(CryptographicUsageMask::Decrypt, *set),
+            Attribute::Sign(set) => set_usage(
+                &mut usage_mask,
+                sign_usage,
+                CryptographicUsageMask::Sign | CryptographicUsageMask::MACGenerate,
+                *set,
+            ),
+            Attribute::Verify(set) => set_usage(
+                &mut usage_mask,
+                verify_usage,
+                CryptographicUsageMask::Verify | CryptographicUsageMask::MACVerify,
+                *set,
+            ),
+            Attribute::Wrap(set) => usage_mask.set(CryptographicUsageMask::WrapKey, *set),
+            Attribute::Unwrap(set) => usage_mask.set(CryptographicUsageMask::UnwrapKey, *set),
+            Attribute::Derive(set) => set_usage(
+                &mut usage_mask,
+                CryptographicUsageMask::DeriveKey,
+                CryptographicUsageMask::DeriveKey | CryptographicUsageMask::KeyAgreement,
+                *set,
+            ),
+            other => {
+                return Err(Pkcs11Error::NotSupported(format!(
+                    "the attribute {} cannot be set on a KMS object",
+                    other.attribute_type()
+                )));
+            }
+        }
+    }
+    if tags != current_tags {
+        kmip_attributes.push(KmipAttribute::VendorAttribute(VendorAttribute {
+            vendor_identification: VENDOR_ID_COSMIAN.to_owned(),
+            attribute_name: VENDOR_ATTR_TAG.to_owned(),
+            attribute_value: VendorAttributeValue::TextString(serde_json::to_string(&tags)?),
+        }));
+    }
+    if usage_mask != current_usage_mask {
+        kmip_attributes.push(KmipAttribute::CryptographicUsageMask(usage_mask));
+    }
+    Ok(kmip_attributes)
+}
+
+/// Set `usage` in the usage `mask`, or clear all the `usages` reported
+/// as the same PKCS#11 attribute by `metadata_from_attributes`
+fn set_usage(
+    mask: &mut CryptographicUsageMask,
+    usage: CryptographicUsageMask,
+    usages: CryptographicUsageMask,
+    set: bool,
+) {
+    if set {
+        mask.insert(usage);
+    } else {
+        mask.remove(usages);
+    }
+}
+
+/// Convert a `CK_DATE`, the 8 digits of `YYYYMMDD`, to midnight UTC of that day.
+/// A KMIP attribute cannot be cleared by `SetAttribute`: empty dates are not supported.
+pub(crate) fn date_from_ck_date(date: &[u8]) -> Pkcs11Result<OffsetDateTime> {
+    let date = std::str::from_utf8(date)?;
+    let invalid = || Pkcs11Error::Conversion(format!("invalid CK_DATE: {date:?}"));
+    if date.len() != 8 || !date.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let field = |range: std::ops::Range<usize>| -> Pkcs11Result<u16> {
+        date.get(range)
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let month = Month::try_from(u8::try_from(field(4..6)?)?).map_err(|e| {
+        debug!("date_from_ck_date: {e}");
+        invalid()
+    })?;
+    let date =
+        Date::from_calendar_date(i32::from(field(0..4)?), month, u8::try_from(field(6..8)?)?)
+            .map_err(|e| {
+                debug!("date_from_ck_date: {e}");
+                invalid()
+            })?;
+    Ok(date.midnight().assume_utc())
+}
+
+/// Set the KMIP `attributes` of the KMS object `id`, which currently has the `current`
+/// attributes, with one `SetAttribute` request each.
+/// When a request fails, the attributes already set are restored to their `current` value,
+/// or deleted when they had none, so that the object is not left half-updated.
+pub(crate) async fn kms_set_attributes_async(
+    kms_rest_client: &KmsClient,
+    id: &str,
+    current: &Attributes,
+    attributes: Vec<KmipAttribute>,
+) -> Pkcs11Result<()> {
+    debug!("kms_set_attributes_async: id: {id}, attributes: {attributes:?}");
+    for (set_count, new_attribute) in attributes.iter().enumerate() {
+        if let Err(e) = set_attribute(kms_rest_client, id, new_attribute.clone()).await {
+            for attribute in attributes.iter().take(set_count) {
+                let restored = match restore_attribute(current, attribute) {
+                    Restore::Set(previous) => set_attribute(kms_rest_client, id, previous).await,
+                    Restore::Delete(attribute) => {
+                        delete_attribute(kms_rest_client, id, attribute).await
+                    }
+                };
+                if let Err(restore_error) = restored {
+                    error!(
+                        "kms_set_attributes_async: failed to restore {attribute:?} on {id}: \
+                         {restore_error}"
+                    );
+                }
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// How a KMIP attribute changed by a failed update is restored
+#[derive(Debug)]
+pub(crate) enum Restore {
+    /// Set the attribute back to its previous value
+    Set(KmipAttribute),
+    /// Delete the attribute, which had no previous value
+    Delete(KmipAttribute),
+}
+
+/// Restore the KMIP `attribute` to its value in `current`, or delete it if it has none
+pub(crate) fn restore_attribute(current: &Attributes, attribute: &KmipAttribute) -> Restore {
+    previous_attribute(current, attribute)
+        .map_or_else(|| Restore::Delete(attribute.clone()), Restore::Set)
+}
+
+/// The value in `current` of the KMIP attribute changed by `attribute`, if it has one
+fn previous_attribute(current: &Attributes, attribute: &KmipAttribute) -> Option<KmipAttribute> {
+    match attribute {
+        KmipAttribute::Name(_) => current
+            .name
+            .iter()
+            .flatten()
+            .next()
+            .cloned()
+            .map(KmipAttribute::Name),
+        KmipAttribute::VendorAttribute(changed) => current
+            .vendor_attributes
+            .iter()
+            .flatten()
+            .find(|vendor_attribute| {
+                vendor_attribute.vendor_identification == changed.vendor_identification
+                    && vendor_attribute.attribute_name == changed.attribute_name
+            })
+            .cloned()
+            .map(KmipAttribute::VendorAttribute),
+        KmipAttribute::ActivationDate(_) => {
+            current.activation_date.map(KmipAttribute::ActivationDate)
+        }
+        KmipAttribute::DeactivationDate(_) => current
+            .deactivation_date
+            .map(KmipAttribute::DeactivationDate),
+        KmipAttribute::CryptographicUsageMask(_) => current
+            .cryptographic_usage_mask
+            .map(KmipAttribute::CryptographicUsageMask),
+        _ => None,
+    }
+}
+
+async fn set_attribute(
+    kms_rest_client: &KmsClient,
+    id: &str,
+    new_attribute: KmipAttribute,
+) -> Pkcs11Result<()> {
+    kms_rest_client
+        .set_attribute(SetAttribute {
+            unique_identifier: Some(UniqueIdentifier::TextString(id.to_owned())),
+            new_attribute,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn delete_attribute(
+    kms_rest_client: &KmsClient,
+    id: &str,
+    current_attribute: KmipAttribute,
+) -> Pkcs11Result<()> {
+    kms_rest_client
+        .delete_attribute(DeleteAttribute {
+            unique_identifier: Some(UniqueIdentifier::TextString(id.to_owned())),
+            current_attribute: Some(current_attribute),
+            attribute_references: None,
+        })
+        .await?;
+    Ok(())
+}
+
+/// Link the KMS object `id` to the KMS object `linked_id`
+pub(crate) async fn kms_set_link_async(
+    kms_rest_client: &KmsClient,
+    id: &str,
+    link_type: LinkType,
+    linked_id: &str,
+) -> Pkcs11Result<()> {
+    set_attribute(
+        kms_rest_client,
+        id,
+        KmipAttribute::Link(Link {
+            link_type,
+            linked_object_identifier: LinkedObjectIdentifier::TextString(linked_id.to_owned()),
+        }),
+    )
+    .await
+}
+
+/// Creates a new key pair in the KMS.
+/// Unlike symmetric keys, the key pair is generated server side.
+/// Returns the `(private key id, public key id)` tuple.
+pub(crate) async fn kms_create_key_pair_async(
+    kms_rest_client: &KmsClient,
+    algorithm: KeyAlgorithm,
+    key_length: Option<usize>,
+    sensitive: bool,
+    extractable: bool,
+    label: Option<&str>,
+    tags: &[String],
+) -> Pkcs11Result<(String, String)> {
+    debug!(
+        "kms_create_key_pair_async: algorithm: {algorithm:?}, key length: {key_length:?}, tags: \
+         {tags:?}"
+    );
+    let mut request = if algorithm == KeyAlgorithm::Rsa {
+        let key_length = key_length.ok_or_else(|| {
+            Pkcs11Error::Default("missing modulus size for RSA key pair".to_owned())
+        })?;
+        create_rsa_key_pair_request(None, tags, key_length, sensitive, None)?
+    } else {
+        create_ec_key_pair_request(
+            None,
+            tags,
+            recommended_curve_from_key_algorithm(algorithm)?,
+            sensitive,
+            None,
+        )?
+    };
+    set_pkcs11_attributes(
+        request
+            .common_attributes
+            .get_or_insert_with(Attributes::default),
+        label,
+        true,
+    );
+    // The public key remains extractable
+    request
+        .private_key_attributes
+        .get_or_insert_with(Attributes::default)
+        .extractable = Some(extractable);
+    let response = kms_rest_client.create_key_pair(request).await?;
+
+    Ok((
+        response.private_key_unique_identifier.to_string(),
+        response.public_key_unique_identifier.to_string(),
+    ))
+}
+
+pub(crate) async fn kms_revoke_object_async(
+    kms_rest_client: &KmsClient,
+    unique_identifier: &str,
+) -> Pkcs11Result<()> {
+    kms_rest_client
+        .revoke(Revoke {
+            unique_identifier: Some(UniqueIdentifier::TextString(unique_identifier.to_owned())),
+            revocation_reason: RevocationReason {
+                revocation_reason_code: RevocationReasonCode::CessationOfOperation,
+                revocation_message: None,
+            },
+            compromise_occurrence_date: None,
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn kms_destroy_object_async(
+    kms_rest_client: &KmsClient,
+    unique_identifier: &str,
+) -> Pkcs11Result<()> {
+    kms_rest_client
+        .destroy(Destroy {
+            unique_identifier: Some(UniqueIdentifier::TextString(unique_identifier.to_owned())),
+            remove: false,
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn kms_encrypt_async(
+    kms_rest_client: &KmsClient,
+    encrypt_ctx: &EncryptContext,
+    data: Vec<u8>,
+) -> Pkcs11Result<Vec<u8>> {
+    let cryptographic_parameters = encryption_cryptographic_parameters(&encrypt_ctx.algorithm)?;
     let encryption_request = Encrypt {
         unique_identifier: Some(UniqueIdentifier::TextString(
             encrypt_ctx.remote_object_id.clone(),
@@ -429,12 +1136,18 @@ pub(crate) async fn kms_encrypt_async(
         cryptographic_parameters: Some(cryptographic_parameters),
         data: Some(Zeroizing::new(data)),
         i_v_counter_nonce: encrypt_ctx.iv.clone(),
+        authenticated_encryption_additional_data: encrypt_ctx.aad.clone(),
         ..Default::default()
     };
     let response = kms_rest_client.encrypt(encryption_request).await?;
-    let ciphertext = response.data.ok_or_else(|| {
+    let mut ciphertext = response.data.ok_or_else(|| {
         Pkcs11Error::ServerError("Encryption response does not contain data".to_owned())
     })?;
+    if matches!(encrypt_ctx.algorithm, EncryptionAlgorithm::AesGcm { .. }) {
+        ciphertext.extend(response.authenticated_encryption_tag.ok_or_else(|| {
+            Pkcs11Error::ServerError("Encryption response does not contain the GCM tag".to_owned())
+        })?);
+    }
 
     debug!(
         "kms_encrypt_async: ciphertext: {}",
@@ -443,20 +1156,124 @@ pub(crate) async fn kms_encrypt_async(
     Ok(ciphertext)
 }
 
-pub(crate) fn kms_decrypt(
+pub(crate) async fn kms_decrypt_async(
     kms_rest_client: &KmsClient,
     decrypt_ctx: &DecryptContext,
-    data: Vec<u8>,
+    mut data: Vec<u8>,
 ) -> Pkcs11Result<Zeroizing<Vec<u8>>> {
-    tokio::runtime::Runtime::new()?.block_on(kms_decrypt_async(kms_rest_client, decrypt_ctx, data))
+    let cryptographic_parameters = encryption_cryptographic_parameters(&decrypt_ctx.algorithm)?;
+    // PKCS#11 appends the AES GCM tag to the ciphertext, KMIP carries it separately
+    let authenticated_encryption_tag = match decrypt_ctx.algorithm {
+        EncryptionAlgorithm::AesGcm { tag_length } => {
+            let tag_start = data.len().checked_sub(*tag_length).ok_or_else(|| {
+                Pkcs11Error::Default("the ciphertext is shorter than the GCM tag".to_owned())
+            })?;
+            Some(data.split_off(tag_start))
+        }
+        _ => None,
+    };
+    let decryption_request = Decrypt {
+        unique_identifier: Some(UniqueIdentifier::TextString(
+            decrypt_ctx.remote_object_id.clone(),
+        )),
+        cryptographic_parameters: Some(cryptographic_parameters),
+        data: Some(data),
+        i_v_counter_nonce: decrypt_ctx.iv.clone(),
+        authenticated_encryption_additional_data: decrypt_ctx.aad.clone(),
+        authenticated_encryption_tag,
+        ..Default::default()
+    };
+    let response = kms_rest_client.decrypt(decryption_request).await?;
+    response.data.ok_or_else(|| {
+        Pkcs11Error::ServerError("Decryption response does not contain data".to_owned())
+    })
 }
 
-pub(crate) async fn kms_decrypt_async(
+/// Encrypts a part of a multipart encryption using the KMIP streaming indicators,
+/// or terminates it when `data` is `None`.
+/// The correlation value returned by the KMS is kept in the context for the next part.
+pub(crate) async fn kms_encrypt_multipart_async(
     kms_rest_client: &KmsClient,
-    decrypt_ctx: &DecryptContext,
-    data: Vec<u8>,
+    encrypt_ctx: &mut EncryptContext,
+    data: Option<Vec<u8>>,
+) -> Pkcs11Result<Vec<u8>> {
+    let cryptographic_parameters = encryption_cryptographic_parameters(&encrypt_ctx.algorithm)?;
+    let init_indicator = encrypt_ctx.correlation_value.is_none();
+    let final_indicator = data.is_none();
+    let encryption_request = Encrypt {
+        unique_identifier: Some(UniqueIdentifier::TextString(
+            encrypt_ctx.remote_object_id.clone(),
+        )),
+        cryptographic_parameters: Some(cryptographic_parameters),
+        data: data.map(Zeroizing::new),
+        i_v_counter_nonce: if init_indicator {
+            encrypt_ctx.iv.clone()
+        } else {
+            None
+        },
+        correlation_value: encrypt_ctx.correlation_value.clone(),
+        init_indicator: Some(init_indicator),
+        final_indicator: Some(final_indicator),
+        ..Default::default()
+    };
+    let response = kms_rest_client.encrypt(encryption_request).await?;
+    encrypt_ctx.correlation_value = if final_indicator {
+        None
+    } else {
+        Some(response.correlation_value.ok_or_else(|| {
+            Pkcs11Error::ServerError(
+                "Encryption response does not contain the correlation value".to_owned(),
+            )
+        })?)
+    };
+    Ok(response.data.unwrap_or_default())
+}
+
+/// Decrypts a part of a multipart decryption using the KMIP streaming indicators,
+/// or terminates it when `data` is `None`.
+/// The correlation value returned by the KMS is kept in the context for the next part.
+pub(crate) async fn kms_decrypt_multipart_async(
+    kms_rest_client: &KmsClient,
+    decrypt_ctx: &mut DecryptContext,
+    data: Option<Vec<u8>>,
 ) -> Pkcs11Result<Zeroizing<Vec<u8>>> {
-    let cryptographic_parameters = match decrypt_ctx.algorithm {
+    let cryptographic_parameters = encryption_cryptographic_parameters(&decrypt_ctx.algorithm)?;
+    let init_indicator = decrypt_ctx.correlation_value.is_none();
+    let final_indicator = data.is_none();
+    let decryption_request = Decrypt {
+        unique_identifier: Some(UniqueIdentifier::TextString(
+            decrypt_ctx.remote_object_id.clone(),
+        )),
+        cryptographic_parameters: Some(cryptographic_parameters),
+        data,
+        i_v_counter_nonce: if init_indicator {
+            decrypt_ctx.iv.clone()
+        } else {
+            None
+        },
+        correlation_value: decrypt_ctx.correlation_value.clone(),
+        init_indicator: Some(init_indicator),
+        final_indicator: Some(final_indicator),
+        ..Default::default()
+    };
+    let response = kms_rest_client.decrypt(decryption_request).await?;
+    decrypt_ctx.correlation_value = if final_indicator {
+        None
+    } else {
+        Some(response.correlation_value.ok_or_else(|| {
+            Pkcs11Error::ServerError(
+                "Decryption response does not contain the correlation value".to_owned(),
+            )
+        })?)
+    };
+    Ok(response.data.unwrap_or_default())
+}
+
+/// Maps the encryption algorithm to the KMIP cryptographic parameters.
+fn encryption_cryptographic_parameters(
+    algorithm: &EncryptionAlgorithm,
+) -> Pkcs11Result<CryptographicParameters> {
+    Ok(match algorithm {
         EncryptionAlgorithm::AesCbcPad => CryptographicParameters {
             cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
             block_cipher_mode: Some(BlockCipherMode::CBC),
@@ -469,32 +1286,390 @@ pub(crate) async fn kms_decrypt_async(
             padding_method: Some(PaddingMethod::None),
             ..Default::default()
         },
+        EncryptionAlgorithm::AesCtr => CryptographicParameters {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+            block_cipher_mode: Some(BlockCipherMode::CTR),
+            ..Default::default()
+        },
+        EncryptionAlgorithm::AesGcm { tag_length } => CryptographicParameters {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+            block_cipher_mode: Some(BlockCipherMode::GCM),
+            tag_length: Some(i32::try_from(*tag_length)?),
+            ..Default::default()
+        },
         EncryptionAlgorithm::RsaPkcs1v15 => CryptographicParameters {
             cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
             padding_method: Some(PaddingMethod::PKCS1v15),
             ..Default::default()
         },
+        EncryptionAlgorithm::RsaOaep {
+            digest,
+            mask_generation_function,
+        } => CryptographicParameters {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
+            padding_method: Some(PaddingMethod::OAEP),
+            hashing_algorithm: Some(hashing_algorithm_from_digest_type(digest)),
+            mask_generator: Some(MaskGenerator::MGF1),
+            mask_generator_hashing_algorithm: Some(hashing_algorithm_from_digest_type(
+                mask_generation_function,
+            )),
+            ..Default::default()
+        },
+    })
+}
+
+/// Signs the data with the KMS private key.
+/// The raw mechanisms (`CKM_ECDSA`, `CKM_RSA_PKCS`, `CKM_RSA_PKCS_PSS`) receive
+/// data which is already digested: it is sent as `digested_data`.
+/// HMAC signatures are computed with the KMS MAC operation on the generic secret.
+pub(crate) async fn kms_sign_async(
+    kms_rest_client: &KmsClient,
+    remote_id: &str,
+    algorithm: &SignatureAlgorithm,
+    data: &[u8],
+) -> Pkcs11Result<Vec<u8>> {
+    if let SignatureAlgorithm::Hmac { digest } = algorithm {
+        return kms_mac_async(kms_rest_client, remote_id, digest, data).await;
+    }
+    let (cryptographic_parameters, digested) = signature_cryptographic_parameters(algorithm)?;
+    let (data, digested_data) = if digested {
+        (None, Some(data.to_vec()))
+    } else {
+        (Some(Zeroizing::new(data.to_vec())), None)
     };
-    let decryption_request = Decrypt {
-        unique_identifier: Some(UniqueIdentifier::TextString(
-            decrypt_ctx.remote_object_id.clone(),
-        )),
+    let sign_request = Sign {
+        unique_identifier: Some(UniqueIdentifier::TextString(remote_id.to_owned())),
         cryptographic_parameters: Some(cryptographic_parameters),
-        data: Some(data),
-        i_v_counter_nonce: decrypt_ctx.iv.clone(),
+        data,
+        digested_data,
         ..Default::default()
     };
-    let response = kms_rest_client.decrypt(decryption_request).await?;
-    response.data.ok_or_else(|| {
-        Pkcs11Error::ServerError("Decryption response does not contain data".to_owned())
+    let response = kms_rest_client.sign(sign_request).await?;
+    response.signature_data.ok_or_else(|| {
+        Pkcs11Error::ServerError("Sign response does not contain signature data".to_owned())
     })
 }
 
-pub(crate) fn get_kms_object_attributes(
-    kms_client: &KmsClient,
-    object_id: &str,
-) -> Pkcs11Result<Attributes> {
-    tokio::runtime::Runtime::new()?.block_on(get_kms_object_attributes_async(kms_client, object_id))
+/// Verifies the signature with the KMS public key.
+/// HMAC signatures are recomputed with the generic secret and compared in constant time.
+/// Returns `false` if the signature is invalid.
+pub(crate) async fn kms_verify_async(
+    kms_rest_client: &KmsClient,
+    remote_id: &str,
+    algorithm: &SignatureAlgorithm,
+    data: &[u8],
+    signature: &[u8],
+) -> Pkcs11Result<bool> {
+    if let SignatureAlgorithm::Hmac { digest } = algorithm {
+        let mac = kms_mac_async(kms_rest_client, remote_id, digest, data).await?;
+        return Ok(mac.len() == signature.len() && openssl::memcmp::eq(&mac, signature));
+    }
+    let (cryptographic_parameters, digested) = signature_cryptographic_parameters(algorithm)?;
+    let (data, digested_data) = if digested {
+        (None, Some(data.to_vec()))
+    } else {
+        (Some(data.to_vec()), None)
+    };
+    let verify_request = SignatureVerify {
+        unique_identifier: Some(UniqueIdentifier::TextString(remote_id.to_owned())),
+        cryptographic_parameters: Some(cryptographic_parameters),
+        data,
+        digested_data,
+        signature_data: Some(signature.to_vec()),
+        ..Default::default()
+    };
+    let response = kms_rest_client.signature_verify(verify_request).await?;
+    Ok(response.validity_indicator == Some(ValidityIndicator::Valid))
+}
+
+/// Computes the HMAC of the data with the KMS generic secret.
+async fn kms_mac_async(
+    kms_rest_client: &KmsClient,
+    remote_id: &str,
+    digest: &DigestType,
+    data: &[u8],
+) -> Pkcs11Result<Vec<u8>> {
+    let mac_request = Mac {
+        unique_identifier: Some(UniqueIdentifier::TextString(remote_id.to_owned())),
+        cryptographic_parameters: Some(CryptographicParameters {
+            hashing_algorithm: Some(hashing_algorithm_from_digest_type(digest)),
+            ..Default::default()
+        }),
+        data: Some(data.to_vec()),
+        ..Default::default()
+    };
+    let response = kms_rest_client.mac(mac_request).await?;
+    response.mac_data.ok_or_else(|| {
+        Pkcs11Error::ServerError("MAC response does not contain MAC data".to_owned())
+    })
+}
+
+/// Exports the key wrapped by the KMS with the wrapping key and returns the wrapped bytes.
+pub(crate) async fn kms_wrap_key_async(
+    kms_rest_client: &KmsClient,
+    wrapping_key_id: &str,
+    key_id: &str,
+    algorithm: &KeyWrappingAlgorithm,
+) -> Pkcs11Result<Vec<u8>> {
+    let (_, object, _) = export_object(
+        kms_rest_client,
+        key_id,
+        ExportObjectParams {
+            wrapping_key_id: Some(wrapping_key_id),
+            wrapping_cryptographic_parameters: Some(key_wrapping_cryptographic_parameters(
+                algorithm,
+            )),
+            ..Default::default()
+        },
+    )
+    .await?;
+    match &object.key_block()?.key_value {
+        Some(KeyValue::ByteString(wrapped_key)) => Ok(wrapped_key.to_vec()),
+        _ => Err(Pkcs11Error::ServerError(format!(
+            "Export response does not contain the wrapped key: {key_id}"
+        ))),
+    }
+}
+
+/// Imports the wrapped AES key and lets the KMS unwrap it with the unwrapping key.
+/// Returns the unique identifier of the unwrapped key.
+pub(crate) async fn kms_unwrap_key_async(
+    kms_rest_client: &KmsClient,
+    unwrapping_key_id: &str,
+    algorithm: &KeyWrappingAlgorithm,
+    wrapped_key: &[u8],
+    tags: &[String],
+) -> Pkcs11Result<String> {
+    let mut attributes = Attributes {
+        cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+        object_type: Some(ObjectType::SymmetricKey),
+        ..Attributes::default()
+    };
+    attributes.set_tags(tags)?;
+    let object = Object::SymmetricKey(SymmetricKey {
+        key_block: KeyBlock {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+            key_format_type: KeyFormatType::Raw,
+            key_compression_type: None,
+            key_value: Some(KeyValue::ByteString(Zeroizing::new(wrapped_key.to_vec()))),
+            cryptographic_length: None,
+            key_wrapping_data: Some(KeyWrappingData {
+                wrapping_method: WrappingMethod::Encrypt,
+                encryption_key_information: Some(EncryptionKeyInformation {
+                    unique_identifier: UniqueIdentifier::TextString(unwrapping_key_id.to_owned()),
+                    cryptographic_parameters: Some(key_wrapping_cryptographic_parameters(
+                        algorithm,
+                    )),
+                }),
+                encoding_option: Some(EncodingOption::NoEncoding),
+                ..Default::default()
+            }),
+        },
+    });
+    let response = kms_rest_client
+        .import(Import {
+            unique_identifier: UniqueIdentifier::default(),
+            object_type: ObjectType::SymmetricKey,
+            replace_existing: Some(false),
+            key_wrap_type: Some(KeyWrapType::NotWrapped),
+            attributes,
+            object,
+        })
+        .await?;
+    Ok(response.unique_identifier.to_string())
+}
+
+/// Derives a new `key_algorithm` key of `key_length` bytes from the KMS symmetric key
+/// or secret data, using KMIP `DeriveKey`.
+/// Returns the unique identifier of the derived key.
+#[expect(clippy::too_many_arguments)]
+pub(crate) async fn kms_derive_key_async(
+    kms_rest_client: &KmsClient,
+    base_key_id: &str,
+    algorithm: &KeyDerivationAlgorithm,
+    key_algorithm: KeyAlgorithm,
+    key_length: usize,
+    sensitive: bool,
+    extractable: bool,
+    tags: &[String],
+) -> Pkcs11Result<String> {
+    let (derivation_method, derivation_parameters) = match algorithm {
+        KeyDerivationAlgorithm::Hkdf { digest, salt, info } => (
+            DerivationMethod::HKDF,
+            DerivationParameters {
+                cryptographic_parameters: Some(CryptographicParameters {
+                    hashing_algorithm: Some(hashing_algorithm_from_digest_type(digest)),
+                    ..Default::default()
+                }),
+                salt: Some(salt.clone()),
+                derivation_data: Some(info.clone()),
+                ..Default::default()
+            },
+        ),
+        KeyDerivationAlgorithm::Pbkdf2 {
+            digest,
+            salt,
+            iterations,
+        } => (
+            DerivationMethod::PBKDF2,
+            DerivationParameters {
+                cryptographic_parameters: Some(CryptographicParameters {
+                    hashing_algorithm: Some(hashing_algorithm_from_digest_type(digest)),
+                    ..Default::default()
+                }),
+                salt: Some(salt.clone()),
+                iteration_count: Some(i32::try_from(*iterations)?),
+                ..Default::default()
+            },
+        ),
+        KeyDerivationAlgorithm::Ecdh { .. } => {
+            return Err(Pkcs11Error::NotSupported(
+                "ECDH is not a KMIP key derivation method".to_owned(),
+            ));
+        }
+    };
+    let (cryptographic_algorithm, cryptographic_usage_mask) =
+        symmetric_key_algorithm_and_usage(key_algorithm, key_length)?;
+    let mut attributes = Attributes {
+        cryptographic_algorithm: Some(cryptographic_algorithm),
+        cryptographic_length: Some(i32::try_from(key_length * 8)?),
+        cryptographic_usage_mask: Some(cryptographic_usage_mask),
+        object_type: Some(ObjectType::SymmetricKey),
+        sensitive: if sensitive { Some(true) } else { None },
+        extractable: Some(extractable),
+        ..Attributes::default()
+    };
+    attributes.set_tags(tags)?;
+    let response = kms_rest_client
+        .derive_key(DeriveKey {
+            object_type: ObjectType::SymmetricKey,
+            object_unique_identifier: UniqueIdentifier::TextString(base_key_id.to_owned()),
+            derivation_method,
+            derivation_parameters,
+            attributes,
+        })
+        .await?;
+    Ok(response.unique_identifier.to_string())
+}
+
+/// Maps the key wrapping algorithm to the KMIP cryptographic parameters.
+fn key_wrapping_cryptographic_parameters(
+    algorithm: &KeyWrappingAlgorithm,
+) -> CryptographicParameters {
+    match algorithm {
+        KeyWrappingAlgorithm::AesKeyWrap => CryptographicParameters {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+            block_cipher_mode: Some(BlockCipherMode::NISTKeyWrap),
+            ..Default::default()
+        },
+        KeyWrappingAlgorithm::AesKeyWrapPad => CryptographicParameters {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+            block_cipher_mode: Some(BlockCipherMode::AESKeyWrapPadding),
+            ..Default::default()
+        },
+        KeyWrappingAlgorithm::RsaOaep {
+            digest,
+            mask_generation_function,
+        } => CryptographicParameters {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
+            padding_method: Some(PaddingMethod::OAEP),
+            hashing_algorithm: Some(hashing_algorithm_from_digest_type(digest)),
+            mask_generator: Some(MaskGenerator::MGF1),
+            mask_generator_hashing_algorithm: Some(hashing_algorithm_from_digest_type(
+                mask_generation_function,
+            )),
+            ..Default::default()
+        },
+    }
+}
+
+/// Maps the signature algorithm to the KMIP cryptographic parameters.
+/// The boolean is `true` when the mechanism expects already digested data.
+fn signature_cryptographic_parameters(
+    algorithm: &SignatureAlgorithm,
+) -> Pkcs11Result<(CryptographicParameters, bool)> {
+    Ok(match algorithm {
+        SignatureAlgorithm::Ecdsa => (
+            CryptographicParameters {
+                cryptographic_algorithm: Some(CryptographicAlgorithm::ECDSA),
+                ..Default::default()
+            },
+            true,
+        ),
+        // The KMS signs with the curve of the key, Ed25519 or Ed448
+        SignatureAlgorithm::EdDsa => (CryptographicParameters::default(), false),
+        // HMAC signatures are dispatched to the KMS MAC operation
+        SignatureAlgorithm::Hmac { .. } => {
+            return Err(Pkcs11Error::Default(
+                "HMAC is not a KMS signature algorithm".to_owned(),
+            ));
+        }
+        SignatureAlgorithm::RsaRaw => (
+            CryptographicParameters {
+                cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
+                padding_method: Some(PaddingMethod::None),
+                ..Default::default()
+            },
+            true,
+        ),
+        SignatureAlgorithm::RsaPkcs1v15Raw => (
+            CryptographicParameters {
+                cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
+                padding_method: Some(PaddingMethod::PKCS1v15),
+                ..Default::default()
+            },
+            true,
+        ),
+        SignatureAlgorithm::RsaPkcs1v15Sha1 => {
+            (rsa_pkcs1v15_parameters(HashingAlgorithm::SHA1), false)
+        }
+        SignatureAlgorithm::RsaPkcs1v15Sha256 => {
+            (rsa_pkcs1v15_parameters(HashingAlgorithm::SHA256), false)
+        }
+        SignatureAlgorithm::RsaPkcs1v15Sha384 => {
+            (rsa_pkcs1v15_parameters(HashingAlgorithm::SHA384), false)
+        }
+        SignatureAlgorithm::RsaPkcs1v15Sha512 => {
+            (rsa_pkcs1v15_parameters(HashingAlgorithm::SHA512), false)
+        }
+        SignatureAlgorithm::RsaPss {
+            digest,
+            mask_generation_function,
+            salt_length,
+        } => (
+            CryptographicParameters {
+                cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
+                padding_method: Some(PaddingMethod::PSS),
+                hashing_algorithm: Some(hashing_algorithm_from_digest_type(digest)),
+                mask_generator: Some(MaskGenerator::MGF1),
+                mask_generator_hashing_algorithm: Some(hashing_algorithm_from_digest_type(
+                    mask_generation_function,
+                )),
+                salt_length: Some(i32::try_from(*salt_length)?),
+                ..Default::default()
+            },
+            true,
+        ),
+    })
+}
+
+fn rsa_pkcs1v15_parameters(hashing_algorithm: HashingAlgorithm) -> CryptographicParameters {
+    CryptographicParameters {
+        cryptographic_algorithm: Some(CryptographicAlgorithm::RSA),
+        padding_method: Some(PaddingMethod::PKCS1v15),
+        hashing_algorithm: Some(hashing_algorithm),
+        ..Default::default()
+    }
+}
+
+const fn hashing_algorithm_from_digest_type(digest_type: &DigestType) -> HashingAlgorithm {
+    match digest_type {
+        DigestType::Sha1 => HashingAlgorithm::SHA1,
+        DigestType::Sha224 => HashingAlgorithm::SHA224,
+        DigestType::Sha256 => HashingAlgorithm::SHA256,
+        DigestType::Sha384 => HashingAlgorithm::SHA384,
+        DigestType::Sha512 => HashingAlgorithm::SHA512,
+    }
 }
 
 pub(crate) async fn get_kms_object_attributes_async(
@@ -514,8 +1689,24 @@ pub(crate) fn key_algorithm_from_attributes(attributes: &Attributes) -> Pkcs11Re
     let algorithm = match attributes.cryptographic_algorithm.ok_or_else(|| {
         Pkcs11Error::Default("missing cryptographic algorithm in attributes".to_owned())
     })? {
-        CryptographicAlgorithm::AES => KeyAlgorithm::Aes256,
+        CryptographicAlgorithm::AES => match attributes.cryptographic_length {
+            Some(128) => KeyAlgorithm::Aes128,
+            Some(192) => KeyAlgorithm::Aes192,
+            Some(256) => KeyAlgorithm::Aes256,
+            length => {
+                return Err(Pkcs11Error::Default(format!(
+                    "unsupported AES key length: {length:?}"
+                )));
+            }
+        },
+        CryptographicAlgorithm::HMACSHA1
+        | CryptographicAlgorithm::HMACSHA224
+        | CryptographicAlgorithm::HMACSHA256
+        | CryptographicAlgorithm::HMACSHA384
+        | CryptographicAlgorithm::HMACSHA512 => KeyAlgorithm::GenericSecret,
         CryptographicAlgorithm::RSA => KeyAlgorithm::Rsa,
+        CryptographicAlgorithm::Ed25519 => KeyAlgorithm::Ed25519,
+        CryptographicAlgorithm::Ed448 => KeyAlgorithm::Ed448,
         CryptographicAlgorithm::ECDH | CryptographicAlgorithm::EC => {
             let curve = attributes
                 .cryptographic_domain_parameters
@@ -554,3 +1745,22 @@ pub(crate) fn key_algorithm_from_attributes(attributes: &Attributes) -> Pkcs11Re
     };
     Ok(algorithm)
 }
+
+fn recommended_curve_from_key_algorithm(algorithm: KeyAlgorithm) -> Pkcs11Result<RecommendedCurve> {
+    Ok(match algorithm {
+        KeyAlgorithm::EccP256 => RecommendedCurve::P256,
+        KeyAlgorithm::EccP384 => RecommendedCurve::P384,
+        KeyAlgorithm::EccP521 => RecommendedCurve::P521,
+        KeyAlgorithm::X448 => RecommendedCurve::CURVE448,
+        KeyAlgorithm::Ed448 => RecommendedCurve::CURVEED448,
+        KeyAlgorithm::X25519 => RecommendedCurve::CURVE25519,
+        KeyAlgorithm::Ed25519 => RecommendedCurve::CURVEED25519,
+        KeyAlgorithm::Secp224k1 => RecommendedCurve::SECP224K1,
+        KeyAlgorithm::Secp256k1 => RecommendedCurve::SECP256K1,
+        x => {
+            return Err(Pkcs11Error::Default(format!(
+                "unsupported key algorithm for an EC key pair: {x:?}"
+            )));
+        }
+    })
+}
@@ -48,38 +48,91 @@
 
 use std::{ptr::addr_of_mut, str::FromStr};
 
+use cosmian_cli::{
+    config::Pkcs11Config, reexport::cosmian_kms_cli::reexport::cosmian_kms_client::KmsClientConfig,
+};
 use cosmian_logger::reexport::tracing::Level;
-use cosmian_pkcs11_module::{pkcs11::FUNC_LIST, traits::register_backend};
-use pkcs11_sys::{CK_FUNCTION_LIST_PTR_PTR, CK_RV, CKR_OK};
+use cosmian_pkcs11_module::{
+    ModuleResult,
+    pkcs11::{FUNC_LIST, SLOT_ID},
+    traits::{Backend, register_backend},
+};
+use pkcs11_sys::{CK_FUNCTION_LIST_PTR_PTR, CK_RV, CK_SLOT_ID, CKR_OK};
 
-use crate::{kms_object::get_kms_client, logging::initialize_logging};
+use crate::{kms_object::load_client_config, logging::initialize_logging};
 
 mod backend;
 mod error;
 mod kms_object;
 mod logging;
+mod pin;
 mod pkcs11_certificate;
 mod pkcs11_data_object;
 mod pkcs11_private_key;
 mod pkcs11_public_key;
 mod pkcs11_symmetric_key;
 
+/// Instantiate the backends of the slots with KMS clients using the `cosmian.toml` file
+/// in the local default directory; called by `C_Initialize`.
+///
+/// The first slot uses the KMS and the `pkcs11_config` section of the configuration,
+/// the following ones the `pkcs11_config.slots` entries.
+/// A configuration which cannot be loaded is reported as `CKR_GENERAL_ERROR`,
+/// a KMS client which cannot be built as `CKR_DEVICE_ERROR`.
+fn instantiate_backends() -> ModuleResult<Vec<Box<dyn Backend>>> {
+    let config = load_client_config()?;
+    let mut pkcs11_config = config.pkcs11_config.unwrap_or_default();
+    let slots = std::mem::take(&mut pkcs11_config.slots);
+    let mut backends = vec![instantiate_backend(
+        SLOT_ID,
+        config.kms_config.clone(),
+        pkcs11_config,
+    )?];
+    for (slot_id, slot) in (SLOT_ID + 1..).zip(slots) {
+        backends.push(instantiate_backend(
+            slot_id,
+            slot.kms_config.unwrap_or_else(|| config.kms_config.clone()),
+            slot.pkcs11_config,
+        )?);
+    }
+    Ok(backends)
+}
+
+fn instantiate_backend(
+    slot_id: CK_SLOT_ID,
+    kms_config: KmsClientConfig,
+    pkcs11_config: Pkcs11Config,
+) -> ModuleResult<Box<dyn Backend>> {
+    Ok(Box::new(backend::CliBackend::instantiate(
+        slot_id,
+        kms_config,
+        pkcs11_config,
+    )?))
+}
+
 /// # Safety
 /// This function is the first one called by the PKCS#11 library client
 /// to get the PKCS#11 functions list.
-/// # Panics
-/// When KMS client cannot be instantiated.
 #[unsafe(no_mangle)]
-#[expect(clippy::expect_used, unsafe_code)]
+#[expect(unsafe_code)]
 pub unsafe extern "C" fn C_GetFunctionList(pp_function_list: CK_FUNCTION_LIST_PTR_PTR) -> CK_RV {
-    let debug_level =
-        std::env::var("COSMIAN_PKCS11_LOGGING_LEVEL").unwrap_or_else(|_| "info".to_owned());
-    initialize_logging("cosmian-pkcs11", Level::from_str(&debug_level).ok(), None);
-    // Instantiate a backend with a kms client using the `cosmian.toml` file in the local default directory.
-    register_backend(Box::new(backend::CliBackend::instantiate(
-        get_kms_client()
-            .expect("failed instantiating the KMS client from the current configuration"),
-    )));
+    // The logging settings of the configuration file, if it can already be loaded
+    let pkcs11_config = load_client_config()
+        .ok()
+        .and_then(|config| config.pkcs11_config)
+        .unwrap_or_default();
+    let debug_level = std::env::var("COSMIAN_PKCS11_LOGGING_LEVEL")
+        .ok()
+        .or(pkcs11_config.logging_level)
+        .unwrap_or_else(|| "info".to_owned());
+    initialize_logging(
+        "cosmian-pkcs11",
+        Level::from_str(&debug_level).ok(),
+        pkcs11_config.logging_folder,
+    );
+    // The backend is only built by `C_Initialize`, so that a missing or broken
+    // configuration is returned as an error code instead of panicking.
+    register_backend(instantiate_backends);
     unsafe {
         // Update the function list with this PKCS#11 entry function
         FUNC_LIST.C_GetFunctionList = Some(C_GetFunctionList);
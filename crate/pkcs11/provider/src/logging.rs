@@ -34,15 +34,17 @@ fn init(
 
 #[cfg(target_os = "linux")]
 #[allow(clippy::print_stdout)]
-/// For Linux, log to /var/log
+/// For Linux, log to /var/log unless another folder is configured
 fn init(
     log_name: &str,
     level: Option<Level>,
-    _log_home: Option<String>,
+    log_home: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let level = level.unwrap_or(Level::INFO);
-    let log_folder =
-        std::env::var("COSMIAN_PKCS11_LOGGING_FOLDER").unwrap_or_else(|_| "/var/log".to_owned());
+    let log_folder = std::env::var("COSMIAN_PKCS11_LOGGING_FOLDER")
+        .ok()
+        .or(log_home)
+        .unwrap_or_else(|| "/var/log".to_owned());
 
     println!("cosmian-pkcs11 module logging at {level} level to file {log_folder}/{log_name}.log");
     log_to_file(log_name, level, &PathBuf::from(log_folder))
@@ -0,0 +1,79 @@
+//! The credentials of a token unlocked by the user PIN.
+//!
+//! A credential is protected with the PIN by
+//! `openssl enc -aes-256-cbc -pbkdf2 -iter 600000 -md sha256 -a -A -pass pass:<PIN>`,
+//! so that no dedicated tool is needed to write the configuration.
+
+use cosmian_logger::debug;
+use cosmian_pkcs11_module::{ModuleError, ModuleResult};
+use openssl::{
+    base64,
+    hash::MessageDigest,
+    pkcs5::pbkdf2_hmac,
+    pkcs12::Pkcs12,
+    symm::{Cipher, decrypt},
+};
+use zeroize::Zeroizing;
+
+/// The iterations of the `-iter` option of `openssl enc`
+const PBKDF2_ITERATIONS: usize = 600_000;
+/// The header of the salted output of `openssl enc`
+const SALTED_HEADER: &[u8] = b"Salted__";
+const SALT_LENGTH: usize = 8;
+const KEY_LENGTH: usize = 32;
+const IV_LENGTH: usize = 16;
+
+/// Decrypt a credential of the configuration protected with the PIN.
+/// A PIN which does not decrypt it is reported as `ModuleError::PinIncorrect`.
+pub(crate) fn decrypt_with_pin(protected: &str, pin: &[u8]) -> ModuleResult<String> {
+    let protected = base64::decode_block(protected.trim()).map_err(|e| {
+        ModuleError::Default(format!("the PIN protected credential is not base64: {e}"))
+    })?;
+    let (salt, ciphertext) = protected
+        .strip_prefix(SALTED_HEADER)
+        .and_then(|salted| salted.split_at_checked(SALT_LENGTH))
+        .ok_or_else(|| {
+            ModuleError::Default("the PIN protected credential is not salted".to_owned())
+        })?;
+    let mut key_iv = Zeroizing::new([0_u8; KEY_LENGTH + IV_LENGTH]);
+    pbkdf2_hmac(
+        pin,
+        salt,
+        PBKDF2_ITERATIONS,
+        MessageDigest::sha256(),
+        key_iv.as_mut_slice(),
+    )
+    .map_err(|e| ModuleError::Cryptography(format!("PIN key derivation failed: {e}")))?;
+    let (key, iv) = key_iv.split_at(KEY_LENGTH);
+    let plaintext = Zeroizing::new(
+        decrypt(Cipher::aes_256_cbc(), key, Some(iv), ciphertext).map_err(|e| {
+            debug!("decrypt_with_pin: the PIN does not decrypt the credential: {e}");
+            ModuleError::PinIncorrect
+        })?,
+    );
+    String::from_utf8(plaintext.to_vec()).map_err(|e| {
+        debug!("decrypt_with_pin: the decrypted credential is not valid UTF-8: {e}");
+        ModuleError::PinIncorrect
+    })
+}
+
+/// Check that `password` opens the client PKCS#12 of the KMS configuration.
+/// A wrong password is reported as `ModuleError::PinIncorrect`.
+pub(crate) fn check_pkcs12_password(path: Option<&str>, password: &str) -> ModuleResult<()> {
+    let path = path.ok_or_else(|| {
+        ModuleError::Default(
+            "a PIN is required but the KMS configuration has no client PKCS#12 and no PIN \
+             protected credential"
+                .to_owned(),
+        )
+    })?;
+    let der = std::fs::read(path)
+        .map_err(|e| ModuleError::Default(format!("cannot read the client PKCS#12 {path}: {e}")))?;
+    Pkcs12::from_der(&der)
+        .and_then(|pkcs12| pkcs12.parse2(password))
+        .map_err(|e| {
+            debug!("check_pkcs12_password: the PIN does not open the client PKCS#12: {e}");
+            ModuleError::PinIncorrect
+        })?;
+    Ok(())
+}
@@ -1,31 +1,80 @@
+use std::sync::OnceLock;
+
 use cosmian_cli::reexport::cosmian_kms_cli::reexport::cosmian_kmip::{
     kmip_0::kmip_types::CertificateType,
-    kmip_2_1::{self, kmip_objects::Object, kmip_types::LinkType},
+    kmip_2_1::{self, kmip_attributes::Attributes, kmip_objects::Object, kmip_types::LinkType},
+};
+use cosmian_pkcs11_module::{
+    ModuleResult,
+    traits::{Certificate, KeyMetadata, PublicKey, SearchOptions, backend_for},
 };
-use cosmian_pkcs11_module::traits::{Certificate, PublicKey};
+use pkcs11_sys::CK_SLOT_ID;
 use x509_cert::{
     Certificate as X509Certificate,
     der::{Decode, Encode},
 };
 
-use crate::{error::Pkcs11Error, kms_object::KmsObject, pkcs11_public_key::Pkcs11PublicKey};
+use crate::{
+    error::Pkcs11Error,
+    kms_object::{KmsObject, metadata_from_attributes},
+    pkcs11_error,
+    pkcs11_public_key::Pkcs11PublicKey,
+};
 
 /// A PKCS11 Certificate is a Certificate that wraps data from a KMS object
 #[derive(Debug)]
 pub(crate) struct Pkcs11Certificate {
+    /// The slot of the backend which holds the certificate
+    slot_id: CK_SLOT_ID,
     /// The remote id
     pub remote_id: String,
-    /// The certificate
-    pub certificate: X509Certificate,
+    /// The certificate - it is lazy loaded
+    /// when the certificate content is used
+    certificate: OnceLock<X509Certificate>,
     /// The private key ID
-    /// This is the `CKA_ID` of the private key associated with the certificate
+    /// This is the `CKA_ID` of the private key associated with the certificate,
+    /// empty when the certificate has none
     pub private_key_id: String,
+    /// The PKCS#11 attributes held in the KMIP attributes
+    metadata: KeyMetadata,
 }
 
-impl TryFrom<KmsObject> for Pkcs11Certificate {
-    type Error = Pkcs11Error;
+impl Pkcs11Certificate {
+    /// Build a certificate from its KMS attributes only.
+    /// The certificate itself is fetched from the KMS when first used.
+    pub(crate) fn from_attributes(
+        slot_id: CK_SLOT_ID,
+        remote_id: String,
+        attributes: &Attributes,
+    ) -> Self {
+        Self {
+            slot_id,
+            remote_id,
+            certificate: OnceLock::new(),
+            private_key_id: private_key_id(attributes),
+            metadata: metadata_from_attributes(attributes),
+        }
+    }
+
+    fn certificate(&self) -> ModuleResult<&X509Certificate> {
+        if let Some(certificate) = self.certificate.get() {
+            return Ok(certificate);
+        }
+        let der_bytes = backend_for(self.slot_id)?
+            .find_certificate(SearchOptions::Id(self.remote_id.clone().into_bytes()))?
+            .ok_or_else(|| pkcs11_error!("certificate {} not found", self.remote_id))?
+            .to_der()?;
+        let certificate = X509Certificate::from_der(&der_bytes).map_err(|e| {
+            Pkcs11Error::ServerError(format!("Invalid X509 Certificate DER bytes: {e:?}"))
+        })?;
+        Ok(self.certificate.get_or_init(|| certificate))
+    }
 
-    fn try_from(kms_object: KmsObject) -> Result<Self, Self::Error> {
+    /// Build a certificate from the KMS object holding it
+    pub(crate) fn try_from_kms_object(
+        slot_id: CK_SLOT_ID,
+        kms_object: KmsObject,
+    ) -> Result<Self, Pkcs11Error> {
         match kms_object.object {
             Object::Certificate(kmip_2_1::kmip_objects::Certificate {
                 certificate_type,
@@ -33,22 +82,17 @@ impl TryFrom<KmsObject> for Pkcs11Certificate {
                 ..
             }) => match certificate_type {
                 CertificateType::X509 => Ok(Self {
-                    certificate: X509Certificate::from_der(&certificate_value).map_err(|e| {
-                        Pkcs11Error::ServerError(format!(
-                            "Invalid X509 Certificate DER bytes: {e:?}"
-                        ))
-                    })?,
-                    remote_id: kms_object.remote_id.clone(),
-                    private_key_id: kms_object
-                        .attributes
-                        .get_link(LinkType::PrivateKeyLink)
-                        .ok_or_else(|| {
+                    slot_id,
+                    certificate: OnceLock::from(
+                        X509Certificate::from_der(&certificate_value).map_err(|e| {
                             Pkcs11Error::ServerError(format!(
-                                "No private key link found for certificate: {:?}",
-                                kms_object.remote_id
+                                "Invalid X509 Certificate DER bytes: {e:?}"
                             ))
-                        })?
-                        .to_string(),
+                        })?,
+                    ),
+                    private_key_id: private_key_id(&kms_object.attributes),
+                    metadata: metadata_from_attributes(&kms_object.attributes),
+                    remote_id: kms_object.remote_id,
                 }),
                 _ => Err(Pkcs11Error::ServerError(format!(
                     "Invalid Certificate Type: {certificate_type:?}"
@@ -61,42 +105,56 @@ impl TryFrom<KmsObject> for Pkcs11Certificate {
     }
 }
 
+/// The id of the private key a certificate is linked to; its `CKA_ID`
+/// unless it was imported with one, held in its metadata
+fn private_key_id(attributes: &Attributes) -> String {
+    attributes
+        .get_link(LinkType::PrivateKeyLink)
+        .map(|link| link.to_string())
+        .unwrap_or_default()
+}
+
 impl Certificate for Pkcs11Certificate {
     fn remote_id(&self) -> String {
         self.remote_id.clone()
     }
 
-    fn to_der(&self) -> cosmian_pkcs11_module::ModuleResult<Vec<u8>> {
-        self.certificate
+    fn to_der(&self) -> ModuleResult<Vec<u8>> {
+        self.certificate()?
             .to_der()
             .map_err(|e| Pkcs11Error::from(e).into())
     }
 
-    fn public_key(&self) -> cosmian_pkcs11_module::ModuleResult<Box<dyn PublicKey>> {
+    fn public_key(&self) -> ModuleResult<Box<dyn PublicKey>> {
         let res: Box<dyn PublicKey> = Pkcs11PublicKey::try_from_spki(
-            &self.certificate.tbs_certificate.subject_public_key_info,
+            self.slot_id,
+            &self.certificate()?.tbs_certificate.subject_public_key_info,
         )
         .map_err(Pkcs11Error::from)
         .map(Box::new)?;
         Ok(res)
     }
 
-    fn issuer(&self) -> cosmian_pkcs11_module::ModuleResult<Vec<u8>> {
-        Encode::to_der(&self.certificate.tbs_certificate.issuer)
+    fn issuer(&self) -> ModuleResult<Vec<u8>> {
+        Encode::to_der(&self.certificate()?.tbs_certificate.issuer)
             .map_err(|e| Pkcs11Error::from(e).into())
     }
 
-    fn serial_number(&self) -> cosmian_pkcs11_module::ModuleResult<Vec<u8>> {
-        Encode::to_der(&self.certificate.tbs_certificate.serial_number)
+    fn serial_number(&self) -> ModuleResult<Vec<u8>> {
+        Encode::to_der(&self.certificate()?.tbs_certificate.serial_number)
             .map_err(|e| Pkcs11Error::from(e).into())
     }
 
-    fn subject(&self) -> cosmian_pkcs11_module::ModuleResult<Vec<u8>> {
-        Encode::to_der(&self.certificate.tbs_certificate.subject)
+    fn subject(&self) -> ModuleResult<Vec<u8>> {
+        Encode::to_der(&self.certificate()?.tbs_certificate.subject)
             .map_err(|e| Pkcs11Error::from(e).into())
     }
 
     fn private_key_id(&self) -> String {
         self.private_key_id.clone()
     }
+
+    fn metadata(&self) -> KeyMetadata {
+        self.metadata.clone()
+    }
 }
@@ -1,31 +1,41 @@
-use cosmian_cli::reexport::cosmian_kms_cli::reexport::cosmian_kmip::kmip_2_1::kmip_objects::Object;
-use cosmian_pkcs11_module::{ModuleError, ModuleResult, traits::DataObject};
+use std::sync::OnceLock;
+
+use cosmian_cli::reexport::cosmian_kms_cli::reexport::cosmian_kmip::kmip_2_1::{
+    kmip_attributes::Attributes, kmip_objects::Object,
+};
+use cosmian_pkcs11_module::{
+    ModuleError, ModuleResult,
+    traits::{DataObject, KeyMetadata, SearchOptions, backend_for},
+};
+use pkcs11_sys::CK_SLOT_ID;
 use sha3::Digest;
 use zeroize::{Zeroize, Zeroizing};
 
-use crate::{error::Pkcs11Error, kms_object::KmsObject, pkcs11_error};
+use crate::{
+    kms_object::{KmsObject, application_from_attributes, metadata_from_attributes},
+    pkcs11_error,
+};
 
 /// A PKCS11 data object is a `DataObject` that wraps data from a KMS object
 #[derive(Debug)]
 pub(crate) struct Pkcs11DataObject {
+    /// The slot of the backend which holds the object
+    slot_id: CK_SLOT_ID,
     remote_id: String,
-    value: Zeroizing<Vec<u8>>,
-}
-
-impl TryFrom<KmsObject> for Pkcs11DataObject {
-    type Error = Pkcs11Error;
-
-    fn try_from(kms_object: KmsObject) -> Result<Self, Self::Error> {
-        Ok(Self {
-            remote_id: kms_object.remote_id.clone(),
-            value: kms_object.object.key_block()?.key_bytes()?,
-        })
-    }
+    /// Value of the data object - it is lazy loaded
+    /// when the value is requested
+    value: OnceLock<Zeroizing<Vec<u8>>>,
+    /// The `CKA_APPLICATION` set on the KMS object, if any
+    application: Option<Vec<u8>>,
+    /// The PKCS#11 attributes held in the KMIP attributes
+    metadata: KeyMetadata,
 }
 
 impl Zeroize for Pkcs11DataObject {
     fn zeroize(&mut self) {
-        self.value.zeroize();
+        if let Some(value) = self.value.get_mut() {
+            value.zeroize();
+        }
     }
 }
 
@@ -34,34 +44,72 @@ impl DataObject for Pkcs11DataObject {
         self.remote_id.clone()
     }
 
-    fn value(&self) -> Zeroizing<Vec<u8>> {
-        self.value.clone()
+    fn value(&self) -> ModuleResult<Zeroizing<Vec<u8>>> {
+        if let Some(value) = self.value.get() {
+            return Ok(value.clone());
+        }
+        let data_object = backend_for(self.slot_id)?
+            .find_data_object(SearchOptions::Id(self.remote_id.clone().into_bytes()))?
+            .ok_or_else(|| {
+                ModuleError::Backend(Box::new(pkcs11_error!(
+                    "data object {} not found",
+                    self.remote_id
+                )))
+            })?;
+        let value = data_object.value()?;
+        Ok(self.value.get_or_init(|| value).clone())
     }
 
     fn application(&self) -> Vec<u8> {
-        b"Cosmian KMS PKCS11 provider".to_vec()
+        self.application
+            .clone()
+            .unwrap_or_else(|| b"Cosmian KMS PKCS11 provider".to_vec())
+    }
+
+    fn metadata(&self) -> KeyMetadata {
+        self.metadata.clone()
     }
 
-    fn data_hash(&self) -> Vec<u8> {
+    fn data_hash(&self) -> ModuleResult<Vec<u8>> {
         // This is a hash of key material which may be leaked by the application
         // We need pre-image and collision resistance.
         // => use a cryptographic SHA3-256 hash
         let mut hasher = sha3::Sha3_256::new();
-        hasher.update(self.value.as_slice());
+        hasher.update(self.value()?.as_slice());
         let result = hasher.finalize();
-        result.to_vec()
+        Ok(result.to_vec())
     }
 }
 
 impl Pkcs11DataObject {
-    pub(crate) fn new(remote_id: String) -> Self {
+    pub(crate) fn new(slot_id: CK_SLOT_ID, remote_id: String) -> Self {
         Self {
+            slot_id,
             remote_id,
-            value: Zeroizing::new(vec![]),
+            value: OnceLock::new(),
+            application: None,
+            metadata: KeyMetadata::default(),
+        }
+    }
+
+    /// Build a data object from its KMS attributes only.
+    /// The value is fetched from the KMS when first requested.
+    pub(crate) fn from_attributes(
+        slot_id: CK_SLOT_ID,
+        remote_id: String,
+        attributes: &Attributes,
+    ) -> Self {
+        Self {
+            application: application_from_attributes(attributes),
+            metadata: metadata_from_attributes(attributes),
+            ..Self::new(slot_id, remote_id)
         }
     }
 
-    pub(crate) fn try_from_kms_object(kms_object: KmsObject) -> ModuleResult<Self> {
+    pub(crate) fn try_from_kms_object(
+        slot_id: CK_SLOT_ID,
+        kms_object: KmsObject,
+    ) -> ModuleResult<Self> {
         let value = match kms_object.object {
             Object::SecretData(data_object) => {
                 let (value, _attrs) =
@@ -79,8 +127,11 @@ impl Pkcs11DataObject {
         }?;
 
         Ok(Self {
+            slot_id,
+            application: application_from_attributes(&kms_object.attributes),
+            metadata: metadata_from_attributes(&kms_object.attributes),
             remote_id: kms_object.remote_id,
-            value,
+            value: OnceLock::from(value),
         })
     }
 }
@@ -3,36 +3,52 @@ use std::sync::{Arc, RwLock};
 use cosmian_logger::error;
 use cosmian_pkcs11_module::{
     ModuleError, ModuleResult,
-    traits::{KeyAlgorithm, PrivateKey, SearchOptions, SignatureAlgorithm, backend},
+    traits::{KeyAlgorithm, KeyMetadata, PrivateKey, PublicKey, SearchOptions, backend_for},
 };
 use pkcs1::{RsaPrivateKey, der::Decode};
+use pkcs11_sys::CK_SLOT_ID;
 use zeroize::Zeroizing;
 
-use crate::kms_object::{KmsObject, key_algorithm_from_attributes};
+use crate::kms_object::{KmsObject, key_algorithm_from_attributes, metadata_from_attributes};
 
 /// A PKCS11 Private Key implementation that may only hold remote
 /// references to the actual private key
 #[derive(Debug)]
 pub(crate) struct Pkcs11PrivateKey {
+    /// The slot of the backend which holds the key
+    slot_id: CK_SLOT_ID,
     remote_id: String,
     algorithm: KeyAlgorithm,
     key_size: usize,
+    /// The PKCS#11 attributes held in the KMIP attributes
+    metadata: KeyMetadata,
     /// DER bytes of the private key - those are lazy loaded
     /// when the private key is used
     der_bytes: Arc<RwLock<Zeroizing<Vec<u8>>>>,
 }
 
 impl Pkcs11PrivateKey {
-    pub(crate) fn new(remote_id: String, algorithm: KeyAlgorithm, key_size: usize) -> Self {
+    pub(crate) fn new(
+        slot_id: CK_SLOT_ID,
+        remote_id: String,
+        algorithm: KeyAlgorithm,
+        key_size: usize,
+        metadata: KeyMetadata,
+    ) -> Self {
         Self {
+            slot_id,
             remote_id,
+            metadata,
             der_bytes: Arc::new(RwLock::new(Zeroizing::new(vec![]))),
             algorithm,
             key_size,
         }
     }
 
-    pub(crate) fn try_from_kms_object(kms_object: KmsObject) -> ModuleResult<Self> {
+    pub(crate) fn try_from_kms_object(
+        slot_id: CK_SLOT_ID,
+        kms_object: KmsObject,
+    ) -> ModuleResult<Self> {
         let der_bytes = Arc::new(RwLock::new(
             kms_object
                 .object
@@ -48,6 +64,8 @@ impl Pkcs11PrivateKey {
         let algorithm = key_algorithm_from_attributes(&kms_object.attributes)?;
 
         Ok(Self {
+            slot_id,
+            metadata: metadata_from_attributes(&kms_object.attributes),
             remote_id: kms_object.remote_id,
             algorithm,
             key_size,
@@ -61,14 +79,6 @@ impl PrivateKey for Pkcs11PrivateKey {
         self.remote_id.clone()
     }
 
-    fn sign(&self, _algorithm: &SignatureAlgorithm, _data: &[u8]) -> ModuleResult<Vec<u8>> {
-        error!(
-            "sign not implemented for Pkcs11PrivateKey with remote_id: {}",
-            self.remote_id
-        );
-        Err(ModuleError::FunctionNotSupported)
-    }
-
     fn algorithm(&self) -> KeyAlgorithm {
         self.algorithm
     }
@@ -77,6 +87,15 @@ impl PrivateKey for Pkcs11PrivateKey {
         self.key_size
     }
 
+    fn metadata(&self) -> KeyMetadata {
+        self.metadata.clone()
+    }
+
+    fn public_key(&self) -> ModuleResult<Arc<dyn PublicKey>> {
+        backend_for(self.slot_id)?
+            .find_public_key(SearchOptions::Id(self.remote_id.clone().into_bytes()))
+    }
+
     fn pkcs8_der_bytes(&self) -> ModuleResult<Zeroizing<Vec<u8>>> {
         let der_bytes = self
             .der_bytes
@@ -89,8 +108,8 @@ impl PrivateKey for Pkcs11PrivateKey {
         if !der_bytes.is_empty() {
             return Ok(der_bytes);
         }
-        let sk =
-            backend().find_private_key(SearchOptions::Id(self.remote_id.clone().into_bytes()))?;
+        let sk = backend_for(self.slot_id)?
+            .find_private_key(SearchOptions::Id(self.remote_id.clone().into_bytes()))?;
         let mut der_bytes = self.der_bytes.write().map_err(|e| {
             error!("Failed to write DER bytes: {:?}", e);
             ModuleError::Cryptography("Failed to write DER bytes".to_owned())
@@ -1,17 +1,21 @@
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use cosmian_logger::error;
 use cosmian_pkcs11_module::{
     ModuleError, ModuleResult,
-    traits::{KeyAlgorithm, PublicKey, SignatureAlgorithm},
+    traits::{KeyAlgorithm, KeyMetadata, PublicKey, SearchOptions, backend_for},
 };
-use p256::pkcs8::DecodePublicKey;
 use pkcs1::{RsaPublicKey, der::Decode};
+use pkcs11_sys::CK_SLOT_ID;
 use sha3::Digest;
 use x509_cert::{der::Encode, spki::SubjectPublicKeyInfoOwned};
 use zeroize::Zeroizing;
 
+use crate::kms_object::{KmsObject, metadata_from_attributes};
+
 pub(crate) struct Pkcs11PublicKey {
+    /// The slot of the backend which holds the key
+    slot_id: CK_SLOT_ID,
     remote_id: String,
     /// DER bytes of the public key
     der_bytes: Zeroizing<Vec<u8>>,
@@ -19,19 +23,55 @@ pub(crate) struct Pkcs11PublicKey {
     fingerprint: Vec<u8>,
     /// DER bytes of the algorithm OID
     algorithm: KeyAlgorithm,
+    /// The PKCS#11 attributes held in the KMIP attributes
+    metadata: KeyMetadata,
+    /// The public key exported from the KMS - it is lazy loaded when
+    /// the key material of a key built from its attributes is used
+    exported: OnceLock<Arc<dyn PublicKey>>,
 }
 
 impl Pkcs11PublicKey {
-    pub(crate) fn new(remote_id: String, algorithm: KeyAlgorithm) -> Self {
+    pub(crate) fn new(
+        slot_id: CK_SLOT_ID,
+        remote_id: String,
+        algorithm: KeyAlgorithm,
+        metadata: KeyMetadata,
+    ) -> Self {
         Self {
+            slot_id,
             remote_id,
             der_bytes: Zeroizing::new(vec![]),
             algorithm,
+            metadata,
             fingerprint: vec![],
+            exported: OnceLock::new(),
         }
     }
 
-    pub(crate) fn try_from_spki(spki: &SubjectPublicKeyInfoOwned) -> ModuleResult<Self> {
+    /// Build the public key from a KMS object exported in the PKCS#8 format,
+    /// which is the `SubjectPublicKeyInfo` DER encoding for public keys.
+    pub(crate) fn try_from_kms_object(
+        slot_id: CK_SLOT_ID,
+        kms_object: KmsObject,
+    ) -> ModuleResult<Self> {
+        let der_bytes = kms_object
+            .object
+            .key_block()
+            .map_err(|e| ModuleError::Cryptography(e.to_string()))?
+            .key_bytes()
+            .map_err(|e| ModuleError::Cryptography(e.to_string()))?;
+        let spki = SubjectPublicKeyInfoOwned::from_der(&der_bytes)?;
+        Ok(Self {
+            metadata: metadata_from_attributes(&kms_object.attributes),
+            remote_id: kms_object.remote_id,
+            ..Self::try_from_spki(slot_id, &spki)?
+        })
+    }
+
+    pub(crate) fn try_from_spki(
+        slot_id: CK_SLOT_ID,
+        spki: &SubjectPublicKeyInfoOwned,
+    ) -> ModuleResult<Self> {
         let algorithm = &spki.algorithm;
         let algorithm = KeyAlgorithm::from_oid(&algorithm.oid).ok_or_else(|| {
             ModuleError::BadArguments(format!("OID not found: {}", algorithm.oid))
@@ -39,12 +79,30 @@ impl Pkcs11PublicKey {
         let der_bytes = Zeroizing::new(spki.to_der()?);
         let fingerprint = sha3::Sha3_256::digest(&der_bytes).to_vec();
         Ok(Self {
+            slot_id,
             remote_id: String::new(),
             der_bytes,
             fingerprint,
             algorithm,
+            metadata: KeyMetadata::default(),
+            exported: OnceLock::new(),
         })
     }
+
+    /// Fetch the key material from the KMS
+    fn exported(&self) -> ModuleResult<&Arc<dyn PublicKey>> {
+        if let Some(public_key) = self.exported.get() {
+            return Ok(public_key);
+        }
+        if self.remote_id.is_empty() {
+            return Err(ModuleError::Cryptography(
+                "the public key has no key material and is not stored in the KMS".to_owned(),
+            ));
+        }
+        let public_key = backend_for(self.slot_id)?
+            .find_public_key(SearchOptions::Id(self.remote_id.clone().into_bytes()))?;
+        Ok(self.exported.get_or_init(|| public_key))
+    }
 }
 
 impl PublicKey for Pkcs11PublicKey {
@@ -52,18 +110,11 @@ impl PublicKey for Pkcs11PublicKey {
         self.remote_id.clone()
     }
 
-    fn fingerprint(&self) -> &[u8] {
-        &self.fingerprint
-    }
-
-    fn verify(
-        &self,
-        _algorithm: &SignatureAlgorithm,
-        _data: &[u8],
-        _signature: &[u8],
-    ) -> ModuleResult<()> {
-        error!("verify not implemented for Pkcs11PublicKey");
-        Err(ModuleError::FunctionNotSupported)
+    fn fingerprint(&self) -> ModuleResult<&[u8]> {
+        if self.der_bytes.is_empty() {
+            return self.exported()?.fingerprint();
+        }
+        Ok(&self.fingerprint)
     }
 
     fn delete(self: Arc<Self>) {}
@@ -72,7 +123,14 @@ impl PublicKey for Pkcs11PublicKey {
         self.algorithm
     }
 
+    fn metadata(&self) -> KeyMetadata {
+        self.metadata.clone()
+    }
+
     fn rsa_public_key(&self) -> ModuleResult<RsaPublicKey<'_>> {
+        if self.der_bytes.is_empty() {
+            return self.exported()?.rsa_public_key();
+        }
         if self.algorithm == KeyAlgorithm::Rsa {
             RsaPublicKey::from_der(&self.der_bytes).map_err(|e| {
                 error!("Failed to parse RSA public key: {:?}", e);
@@ -86,17 +144,18 @@ impl PublicKey for Pkcs11PublicKey {
         }
     }
 
-    fn ec_p256_public_key(&self) -> ModuleResult<p256::PublicKey> {
-        if self.algorithm == KeyAlgorithm::EccP256 {
-            let ec_p256 = p256::PublicKey::from_public_key_der(&self.der_bytes).map_err(|e| {
-                ModuleError::Cryptography(format!("Failed to parse EC P256 public key: {e:?}"))
-            })?;
-            Ok(ec_p256)
-        } else {
-            error!("Public key is not an EC P256 key");
-            Err(ModuleError::Cryptography(
-                "Public key is not an EC P256 key".to_owned(),
-            ))
+    fn ec_public_point(&self) -> ModuleResult<Vec<u8>> {
+        if self.der_bytes.is_empty() {
+            return self.exported()?.ec_public_point();
+        }
+        if !self.algorithm.is_ecc() {
+            error!("Public key is not an EC key");
+            return Err(ModuleError::Cryptography(
+                "Public key is not an EC key".to_owned(),
+            ));
         }
+        // The `subjectPublicKey` holds the SEC1 point or the raw Edwards/Montgomery key
+        let spki = SubjectPublicKeyInfoOwned::from_der(&self.der_bytes)?;
+        Ok(spki.subject_public_key.raw_bytes().to_vec())
     }
 }
@@ -3,35 +3,51 @@ use std::sync::{Arc, RwLock};
 use cosmian_logger::error;
 use cosmian_pkcs11_module::{
     ModuleError, ModuleResult,
-    traits::{KeyAlgorithm, SearchOptions, SymmetricKey, backend},
+    traits::{KeyAlgorithm, KeyMetadata, SearchOptions, SymmetricKey, backend_for},
 };
+use pkcs11_sys::CK_SLOT_ID;
 use zeroize::Zeroizing;
 
-use crate::kms_object::{KmsObject, key_algorithm_from_attributes};
+use crate::kms_object::{KmsObject, key_algorithm_from_attributes, metadata_from_attributes};
 
 /// A PKCS11 Symmetric Key implementation that may only hold remote
 /// references to the actual symmetric key
 #[derive(Debug)]
 pub(crate) struct Pkcs11SymmetricKey {
+    /// The slot of the backend which holds the key
+    slot_id: CK_SLOT_ID,
     remote_id: String,
     algorithm: KeyAlgorithm,
     key_size: usize,
+    /// The PKCS#11 attributes held in the KMIP attributes
+    metadata: KeyMetadata,
     /// Raw bytes of the symmetric key - those are lazy loaded
     /// when the symmetric key is used
     raw_bytes: Arc<RwLock<Zeroizing<Vec<u8>>>>,
 }
 
 impl Pkcs11SymmetricKey {
-    pub(crate) fn new(remote_id: String, algorithm: KeyAlgorithm, key_size: usize) -> Self {
+    pub(crate) fn new(
+        slot_id: CK_SLOT_ID,
+        remote_id: String,
+        algorithm: KeyAlgorithm,
+        key_size: usize,
+        metadata: KeyMetadata,
+    ) -> Self {
         Self {
+            slot_id,
             remote_id,
+            metadata,
             raw_bytes: Arc::new(RwLock::new(Zeroizing::new(vec![]))),
             algorithm,
             key_size,
         }
     }
 
-    pub(crate) fn try_from_kms_object(kms_object: KmsObject) -> ModuleResult<Self> {
+    pub(crate) fn try_from_kms_object(
+        slot_id: CK_SLOT_ID,
+        kms_object: KmsObject,
+    ) -> ModuleResult<Self> {
         let raw_bytes = Arc::new(RwLock::new(
             kms_object
                 .object
@@ -47,6 +63,8 @@ impl Pkcs11SymmetricKey {
         let algorithm = key_algorithm_from_attributes(&kms_object.attributes)?;
 
         Ok(Self {
+            slot_id,
+            metadata: metadata_from_attributes(&kms_object.attributes),
             remote_id: kms_object.remote_id,
             algorithm,
             key_size,
@@ -68,6 +86,10 @@ impl SymmetricKey for Pkcs11SymmetricKey {
         self.key_size
     }
 
+    fn metadata(&self) -> KeyMetadata {
+        self.metadata.clone()
+    }
+
     fn raw_bytes(&self) -> ModuleResult<Zeroizing<Vec<u8>>> {
         let raw_bytes = self
             .raw_bytes
@@ -80,8 +102,8 @@ impl SymmetricKey for Pkcs11SymmetricKey {
         if !raw_bytes.is_empty() {
             return Ok(raw_bytes);
         }
-        let sk =
-            backend().find_symmetric_key(SearchOptions::Id(self.remote_id.clone().into_bytes()))?;
+        let sk = backend_for(self.slot_id)?
+            .find_symmetric_key(SearchOptions::Id(self.remote_id.clone().into_bytes()))?;
 
         let mut raw_bytes = self.raw_bytes.write().map_err(|e| {
             error!("Failed to write raw bytes: {:?}", e);
@@ -1,26 +1,67 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use cosmian_cli::{
-    config::{COSMIAN_CLI_CONF_ENV, ClientConfig},
+    config::{COSMIAN_CLI_CONF_ENV, ClientConfig, Pkcs11Config},
     reexport::cosmian_kms_cli::reexport::{
-        cosmian_kmip::kmip_2_1::{
-            kmip_attributes::Attributes,
-            kmip_data_structures::{KeyBlock, KeyMaterial, KeyValue},
-            kmip_objects::{Object, PrivateKey},
-            kmip_types::{CryptographicAlgorithm, KeyFormatType},
-            requests::{self, create_symmetric_key_kmip_object, import_object_request},
+        cosmian_kmip::{
+            kmip_0::kmip_types::{CryptographicUsageMask, State},
+            kmip_2_1::{
+                kmip_attributes::{Attribute as KmipAttribute, Attributes},
+                kmip_data_structures::{KeyBlock, KeyMaterial, KeyValue},
+                kmip_objects::{Object, PrivateKey},
+                kmip_types::{
+                    CryptographicAlgorithm, KeyFormatType, Name, NameType, VendorAttribute,
+                    VendorAttributeValue,
+                },
+                requests::{self, create_symmetric_key_kmip_object, import_object_request},
+            },
         },
-        cosmian_kms_client::KmsClient,
+        cosmian_kms_client::{KmsClient, KmsClientConfig},
     },
 };
 use cosmian_config_utils::ConfigUtils;
-use cosmian_logger::{debug, log_init};
+use cosmian_logger::{debug, info, log_init};
 use cosmian_pkcs11_module::{
+    ModuleError,
+    core::{
+        attribute::{Attribute, AttributeType, Attributes as Template},
+        object::Object as Pkcs11Object,
+    },
     pkcs11::{C_CloseSession, C_Finalize, C_Initialize, C_OpenSession, SLOT_ID},
     test_decrypt, test_encrypt, test_generate_key,
-    traits::Backend,
+    traits::{
+        Backend, DataObject, DecryptContext, DigestType, EncryptContext, EncryptionAlgorithm,
+        KeyAlgorithm, KeyDerivationAlgorithm, KeyMetadata, KeyUsage, KeyWrappingAlgorithm,
+        PublicKey, SearchOptions, SearchTemplate, SignatureAlgorithm, backend_for,
+    },
+};
+use openssl::{
+    asn1::Asn1Time,
+    base64,
+    error::ErrorStack,
+    hash::MessageDigest,
+    pkcs5::pbkdf2_hmac,
+    pkey::{PKey, Private},
+    rand::rand_bytes,
+    rsa::Rsa,
+    sign::Signer,
+    symm::{self, Cipher},
+    x509::{X509Builder, X509NameBuilder},
+};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use pkcs11_sys::{
+    CK_FUNCTION_LIST, CK_INVALID_HANDLE, CK_MECHANISM_INFO, CKF_DERIVE, CKF_SERIAL_SESSION,
+    CKK_AES, CKK_EC_EDWARDS, CKK_GENERIC_SECRET, CKK_RSA, CKM_ECDH1_DERIVE, CKO_CERTIFICATE,
+    CKO_DATA, CKO_PRIVATE_KEY, CKO_SECRET_KEY, CKR_OK,
 };
-use pkcs11_sys::{CK_FUNCTION_LIST, CK_INVALID_HANDLE, CKF_SERIAL_SESSION, CKR_OK};
 use serial_test::serial;
 use test_kms_server::start_default_test_kms_server;
 
@@ -28,7 +69,14 @@ use crate::{
     C_GetFunctionList,
     backend::{COSMIAN_PKCS11_DISK_ENCRYPTION_TAG, CliBackend},
     error::{Pkcs11Error, result::Pkcs11Result},
-    kms_object::get_kms_objects_async,
+    kms_object::{
+        Restore, ck_date_from_date, date_from_ck_date, get_kms_object_attributes_async,
+        get_kms_objects_attributes_async, id_tag, key_algorithm_from_attributes,
+        kmip_attributes_from_template, kms_encrypt_async, metadata_from_attributes,
+        restore_attribute,
+    },
+    pkcs11_data_object::Pkcs11DataObject,
+    pkcs11_public_key::Pkcs11PublicKey,
 };
 
 fn save_pkcs11_client_config() -> String {
@@ -45,6 +93,7 @@ fn save_pkcs11_client_config() -> String {
         let conf = ClientConfig {
             kms_config: ctx.owner_client_config.clone(),
             findex_config: None,
+            pkcs11_config: None,
         };
         conf.to_toml(&owner_file_path)
             .expect("Failed to save owner test config");
@@ -68,9 +117,11 @@ fn initialize_backend() -> Result<CliBackend, Pkcs11Error> {
         ctx.owner_client_config.clone()
     });
 
-    Ok(CliBackend::instantiate(KmsClient::new_with_config(
+    Ok(CliBackend::instantiate(
+        SLOT_ID,
         owner_client_conf,
-    )?))
+        Pkcs11Config::default(),
+    )?)
 }
 
 async fn create_keys(
@@ -167,16 +218,16 @@ async fn test_kms_client() -> Result<(), Pkcs11Error> {
     let kms_rest_client = ctx.get_owner_client();
     create_keys(&kms_rest_client, COSMIAN_PKCS11_DISK_ENCRYPTION_TAG).await?;
 
-    let keys = get_kms_objects_async(
+    let keys = get_kms_objects_attributes_async(
         &kms_rest_client,
         &[COSMIAN_PKCS11_DISK_ENCRYPTION_TAG.to_owned()],
-        Some(KeyFormatType::Raw),
     )
     .await?;
     assert_eq!(keys.len(), 2);
     let mut labels = keys
         .iter()
-        .flat_map(|k| k.other_tags.clone())
+        .flat_map(|(_id, attributes)| attributes.get_tags())
+        .filter(|t| !t.is_empty() && t != COSMIAN_PKCS11_DISK_ENCRYPTION_TAG && !t.starts_with('_'))
         .collect::<Vec<String>>();
     labels.sort();
     assert_eq!(labels, vec!["vol1".to_owned(), "vol2".to_owned()]);
@@ -193,7 +244,7 @@ fn test_kms_client_and_backend() -> Result<(), Pkcs11Error> {
         test_kms_client().await.expect("failed to test kms client");
     });
 
-    let backend = initialize_backend()?;
+    let backend = initialize_backend_for(SLOT_ID)?;
 
     //TODO fix this test
     // // data objects
@@ -209,7 +260,28 @@ fn test_kms_client_and_backend() -> Result<(), Pkcs11Error> {
     // RSA certificate
     let certificates = backend.find_all_certificates()?;
     assert_eq!(certificates.len(), 1);
+    let private_key_id = certificates
+        .first()
+        .map(|certificate| certificate.private_key_id())
+        .unwrap_or_default();
+    // the certificate can be found using the CKA_ID of its private key
+    let certificate = backend
+        .find_certificate(SearchOptions::Id(private_key_id.into_bytes()))?
+        .expect("certificate not found");
+    assert_eq!(
+        certificate.remote_id(),
+        certificates
+            .first()
+            .map(|certificate| certificate.remote_id())
+            .unwrap_or_default()
+    );
     // assert_eq!(certificates[0].label(), "luks_volume");
+    // an unknown CKA_ID matches no certificate
+    assert!(
+        backend
+            .find_certificate(SearchOptions::Id(b"unknown-certificate-id".to_vec()))?
+            .is_none()
+    );
 
     // RSA private key
     let private_keys = backend.find_all_private_keys()?;
@@ -218,6 +290,1190 @@ fn test_kms_client_and_backend() -> Result<(), Pkcs11Error> {
     Ok(())
 }
 
+#[test]
+fn test_generate_key_pair_sign() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let (public_key, private_key) = backend.generate_key_pair(
+        KeyAlgorithm::Rsa,
+        Some(2048),
+        false,
+        true,
+        None,
+        Some("pkcs11_key_pair"),
+    )?;
+    assert_eq!(private_key.algorithm(), KeyAlgorithm::Rsa);
+    assert_eq!(private_key.key_size(), 2048);
+    // the public key can be found using the CKA_ID of its private key
+    let found_public_key =
+        backend.find_public_key(SearchOptions::Id(private_key.remote_id().into_bytes()))?;
+    assert_eq!(found_public_key.remote_id(), public_key.remote_id());
+    assert!(!found_public_key.rsa_modulus()?.is_empty());
+
+    let signature = backend.sign(
+        &private_key.remote_id(),
+        &SignatureAlgorithm::RsaPkcs1v15Sha256,
+        b"data to sign",
+    )?;
+    assert_eq!(signature.len(), 256);
+    backend.verify(
+        &public_key.remote_id(),
+        &SignatureAlgorithm::RsaPkcs1v15Sha256,
+        b"data to sign",
+        &signature,
+    )?;
+    assert!(
+        backend
+            .verify(
+                &public_key.remote_id(),
+                &SignatureAlgorithm::RsaPkcs1v15Sha256,
+                b"other data",
+                &signature,
+            )
+            .is_err()
+    );
+
+    let (_public_key, private_key) =
+        backend.generate_key_pair(KeyAlgorithm::EccP256, None, false, true, None, None)?;
+    let digest = [0_u8; 32];
+    let signature = backend.sign(
+        &private_key.remote_id(),
+        &SignatureAlgorithm::Ecdsa,
+        &digest,
+    )?;
+    assert!(!signature.is_empty());
+
+    Ok(())
+}
+
+/// A self-signed X.509 certificate of `private_key`, as DER
+fn self_signed_certificate(private_key: &PKey<Private>) -> Result<Vec<u8>, ErrorStack> {
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", "pkcs11_import")?;
+    let name = name.build();
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(private_key)?;
+    builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&Asn1Time::days_from_now(1)?)?;
+    builder.sign(private_key, MessageDigest::sha256())?;
+    builder.build().to_der()
+}
+
+#[test]
+fn test_generate_key_pair_with_id() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    // a binary CKA_ID, shared by two key pairs
+    let mut id = vec![0_u8; 8];
+    rand_bytes(&mut id).expect("failed to generate the CKA_ID");
+    let mut private_key_ids = vec![];
+    for _ in 0..2 {
+        let (public_key, private_key) =
+            backend.generate_key_pair(KeyAlgorithm::EccP256, None, false, true, Some(&id), None)?;
+        assert_eq!(private_key.metadata().id, Some(id.clone()));
+        assert_eq!(public_key.metadata().id, Some(id.clone()));
+        private_key_ids.push(private_key.remote_id());
+    }
+    assert_ne!(private_key_ids.first(), private_key_ids.last());
+
+    let template = SearchTemplate {
+        class: Some(CKO_PRIVATE_KEY),
+        id: Some(id.clone()),
+        ..Default::default()
+    };
+    let mut found = backend
+        .find_objects(&template)?
+        .iter()
+        .map(|object| object.remote_id())
+        .collect::<Vec<_>>();
+    found.sort();
+    private_key_ids.sort();
+    assert_eq!(found, private_key_ids);
+    backend.find_public_key(SearchOptions::Id(id))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_import_private_key_and_certificate() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let rsa = Rsa::generate(2048).expect("failed to generate an RSA key");
+    let modulus = rsa.n().to_vec();
+    let private_key = PKey::from_rsa(rsa).expect("invalid RSA key");
+    let pkcs8 = private_key
+        .private_key_to_pkcs8()
+        .expect("failed to encode the RSA key");
+    let der = self_signed_certificate(&private_key).expect("failed to build the certificate");
+    // tools usually set a binary CKA_ID
+    let mut id = vec![0_u8; 8];
+    rand_bytes(&mut id).expect("failed to generate the CKA_ID");
+
+    // the KMS generates the identifier of the key: its CKA_ID is kept aside
+    let imported = backend.import_private_key(
+        KeyAlgorithm::Rsa,
+        zeroize::Zeroizing::new(pkcs8.clone()),
+        false,
+        true,
+        Some(&id),
+        Some("pkcs11_import"),
+    )?;
+    assert_eq!(imported.key_size(), 2048);
+    assert_eq!(imported.metadata().id, Some(id.clone()));
+    // its public key is imported too, found by the CKA_ID or through the private key
+    let public_key = backend.find_public_key(SearchOptions::Id(id.clone()))?;
+    assert_eq!(public_key.rsa_modulus()?, modulus);
+    let linked_public_key =
+        backend.find_public_key(SearchOptions::Id(imported.remote_id().into_bytes()))?;
+    assert_eq!(linked_public_key.remote_id(), public_key.remote_id());
+
+    // the certificate with the same CKA_ID is linked to the private key
+    let certificate = backend.import_certificate(&der, Some(&id), Some("pkcs11_import"))?;
+    assert_eq!(certificate.private_key_id(), imported.remote_id());
+    assert_eq!(certificate.metadata().id, Some(id.clone()));
+    for search_id in [id.clone(), imported.remote_id().into_bytes()] {
+        let found = backend
+            .find_certificate(SearchOptions::Id(search_id))?
+            .expect("certificate not found");
+        assert_eq!(found.remote_id(), certificate.remote_id());
+    }
+    let template = SearchTemplate {
+        id: Some(id.clone()),
+        ..Default::default()
+    };
+    assert_eq!(backend.find_objects(&template)?.len(), 3);
+
+    // a certificate without CKA_ID is linked to no private key
+    let unlinked = backend.import_certificate(&der, None, None)?;
+    assert!(unlinked.private_key_id().is_empty());
+
+    // importing a key with the same CKA_ID does not replace the first one
+    let duplicate = backend.import_private_key(
+        KeyAlgorithm::Rsa,
+        zeroize::Zeroizing::new(pkcs8),
+        false,
+        true,
+        Some(&id),
+        None,
+    )?;
+    assert_ne!(duplicate.remote_id(), imported.remote_id());
+    backend.find_private_key(SearchOptions::Id(imported.remote_id().into_bytes()))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_ec_point_and_eddsa() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    // CKA_EC_POINT is the DER OCTET STRING of the point, whatever the curve
+    for (algorithm, point_len) in [
+        (KeyAlgorithm::EccP256, 65),
+        (KeyAlgorithm::EccP384, 97),
+        (KeyAlgorithm::Ed25519, 32),
+    ] {
+        let (public_key, _private_key) =
+            backend.generate_key_pair(algorithm, None, false, true, None, None)?;
+        let found_public_key =
+            backend.find_public_key(SearchOptions::Id(public_key.remote_id().into_bytes()))?;
+        let ec_point = found_public_key.ec_point()?;
+        assert_eq!(ec_point.len(), point_len + 2);
+        assert!(ec_point.starts_with(&[0x04, u8::try_from(point_len)?]));
+    }
+
+    let (public_key, private_key) =
+        backend.generate_key_pair(KeyAlgorithm::Ed25519, None, false, true, None, None)?;
+    assert_eq!(private_key.algorithm().to_ck_key_type(), CKK_EC_EDWARDS);
+    let signature = backend.sign(
+        &private_key.remote_id(),
+        &SignatureAlgorithm::EdDsa,
+        b"data to sign",
+    )?;
+    assert_eq!(signature.len(), 64);
+    backend.verify(
+        &public_key.remote_id(),
+        &SignatureAlgorithm::EdDsa,
+        b"data to sign",
+        &signature,
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_aes_128_and_hmac() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let aes_key = backend.generate_key(KeyAlgorithm::Aes128, 16, false, true, None)?;
+    let found_key =
+        backend.find_symmetric_key(SearchOptions::Id(aes_key.remote_id().into_bytes()))?;
+    assert_eq!(found_key.algorithm(), KeyAlgorithm::Aes128);
+
+    // The HMAC computed by the KMS matches the one computed locally
+    let secret = vec![0x42_u8; 32];
+    let hmac_key = backend.import_symmetric_key(
+        KeyAlgorithm::GenericSecret,
+        zeroize::Zeroizing::new(secret.clone()),
+        false,
+        true,
+        None,
+    )?;
+    assert_eq!(hmac_key.algorithm(), KeyAlgorithm::GenericSecret);
+    let pkey = PKey::hmac(&secret).expect("invalid HMAC key");
+    // every HMAC mechanism is computed with the same generic secret
+    for (digest, message_digest) in [
+        (DigestType::Sha1, MessageDigest::sha1()),
+        (DigestType::Sha224, MessageDigest::sha224()),
+        (DigestType::Sha256, MessageDigest::sha256()),
+        (DigestType::Sha384, MessageDigest::sha384()),
+        (DigestType::Sha512, MessageDigest::sha512()),
+    ] {
+        let algorithm = SignatureAlgorithm::Hmac { digest };
+        let mac = backend.sign(&hmac_key.remote_id(), &algorithm, b"data to sign")?;
+        let mut signer = Signer::new(message_digest, &pkey).expect("HMAC signer failed");
+        signer.update(b"data to sign").expect("HMAC update failed");
+        assert_eq!(mac, signer.sign_to_vec().expect("HMAC failed"));
+        backend.verify(&hmac_key.remote_id(), &algorithm, b"data to sign", &mac)?;
+        assert!(matches!(
+            backend.verify(&hmac_key.remote_id(), &algorithm, b"other data", &mac),
+            Err(ModuleError::SignatureInvalid)
+        ));
+    }
+
+    // a generic secret is found whatever the HMAC algorithm it is stored with
+    let label = "pkcs11_hmac_sha512_key";
+    let rt = tokio::runtime::Runtime::new()?;
+    let hmac_sha512_id = rt.block_on(async {
+        let ctx = start_default_test_kms_server().await;
+        let key = create_symmetric_key_kmip_object(
+            &[0x42_u8; 64],
+            &Attributes {
+                cryptographic_algorithm: Some(CryptographicAlgorithm::HMACSHA512),
+                ..Default::default()
+            },
+        )?;
+        let request = import_object_request(None, key, None, false, true, [label]);
+        let response = ctx.get_owner_client().import(request).await?;
+        Ok::<_, Pkcs11Error>(String::from(response.unique_identifier))
+    })?;
+    let objects = backend.find_objects(&SearchTemplate {
+        class: Some(CKO_SECRET_KEY),
+        key_type: Some(CKK_GENERIC_SECRET),
+        label: Some(label.to_owned()),
+        ..Default::default()
+    })?;
+    assert_eq!(
+        objects
+            .iter()
+            .map(|object| object.remote_id())
+            .collect::<Vec<_>>(),
+        vec![hmac_sha512_id]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_wrap_unwrap_key() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let wrapping_key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_wrapping_key"),
+    )?;
+    let key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_wrapped_key"),
+    )?;
+
+    for algorithm in [
+        KeyWrappingAlgorithm::AesKeyWrap,
+        KeyWrappingAlgorithm::AesKeyWrapPad,
+    ] {
+        let wrapped_key =
+            backend.wrap_key(&wrapping_key.remote_id(), &key.remote_id(), &algorithm)?;
+        // RFC 3394 and RFC 5649 add an 8-byte integrity check value to the 32-byte key
+        assert_eq!(wrapped_key.len(), 40);
+
+        let unwrapped_key = backend.unwrap_key(
+            &wrapping_key.remote_id(),
+            &algorithm,
+            &wrapped_key,
+            Some("pkcs11_unwrapped_key"),
+        )?;
+        assert_ne!(unwrapped_key.remote_id(), key.remote_id());
+        assert_eq!(unwrapped_key.algorithm(), KeyAlgorithm::Aes256);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_derive_key() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let base_key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_derivation_base_key"),
+    )?;
+    for algorithm in [
+        KeyDerivationAlgorithm::Hkdf {
+            digest: DigestType::Sha256,
+            salt: b"salt".to_vec(),
+            info: b"info".to_vec(),
+        },
+        KeyDerivationAlgorithm::Pbkdf2 {
+            digest: DigestType::Sha256,
+            salt: b"0123456789abcdef".to_vec(),
+            iterations: 4096,
+        },
+    ] {
+        let derived_key = backend.derive_key(
+            &base_key.remote_id(),
+            &algorithm,
+            KeyAlgorithm::Aes256,
+            Some(32),
+            false,
+            true,
+            None,
+        )?;
+        assert_ne!(derived_key.remote_id(), base_key.remote_id());
+        assert_eq!(derived_key.algorithm(), KeyAlgorithm::Aes256);
+    }
+
+    // The derived key has the type, the length and the sensitivity of the template
+    let hkdf = KeyDerivationAlgorithm::Hkdf {
+        digest: DigestType::Sha256,
+        salt: b"salt".to_vec(),
+        info: b"info".to_vec(),
+    };
+    let derived_key = backend.derive_key(
+        &base_key.remote_id(),
+        &hkdf,
+        KeyAlgorithm::Aes128,
+        Some(16),
+        true,
+        false,
+        None,
+    )?;
+    assert_eq!(derived_key.algorithm(), KeyAlgorithm::Aes128);
+    assert_eq!(derived_key.key_size(), 128);
+    assert_eq!(derived_key.metadata().sensitive, Some(true));
+    let derived_key = backend.derive_key(
+        &base_key.remote_id(),
+        &hkdf,
+        KeyAlgorithm::GenericSecret,
+        Some(48),
+        false,
+        false,
+        None,
+    )?;
+    assert_eq!(derived_key.algorithm(), KeyAlgorithm::GenericSecret);
+
+    // The KMS cannot compute ECDH shared secrets: the private key is never exported
+    let (_public_key, private_key) =
+        backend.generate_key_pair(KeyAlgorithm::EccP256, None, false, false, None, None)?;
+    let peer_key = p256::SecretKey::from_slice(&[1_u8; 32]).expect("invalid peer key");
+    let result = backend.derive_key(
+        &private_key.remote_id(),
+        &KeyDerivationAlgorithm::Ecdh {
+            public_data: peer_key
+                .public_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+        },
+        KeyAlgorithm::Aes256,
+        None,
+        false,
+        false,
+        Some("pkcs11_ecdh_key"),
+    );
+    assert!(matches!(
+        result,
+        Err(ModuleError::MechanismInvalid(CKM_ECDH1_DERIVE))
+    ));
+    assert!(
+        backend
+            .mechanism_info(
+                CKM_ECDH1_DERIVE,
+                CK_MECHANISM_INFO {
+                    ulMinKeySize: 256,
+                    ulMaxKeySize: 521,
+                    flags: CKF_DERIVE,
+                }
+            )
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_decrypt_aes_gcm_ctr() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_aes_gcm_ctr"),
+    )?;
+    let plaintext = b"plaintext to encrypt".to_vec();
+    for (algorithm, iv, aad) in [
+        (
+            EncryptionAlgorithm::AesGcm { tag_length: 16 },
+            vec![1_u8; 12],
+            Some(b"additional data".to_vec()),
+        ),
+        (EncryptionAlgorithm::AesCtr, vec![2_u8; 16], None),
+    ] {
+        let ciphertext = backend.encrypt(
+            &EncryptContext {
+                remote_object_id: key.remote_id(),
+                algorithm: algorithm.clone(),
+                iv: Some(iv.clone()),
+                aad: aad.clone(),
+                correlation_value: None,
+                pending_output: None,
+            },
+            plaintext.clone(),
+        )?;
+        let decrypt_ctx = DecryptContext {
+            remote_object_id: key.remote_id(),
+            algorithm,
+            iv: Some(iv),
+            aad,
+            correlation_value: None,
+            pending_output: None,
+        };
+        let decrypted = backend.decrypt(&decrypt_ctx, ciphertext.clone())?;
+        assert_eq!(decrypted.as_slice(), plaintext.as_slice());
+
+        if matches!(decrypt_ctx.algorithm, EncryptionAlgorithm::AesGcm { .. }) {
+            // The tag is appended to the ciphertext and authenticates it
+            assert_eq!(ciphertext.len(), plaintext.len() + 16);
+            let mut tampered = ciphertext;
+            if let Some(byte) = tampered.first_mut() {
+                *byte ^= 1;
+            }
+            assert!(backend.decrypt(&decrypt_ctx, tampered).is_err());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_decrypt_rsa_oaep() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let (public_key, private_key) = backend.generate_key_pair(
+        KeyAlgorithm::Rsa,
+        Some(2048),
+        false,
+        true,
+        None,
+        Some("pkcs11_rsa_oaep"),
+    )?;
+    let algorithm = EncryptionAlgorithm::RsaOaep {
+        digest: DigestType::Sha256,
+        mask_generation_function: DigestType::Sha256,
+    };
+    let plaintext = b"plaintext to encrypt".to_vec();
+    let ciphertext = backend.encrypt(
+        &EncryptContext {
+            remote_object_id: public_key.remote_id(),
+            algorithm: algorithm.clone(),
+            iv: None,
+            aad: None,
+            correlation_value: None,
+            pending_output: None,
+        },
+        plaintext.clone(),
+    )?;
+    assert_eq!(ciphertext.len(), 256);
+    let decrypted = backend.decrypt(
+        &DecryptContext {
+            remote_object_id: private_key.remote_id(),
+            algorithm,
+            iv: None,
+            aad: None,
+            correlation_value: None,
+            pending_output: None,
+        },
+        ciphertext,
+    )?;
+    assert_eq!(decrypted.as_slice(), plaintext.as_slice());
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_decrypt_multipart() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_multipart"),
+    )?;
+    let iv = vec![3_u8; 16];
+    let plaintext = vec![4_u8; 100];
+    let mut encrypt_ctx = EncryptContext {
+        remote_object_id: key.remote_id(),
+        algorithm: EncryptionAlgorithm::AesCbcPad,
+        iv: Some(iv.clone()),
+        aad: None,
+        correlation_value: None,
+        pending_output: None,
+    };
+    let mut ciphertext = vec![];
+    for part in plaintext.chunks(30) {
+        ciphertext.extend(backend.encrypt_multipart(&mut encrypt_ctx, Some(part.to_vec()))?);
+    }
+    ciphertext.extend(backend.encrypt_multipart(&mut encrypt_ctx, None)?);
+    // 100 bytes are padded to 7 blocks
+    assert_eq!(ciphertext.len(), 112);
+
+    // The multipart ciphertext is the same as the single part one
+    let mut decrypt_ctx = DecryptContext {
+        remote_object_id: key.remote_id(),
+        algorithm: EncryptionAlgorithm::AesCbcPad,
+        iv: Some(iv),
+        aad: None,
+        correlation_value: None,
+        pending_output: None,
+    };
+    let decrypted = backend.decrypt(&decrypt_ctx, ciphertext.clone())?;
+    assert_eq!(decrypted.as_slice(), plaintext.as_slice());
+
+    let mut decrypted = vec![];
+    for part in ciphertext.chunks(40) {
+        decrypted
+            .extend_from_slice(&backend.decrypt_multipart(&mut decrypt_ctx, Some(part.to_vec()))?);
+    }
+    decrypted.extend_from_slice(&backend.decrypt_multipart(&mut decrypt_ctx, None)?);
+    assert_eq!(decrypted, plaintext);
+
+    Ok(())
+}
+
+#[test]
+fn test_digest_key() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    // The KMS cannot digest a key where it is stored: no key value is exported
+    // to be digested, whether the key is sensitive, non-extractable or neither
+    for (sensitive, extractable) in [(false, true), (true, true), (false, false)] {
+        let key = backend.generate_key(
+            KeyAlgorithm::Aes256,
+            32,
+            sensitive,
+            extractable,
+            Some("pkcs11_digest_key"),
+        )?;
+        assert!(matches!(
+            backend.digest_key(&key.remote_id(), &DigestType::Sha256),
+            Err(ModuleError::KeyIndigestible(_))
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_default_extractable_policy() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+    // keys are not extractable unless the configuration says otherwise
+    let extractable = backend.extractable_by_default();
+    assert!(!extractable);
+
+    let key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        extractable,
+        Some("pkcs11_default_policy_key"),
+    )?;
+    assert!(!key.metadata().is_extractable());
+    assert!(matches!(
+        backend.digest_key(&key.remote_id(), &DigestType::Sha256),
+        Err(ModuleError::KeyIndigestible(_))
+    ));
+    assert!(matches!(
+        Pkcs11Object::SymmetricKey(key.clone()).attribute(SLOT_ID, AttributeType::Value),
+        Err(ModuleError::AttributeSensitive(AttributeType::Value))
+    ));
+
+    // a key derived with the default policy does not reveal its value either
+    let derived_key = backend.derive_key(
+        &key.remote_id(),
+        &KeyDerivationAlgorithm::Hkdf {
+            digest: DigestType::Sha256,
+            salt: b"salt".to_vec(),
+            info: b"info".to_vec(),
+        },
+        KeyAlgorithm::GenericSecret,
+        Some(32),
+        false,
+        extractable,
+        None,
+    )?;
+    assert!(!derived_key.metadata().is_extractable());
+    assert!(matches!(
+        Pkcs11Object::SymmetricKey(derived_key).attribute(SLOT_ID, AttributeType::Value),
+        Err(ModuleError::AttributeSensitive(AttributeType::Value))
+    ));
+
+    // the public components of a private key are read from its public key,
+    // its value is not revealed
+    let (public_key, private_key) = backend.generate_key_pair(
+        KeyAlgorithm::Rsa,
+        Some(2048),
+        false,
+        extractable,
+        None,
+        None,
+    )?;
+    let private_key = Pkcs11Object::PrivateKey(private_key);
+    assert_eq!(
+        private_key.attribute(SLOT_ID, AttributeType::Modulus)?,
+        Some(Attribute::Modulus(public_key.rsa_modulus()?))
+    );
+    assert_eq!(
+        private_key.attribute(SLOT_ID, AttributeType::PublicExponent)?,
+        Some(Attribute::PublicExponent(public_key.rsa_public_exponent()?))
+    );
+    assert!(matches!(
+        private_key.attribute(SLOT_ID, AttributeType::Value),
+        Err(ModuleError::AttributeSensitive(AttributeType::Value))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_find_objects() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let label = "pkcs11_find_objects";
+    let key = backend.generate_key(KeyAlgorithm::Aes256, 32, false, true, Some(label))?;
+    let template = SearchTemplate {
+        class: Some(CKO_SECRET_KEY),
+        label: Some(label.to_owned()),
+        ..Default::default()
+    };
+    let objects = backend.find_objects(&template)?;
+    assert_eq!(objects.len(), 1);
+    assert_eq!(
+        objects.first().map(|object| object.remote_id()),
+        Some(key.remote_id())
+    );
+
+    for (filtered, expected_count) in [
+        (
+            SearchTemplate {
+                key_type: Some(CKK_AES),
+                value_len: Some(32),
+                decrypt: Some(true),
+                token: Some(true),
+                ..template.clone()
+            },
+            1,
+        ),
+        (
+            SearchTemplate {
+                value_len: Some(16),
+                ..template.clone()
+            },
+            0,
+        ),
+        (
+            SearchTemplate {
+                key_type: Some(CKK_RSA),
+                ..template.clone()
+            },
+            0,
+        ),
+        (
+            SearchTemplate {
+                sign: Some(true),
+                ..template.clone()
+            },
+            0,
+        ),
+        (
+            SearchTemplate {
+                token: Some(false),
+                ..template.clone()
+            },
+            0,
+        ),
+        (
+            SearchTemplate {
+                class: Some(CKO_PRIVATE_KEY),
+                ..template.clone()
+            },
+            0,
+        ),
+        // an unknown binary CKA_ID matches no object
+        (
+            SearchTemplate {
+                id: Some(vec![0xde, 0xad, 0xbe, 0xef]),
+                ..template.clone()
+            },
+            0,
+        ),
+    ] {
+        assert_eq!(backend.find_objects(&filtered)?.len(), expected_count);
+    }
+
+    // The label of a data object is a tag of its KMS object
+    let data_label = "pkcs11_find_objects_data";
+    let data_object = backend.create_object(data_label, b"data")?;
+    let objects = backend.find_objects(&SearchTemplate {
+        class: Some(CKO_DATA),
+        label: Some(data_label.to_owned()),
+        ..Default::default()
+    })?;
+    assert_eq!(
+        objects
+            .iter()
+            .map(|object| object.remote_id())
+            .collect::<Vec<_>>(),
+        vec![data_object.remote_id()]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_ck_date() -> Result<(), Pkcs11Error> {
+    for ck_date in ["20250315", "19991231", "20240229"] {
+        assert_eq!(
+            ck_date_from_date(date_from_ck_date(ck_date.as_bytes())?),
+            ck_date
+        );
+    }
+    // the CK_DATE is the day in UTC
+    let date = date_from_ck_date(b"20250315")?
+        .to_offset(time::UtcOffset::from_hms(-5, 0, 0).expect("invalid offset"));
+    assert_eq!(ck_date_from_date(date), "20250315");
+    for invalid in [
+        b"2025-3-15".as_slice(),
+        b"20251315",
+        b"20250230",
+        b"2025031",
+        b"202503150",
+        b"+0250315",
+        b"",
+        b"\xff\xff\xff\xff\xff\xff\xff\xff",
+    ] {
+        assert!(date_from_ck_date(invalid).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_metadata_from_attributes() -> Result<(), Pkcs11Error> {
+    let mut attributes = Attributes {
+        cryptographic_usage_mask: Some(
+            CryptographicUsageMask::Encrypt
+                | CryptographicUsageMask::Decrypt
+                | CryptographicUsageMask::MACGenerate,
+        ),
+        activation_date: Some(date_from_ck_date(b"20250315")?),
+        name: Some(vec![Name {
+            name_value: "pkcs11_metadata".to_owned(),
+            name_type: NameType::UninterpretedTextString,
+        }]),
+        sensitive: Some(true),
+        extractable: Some(false),
+        ..Attributes::default()
+    };
+    attributes.set_tags(vec![id_tag(&[0xca, 0xfe])])?;
+    assert_eq!(
+        metadata_from_attributes(&attributes),
+        KeyMetadata {
+            label: Some("pkcs11_metadata".to_owned()),
+            id: Some(vec![0xca, 0xfe]),
+            usage: Some(KeyUsage {
+                encrypt: true,
+                decrypt: true,
+                sign: true,
+                ..KeyUsage::default()
+            }),
+            start_date: Some("20250315".to_owned()),
+            // a missing date keeps its default value
+            end_date: None,
+            local: Some(false),
+            sensitive: Some(true),
+            extractable: Some(false),
+        }
+    );
+
+    // a key which is no longer active cannot be used
+    attributes.state = Some(State::Deactivated);
+    assert_eq!(
+        metadata_from_attributes(&attributes).usage,
+        Some(KeyUsage::default())
+    );
+    // the metadata unknown to the KMS are left to their default value
+    assert_eq!(
+        metadata_from_attributes(&Attributes::default()),
+        KeyMetadata {
+            local: Some(false),
+            ..KeyMetadata::default()
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_key_algorithm_from_attributes() -> Result<(), Pkcs11Error> {
+    let aes = |cryptographic_length| Attributes {
+        cryptographic_algorithm: Some(CryptographicAlgorithm::AES),
+        cryptographic_length,
+        ..Attributes::default()
+    };
+    assert_eq!(
+        key_algorithm_from_attributes(&aes(Some(128)))?,
+        KeyAlgorithm::Aes128
+    );
+    assert_eq!(
+        key_algorithm_from_attributes(&aes(Some(256)))?,
+        KeyAlgorithm::Aes256
+    );
+    // an AES key of unknown length is not mistaken for an AES-256 key
+    assert!(key_algorithm_from_attributes(&aes(Some(512))).is_err());
+    assert!(key_algorithm_from_attributes(&aes(None)).is_err());
+    assert_eq!(
+        key_algorithm_from_attributes(&Attributes {
+            cryptographic_algorithm: Some(CryptographicAlgorithm::HMACSHA512),
+            ..Attributes::default()
+        })?,
+        KeyAlgorithm::GenericSecret
+    );
+    Ok(())
+}
+
+#[test]
+fn test_kmip_attributes_from_template() -> Result<(), Pkcs11Error> {
+    // clearing CKA_SIGN on an HMAC key removes the MAC usage
+    let hmac_key = Attributes {
+        cryptographic_algorithm: Some(CryptographicAlgorithm::HMACSHA256),
+        cryptographic_usage_mask: Some(
+            CryptographicUsageMask::MACGenerate | CryptographicUsageMask::MACVerify,
+        ),
+        ..Attributes::default()
+    };
+    let kmip_attributes =
+        kmip_attributes_from_template(&hmac_key, &vec![Attribute::Sign(false)].into())?;
+    assert!(matches!(
+        kmip_attributes.as_slice(),
+        [KmipAttribute::CryptographicUsageMask(mask)] if *mask == CryptographicUsageMask::MACVerify
+    ));
+    let kmip_attributes = kmip_attributes_from_template(
+        &Attributes {
+            cryptographic_usage_mask: Some(CryptographicUsageMask::MACVerify),
+            ..hmac_key
+        },
+        &vec![Attribute::Sign(true)].into(),
+    )?;
+    assert!(matches!(
+        kmip_attributes.as_slice(),
+        [KmipAttribute::CryptographicUsageMask(mask)]
+            if *mask == CryptographicUsageMask::MACGenerate | CryptographicUsageMask::MACVerify
+    ));
+
+    // the binary CKA_ID is held hex encoded in the tags
+    let kmip_attributes = kmip_attributes_from_template(
+        &Attributes::default(),
+        &vec![Attribute::Id(vec![0xde, 0xad, 0xbe, 0xef])].into(),
+    )?;
+    assert!(matches!(
+        kmip_attributes.as_slice(),
+        [KmipAttribute::VendorAttribute(VendorAttribute {
+            attribute_value: VendorAttributeValue::TextString(tags),
+            ..
+        })] if tags.contains("pkcs11_id:deadbeef")
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_restore_attribute() -> Result<(), Pkcs11Error> {
+    let date = date_from_ck_date(b"20250315")?;
+    // an attribute without a previous value is deleted
+    assert!(matches!(
+        restore_attribute(&Attributes::default(), &KmipAttribute::ActivationDate(date)),
+        Restore::Delete(KmipAttribute::ActivationDate(deleted)) if deleted == date
+    ));
+    let previous = date_from_ck_date(b"20240101")?;
+    assert!(matches!(
+        restore_attribute(
+            &Attributes {
+                activation_date: Some(previous),
+                ..Attributes::default()
+            },
+            &KmipAttribute::ActivationDate(date)
+        ),
+        Restore::Set(KmipAttribute::ActivationDate(restored)) if restored == previous
+    ));
+    assert!(matches!(
+        restore_attribute(
+            &Attributes::default(),
+            &KmipAttribute::Name(Name {
+                name_value: "pkcs11_restore".to_owned(),
+                name_type: NameType::UninterpretedTextString,
+            })
+        ),
+        Restore::Delete(KmipAttribute::Name(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_set_attributes() -> Result<(), Pkcs11Error> {
+    let backend = initialize_backend_for(SLOT_ID)?;
+
+    let key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_set_attributes"),
+    )?;
+    let template: Template = vec![
+        Attribute::Label("pkcs11_relabelled".to_owned()),
+        Attribute::Id(b"pkcs11_set_attributes_id".to_vec()),
+    ]
+    .into();
+    let object = backend.set_attributes(&Pkcs11Object::SymmetricKey(key), &template)?;
+    assert_eq!(
+        object.attribute(SLOT_ID, AttributeType::Label)?,
+        Some(Attribute::Label("pkcs11_relabelled".to_owned()))
+    );
+    assert_eq!(
+        object.attribute(SLOT_ID, AttributeType::Id)?,
+        Some(Attribute::Id(b"pkcs11_set_attributes_id".to_vec()))
+    );
+
+    // the new label replaces the previous one in the KMIP Name and in the tags
+    let kms_rest_client = backend.kms_client()?;
+    let attributes = tokio::runtime::Runtime::new()?.block_on(get_kms_object_attributes_async(
+        &kms_rest_client,
+        &object.remote_id(),
+    ))?;
+    let names = attributes
+        .name
+        .iter()
+        .flatten()
+        .map(|name| name.name_value.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["pkcs11_relabelled"]);
+    let tags = attributes.get_tags();
+    assert!(tags.iter().any(|tag| tag == "pkcs11_relabelled"));
+    assert!(!tags.iter().any(|tag| tag == "pkcs11_set_attributes"));
+    for (label, expected_count) in [("pkcs11_set_attributes", 0), ("pkcs11_relabelled", 1)] {
+        let template = SearchTemplate {
+            class: Some(CKO_SECRET_KEY),
+            label: Some(label.to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(backend.find_objects(&template)?.len(), expected_count);
+    }
+    // the key is found by its new CKA_ID
+    let template = SearchTemplate {
+        id: Some(b"pkcs11_set_attributes_id".to_vec()),
+        ..Default::default()
+    };
+    assert_eq!(backend.find_objects(&template)?.len(), 1);
+
+    // an HMAC key no longer signs once CKA_SIGN is cleared
+    let hmac_key = backend.import_symmetric_key(
+        KeyAlgorithm::GenericSecret,
+        zeroize::Zeroizing::new(vec![0x42_u8; 32]),
+        false,
+        true,
+        None,
+    )?;
+    let object = backend.set_attributes(
+        &Pkcs11Object::SymmetricKey(hmac_key),
+        &vec![Attribute::Sign(false)].into(),
+    )?;
+    assert_eq!(
+        object.attribute(SLOT_ID, AttributeType::Sign)?,
+        Some(Attribute::Sign(false))
+    );
+    assert_eq!(
+        object.attribute(SLOT_ID, AttributeType::Verify)?,
+        Some(Attribute::Verify(true))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_pkcs11_config() -> Result<(), Pkcs11Error> {
+    let backend = CliBackend::instantiate(
+        SLOT_ID,
+        KmsClientConfig::default(),
+        Pkcs11Config {
+            token_label: Some("Oracle TDE".to_owned()),
+            token_serial: Some("a-serial-number-longer-than-16".to_owned()),
+            tag: Some("oracle-tde".to_owned()),
+            private_key_tag: Some("oracle-tde-keys".to_owned()),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(&backend.token_label(), b"Oracle TDE                      ");
+    assert_eq!(
+        &backend.token_manufacturer_id(),
+        b"Cosmian                         "
+    );
+    assert_eq!(&backend.token_serial_number(), b"a-serial-number-");
+    assert_eq!(backend.object_tag(CKO_PRIVATE_KEY), "oracle-tde-keys");
+    assert_eq!(backend.object_tag(CKO_CERTIFICATE), "oracle-tde");
+    Ok(())
+}
+
+/// Protect `secret` with `pin` like
+/// `openssl enc -aes-256-cbc -pbkdf2 -iter 600000 -md sha256 -a -A`
+fn protect_with_pin(secret: &str, pin: &[u8]) -> String {
+    let salt = *b"pinsalt!";
+    let mut key_iv = [0_u8; 48];
+    pbkdf2_hmac(pin, &salt, 600_000, MessageDigest::sha256(), &mut key_iv)
+        .expect("PIN key derivation failed");
+    let (key, iv) = key_iv.split_at(32);
+    let ciphertext = symm::encrypt(Cipher::aes_256_cbc(), key, Some(iv), secret.as_bytes())
+        .expect("credential encryption failed");
+    base64::encode_block(&[b"Salted__".as_slice(), &salt, &ciphertext].concat())
+}
+
+#[test]
+fn test_pin_login() -> Result<(), Pkcs11Error> {
+    let backend = CliBackend::instantiate(
+        SLOT_ID,
+        KmsClientConfig::default(),
+        Pkcs11Config {
+            login_required: true,
+            pin_protected_access_token: Some(protect_with_pin("a-bearer-token", b"1234")),
+            ..Default::default()
+        },
+    )?;
+    assert!(backend.login_required());
+    // the KMS client is only built by a successful login
+    assert!(matches!(
+        backend.find_all_data_objects(),
+        Err(ModuleError::UserNotLoggedIn)
+    ));
+    assert!(matches!(
+        backend.login(b"0000"),
+        Err(ModuleError::PinIncorrect)
+    ));
+    assert!(matches!(
+        backend.kms_client(),
+        Err(ModuleError::UserNotLoggedIn)
+    ));
+    backend.login(b"1234")?;
+    backend.kms_client()?;
+    backend.logout();
+    assert!(matches!(
+        backend.kms_client(),
+        Err(ModuleError::UserNotLoggedIn)
+    ));
+
+    // without protected credentials, the PIN opens the client PKCS#12
+    let backend = CliBackend::instantiate(
+        SLOT_ID,
+        KmsClientConfig::default(),
+        Pkcs11Config {
+            login_required: true,
+            ..Default::default()
+        },
+    )?;
+    assert!(matches!(
+        backend.login(b"1234"),
+        Err(ModuleError::Default(_))
+    ));
+    Ok(())
+}
+
+/// Compare the throughput of the backend, which reuses one runtime and the
+/// connections of its KMS client, with a runtime built for every request.
+/// The timings are noisy: the shared runtime may be up to `TOLERANCE` times
+/// slower than a runtime per request before the test fails.
+#[test]
+fn test_encrypt_throughput() -> Result<(), Pkcs11Error> {
+    const BLOCKS: u32 = 32;
+    const TOLERANCE: f64 = 1.5;
+
+    let backend = initialize_backend_for(SLOT_ID)?;
+    let key = backend.generate_key(
+        KeyAlgorithm::Aes256,
+        32,
+        false,
+        true,
+        Some("pkcs11_throughput"),
+    )?;
+    let encrypt_ctx = EncryptContext {
+        remote_object_id: key.remote_id(),
+        algorithm: EncryptionAlgorithm::AesCbc,
+        iv: Some(vec![0_u8; 16]),
+        aad: None,
+        correlation_value: None,
+        pending_output: None,
+    };
+    let block = vec![0x2a_u8; 4096];
+    // warm up the connections of the backend
+    backend.encrypt(&encrypt_ctx, block.clone())?;
+
+    let start = Instant::now();
+    for _ in 0..BLOCKS {
+        backend.encrypt(&encrypt_ctx, block.clone())?;
+    }
+    let shared_runtime = start.elapsed();
+
+    let kms_rest_client = tokio::runtime::Runtime::new()?
+        .block_on(async { start_default_test_kms_server().await.get_owner_client() });
+    let start = Instant::now();
+    for _ in 0..BLOCKS {
+        tokio::runtime::Runtime::new()?.block_on(kms_encrypt_async(
+            &kms_rest_client,
+            &encrypt_ctx,
+            block.clone(),
+        ))?;
+    }
+    let runtime_per_request = start.elapsed();
+
+    info!(
+        "encryption of {BLOCKS} blocks of 4096 bytes: shared runtime: {:.1} blocks/s, runtime per \
+         request: {:.1} blocks/s",
+        f64::from(BLOCKS) / shared_runtime.as_secs_f64(),
+        f64::from(BLOCKS) / runtime_per_request.as_secs_f64()
+    );
+    assert!(shared_runtime <= runtime_per_request.mul_f64(TOLERANCE));
+
+    Ok(())
+}
+
 static INITIALIZED: AtomicBool = AtomicBool::new(false);
 
 #[expect(unsafe_code)]
@@ -273,3 +1529,147 @@ fn test_generate_key_encrypt_decrypt() -> Pkcs11Result<()> {
     assert_eq!(C_Finalize(std::ptr::null_mut()), CKR_OK);
     Ok(())
 }
+
+#[test]
+#[serial]
+#[expect(unsafe_code)]
+fn test_lazy_fetching() -> Pkcs11Result<()> {
+    let conf_path = save_pkcs11_client_config();
+    unsafe {
+        std::env::set_var(COSMIAN_CLI_CONF_ENV, &conf_path);
+    }
+
+    test_init();
+    assert_eq!(C_Initialize(std::ptr::null_mut()), CKR_OK);
+
+    // The value of a data object built from its identifier is fetched when first requested
+    let data_object =
+        backend_for(SLOT_ID)?.create_object("pkcs11_lazy_data_object", b"lazy value")?;
+    let lazy_data_object = Pkcs11DataObject::new(SLOT_ID, data_object.remote_id());
+    assert_eq!(lazy_data_object.value()?.as_slice(), b"lazy value");
+    assert_eq!(lazy_data_object.data_hash()?, data_object.data_hash()?);
+    // nothing is fetched until then, and a failed fetch is reported
+    let missing_data_object =
+        Pkcs11DataObject::new(SLOT_ID, "pkcs11_missing_data_object".to_owned());
+    missing_data_object
+        .value()
+        .expect_err("the data object does not exist");
+
+    // The same holds for the fingerprint of a public key built from its identifier
+    let (public_key, _private_key) = backend_for(SLOT_ID)?.generate_key_pair(
+        KeyAlgorithm::Rsa,
+        Some(2048),
+        false,
+        true,
+        None,
+        Some("pkcs11_lazy_key_pair"),
+    )?;
+    let lazy_public_key = Pkcs11PublicKey::new(
+        SLOT_ID,
+        public_key.remote_id(),
+        KeyAlgorithm::Rsa,
+        KeyMetadata::default(),
+    );
+    assert_eq!(lazy_public_key.fingerprint()?, public_key.fingerprint()?);
+    let missing_public_key = Pkcs11PublicKey::new(
+        SLOT_ID,
+        "pkcs11_missing_public_key".to_owned(),
+        KeyAlgorithm::Rsa,
+        KeyMetadata::default(),
+    );
+    missing_public_key
+        .fingerprint()
+        .expect_err("the public key does not exist");
+
+    assert_eq!(C_Finalize(std::ptr::null_mut()), CKR_OK);
+    Ok(())
+}
+
+/// A TCP proxy to the test KMS server: stopping it drops the connections to the KMS,
+/// starting it again on the same port restores them.
+struct KmsProxy {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl KmsProxy {
+    /// Forward the connections to `port`, any free port when 0, to the KMS on `kms_port`
+    fn start(port: u16, kms_port: u16) -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", port)).expect("failed binding the proxy");
+        listener
+            .set_nonblocking(true)
+            .expect("failed configuring the proxy");
+        let port = listener.local_addr().expect("no proxy address").port();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            let mut streams = vec![];
+            while !stopped.load(Ordering::SeqCst) {
+                let Ok((client, _)) = listener.accept() else {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                };
+                client
+                    .set_nonblocking(false)
+                    .expect("failed configuring the proxied connection");
+                let server = TcpStream::connect(("127.0.0.1", kms_port))
+                    .expect("failed connecting to the KMS");
+                for (from, to) in [(&client, &server), (&server, &client)] {
+                    let mut from = from.try_clone().expect("failed cloning the connection");
+                    let mut to = to.try_clone().expect("failed cloning the connection");
+                    thread::spawn(move || {
+                        let _ = std::io::copy(&mut from, &mut to);
+                        let _ = to.shutdown(Shutdown::Write);
+                    });
+                }
+                streams.extend([client, server]);
+            }
+            for stream in streams {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        });
+        Self { port, stop, thread }
+    }
+
+    /// Close the proxy and the connections it forwards
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.thread.join().expect("the proxy failed");
+    }
+}
+
+#[test]
+fn test_kms_reconnection() -> Result<(), Pkcs11Error> {
+    log_init(None);
+    let rt = tokio::runtime::Runtime::new()?;
+    let (mut kms_config, kms_port) = rt.block_on(async {
+        let ctx = start_default_test_kms_server().await;
+        (ctx.owner_client_config.clone(), ctx.server_port)
+    });
+    let proxy = KmsProxy::start(0, kms_port);
+    let port = proxy.port;
+    kms_config.http_config.server_url = kms_config
+        .http_config
+        .server_url
+        .replace(&format!(":{kms_port}"), &format!(":{port}"));
+    let backend = CliBackend::instantiate(SLOT_ID, kms_config, Pkcs11Config::default())?;
+    backend.find_all_data_objects()?;
+    let kms_client = backend.kms_client()?;
+
+    // the connection is dropped: the request fails at the transport level
+    proxy.stop();
+    // let the KMS client notice that its connections are closed
+    thread::sleep(Duration::from_millis(100));
+    assert!(matches!(
+        backend.find_all_data_objects(),
+        Err(ModuleError::DeviceError(_))
+    ));
+
+    // the connection is restored: the next request reconnects with a new KMS client
+    let proxy = KmsProxy::start(port, kms_port);
+    backend.find_all_data_objects()?;
+    assert!(!Arc::ptr_eq(&kms_client, &backend.kms_client()?));
+    proxy.stop();
+    Ok(())
+}